@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, io};
+
+use bevy::asset::FileAssetIo;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::{CompressedImageFormats, ImageType, TextureError};
+use bevy::sprite::TextureAtlasBuilderError;
+
+use crate::prelude::*;
+
+/// Where the block textures are, in the assets folder. Every PNG in it is a texture, named after the file without
+/// the extension, like `grass_top`.
+pub const BLOCK_TEXTURES_DIR: &str = "textures/blocks";
+
+/// The name of the magenta and black checker the atlas always has. A texture that doesn't have a file is drawn
+/// with it.
+pub const MISSING_TEXTURE: &str = "missing";
+
+/// How big the checker is, the size of the block textures.
+const MISSING_SIZE: u32 = 32;
+/// How big the squares of the checker are.
+const CHECKER_SIZE: u32 = 8;
+/// How many pixels of its edges are copied around each tile, on every side. Filtering samples a little past the
+/// edge of a tile, with them it samples more of the same tile instead of its neighbour.
+pub const ATLAS_GUTTER: u32 = 2;
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io(PathBuf, io::Error),
+    Decode(PathBuf, TextureError),
+    /// A texture is named after one of the built in ones.
+    ReservedName(PathBuf),
+    Pack(TextureAtlasBuilderError),
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AtlasError::Io(path, err) => write!(f, "couldn't read {}: {}", path.display(), err),
+            AtlasError::Decode(path, err) => write!(f, "invalid image {}: {}", path.display(), err),
+            AtlasError::ReservedName(path) => {
+                write!(f, "{} has the name of a built in texture", path.display())
+            }
+            AtlasError::Pack(err) => write!(f, "couldn't pack the textures: {}", err),
+        }
+    }
+}
+
+/// Reads a texture from a PNG file, as 8-bit RGBA like the atlas.
+pub fn read_texture(path: &Path) -> Result<Image, AtlasError> {
+    let bytes = fs::read(path).map_err(|err| AtlasError::Io(path.to_path_buf(), err))?;
+    let texture = Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+    )
+    .map_err(|err| AtlasError::Decode(path.to_path_buf(), err))?;
+    let format = texture.texture_descriptor.format;
+    if format == TextureFormat::Rgba8UnormSrgb {
+        return Ok(texture);
+    }
+    texture
+        .convert(TextureFormat::Rgba8UnormSrgb)
+        .ok_or_else(|| {
+            AtlasError::Decode(
+                path.to_path_buf(),
+                TextureError::UnsupportedTextureFormat(format!("{:?}", format)),
+            )
+        })
+}
+
+/// Reads every PNG in a directory, sorted by name so the atlas comes out the same every time. A file that can't be
+/// read is left out with a warning, whatever uses it is drawn with the checker.
+pub fn read_textures(dir: &Path) -> Result<Vec<(String, Image)>, AtlasError> {
+    let entries = fs::read_dir(dir).map_err(|err| AtlasError::Io(dir.to_path_buf(), err))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+        .collect();
+    paths.sort();
+
+    let mut textures = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let texture = if name == MISSING_TEXTURE {
+            Err(AtlasError::ReservedName(path.clone()))
+        } else {
+            read_texture(&path)
+        };
+        match texture {
+            Ok(texture) => textures.push((name.to_string(), texture)),
+            Err(err) => warn!("Skipping a block texture: {}", err),
+        }
+    }
+    Ok(textures)
+}
+
+/// The magenta and black checker drawn for the textures that are missing.
+pub fn missing_texture() -> Image {
+    let mut data = Vec::with_capacity((MISSING_SIZE * MISSING_SIZE * 4) as usize);
+    for y in 0..MISSING_SIZE {
+        for x in 0..MISSING_SIZE {
+            let magenta = (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2);
+            data.extend_from_slice(if magenta {
+                &[255, 0, 255, 255]
+            } else {
+                &[0, 0, 0, 255]
+            });
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: MISSING_SIZE,
+            height: MISSING_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Copies the edge pixels of an 8-bit RGBA texture outwards, so it's `gutter` pixels bigger on every side. The
+/// corners are filled with the corner pixels.
+pub fn with_gutter(texture: &Image, gutter: u32) -> Image {
+    debug_assert_eq!(
+        texture.texture_descriptor.format,
+        TextureFormat::Rgba8UnormSrgb
+    );
+    let size = texture.texture_descriptor.size;
+    let (width, height) = (size.width + 2 * gutter, size.height + 2 * gutter);
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        let source_y = y.saturating_sub(gutter).min(size.height - 1);
+        for x in 0..width {
+            let source_x = x.saturating_sub(gutter).min(size.width - 1);
+            let start = ((source_y * size.width + source_x) * 4) as usize;
+            data.extend_from_slice(&texture.data[start..start + 4]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Packs named textures into one atlas, with the checker for the missing ones, and looks up the tiles of every
+/// block in it. Every texture gets an [`ATLAS_GUTTER`] around it, the tiles are only what's inside. The textures
+/// are only needed while they're copied in, they're removed from the images after.
+pub fn build_block_atlas(
+    textures: Vec<(String, Image)>,
+    images: &mut Assets<Image>,
+) -> Result<GameTextureAtlas, AtlasError> {
+    let mut builder = TextureAtlasBuilder::default();
+    let mut handles = Vec::new();
+    for (name, texture) in
+        std::iter::once((MISSING_TEXTURE.to_string(), missing_texture())).chain(textures)
+    {
+        let handle = images.add(with_gutter(&texture, ATLAS_GUTTER));
+        builder.add_texture(handle.clone(), images.get(&handle).unwrap());
+        handles.push((name, handle));
+    }
+    let built = builder.finish(images);
+    for (_, handle) in &handles {
+        images.remove(handle);
+    }
+
+    let mut atlas = built.map_err(AtlasError::Pack)?;
+    for tile in &mut atlas.textures {
+        tile.min += ATLAS_GUTTER as f32;
+        tile.max -= ATLAS_GUTTER as f32;
+    }
+    let names: HashMap<String, usize> = handles
+        .into_iter()
+        .map(|(name, handle)| (name, atlas.get_texture_index(&handle).unwrap()))
+        .collect();
+    let missing = names[MISSING_TEXTURE];
+    Ok(GameTextureAtlas::new(atlas, names, missing))
+}
+
+/// Builds the atlas from the textures in [`BLOCK_TEXTURES_DIR`]. If they can't be read or packed, every block is
+/// drawn with the checker.
+pub fn load_block_atlas(
+    images: &mut Assets<Image>,
+    graphics: &GraphicsSettings,
+) -> GameTextureAtlas {
+    // The same folder the asset server loads from.
+    let dir = FileAssetIo::get_base_path()
+        .join("assets")
+        .join(BLOCK_TEXTURES_DIR);
+    let atlas = read_textures(&dir).and_then(|textures| build_block_atlas(textures, images));
+    let mut atlas = match atlas {
+        Ok(atlas) => atlas,
+        Err(err) => {
+            warn!("No block textures, using the checker: {}", err);
+            build_block_atlas(Vec::new(), images).expect("the checker always fits in an atlas")
+        }
+    };
+    atlas.uv_inset = graphics.texture_inset;
+    atlas
+}
+
+/// Samples the atlas with the filtering of the settings. Only the sampler changes, so nothing is remeshed.
+pub fn apply_texture_filter(
+    settings: Res<Settings>,
+    game_atlas: Res<GameTextureAtlas>,
+    mut images: ResMut<Assets<Image>>,
+    mut applied: Local<Option<TextureFilter>>,
+) {
+    let filter = settings.graphics.texture_filter;
+    if *applied == Some(filter) {
+        return;
+    }
+    if let Some(image) = images.get_mut(&game_atlas.atlas.texture) {
+        image.sampler_descriptor = filter.sampler();
+        *applied = Some(filter);
+    }
+}
@@ -0,0 +1,140 @@
+use noise::Perlin;
+
+use crate::game::trees::TreeSpecies;
+use crate::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Biome {
+    Plains,
+    Desert,
+    Forest,
+    Tundra,
+    /// The cold forest between the tundra and the warmer biomes.
+    Taiga,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 5] = [
+        Biome::Plains,
+        Biome::Desert,
+        Biome::Forest,
+        Biome::Tundra,
+        Biome::Taiga,
+    ];
+
+    /// The human-readable name, used in the HUD.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Biome::Plains => "Plains",
+            Biome::Desert => "Desert",
+            Biome::Forest => "Forest",
+            Biome::Tundra => "Tundra",
+            Biome::Taiga => "Taiga",
+        }
+    }
+
+    /// The block on top of the terrain and the few blocks below it, above the stone.
+    pub fn surface_blocks(self) -> (BlockType, BlockType) {
+        match self {
+            Biome::Plains | Biome::Forest | Biome::Taiga => (BlockType::Grass, BlockType::Dirt),
+            Biome::Desert => (BlockType::Sand, BlockType::Sand),
+            Biome::Tundra => (BlockType::SnowyGrass, BlockType::Dirt),
+        }
+    }
+
+    /// The color the grass tops are multiplied with, over the green of their texture. The plains keep it as it is,
+    /// the grass is drier towards the desert and darker in the forests.
+    pub fn grass_tint(self) -> [f32; 3] {
+        match self {
+            Biome::Plains => [1.0, 1.0, 1.0],
+            Biome::Desert => [1.0, 0.85, 0.55],
+            Biome::Forest => [0.8, 0.95, 0.75],
+            Biome::Tundra => [0.85, 0.95, 0.95],
+            Biome::Taiga => [0.75, 0.9, 0.85],
+        }
+    }
+
+    /// Whether the water freezes over.
+    pub fn is_cold(self) -> bool {
+        matches!(self, Biome::Tundra | Biome::Taiga)
+    }
+
+    /// The chance of a tree growing out of a column.
+    pub fn tree_chance(self) -> f32 {
+        match self {
+            Biome::Plains => 0.002,
+            Biome::Desert => 0.0,
+            Biome::Forest => 0.03,
+            Biome::Tundra => 0.004,
+            Biome::Taiga => 0.02,
+        }
+    }
+
+    /// The trees that grow in the biome, if any.
+    pub fn tree_species(self) -> Option<TreeSpecies> {
+        match self {
+            Biome::Plains | Biome::Forest => Some(TreeSpecies::Oak),
+            Biome::Desert => None,
+            Biome::Tundra | Biome::Taiga => Some(TreeSpecies::Spruce),
+        }
+    }
+
+    /// The highest tree chance of any biome.
+    pub fn max_tree_chance() -> f32 {
+        Self::ALL
+            .into_iter()
+            .map(Biome::tree_chance)
+            .fold(0.0, f32::max)
+    }
+
+    /// Picks the biome from the temperature and humidity, both roughly in -1..1.
+    pub fn from_climate(temperature: f64, humidity: f64) -> Self {
+        if temperature < -0.25 {
+            Biome::Tundra
+        } else if temperature < -0.1 && humidity > 0.0 {
+            Biome::Taiga
+        } else if temperature > 0.2 && humidity < 0.0 {
+            Biome::Desert
+        } else if humidity > 0.15 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+}
+
+/// The temperature and humidity layers. They're seeded separately from the terrain, so biomes don't follow the height.
+#[derive(Clone)]
+pub struct BiomeNoise {
+    temperature: Perlin,
+    humidity: Perlin,
+    scale: f64,
+}
+
+impl BiomeNoise {
+    pub fn new(seed: u32) -> Self {
+        Self::with_scale(seed, BIOME_SCALE)
+    }
+
+    /// The noise layers sampled at another frequency than `BIOME_SCALE`, for bigger or smaller biomes.
+    pub fn with_scale(seed: u32, scale: f64) -> Self {
+        Self {
+            temperature: Perlin::new(seed.wrapping_add(1)),
+            humidity: Perlin::new(seed.wrapping_add(2)),
+            scale,
+        }
+    }
+
+    /// The biome of a block column, in world coordinates.
+    pub fn biome_at(&self, pos: IVec2XZ) -> Biome {
+        let point = [pos.x as f64 * self.scale, pos.z as f64 * self.scale];
+        Biome::from_climate(self.temperature.get(point), self.humidity.get(point))
+    }
+}
+
+/// The biome of a block column, in world coordinates. The same column always gets the same biome for a seed.
+///
+/// This builds the noise layers every time, use [`BiomeNoise`] when sampling many columns.
+pub fn biome_at(pos: IVec2XZ, seed: u32) -> Biome {
+    BiomeNoise::new(seed).biome_at(pos)
+}
@@ -0,0 +1,158 @@
+use crate::prelude::*;
+
+/// Kinds of short-lived entities that are limited by the [`EntityBudget`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EntityCategory {
+    Item,
+    Particle,
+    Mob,
+}
+
+impl EntityCategory {
+    pub const ALL: [EntityCategory; 3] = [
+        EntityCategory::Item,
+        EntityCategory::Particle,
+        EntityCategory::Mob,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            EntityCategory::Item => 0,
+            EntityCategory::Particle => 1,
+            EntityCategory::Mob => 2,
+        }
+    }
+}
+
+/// Marks an entity as counted by the [`EntityBudget`]. Create it with [`EntityBudget::track`].
+#[derive(Component, Debug)]
+pub struct Budgeted {
+    pub category: EntityCategory,
+    /// Spawn order, used to find the oldest (or newest) entities.
+    order: u64,
+}
+
+/// Entities that must never be evicted or cleaned up, like named or persistent ones.
+/// They still count towards the cap.
+#[derive(Component)]
+pub struct Protected;
+
+#[derive(Clone, Copy, Debug)]
+pub struct CategoryBudget {
+    pub cap: usize,
+    /// Entities farther than this from the player are despawned.
+    pub despawn_distance: f32,
+}
+
+#[derive(Resource)]
+pub struct EntityBudget {
+    pub items: CategoryBudget,
+    pub particles: CategoryBudget,
+    pub mobs: CategoryBudget,
+    counts: [usize; 3],
+    next_order: u64,
+}
+
+impl Default for EntityBudget {
+    fn default() -> Self {
+        Self {
+            items: CategoryBudget {
+                cap: 256,
+                despawn_distance: 64.0,
+            },
+            particles: CategoryBudget {
+                cap: 2048,
+                despawn_distance: 48.0,
+            },
+            mobs: CategoryBudget {
+                cap: 64,
+                despawn_distance: 128.0,
+            },
+            counts: [0; 3],
+            next_order: 0,
+        }
+    }
+}
+
+impl EntityBudget {
+    pub fn limits(&self, category: EntityCategory) -> CategoryBudget {
+        match category {
+            EntityCategory::Item => self.items,
+            EntityCategory::Particle => self.particles,
+            EntityCategory::Mob => self.mobs,
+        }
+    }
+
+    /// The number of entities in the category, as of the last budget check.
+    pub fn count(&self, category: EntityCategory) -> usize {
+        self.counts[category.index()]
+    }
+
+    /// Items and particles can always be spawned (the oldest ones get evicted instead),
+    /// mobs are refused once the cap is reached.
+    pub fn can_spawn(&self, category: EntityCategory) -> bool {
+        category != EntityCategory::Mob || self.count(category) < self.limits(category).cap
+    }
+
+    /// Returns the component a spawning system should add to a new entity of this category.
+    pub fn track(&mut self, category: EntityCategory) -> Budgeted {
+        self.counts[category.index()] += 1;
+        self.next_order += 1;
+        Budgeted {
+            category,
+            order: self.next_order,
+        }
+    }
+}
+
+/// Despawns budgeted entities that are too far from the player.
+pub fn despawn_distant_entities(
+    mut commands: Commands,
+    budget: Res<EntityBudget>,
+    player: Res<PlayerPos>,
+    query: Query<(Entity, &Budgeted, &Transform), Without<Protected>>,
+) {
+    for (entity, budgeted, transform) in query.iter() {
+        let max_distance = budget.limits(budgeted.category).despawn_distance;
+        if transform.translation.distance_squared(player.pos) > max_distance * max_distance {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Counts the budgeted entities and evicts the ones over the cap.
+///
+/// Items and particles lose their oldest entities, mobs lose the newest ones, which means the spawn was refused.
+pub fn enforce_entity_budget(
+    mut commands: Commands,
+    mut budget: ResMut<EntityBudget>,
+    query: Query<(Entity, &Budgeted, Option<&Protected>)>,
+) {
+    for category in EntityCategory::ALL {
+        let mut evictable: Vec<(u64, Entity)> = Vec::new();
+        let mut count: usize = 0;
+        for (entity, budgeted, protected) in query.iter() {
+            if budgeted.category != category {
+                continue;
+            }
+            count += 1;
+            if protected.is_none() {
+                evictable.push((budgeted.order, entity));
+            }
+        }
+
+        let cap = budget.limits(category).cap;
+        let excess = count.saturating_sub(cap).min(evictable.len());
+        if excess > 0 {
+            evictable.sort_unstable();
+            if category == EntityCategory::Mob {
+                evictable.reverse();
+            }
+            for &(_, entity) in &evictable[..excess] {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        budget.counts[category.index()] = count - excess;
+    }
+}
@@ -5,15 +5,29 @@ use bevy::{
 };
 use bevy_atmosphere::prelude::AtmosphereCamera;
 
+use crate::game::flight::*;
+use crate::game::freecam::CameraMode;
+use crate::game::generator::SharedGenerator;
+use crate::game::interaction::Sneaking;
+use crate::game::status::Health;
+use crate::game::tune::TunePanel;
 use crate::prelude::*;
 
+/// The fog the terrain fades into at the render distance, so the chunks don't pop in.
+pub fn distance_fog() -> FogFalloff {
+    FogFalloff::Linear {
+        start: RENDER_DISTANCE as f32 * CHUNK_SIZE as f32 * 0.8,
+        end: RENDER_DISTANCE as f32 * CHUNK_SIZE as f32 * 0.95,
+    }
+}
+
 pub fn spawn_player(mut commands: Commands) {
     // Spawn camera
     commands
         .spawn((
             Name::new("Player Camera"),
             Camera3dBundle {
-                transform: Transform::from_translation(Vec3::new(0.0, 200.0, 0.0))
+                transform: Transform::from_translation(WORLD_SPAWN)
                     .looking_at(Vec3::new(0.0, 0.0, 0.0), Vec3::Y),
                 projection: Projection::Perspective(PerspectiveProjection {
                     fov: FOV.to_radians(),
@@ -23,31 +37,32 @@ pub fn spawn_player(mut commands: Commands) {
             },
             FogSettings {
                 color: Color::rgba(0.05, 0.05, 0.05, 1.0),
-                falloff: FogFalloff::Linear {
-                    start: RENDER_DISTANCE as f32 * CHUNK_SIZE as f32 * 0.8,
-                    end: RENDER_DISTANCE as f32 * CHUNK_SIZE as f32 * 0.95,
-                },
+                falloff: distance_fog(),
                 ..default()
             },
             AtmosphereCamera::default(),
             NotShadowCaster,
         ))
-        .insert(TransformBundle::from(Transform::from_xyz(0.0, 200.0, 0.0)));
+        .insert(TransformBundle::from(Transform::from_translation(
+            WORLD_SPAWN,
+        )));
 
     commands
         .spawn((
             Name::new("Player Collider"),
-            TransformBundle::from(Transform::from_xyz(0.0, 200.0, 0.0)),
+            TransformBundle::from(Transform::from_translation(WORLD_SPAWN)),
             RigidBody::Dynamic,
             LockedAxes::ROTATION_LOCKED_Z
                 | LockedAxes::ROTATION_LOCKED_X
                 | LockedAxes::ROTATION_LOCKED_Y,
             // Collider::capsule_y(0.5, 0.5),
-            Collider::cylinder(1.0, 0.5),
+            player_collider(),
             // Collider::cuboid(0.5, 1.0, 0.5),
             Velocity::zero(),
+            GravityScale(1.0),
             Sleeping::disabled(),
             Ccd::enabled(),
+            Health::default(),
         ))
         .insert(KinematicCharacterController {
             offset: CharacterLength::Absolute(0.1),
@@ -57,23 +72,92 @@ pub fn spawn_player(mut commands: Commands) {
         });
 }
 
+/// Moves the player to where the world wants them to start, once its generator is set up.
+pub fn move_to_spawn_point(
+    generator: Res<SharedGenerator>,
+    mut player_state: ResMut<PlayerPos>,
+    mut bodies: Query<&mut Transform, With<KinematicCharacterController>>,
+) {
+    let spawn = generator.0.spawn_point();
+    for mut transform in bodies.iter_mut() {
+        transform.translation = spawn;
+    }
+    player_state.pos = spawn;
+}
+
+/// Half the height of the player, from the middle of the body down to the feet.
+pub const PLAYER_HALF_HEIGHT: f32 = 1.0;
+/// How far ahead a sneaking player looks for the edge of the ground, in seconds of walking.
+const SNEAK_EDGE_LOOKAHEAD: f32 = 0.25;
+
+/// The player's collision shape, two blocks tall and one wide.
+pub fn player_collider() -> Collider {
+    Collider::cylinder(PLAYER_HALF_HEIGHT, 0.5)
+}
+
+/// Stops a sneaking player at the edge of the ground. Each way along `velocity` that would take the middle of the feet
+/// of the `body` over a cell with nothing solid under it is left out, so the player still slides along the edge.
+pub fn guard_edges(chunk_map: &ChunkMap, body: Vec3, velocity: Vec3) -> Vec3 {
+    let feet = body - Vec3::Y * PLAYER_HALF_HEIGHT;
+    let supported = |step: Vec3| {
+        let ground = (feet + step * SNEAK_EDGE_LOOKAHEAD - Vec3::Y * 0.5)
+            .floor()
+            .as_ivec3();
+        chunk_map
+            .get_block(ground)
+            .is_some_and(|block| block.is_solid())
+    };
+    let mut guarded = velocity;
+    if !supported(Vec3::new(velocity.x, 0.0, 0.0)) {
+        guarded.x = 0.0;
+    }
+    if !supported(Vec3::new(0.0, 0.0, velocity.z)) {
+        guarded.z = 0.0;
+    }
+    // Around an outer corner both ways are fine on their own, but not together.
+    if !supported(Vec3::new(guarded.x, 0.0, guarded.z)) {
+        guarded.x = 0.0;
+        guarded.z = 0.0;
+    }
+    guarded
+}
+
 // todo: make the query more readable
+#[allow(clippy::too_many_arguments)]
 pub fn move_player(
     mut controllers: Query<(
         &mut KinematicCharacterController,
         &mut Transform,
         &mut Velocity,
+        &mut GravityScale,
     )>,
     // mut camera: Query<(&Camera3d, &mut Transform)>,
     ground_touching: Query<&KinematicCharacterControllerOutput>,
     keys: Res<Input<KeyCode>>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     mut player_state: ResMut<PlayerPos>,
+    time: Res<Time>,
+    config: Res<PlayerPhysicsConfig>,
+    mut flight: ResMut<Flight>,
+    camera_mode: Res<CameraMode>,
+    loader: Res<ChunkLoader>,
+    (chunk_map, sneaking): (Res<ChunkMap>, Res<Sneaking>),
 ) {
     if primary_window.get_single().is_err() {
         return;
     }
 
+    // The body stays where it was while the freecam flies around, or while the ground under it is generating.
+    if camera_mode.is_freecam() || loader.hold_player {
+        for (_, _, mut velocity, mut gravity) in controllers.iter_mut() {
+            velocity.linvel = Vec3::ZERO;
+            if gravity.0 != 0.0 {
+                *gravity = GravityScale(0.0);
+            }
+        }
+        return;
+    }
+
     let mut new_translation = Vec3::new(0.0, 0.0, 0.0);
 
     // Local z is the direction the player is facing
@@ -89,6 +173,11 @@ pub fn move_player(
     let right = Vec3::new(local_z.z, 0.0, -local_z.x).normalize();
     let mut sprinting = false;
 
+    // Double tapping jump toggles flying.
+    if keys.just_pressed(KeyCode::Space) && flight.jump_pressed(time.elapsed_seconds(), &config) {
+        info!("Flying: {}", flight.flying);
+    }
+
     for key in keys.get_pressed() {
         match key {
             KeyCode::W => new_translation -= forward,
@@ -100,17 +189,52 @@ pub fn move_player(
         }
     }
 
+    if flight.flying {
+        // Space and shift fly up and down instead of jumping and sprinting.
+        let mut wish = new_translation;
+        if keys.pressed(KeyCode::Space) {
+            wish += Vec3::Y;
+        }
+        if keys.pressed(KeyCode::ShiftLeft) {
+            wish -= Vec3::Y;
+        }
+        let target = wish.normalize_or_zero() * config.fly_speed * flight.multiplier;
+
+        for (_, mut transform, mut velocity, mut gravity) in controllers.iter_mut() {
+            if gravity.0 != 0.0 {
+                *gravity = GravityScale(0.0);
+            }
+            velocity.linvel = approach_velocity(
+                velocity.linvel,
+                target,
+                config.fly_smoothing,
+                time.delta_seconds(),
+            );
+            transform.rotation = player_state.rot;
+            player_state.pos = transform.translation;
+        }
+        return;
+    }
+
     // Normalize so that diagonal movement isn't faster
     new_translation = new_translation.normalize_or_zero();
 
-    // Sprinting
-    if sprinting {
+    // Sprinting, sneaking is slower and doesn't sprint.
+    if sneaking.0 {
+        new_translation *= SNEAK_SPEED_FACTOR;
+    } else if sprinting {
         new_translation *= 2.0;
     }
 
     // Scale by time
     new_translation *= SPEED;
 
+    // A sneaking player on the ground doesn't walk off it.
+    let grounded = ground_touching.iter().any(|output| output.grounded);
+    if sneaking.0 && grounded {
+        new_translation = guard_edges(&chunk_map, player_state.pos, new_translation);
+    }
+
     // Jumping
     let mut jump: bool = false;
 
@@ -125,6 +249,9 @@ pub fn move_player(
     }
 
     for mut controller in controllers.iter_mut() {
+        if controller.3 .0 != 1.0 {
+            *controller.3 = GravityScale(1.0);
+        }
         // controller.0.translation = Some(new_translation);
         controller.2.linvel.x = new_translation.x;
         controller.2.linvel.z = new_translation.z;
@@ -149,10 +276,14 @@ pub fn read_result_system(controllers: Query<(Entity, &KinematicCharacterControl
 }
 
 pub fn update_camera(
-    mut camera: Query<(&mut Transform, &AtmosphereCamera)>,
+    mut camera: Query<&mut Transform, With<Camera3d>>,
     player_state: Res<PlayerPos>,
+    camera_mode: Res<CameraMode>,
 ) {
-    for (mut transform, _) in camera.iter_mut() {
+    if camera_mode.is_freecam() {
+        return;
+    }
+    for mut transform in camera.iter_mut() {
         transform.translation = Vec3::new(
             player_state.pos.x,
             player_state.pos.y + 0.5,
@@ -165,8 +296,9 @@ pub fn player_look(
     primary_window: Query<&Window, With<PrimaryWindow>>,
     mut state: ResMut<InputState>,
     motion: Res<Events<MouseMotion>>,
-    mut camera: Query<&mut Transform, With<AtmosphereCamera>>,
+    mut camera: Query<&mut Transform, With<Camera3d>>,
     mut player_state: ResMut<PlayerPos>,
+    camera_mode: Res<CameraMode>,
 ) {
     if let Ok(window) = primary_window.get_single() {
         for mut transform in camera.iter_mut() {
@@ -188,7 +320,10 @@ pub fn player_look(
                 transform.rotation =
                     Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
 
-                player_state.rot = Quat::from_axis_angle(Vec3::Y, yaw);
+                // The freecam only turns the camera, the player keeps facing where it was.
+                if !camera_mode.is_freecam() {
+                    player_state.rot = Quat::from_axis_angle(Vec3::Y, yaw);
+                }
             }
         }
     } else {
@@ -200,12 +335,14 @@ pub fn cursor_grab_system(
     mut window: Query<&mut Window>,
     button: Res<Input<MouseButton>>,
     key: Res<Input<KeyCode>>,
+    tune_panel: Option<Res<TunePanel>>,
 ) {
     let mut window = window.single_mut();
     let window = &mut *window;
 
-    // Toggle cursor grab mode and visibility.
-    if button.just_pressed(MouseButton::Left) {
+    // Toggle cursor grab mode and visibility. Clicks on the tuning panel are for its sliders.
+    let on_panel = tune_panel.is_some_and(|panel| panel.pointer_over);
+    if button.just_pressed(MouseButton::Left) && !on_panel {
         window.cursor.grab_mode = CursorGrabMode::Confined;
         window.cursor.visible = false;
     }
@@ -0,0 +1,67 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::window::PrimaryWindow;
+
+use crate::prelude::*;
+
+/// Takes a screenshot without the HUD and the screen effects.
+pub const CLEAN_CAPTURE_KEY: KeyCode = KeyCode::F2;
+/// Where the screenshots are saved, relative to the working directory.
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// How far along a clean capture is. The UI is hidden a frame before the screenshot and shown again after it.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanCapture {
+    #[default]
+    Idle,
+    Hiding,
+    Capturing,
+}
+
+pub fn clean_capture(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    camera_query: Query<Entity, With<Camera3d>>,
+    mut state: Local<CleanCapture>,
+) {
+    let show_ui = |commands: &mut Commands, show_ui: bool| {
+        for camera in camera_query.iter() {
+            commands.entity(camera).insert(UiCameraConfig { show_ui });
+        }
+    };
+
+    *state = match *state {
+        CleanCapture::Idle if keys.just_pressed(CLEAN_CAPTURE_KEY) => {
+            show_ui(&mut commands, false);
+            CleanCapture::Hiding
+        }
+        CleanCapture::Idle => CleanCapture::Idle,
+        CleanCapture::Hiding => {
+            let seconds = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+            let path = format!("{}/clean-{}.png", SCREENSHOT_DIR, seconds);
+            let saved = fs::create_dir_all(SCREENSHOT_DIR)
+                .map_err(|err| err.to_string())
+                .and_then(|_| window_query.get_single().map_err(|err| err.to_string()))
+                .and_then(|window| {
+                    screenshots
+                        .save_screenshot_to_disk(window, &path)
+                        .map_err(|err| err.to_string())
+                });
+            match saved {
+                Ok(()) => info!("Saved a clean screenshot to {}", path),
+                Err(err) => warn!("Failed to take a screenshot: {}", err),
+            }
+            CleanCapture::Capturing
+        }
+        CleanCapture::Capturing => {
+            show_ui(&mut commands, true);
+            CleanCapture::Idle
+        }
+    };
+}
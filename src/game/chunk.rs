@@ -1,145 +1,454 @@
-use std::{collections::HashSet, time::Instant};
+use std::{collections::HashSet, sync::Arc, time::Instant};
 
 use bevy::{
     render::{
         mesh::{Indices, VertexAttributeValues},
+        primitives::Aabb,
         render_resource::PrimitiveTopology,
     },
     tasks::AsyncComputeTaskPool,
+    window::WindowFocused,
 };
 use futures_lite::future;
-use noise::Perlin;
 
+use crate::game::fluid_material::FluidMaterial;
+use crate::game::freecam::CameraMode;
+use crate::game::generator::{chunk_origin, PaddedBlocks, SharedGenerator, WorldGenerator};
+use crate::game::lod::{lod_chunk_meshes, lod_scale, skirt_sides};
+use crate::game::packed::{
+    is_packed, pack_chunk_meshes, PackedChunkMaterial, PackedVertex, ATTRIBUTE_PACKED,
+};
 use crate::prelude::*;
 
-/// Creates a 16x256x16 chunk mesh using a combination of 3D and 2D Perlin noise.
-fn create_chunk_mesh(chunk_position: IVec2XZ, game_texture: GameTextureAtlas) -> Mesh {
+/// Multiplied with the water texture. The alpha is how much of the water hides what's behind it.
+pub const WATER_TINT: Color = Color::rgba(0.55, 0.7, 1.0, 0.7);
+/// How fast the textures of the fluids scroll, in tiles per second. Whole tiles an hour, so they don't jump when
+/// the time they're scrolled by wraps around.
+pub const WATER_SCROLL: Vec2 = Vec2::new(0.0, 0.05);
+pub const LAVA_SCROLL: Vec2 = Vec2::new(0.0, 0.02);
+/// How high the surface of a fluid is in its top block.
+pub const FLUID_SURFACE: f32 = 0.9;
+
+/// How bright a corner is for each level of [`corner_light`], multiplied into the texture through the vertex colors.
+pub const AO_BRIGHTNESS: [f32; 4] = [0.45, 0.65, 0.83, 1.0];
+
+/// Creates the meshes of the 16 sections of a chunk from the blocks of the world generator.
+///
+/// The generated blocks are returned too, so they can be looked up later.
+pub fn create_chunk_mesh(
+    chunk_position: IVec2XZ,
+    game_texture: GameTextureAtlas,
+    generator: Arc<dyn WorldGenerator>,
+    ambient_occlusion: bool,
+) -> (ChunkMeshes, ChunkBlocks) {
     // Start the timer.
     let start = Instant::now();
 
-    // Create a new mesh.
-    let mut chunk_mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    // Generate an array of Blocks, representing whether a cube should be created at that position.
+    let chunk_blocks = generator.generate_chunk(chunk_position);
+    let padded = generator.pad_chunk(chunk_position, &chunk_blocks);
+    let meshing = Instant::now();
+    let chunk_meshes = mesh_chunk(
+        chunk_position,
+        0..SECTIONS,
+        &chunk_blocks,
+        &padded,
+        &game_texture,
+        generator.as_ref(),
+        ambient_occlusion,
+    );
 
-    let mut vertices: Vec<[f32; 3]> = Vec::new();
-    let mut indices: Vec<u32> = Vec::new();
-    let mut normals: Vec<[f32; 3]> = Vec::new();
-    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    // Stop the timer. The meshing is timed on its own too, it's where the ambient occlusion costs. The collider is
+    // built with the mesh.
+    let elapsed = start.elapsed();
+    info!(
+        "Chunk generation @ x: {} z: {} took: {:?} (meshing and collider: {:?}, ambient occlusion: {})",
+        chunk_position.x,
+        chunk_position.z,
+        elapsed,
+        meshing.elapsed(),
+        if ambient_occlusion { "on" } else { "off" }
+    );
 
-    // Generate an array of Blocks, representing whether a cube should be created at that position.
-    let mut chunk_blocks: [[[BlockType; CHUNK_SIZE]; CHUNK_HEIGHT]; CHUNK_SIZE] =
-        [[[BlockType::Air; CHUNK_SIZE]; CHUNK_HEIGHT]; CHUNK_SIZE];
-
-    // Create a 3D Perlin noise function with a random seed for the cave and surface generation
-    let perlin = Perlin::new(SEED);
-
-    // Loop over each block position in the chunk.
-    // Remember to offset the position by the chunk position.
-    #[allow(clippy::needless_range_loop)]
-    for x in 0..CHUNK_SIZE {
-        for y in 0..CHUNK_HEIGHT {
-            for z in 0..CHUNK_SIZE {
-                // Scale the position down by the chunk size.
-                let scaled_x = x as i32 + (chunk_position.x * CHUNK_SIZE as i32);
-                let scaled_y = y as i32;
-                let scaled_z = z as i32 + (chunk_position.z * CHUNK_SIZE as i32);
-
-                // Sample the noise function at the scaled position.
-                // The perlin noise needs a float value, so we need to cast the scaled position to a float.
-                chunk_blocks[x][y][z] = is_block(IVec3::new(scaled_x, scaled_y, scaled_z), &perlin);
-            }
+    (chunk_meshes, chunk_blocks)
+}
+
+/// Meshes sections of a chunk again after their blocks were edited, with the loaded blocks of its neighbours around
+/// it. The sections that are empty now are meshed too, so they can be despawned.
+///
+/// The blocks are returned too, they're the ones the colliders are built from.
+pub fn remesh_chunk(
+    chunk_position: IVec2XZ,
+    sections: Vec<usize>,
+    chunk_blocks: ChunkBlocks,
+    neighbors: ChunkMap,
+    game_texture: GameTextureAtlas,
+    generator: Arc<dyn WorldGenerator>,
+    ambient_occlusion: bool,
+) -> (ChunkMeshes, ChunkBlocks) {
+    let origin = chunk_origin(chunk_position);
+    // A neighbour that isn't loaded (at the edge of the render distance) hasn't been edited, it's still generated.
+    let padded = PaddedBlocks::new(&chunk_blocks, |local| {
+        neighbors
+            .get_block(origin + local)
+            .unwrap_or_else(|| generator.block_at(origin + local))
+    });
+    let chunk_meshes = mesh_chunk(
+        chunk_position,
+        sections,
+        &chunk_blocks,
+        &padded,
+        &game_texture,
+        generator.as_ref(),
+        ambient_occlusion,
+    );
+    (chunk_meshes, chunk_blocks)
+}
+
+/// Meshes sections of a chunk from its padded blocks. Every block the mesher looks at comes from them, the sections
+/// above and below included, there's nothing above or below the world so those faces are always drawn.
+fn mesh_chunk(
+    chunk_position: IVec2XZ,
+    sections: impl IntoIterator<Item = usize>,
+    chunk_blocks: &ChunkBlocks,
+    padded: &PaddedBlocks,
+    atlas: &GameTextureAtlas,
+    generator: &dyn WorldGenerator,
+    ambient_occlusion: bool,
+) -> ChunkMeshes {
+    ChunkMeshes {
+        sections: sections
+            .into_iter()
+            .map(|index| {
+                mesh_section(
+                    chunk_position,
+                    index,
+                    chunk_blocks,
+                    padded,
+                    atlas,
+                    generator,
+                    ambient_occlusion,
+                )
+            })
+            .collect(),
+        scale: 1,
+    }
+}
+
+/// Meshes one section of a chunk. A section of air has no faces, it isn't looked at, and in a section of opaque
+/// blocks only the outer layer is, a buried one comes out empty. The collider is built from the mesh and the
+/// chunk's blocks.
+fn mesh_section(
+    chunk_position: IVec2XZ,
+    index: usize,
+    chunk_blocks: &ChunkBlocks,
+    padded: &PaddedBlocks,
+    atlas: &GameTextureAtlas,
+    generator: &dyn WorldGenerator,
+    ambient_occlusion: bool,
+) -> SectionMesh {
+    let fill = chunk_blocks.section_fill(index);
+    if fill == SectionFill::Air {
+        return SectionMesh {
+            index,
+            solid_mesh: ChunkMeshData::default().into_mesh(),
+            fluid_meshes: Vec::new(),
+            collider: None,
+        };
+    }
+
+    let offset = IVec3::new(0, (index * SECTION_HEIGHT) as i32, 0);
+    let origin = chunk_origin(chunk_position) + offset;
+    let size = UVec3::new(CHUNK_SIZE as u32, SECTION_HEIGHT as u32, CHUNK_SIZE as u32);
+    let (solid, fluids) = mesh_box_parts(
+        size,
+        origin,
+        atlas,
+        (ambient_occlusion, fill == SectionFill::Opaque),
+        |column| generator.biome_at(column).grass_tint(),
+        |pos| padded.get(pos + offset),
+    );
+    let solid_mesh = solid.into_mesh();
+    SectionMesh {
+        index,
+        collider: chunk_collider(&solid_mesh, chunk_position, chunk_blocks),
+        solid_mesh,
+        fluid_meshes: fluids
+            .into_iter()
+            .map(|(block, mut fluid)| {
+                let center = fluid.recenter();
+                FluidMesh {
+                    block,
+                    mesh: fluid.into_mesh(),
+                    center,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Meshes a box of blocks of any size, with its minimum corner at `origin` in world space.
+///
+/// `block_at` takes positions relative to the origin. It's also asked for the blocks just outside of the box,
+/// to cull the faces touching them.
+#[allow(dead_code)] // Only the tests mesh boxes of blocks that aren't chunks
+pub fn mesh_blocks(
+    size: UVec3,
+    origin: IVec3,
+    atlas: &GameTextureAtlas,
+    block_at: impl Fn(IVec3) -> BlockType,
+) -> Mesh {
+    mesh_block_data(size, origin, atlas, block_at).into_mesh()
+}
+
+/// The vertices of a mesh before they're handed to Bevy, so they can still be worked on.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ChunkMeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    /// The color of every vertex, the shade of the ambient occlusion and the tint of the biome. Empty if nothing was
+    /// shaded or tinted. It can be shorter than the vertices, the ones past its end are white.
+    pub colors: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl ChunkMeshData {
+    /// Sets the colors of the face that was added last. The faces before it that don't have colors are white.
+    pub fn push_face_colors(&mut self, colors: [[f32; 4]; 4]) {
+        self.colors.resize(self.positions.len() - 4, [1.0; 4]);
+        self.colors.extend(colors);
+    }
+
+    /// Adds the faces of another mesh to this one.
+    pub fn append(&mut self, other: ChunkMeshData) {
+        let first = self.positions.len() as u32;
+        // Both have to be shaded for the colors to line up with the vertices, what isn't shaded is fully lit.
+        if !self.colors.is_empty() || !other.colors.is_empty() {
+            self.colors.resize(self.positions.len(), [1.0; 4]);
+            self.colors.extend(other.colors);
+            self.colors
+                .resize(self.positions.len() + other.positions.len(), [1.0; 4]);
         }
+        self.positions.extend(other.positions);
+        self.normals.extend(other.normals);
+        self.uvs.extend(other.uvs);
+        self.indices
+            .extend(other.indices.into_iter().map(|i| first + i));
     }
 
-    // From now on, we don't need the chunk position anymore, so we can just use the local block position.
-    // Now that the chunk data is generated, check the neighbouring blocks to see if we need to create faces.
-    // Loop over each block position in the chunk.
-    for x in 0..CHUNK_SIZE {
-        for y in 0..CHUNK_HEIGHT {
-            for z in 0..CHUNK_SIZE {
+    /// Moves the vertices so they're centred on the origin, and returns where their centre was. The centre is
+    /// rounded to a whole block, so the vertices stay exactly where they were once they're moved back.
+    pub fn recenter(&mut self) -> Vec3 {
+        if self.positions.is_empty() {
+            return Vec3::ZERO;
+        }
+        let center = self
+            .positions
+            .iter()
+            .map(|&position| Vec3::from(position))
+            .sum::<Vec3>()
+            / self.positions.len() as f32;
+        let center = center.round();
+        for position in &mut self.positions {
+            *position = (Vec3::from(*position) - center).to_array();
+        }
+        center
+    }
+
+    pub fn into_mesh(self) -> Mesh {
+        let vertices = self.positions.len();
+        // Create a new mesh.
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+        // Convert the vectors to VertexAttributeValues and add them to the mesh.
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(self.positions),
+        );
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            VertexAttributeValues::Float32x3(self.normals),
+        );
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            VertexAttributeValues::Float32x2(self.uvs),
+        );
+        // The material multiplies the vertex colors in, a mesh without them is drawn as it is.
+        if !self.colors.is_empty() {
+            let mut colors = self.colors;
+            colors.resize(vertices, [1.0; 4]);
+            mesh.insert_attribute(
+                Mesh::ATTRIBUTE_COLOR,
+                VertexAttributeValues::Float32x4(colors),
+            );
+        }
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+
+        mesh
+    }
+}
+
+/// Like [`mesh_blocks`], without turning the vertices into a [`Mesh`] yet.
+pub fn mesh_block_data(
+    size: UVec3,
+    origin: IVec3,
+    atlas: &GameTextureAtlas,
+    block_at: impl Fn(IVec3) -> BlockType,
+) -> ChunkMeshData {
+    let (mut data, fluids) = mesh_block_parts(size, origin, atlas, false, |_| [1.0; 3], block_at);
+    for (_, fluid) in fluids {
+        data.append(fluid);
+    }
+    data
+}
+
+/// Like [`mesh_block_data`], with the faces of every fluid in a mesh of their own. The fluids come in the order
+/// they were first found in.
+///
+/// With `ambient_occlusion`, the corners of the cube faces are shaded by the solid blocks around them. The faces
+/// that are tinted get the color `tint_at` gives for their world column.
+pub fn mesh_block_parts(
+    size: UVec3,
+    origin: IVec3,
+    atlas: &GameTextureAtlas,
+    ambient_occlusion: bool,
+    tint_at: impl Fn(IVec2XZ) -> [f32; 3],
+    block_at: impl Fn(IVec3) -> BlockType,
+) -> (ChunkMeshData, Vec<(BlockType, ChunkMeshData)>) {
+    mesh_box_parts(
+        size,
+        origin,
+        atlas,
+        (ambient_occlusion, false),
+        tint_at,
+        block_at,
+    )
+}
+
+/// Like [`mesh_block_parts`]. A box that's `opaque` is known to be full of blocks that cover every face, the faces
+/// between them are all hidden, so only its outer layer of blocks is looked at. The faces come out in the same
+/// order as if every block was.
+fn mesh_box_parts(
+    size: UVec3,
+    origin: IVec3,
+    atlas: &GameTextureAtlas,
+    (ambient_occlusion, opaque): (bool, bool),
+    tint_at: impl Fn(IVec2XZ) -> [f32; 3],
+    block_at: impl Fn(IVec3) -> BlockType,
+) -> (ChunkMeshData, Vec<(BlockType, ChunkMeshData)>) {
+    // Only the blocks that fill their cell shade the corners around them, a slab doesn't.
+    let occludes = |world: IVec3| {
+        let block = block_at(world - origin);
+        block.is_solid() && block.shape() == BlockShape::Cube
+    };
+    let occludes: Option<&dyn Fn(IVec3) -> bool> = ambient_occlusion.then_some(&occludes);
+    let mut solid = ChunkMeshData::default();
+    let mut fluids: Vec<(BlockType, ChunkMeshData)> = Vec::new();
+
+    // Check the neighbouring blocks to see if we need to create faces.
+    // Loop over each block position in the box.
+    // y is the innermost loop, since that's the order the chunk blocks are stored in.
+    let (last_x, last_z) = (size.x as i32 - 1, size.z as i32 - 1);
+    for x in 0..size.x as i32 {
+        for z in 0..size.z as i32 {
+            // Inside an opaque box, only the bottom and the top of a column are on its outer layer.
+            let inner = opaque && x > 0 && z > 0 && x < last_x && z < last_z;
+            let step = if inner {
+                size.y.saturating_sub(1).max(1) as usize
+            } else {
+                1
+            };
+            for y in (0..size.y as i32).step_by(step) {
+                let position = IVec3::new(x, y, z);
                 // Get the block type at the current position.
-                let block_type = chunk_blocks[x][y][z];
+                let block_type = block_at(position);
+                let world = origin + position;
 
                 // If the block is Air, we don't need to create any faces.
                 if block_type == BlockType::Air {
                     continue;
                 }
-
-                if block_type == BlockType::Water {}
+                let data = if block_type.is_fluid() {
+                    let index = match fluids.iter().position(|(fluid, _)| *fluid == block_type) {
+                        Some(index) => index,
+                        None => {
+                            fluids.push((block_type, ChunkMeshData::default()));
+                            fluids.len() - 1
+                        }
+                    };
+                    &mut fluids[index].1
+                } else {
+                    &mut solid
+                };
+
+                // Crosses are drawn whole, whatever is around them.
+                if block_type.shape() == BlockShape::Cross {
+                    create_cross(data, world.as_vec3(), block_type, atlas);
+                    // Plants aren't shaded, but their vertices need a color next to the shaded faces.
+                    if ambient_occlusion {
+                        data.colors.resize(data.positions.len(), [1.0; 4]);
+                    }
+                    continue;
+                }
 
                 // Check the blocks around the current block to see if we need to create faces.
-                for &(x_offset, y_offset, z_offset, face) in &[
-                    (0, 1, 0, BlockFace::Top),
-                    (0, -1, 0, BlockFace::Bottom),
-                    (1, 0, 0, BlockFace::Right),
-                    (-1, 0, 0, BlockFace::Left),
-                    (0, 0, 1, BlockFace::Front),
-                    (0, 0, -1, BlockFace::Back),
-                ] {
-                    let neighbor_x = x as i32 + x_offset;
-                    let neighbor_y = y as i32 + y_offset;
-                    let neighbor_z = z as i32 + z_offset;
-
-                    // Check if the neighbor block is outside the chunk.
-                    if neighbor_x < 0
-                        || neighbor_x >= CHUNK_SIZE as i32
-                        || neighbor_y < 0
-                        || neighbor_y >= CHUNK_HEIGHT as i32
-                        || neighbor_z < 0
-                        || neighbor_z >= CHUNK_SIZE as i32
-                    {
-                        // If the neighbor block is outside the chunk, we need to calculate if there is block in other chunk.
-                        let neighbor_block_pos = IVec3::new(
-                            x as i32 + (chunk_position.x * CHUNK_SIZE as i32) + x_offset,
-                            y as i32 + y_offset,
-                            z as i32 + (chunk_position.z * CHUNK_SIZE as i32) + z_offset,
-                        );
-                        let neighbor_block_type = is_block(neighbor_block_pos, &perlin);
-                        if neighbor_block_type == BlockType::Air
-                            || (block_type != BlockType::Water
-                                && neighbor_block_type == BlockType::Water)
-                            || (block_type != BlockType::Lava
-                                && neighbor_block_type == BlockType::Lava)
-                            || neighbor_block_pos.y < 0
-                            || neighbor_block_pos.y > CHUNK_HEIGHT as i32
-                        {
-                            // Create the face.
-                            create_face(
-                                &mut vertices,
-                                &mut indices,
-                                &mut normals,
-                                &mut uvs,
-                                IVec2XZ::new(chunk_position.x, chunk_position.z),
-                                [x as f32, y as f32, z as f32],
-                                face,
-                                block_type,
-                                &game_texture.0.textures,
-                                &game_texture.0.size,
-                            );
+                let tint = block_type.properties().tint;
+                let tint_at = |face: BlockFace| {
+                    tint.applies(face)
+                        .then(|| tint_at(IVec2XZ::new(world.x, world.z)))
+                };
+
+                // The other shapes are meshed box by box, each face of a box that can be seen.
+                if block_type.shape() != BlockShape::Cube {
+                    for part in block_type.shape().boxes() {
+                        for face in BlockFace::ALL {
+                            let neighbor_block_type = block_at(position + face.offset());
+                            if part_visible(block_type, neighbor_block_type, face, part) {
+                                create_box_face(
+                                    data,
+                                    world.as_vec3().to_array(),
+                                    face,
+                                    part,
+                                    block_type,
+                                    atlas,
+                                    occludes,
+                                    tint_at(face),
+                                );
+                            }
                         }
-                    } else {
-                        // Get the block type of the neighbor block in the current chunk.
-                        let neighbor_block_type = chunk_blocks[neighbor_x as usize]
-                            [neighbor_y as usize][neighbor_z as usize];
-                        // If the neighbor block is Air, we need to create a face.
-                        if neighbor_block_type == BlockType::Air
-                            || (block_type != BlockType::Water
-                                && neighbor_block_type == BlockType::Water)
-                            || (block_type != BlockType::Lava
-                                && neighbor_block_type == BlockType::Lava)
-                        {
-                            // Create the face.
-                            create_face(
-                                &mut vertices,
-                                &mut indices,
-                                &mut normals,
-                                &mut uvs,
-                                IVec2XZ::new(chunk_position.x, chunk_position.z),
-                                [x as f32, y as f32, z as f32],
-                                face,
-                                block_type,
-                                &game_texture.0.textures,
-                                &game_texture.0.size,
-                            );
+                    }
+                    continue;
+                }
+
+                // The top of a fluid with nothing over it is its surface, a bit below the top of the block.
+                let surface = block_type.is_fluid()
+                    && face_visible(
+                        block_type,
+                        block_at(position + BlockFace::Top.offset()),
+                        BlockFace::Top,
+                    );
+                for face in BlockFace::ALL {
+                    let neighbor_block_type = block_at(position + face.offset());
+                    if face_visible(block_type, neighbor_block_type, face) {
+                        // Create the face.
+                        create_face(
+                            data,
+                            world.as_vec3().to_array(),
+                            face,
+                            block_type,
+                            atlas,
+                            occludes,
+                            tint_at(face),
+                        );
+                        // The sides of the surface stop at it, a waterfall's edge meets its top.
+                        if surface && face != BlockFace::Top && face != BlockFace::Bottom {
+                            let face = data.positions.len() - 4;
+                            for position in &mut data.positions[face..] {
+                                if position[1] == (world.y + 1) as f32 {
+                                    position[1] = world.y as f32 + FLUID_SURFACE;
+                                }
+                            }
                         }
                     }
                 }
@@ -147,212 +456,389 @@ fn create_chunk_mesh(chunk_position: IVec2XZ, game_texture: GameTextureAtlas) ->
         }
     }
 
-    // Convert the vectors to VertexAttributeValues and add them to the mesh.
-    chunk_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::Float32x3(vertices),
-    );
-    chunk_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_NORMAL,
-        VertexAttributeValues::Float32x3(normals),
-    );
-    chunk_mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::Float32x2(uvs));
-    chunk_mesh.set_indices(Some(Indices::U32(indices)));
+    (solid, fluids)
+}
 
-    // Stop the timer
-    let elapsed = start.elapsed();
-    info!(
-        "Chunk generation @ x: {} z: {} took: {:?}",
-        chunk_position.x, chunk_position.z, elapsed
-    );
+/// Whether the face of a block is visible next to the neighbour on that side, any part of it.
+///
+/// Faces between two blocks of the same fluid are hidden too, so water and lava render as one body. So is
+/// the bottom of ice on water, the ice is the frozen top of the same body. A face that doesn't reach the edges of
+/// its cell, like the top of a slab, shows whatever is next to it.
+pub fn face_visible(block: BlockType, neighbor: BlockType, face: BlockFace) -> bool {
+    block.shape() == BlockShape::Cross
+        || block
+            .shape()
+            .boxes()
+            .any(|part| part_visible(block, neighbor, face, part))
+}
 
-    chunk_mesh
+/// Whether the face of one of the boxes of a block is visible next to the neighbour on that side, see
+/// [`face_visible`]. A face in the middle of the cell is, unless it's pressed against another box of the block.
+/// One on the edge of the cell is hidden by a neighbour that covers the face, or by the same block next to it
+/// where their faces overlap.
+pub fn part_visible(
+    block: BlockType,
+    neighbor: BlockType,
+    face: BlockFace,
+    part: BlockBox,
+) -> bool {
+    let shape = block.shape();
+    if !part.touches(face) {
+        return !shape.boxes().any(|other| part.hidden_by(other, face));
+    }
+    if face == BlockFace::Bottom && block.properties().frozen_from == Some(neighbor) {
+        return false;
+    }
+    let opposite = face.opposite();
+    let joined = neighbor == block
+        && shape
+            .boxes()
+            .any(|other| other.touches(opposite) && part.within(other, face));
+    !joined && !neighbor.covers_face(opposite)
 }
 
-/// Creates a face on a block.
-#[allow(clippy::too_many_arguments)] // too lazy to fix
-fn create_face(
-    vertices: &mut Vec<[f32; 3]>,
-    indices: &mut Vec<u32>,
-    normals: &mut Vec<[f32; 3]>,
-    uvs: &mut Vec<[f32; 2]>,
-    chunk_position: IVec2XZ,
+/// The corners of a face of a box in a cell at a position, in the clockwise order the faces are drawn in.
+///
+/// Bevy has backface culling enabled by default. This means that the vertices need to be in clockwise order. If a
+/// face is not showing up, this is probably the reason. (this took me so long)
+fn face_corners(position: [f32; 3], face: BlockFace, part: BlockBox) -> [[f32; 3]; 4] {
+    let [x0, y0, z0] = part.min.to_array();
+    let [x1, y1, z1] = part.max.to_array();
+    let corners = match face {
+        BlockFace::Top => [[x0, y1, z0], [x0, y1, z1], [x1, y1, z1], [x1, y1, z0]],
+        BlockFace::Bottom => [[x0, y0, z1], [x0, y0, z0], [x1, y0, z0], [x1, y0, z1]],
+        BlockFace::Left => [[x0, y1, z1], [x0, y1, z0], [x0, y0, z0], [x0, y0, z1]],
+        BlockFace::Right => [[x1, y1, z0], [x1, y1, z1], [x1, y0, z1], [x1, y0, z0]],
+        BlockFace::Front => [[x1, y1, z1], [x0, y1, z1], [x0, y0, z1], [x1, y0, z1]],
+        BlockFace::Back => [[x0, y1, z0], [x1, y1, z0], [x1, y0, z0], [x0, y0, z0]],
+    };
+    corners.map(|[x, y, z]| [position[0] + x, position[1] + y, position[2] + z])
+}
+
+/// Creates a face on the block at a world position.
+///
+/// With `occludes`, which tells which world positions hold a block that shades the corners next to it, the corners
+/// of the face are darkened by the blocks around them, see [`corner_light`]. A `tint` is multiplied into the colors
+/// of the corners too.
+pub fn create_face(
+    data: &mut ChunkMeshData,
     position: [f32; 3],
     direction: BlockFace,
     block: BlockType,
-    textures: &[Rect],
-    size: &Vec2,
+    atlas: &GameTextureAtlas,
+    occludes: Option<&dyn Fn(IVec3) -> bool>,
+    tint: Option<[f32; 3]>,
 ) {
-    // Offset the position of the face based on the chunk position.
-    let position = [
-        position[0] + chunk_position.x as f32 * CHUNK_SIZE as f32,
-        position[1],
-        position[2] + chunk_position.z as f32 * CHUNK_SIZE as f32,
-    ];
+    // If this is water or lava and the face is the top, it's the surface of the fluid.
+    let part = if block.is_fluid() && direction == BlockFace::Top {
+        BlockBox {
+            max: Vec3::new(1.0, FLUID_SURFACE, 1.0),
+            ..BlockBox::CELL
+        }
+    } else {
+        BlockBox::CELL
+    };
+    create_box_face(
+        data, position, direction, part, block, atlas, occludes, tint,
+    );
+}
 
+/// Like [`create_face`], for the face of a box that's only part of the cell. The texture is cut down to the box
+/// like the face is, and a face in the middle of the cell is shaded by the blocks beside it.
+#[allow(clippy::too_many_arguments)]
+fn create_box_face(
+    data: &mut ChunkMeshData,
+    position: [f32; 3],
+    direction: BlockFace,
+    part: BlockBox,
+    block: BlockType,
+    atlas: &GameTextureAtlas,
+    occludes: Option<&dyn Fn(IVec3) -> bool>,
+    tint: Option<[f32; 3]>,
+) {
     // Get the len of the vertices
-    let vertices_len = vertices.len() as u32;
+    let vertices_len = data.positions.len() as u32;
 
     // The normal of the face.
-    let normal = match direction {
-        BlockFace::Top => [0.0, 1.0, 0.0],
-        BlockFace::Bottom => [0.0, -1.0, 0.0],
-        BlockFace::Left => [-1.0, 0.0, 0.0],
-        BlockFace::Right => [1.0, 0.0, 0.0],
-        BlockFace::Front => [0.0, 0.0, 1.0],
-        BlockFace::Back => [0.0, 0.0, -1.0],
-    };
+    let normal = direction.offset().as_vec3().to_array();
 
     // The vertices of the face.
-    // Bevy has backface culling enabled by default. This means that the vertices need to be in clockwise order. If a face is not showing up, this is probably the reason. (this took me so long)
-    let face_vertices = match direction {
-        BlockFace::Top => {
-            // If this is water or lava and the face is the top, the top vert should be offset down by 0.1
-            if BlockType::Water == block || BlockType::Lava == block {
-                [
-                    [position[0], position[1] + 0.9, position[2]],
-                    [position[0], position[1] + 0.9, position[2] + 1.0],
-                    [position[0] + 1.0, position[1] + 0.9, position[2] + 1.0],
-                    [position[0] + 1.0, position[1] + 0.9, position[2]],
-                ]
-            } else {
-                [
-                    [position[0], position[1] + 1.0, position[2]],
-                    [position[0], position[1] + 1.0, position[2] + 1.0],
-                    [position[0] + 1.0, position[1] + 1.0, position[2] + 1.0],
-                    [position[0] + 1.0, position[1] + 1.0, position[2]],
-                ]
+    let face_vertices = face_corners(position, direction, part);
+    let cell_vertices = face_corners(position, direction, BlockBox::CELL);
+
+    let world_position = IVec3::new(position[0] as i32, position[1] as i32, position[2] as i32);
+
+    // The quad is split along the diagonal 0-2, unless the other one has the lighter corners. Splitting through
+    // the darker ones would smear their shade over both triangles, and the seam between neighbours would show.
+    let mut split = [0, 1, 2, 0, 2, 3];
+    let mut colors = None;
+    if let Some(occludes) = occludes {
+        // The corners are shaded like the face of the cell, from the layer in front of the face. Inside the cell
+        // that's the layer of the block itself. The lowered top of a fluid is shaded like the top of its cell.
+        let layer = if part.touches(direction) || block.is_fluid() {
+            world_position
+        } else {
+            world_position - direction.offset()
+        };
+        let light = corner_light(layer, direction, &cell_vertices, occludes);
+        colors = Some(light.map(|level| {
+            let brightness = AO_BRIGHTNESS[level as usize];
+            [brightness, brightness, brightness, 1.0]
+        }));
+        if light[0] + light[2] < light[1] + light[3] {
+            split = [0, 1, 3, 1, 2, 3];
+        }
+    }
+    if let Some(tint) = tint {
+        for color in colors.get_or_insert([[1.0; 4]; 4]) {
+            for (channel, tint) in color.iter_mut().zip(tint) {
+                *channel *= tint;
             }
         }
-        BlockFace::Bottom => [
-            [position[0], position[1], position[2] + 1.0],
-            [position[0], position[1], position[2]],
-            [position[0] + 1.0, position[1], position[2]],
-            [position[0] + 1.0, position[1], position[2] + 1.0],
-        ],
-
-        BlockFace::Left => [
-            [position[0], position[1] + 1.0, position[2] + 1.0],
-            [position[0], position[1] + 1.0, position[2]],
-            [position[0], position[1], position[2]],
-            [position[0], position[1], position[2] + 1.0],
-        ],
-        BlockFace::Right => [
-            [position[0] + 1.0, position[1] + 1.0, position[2]],
-            [position[0] + 1.0, position[1] + 1.0, position[2] + 1.0],
-            [position[0] + 1.0, position[1], position[2] + 1.0],
-            [position[0] + 1.0, position[1], position[2]],
-        ],
-        BlockFace::Front => [
-            [position[0] + 1.0, position[1] + 1.0, position[2] + 1.0],
-            [position[0], position[1] + 1.0, position[2] + 1.0],
-            [position[0], position[1], position[2] + 1.0],
-            [position[0] + 1.0, position[1], position[2] + 1.0],
-        ],
-        BlockFace::Back => [
-            [position[0], position[1] + 1.0, position[2]],
-            [position[0] + 1.0, position[1] + 1.0, position[2]],
-            [position[0] + 1.0, position[1], position[2]],
-            [position[0], position[1], position[2]],
-        ],
-    };
+    }
 
     // Add the vertices and normals to the vectors.
-    vertices.extend_from_slice(&face_vertices);
-    normals.extend_from_slice(&[normal; 4]);
-
-    let texture = match block {
-        BlockType::Bedrock => textures[0],
-        BlockType::Stone => textures[1],
-        BlockType::Dirt => textures[2],
-        BlockType::Grass => {
-            if direction == BlockFace::Top {
-                textures[3]
-            } else if direction == BlockFace::Bottom {
-                textures[2]
-            } else {
-                textures[4]
-            }
-        }
-        BlockType::Log => {
-            if direction == BlockFace::Top || direction == BlockFace::Bottom {
-                textures[12]
-            } else {
-                textures[5]
-            }
-        }
-        BlockType::Lava => textures[21],
-        BlockType::Water => textures[22],
-        BlockType::DiamondOre => textures[15],
-        BlockType::RedstoneOre => textures[14],
-        BlockType::GoldOre => textures[9],
-        BlockType::IronOre => textures[8],
-        BlockType::CoalOre => textures[7],
-        BlockType::Sand => textures[10],
-        BlockType::Air => textures[0], // todo: make this not cringe
-    };
+    data.positions.extend_from_slice(&face_vertices);
+    data.normals.extend_from_slice(&[normal; 4]);
+    if let Some(colors) = colors {
+        data.push_face_colors(colors);
+    }
 
-    let uv = [
-        [texture.min.x / size.x, texture.min.y / size.y],
-        [texture.max.x / size.x, texture.min.y / size.y],
-        [texture.max.x / size.x, texture.max.y / size.y],
-        [texture.min.x / size.x, texture.max.y / size.y],
-    ];
+    let mut uv = atlas.tile_uvs(texture_index(atlas, block, direction, world_position));
+    // A box smaller than the cell shows the part of the tile it covers, each corner where it is on the cell's face.
+    if part != BlockBox::CELL {
+        let [first, across, _, down] = cell_vertices.map(Vec3::from);
+        let [uv_first, uv_across, _, uv_down] = uv.map(Vec2::from);
+        uv = face_vertices.map(|corner| {
+            let corner = Vec3::from(corner) - first;
+            let (u, v) = (corner.dot(across - first), corner.dot(down - first));
+            (uv_first + u * (uv_across - uv_first) + v * (uv_down - uv_first)).to_array()
+        });
+    }
 
     // Add the UV coordinates to the vector.
-    uvs.extend_from_slice(&uv);
+    data.uvs.extend_from_slice(&uv);
 
-    // Add the indices to the vector. This is clockwise order.
-    indices.extend_from_slice(&[
-        vertices_len,
-        vertices_len + 1,
-        vertices_len + 2,
-        vertices_len,
-        vertices_len + 2,
-        vertices_len + 3,
-    ]);
+    // Add the indices to the vector. This is clockwise order, either way the quad is split.
+    data.indices.extend(split.map(|i| vertices_len + i));
 }
 
-pub fn chunk_system(
-    mut chunks_loaded: ResMut<ChunksLoaded>,
-    mut chunk_query: Query<(Entity, &ChunkMesh)>,
-    mut commands: Commands,
-    camera_query: Query<&Transform, With<Camera3d>>,
-    generating: Res<Generating>,
-    game_atlas: Res<GameTextureAtlas>,
+/// How much light reaches each corner of a face, from 0 for a corner tucked between two blocks to 3 for a corner
+/// with nothing around it.
+///
+/// This is the classic voxel ambient occlusion. The corner is shaded by the two blocks along the edges of the face
+/// that touch it and the one diagonally across, all in the layer in front of the face. Two edge blocks shade it
+/// fully, whatever is in between.
+pub fn corner_light(
+    position: IVec3,
+    face: BlockFace,
+    corners: &[[f32; 3]; 4],
+    occludes: &dyn Fn(IVec3) -> bool,
+) -> [u8; 4] {
+    let front = position + face.offset();
+    let center = position.as_vec3() + Vec3::splat(0.5);
+    let normal = face.offset().abs();
+    corners.map(|corner| {
+        // Which way the corner is from the middle of the face, along the two axes of the face.
+        let toward = (Vec3::from(corner) - center).signum().as_ivec3() * (IVec3::ONE - normal);
+        let (first, second) = if normal.x == 0 {
+            (
+                IVec3::new(toward.x, 0, 0),
+                toward - IVec3::new(toward.x, 0, 0),
+            )
+        } else {
+            (IVec3::new(0, toward.y, 0), IVec3::new(0, 0, toward.z))
+        };
+        let side_a = occludes(front + first);
+        let side_b = occludes(front + second);
+        if side_a && side_b {
+            return 0;
+        }
+        3 - side_a as u8 - side_b as u8 - occludes(front + toward) as u8
+    })
+}
+
+/// Creates the two diagonal quads of a cross shaped block at a world position.
+///
+/// Each quad is emitted with both windings, so it shows from both sides. They're lit like the ground they stand on,
+/// so their normals point up.
+fn create_cross(
+    data: &mut ChunkMeshData,
+    position: Vec3,
+    block: BlockType,
+    atlas: &GameTextureAtlas,
 ) {
-    // Check if the world is generating.
-    if !generating.0 {
-        return;
+    let world_position = position.as_ivec3();
+    let uv = atlas.tile_uvs(texture_index(
+        atlas,
+        block,
+        BlockFace::Front,
+        world_position,
+    ));
+
+    for (from, to) in [(Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0)), (Vec3::X, Vec3::Z)] {
+        let first = data.positions.len() as u32;
+        data.positions.extend_from_slice(&[
+            (position + from + Vec3::Y).to_array(),
+            (position + to + Vec3::Y).to_array(),
+            (position + to).to_array(),
+            (position + from).to_array(),
+        ]);
+        data.normals.extend_from_slice(&[[0.0, 1.0, 0.0]; 4]);
+        data.uvs.extend_from_slice(&uv);
+        for side in [[0, 1, 2, 0, 2, 3], [0, 2, 1, 0, 3, 2]] {
+            data.indices.extend(side.map(|i| first + i));
+        }
     }
+}
 
-    let task_pool = AsyncComputeTaskPool::get();
+/// Picks one of the variants for the block at a world position. The same position always gets the same variant.
+pub fn pick_variant(variants: &[usize], pos: IVec3) -> usize {
+    if variants.len() == 1 {
+        return variants[0];
+    }
+    let hash = mix(((pos.x as u32 as u64) << 32 | pos.z as u32 as u64) ^ mix(pos.y as u32 as u64));
+    variants[(hash % variants.len() as u64) as usize]
+}
+
+/// Index of the atlas tile used for a face of the block at a world position.
+pub fn texture_index(
+    atlas: &GameTextureAtlas,
+    block: BlockType,
+    face: BlockFace,
+    pos: IVec3,
+) -> usize {
+    pick_variant(atlas.tiles(block, face), pos)
+}
+
+/// Despawns every chunk (and the chunks that are still generating), so they're generated again. The cached
+/// chunks are dropped too.
+pub fn reset_chunks(
+    commands: &mut Commands,
+    chunk_query: &Query<Entity, With<ChunkMesh>>,
+    chunks_loaded: &mut ChunksLoaded,
+    loader: &mut ChunkLoader,
+    chunk_map: &mut ChunkMap,
+    mesh_cache: &mut MeshCache,
+    unloaded: &mut EventWriter<ChunkUnloaded>,
+) {
+    for entity in chunk_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    unloaded.send_batch(
+        chunks_loaded
+            .wanted()
+            .filter(|&position| chunks_loaded.is_ready(position))
+            .map(ChunkUnloaded),
+    );
+    chunks_loaded.clear();
+    loader.rescan();
+    chunk_map.clear();
+    mesh_cache.clear();
+}
+
+/// What the streaming does in a frame.
+#[derive(Debug, Default)]
+pub struct StreamPlan {
+    /// The chunks to start mesh tasks for, most urgent first.
+    pub dispatch: Vec<IVec2XZ>,
+    /// The chunks that left the render distance.
+    pub unloaded: Vec<IVec2XZ>,
+    /// The ones of them that were ready, their entities are despawned.
+    pub despawned_chunks: Vec<IVec2XZ>,
+    /// Chunk and task entities to despawn.
+    pub despawn: Vec<Entity>,
+    /// The chunks to mesh again at another scale, see [`ChunksLoaded::rescale`].
+    pub rescaled: Vec<IVec2XZ>,
+    pub teleported: bool,
+}
+
+/// Loads the chunks around the centre chunk and unloads the ones too far away. That's only looked at when the centre
+/// chunk changes or a rescan was asked for, the requested chunks get their tasks every frame.
+///
+/// A teleport also drops the tasks of the chunks that were left behind, so the destination doesn't wait for them.
+/// `forward` is where the camera looks, the chunks in front of it go before the ones behind.
+///
+/// While the generation is paused nothing is requested or started, but the chunks left behind still unload.
+pub fn stream_chunks(
+    chunks_loaded: &mut ChunksLoaded,
+    loader: &mut ChunkLoader,
+    center: IVec2XZ,
+    forward: Vec3,
+    generating: bool,
+) -> StreamPlan {
+    let teleported = loader.move_to(center, chunks_loaded);
+    let mut plan = StreamPlan {
+        teleported,
+        ..default()
+    };
+    if loader.take_scan(center) {
+        scan_chunks(chunks_loaded, center, generating, &mut plan);
+    }
+    if !generating {
+        return plan;
+    }
+
+    // The chunks right around the centre go first, then the spawn chunks, then the ones in front of the camera.
+    let near: HashSet<IVec2XZ> = destination(center).collect();
+    let mut requested: Vec<IVec2XZ> = chunks_loaded.requested().collect();
+    requested.sort_by_key(|&chunk_position| {
+        (
+            !near.contains(&chunk_position),
+            !chunks_loaded.is_persistent(chunk_position),
+            load_priority(chunk_position - center, forward),
+        )
+    });
+    // The tasks that are already running count against the cap, the closest chunks get the slots that are free.
+    let free = MAX_CHUNK_TASKS_IN_FLIGHT.saturating_sub(chunks_loaded.tasks_in_flight());
+    requested.truncate(loader.tasks_per_frame().min(free));
+    plan.dispatch = requested;
+
+    plan
+}
+
+/// The order chunks around the centre get their mesh tasks in, lowest first: the ones in front of the camera, then
+/// the rest, each the closest first.
+pub fn load_priority(offset: IVec2XZ, forward: Vec3) -> (bool, i32) {
+    (
+        !in_view_cone(offset, forward),
+        offset.x * offset.x + offset.z * offset.z,
+    )
+}
+
+/// Whether a chunk is roughly in front of the camera, within [`VIEW_CONE_COS`] of where it looks along the ground.
+/// The centre chunk is, and so is every chunk when the camera looks straight up or down.
+pub fn in_view_cone(offset: IVec2XZ, forward: Vec3) -> bool {
+    let look = Vec2::new(forward.x, forward.z).normalize_or_zero();
+    let direction = Vec2::new(offset.x as f32, offset.z as f32).normalize_or_zero();
+    look == Vec2::ZERO || direction == Vec2::ZERO || direction.dot(look) >= VIEW_CONE_COS
+}
 
+/// Requests the chunks in the render distance and the spawn chunks that aren't loaded yet, unless `load` is false,
+/// and unloads the ones that left it. The chunks past the [`LOD_DISTANCE`] get a lower detail, the ones that crossed
+/// it are meshed again.
+fn scan_chunks(
+    chunks_loaded: &mut ChunksLoaded,
+    center: IVec2XZ,
+    load: bool,
+    plan: &mut StreamPlan,
+) {
     // Check for differences between the chunks that are loaded and the chunks that should be loaded.
     let mut chunks_to_load: HashSet<IVec2XZ> = HashSet::new();
     let mut chunks_to_unload: HashSet<IVec2XZ> = HashSet::new();
 
-    // Get the camera position.
-    let camera_position = camera_query.single().translation;
-
-    // Calculate the player's chunk position based on their world position.
-    let player_chunk_position = IVec2XZ::new(
-        (camera_position.x / CHUNK_SIZE as f32).floor() as i32,
-        (camera_position.z / CHUNK_SIZE as f32).floor() as i32,
-    );
-
     // Calculate the radius of the sphere around the player.
-    let radius = RENDER_DISTANCE;
+    let radius = LOAD_DISTANCE;
 
     // Check for chunks to load in a circle.
     for x in -radius..=radius {
         for z in -radius..=radius {
             // Check if the chunk position is within the circle.
             if x * x + z * z <= radius * radius {
-                let chunk_position = player_chunk_position + IVec2XZ::new(x, z);
+                let chunk_position = center + IVec2XZ::new(x, z);
 
-                // Check if the chunk is already loaded.
-                if !chunks_loaded.chunks.contains(&chunk_position) {
+                // Check if the chunk is already loaded (or on its way).
+                if !chunks_loaded.is_wanted(chunk_position) {
                     // Chunk is not loaded, add it to the list of chunks to load.
                     chunks_to_load.insert(chunk_position);
                 }
@@ -360,337 +846,900 @@ pub fn chunk_system(
         }
     }
 
+    // The spawn chunks are loaded no matter where the player is.
+    chunks_to_load.extend(
+        chunks_loaded
+            .persistent()
+            .filter(|&chunk_position| !chunks_loaded.is_wanted(chunk_position)),
+    );
+
     // Check for chunks to unload in a circle.
-    for loaded_chunk_position in chunks_loaded.chunks.iter() {
-        let distance = *loaded_chunk_position - player_chunk_position;
+    for loaded_chunk_position in chunks_loaded.wanted() {
+        // Spawn chunks are never unloaded.
+        if chunks_loaded.is_persistent(loaded_chunk_position) {
+            continue;
+        }
 
-        // Check if the chunk is outside the render distance.
-        if distance.x * distance.x + distance.z * distance.z > radius * radius {
-            chunks_to_unload.insert(*loaded_chunk_position);
+        let distance = loaded_chunk_position - center;
+        let distance = distance.x * distance.x + distance.z * distance.z;
+
+        // Check if the chunk is past the unload distance. A chunk that didn't get its task yet is dropped as soon
+        // as it leaves the load distance, there's nothing to keep.
+        let requested = chunks_loaded.state(loaded_chunk_position) == Some(ChunkState::Requested);
+        if distance > UNLOAD_DISTANCE * UNLOAD_DISTANCE || requested && distance > radius * radius {
+            chunks_to_unload.insert(loaded_chunk_position);
         }
     }
 
-    // Load the chunks.
-    for chunk_position in chunks_to_load {
-        // Spawn a new task to generate chunk mesh.
-        let game_atlas = game_atlas.clone();
-        let task = task_pool.spawn(async move { create_chunk_mesh(chunk_position, game_atlas) });
-
-        // Add the task as a component to a new entity.
-        commands.spawn((
-            Name::new(format!(
-                "ChunkMesh ({}, {})",
-                chunk_position.x, chunk_position.z
-            )),
-            ComputeMeshTask(task),
-            ChunkMesh {
-                position: chunk_position,
-            },
-        ));
-
-        chunks_loaded.chunks.insert(chunk_position);
-    }
-
-    // Unload the chunks.
-    // info!("Unloading {} chunks", chunks_to_unload.len());
+    // Unload the chunks. Chunks that are still generating are despawned once their task finishes.
     for chunk_position in chunks_to_unload {
-        // Find the entity corresponding to the chunk.
-        for (entity, chunk_mesh) in chunk_query.iter_mut() {
-            if chunk_mesh.position == chunk_position {
-                // TODO: Make this async
-
-                // Remove the chunk from the loaded chunks.
-                chunks_loaded.chunks.retain(|&x| x != chunk_position);
+        if let Some(entity) = chunks_loaded.unload(chunk_position) {
+            plan.despawn.push(entity);
+            plan.despawned_chunks.push(chunk_position);
+        }
+        plan.unloaded.push(chunk_position);
+    }
 
-                // Despawn the chunk.
-                commands.entity(entity).despawn(); // TODO: Fix the warning if the chunk has been despawned already by another thread.
+    // Nobody is going to look at the chunks the teleport left behind, don't wait for them to finish.
+    if plan.teleported {
+        plan.despawn.extend(chunks_loaded.cancel_unloading());
+    }
 
-                break;
-            }
-        }
+    // Request the chunks. Chunks that were still unloading pick up their old task.
+    if !load {
+        return;
     }
+    for chunk_position in chunks_to_load {
+        chunks_loaded.request(chunk_position);
+    }
+    plan.rescaled = chunks_loaded.rescale(|chunk_position| lod_scale(chunk_position - center));
 }
 
-pub fn handle_mesh_tasks(
+/// Streams the chunks around the camera: asks for the chunks to load with [`ChunkLoadRequested`], and unloads the
+/// ones left behind, sending [`ChunkUnloaded`] for the ones that were ready. [`ChunkRescaled`] goes out for the
+/// chunks that crossed the [`LOD_DISTANCE`].
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn chunk_system(
+    mut chunks_loaded: ResMut<ChunksLoaded>,
+    (mut chunk_map, mut mesh_cache): (ResMut<ChunkMap>, ResMut<MeshCache>),
+    mut loader: ResMut<ChunkLoader>,
     mut commands: Commands,
-    mut mesh_tasks: Query<(Entity, &mut ComputeMeshTask)>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+    generating: Res<Generating>,
+    camera_mode: Res<CameraMode>,
+    player_state: Res<PlayerPos>,
+    (unloaded_query, section_query, fluid_query, packed_query): (
+        Query<(&ChunkMesh, Option<&Children>), (Without<ComputeMeshTask>, Without<Remeshed>)>,
+        Query<(
+            &ChunkSection,
+            Option<&Handle<Mesh>>,
+            Option<&Children>,
+            Option<&ChunkCollider>,
+        )>,
+        Query<(&ChunkFluid, &Handle<Mesh>, &Transform)>,
+        Query<&Handle<Mesh>, With<PackedSection>>,
+    ),
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    game_atlas: Res<GameTextureAtlas>,
+    (mut requests, mut unloaded, mut rescaled): (
+        EventWriter<ChunkLoadRequested>,
+        EventWriter<ChunkUnloaded>,
+        EventWriter<ChunkRescaled>,
+    ),
+) {
+    // Turning the generation back on looks for the chunks to load again, the player may have moved meanwhile.
+    if generating.is_changed() {
+        loader.rescan();
+    }
+
+    // Get the camera position. The freecam doesn't take the chunks with it, unless asked to.
+    let camera = camera_query.single();
+    let camera_position = camera_mode.streaming_center(camera.translation, player_state.pos);
+
+    let center = IVec2XZ::containing(camera_position);
+    // A pause only stops the loading, the chunks left behind still unload.
+    let plan = stream_chunks(
+        &mut chunks_loaded,
+        &mut loader,
+        center,
+        camera.forward(),
+        generating.0,
+    );
+    if plan.teleported {
+        info!("Teleported to chunk ({}, {})", center.x, center.z);
+    }
+    requests.send_batch(plan.dispatch.into_iter().map(ChunkLoadRequested));
+    rescaled.send_batch(plan.rescaled.into_iter().map(ChunkRescaled));
+
+    // The unloaded chunks take the meshes of their sections out of the assets with them, into the cache.
+    for &entity in &plan.despawn {
+        let Ok((chunk, children)) = unloaded_query.get(entity) else {
+            continue;
+        };
+        let Some(chunk_blocks) = chunk_map.remove_chunk(chunk.position) else {
+            continue;
+        };
+        let sections = children
+            .into_iter()
+            .flatten()
+            .filter_map(|&child| section_query.get(child).ok())
+            .map(|(section, mesh_handle, children, collider)| SectionMesh {
+                collider: collider.map(|collider| collider.0.clone()),
+                ..take_section_meshes(
+                    section.index,
+                    mesh_handle,
+                    children,
+                    (&fluid_query, &packed_query),
+                    &mut meshes,
+                )
+            })
+            .collect();
+        let meshes = ChunkMeshes {
+            sections,
+            scale: chunk.scale,
+        };
+        mesh_cache.insert(chunk.position, meshes, chunk_blocks);
+    }
+    for chunk_position in plan.unloaded {
+        // TODO: Make this async
+        chunk_map.remove_chunk(chunk_position);
+    }
+    for entity in plan.despawn {
+        commands.entity(entity).despawn_recursive();
+    }
+    unloaded.send_batch(plan.despawned_chunks.into_iter().map(ChunkUnloaded));
+
+    // Nothing is coming while the generation is paused, the player isn't held up waiting for it.
+    if generating.0 {
+        loader.check_ground(player_state.pos, &chunks_loaded);
+    } else {
+        loader.hold_player = false;
+    }
+}
+
+/// Starts the mesh tasks of the chunks the streaming asked for. A chunk that was unloaded not long ago is still
+/// cached, its task is already done. The chunks past the [`LOD_DISTANCE`] are meshed with [`lod_chunk_meshes`].
+pub fn spawn_chunk_tasks(
+    mut requests: EventReader<ChunkLoadRequested>,
+    mut commands: Commands,
     mut chunks_loaded: ResMut<ChunksLoaded>,
+    mut mesh_cache: ResMut<MeshCache>,
+    game_atlas: Res<GameTextureAtlas>,
+    generator: Res<SharedGenerator>,
+    (ambient_occlusion, packed_meshes): (Res<AmbientOcclusion>, Res<PackedMeshes>),
 ) {
-    let texture = game_atlas.0.texture.clone_weak();
+    let task_pool = AsyncComputeTaskPool::get();
+    for &ChunkLoadRequested(chunk_position) in requests.iter() {
+        // The chunks were reset or unloaded since they were asked for.
+        if chunks_loaded.state(chunk_position) != Some(ChunkState::Requested) {
+            continue;
+        }
+        let scale = chunks_loaded.scale(chunk_position).unwrap_or(1);
+        let task = match mesh_cache.take(chunk_position) {
+            // The edited chunks aren't cached, one cached at another scale can be generated again.
+            Some(cached) if cached.0.scale == scale => task_pool.spawn(async move { cached }),
+            _ if scale > 1 => {
+                let skirts = skirt_sides(chunk_position, scale, |position| {
+                    chunks_loaded.scale(position)
+                });
+                let game_atlas = game_atlas.clone();
+                let generator = generator.0.clone();
+                task_pool.spawn(async move {
+                    let chunk_blocks = generator.generate_chunk(chunk_position);
+                    let chunk_meshes = lod_chunk_meshes(
+                        &chunk_blocks,
+                        chunk_position,
+                        scale,
+                        &skirts,
+                        &game_atlas,
+                        generator.as_ref(),
+                    );
+                    (chunk_meshes, chunk_blocks)
+                })
+            }
+            _ => {
+                // Spawn a new task to generate chunk mesh.
+                let game_atlas = game_atlas.clone();
+                let generator = generator.0.clone();
+                let ambient_occlusion = ambient_occlusion.0;
+                let packed = packed_meshes.0;
+                task_pool.spawn(async move {
+                    let (mut chunk_meshes, chunk_blocks) = create_chunk_mesh(
+                        chunk_position,
+                        game_atlas.clone(),
+                        generator,
+                        ambient_occlusion,
+                    );
+                    if packed {
+                        pack_chunk_meshes(chunk_position, &mut chunk_meshes, &game_atlas);
+                    }
+                    (chunk_meshes, chunk_blocks)
+                })
+            }
+        };
 
-    for (entity, mut task) in &mut mesh_tasks {
-        if let Some(chunk_mesh) = future::block_on(future::poll_once(&mut task.0)) {
-            let chunk_mesh_handle: Handle<Mesh> = meshes.add(chunk_mesh);
+        // Add the task as a component to a new entity.
+        let task_entity = commands
+            .spawn((
+                Name::new(format!(
+                    "ChunkMesh ({}, {})",
+                    chunk_position.x, chunk_position.z
+                )),
+                ComputeMeshTask(task),
+                ChunkMesh {
+                    position: chunk_position,
+                    scale,
+                },
+                // The sections are its children, they need the transform of the chunk.
+                SpatialBundle::default(),
+            ))
+            .id();
+
+        chunks_loaded.start_generating(chunk_position, task_entity);
+    }
+}
 
-            // Get the vertices and indices from the mesh. This is needed to create the collider.
-            let (vertices, indices) = get_verts_indices(meshes.get(&chunk_mesh_handle).unwrap());
+/// Removes the meshes of a section and its fluids from the assets, back into the meshes its task made. A section
+/// with only fluids has no solid mesh, it gets an empty one. A packed solid mesh is on a child.
+#[allow(clippy::type_complexity)]
+fn take_section_meshes(
+    index: usize,
+    mesh_handle: Option<&Handle<Mesh>>,
+    children: Option<&Children>,
+    (fluid_query, packed_query): (
+        &Query<(&ChunkFluid, &Handle<Mesh>, &Transform)>,
+        &Query<&Handle<Mesh>, With<PackedSection>>,
+    ),
+    meshes: &mut Assets<Mesh>,
+) -> SectionMesh {
+    let packed_handle = children
+        .into_iter()
+        .flatten()
+        .find_map(|&child| packed_query.get(child).ok());
+    let solid_mesh = mesh_handle
+        .or(packed_handle)
+        .and_then(|handle| meshes.remove(handle))
+        .unwrap_or_else(|| ChunkMeshData::default().into_mesh());
+    let fluid_meshes = children
+        .into_iter()
+        .flatten()
+        .filter_map(|&child| {
+            let (fluid, handle, transform) = fluid_query.get(child).ok()?;
+            Some(FluidMesh {
+                block: fluid.0,
+                mesh: meshes.remove(handle)?,
+                center: transform.translation,
+            })
+        })
+        .collect();
+    SectionMesh {
+        index,
+        solid_mesh,
+        fluid_meshes,
+        collider: None,
+    }
+}
 
-            // Check if there are vertices in the mesh.
-            if vertices.is_empty() {
-                // Despawn the entity.
-                commands.entity(entity).despawn_recursive();
+/// Keeps the spawn chunks in sync with the spawn chunk radius setting.
+pub fn update_spawn_chunks(
+    settings: Res<Settings>,
+    mut chunks_loaded: ResMut<ChunksLoaded>,
+    mut loader: ResMut<ChunkLoader>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    // The new spawn chunks have to be requested.
+    loader.rescan();
 
-                break;
-            }
+    chunks_loaded
+        .set_persistent(IVec2XZ::containing(WORLD_SPAWN).within(settings.spawn_chunk_radius));
+}
 
-            // Check if every vertice is contained in at least one chunk from the chunks that are loaded.
-            // The vertices should be scaled by the chunk size.
+/// Starts meshing sections of loaded chunks again after their blocks changed. The old meshes and colliders stay until
+/// the new ones are done. A chunk that's remeshed again before that drops the older task, the new one meshes the
+/// sections it was waiting for too.
+///
+/// Chunks that are still generating are skipped, nothing could be edited in them yet. A chunk past the
+/// [`LOD_DISTANCE`] is meshed whole at its scale, whatever sections changed.
+#[allow(clippy::too_many_arguments)]
+pub fn remesh_chunks(
+    commands: &mut Commands,
+    sections: impl IntoIterator<Item = ChunkSection>,
+    remeshing: &Query<&Remeshing>,
+    chunks_loaded: &ChunksLoaded,
+    chunk_map: &ChunkMap,
+    game_atlas: &GameTextureAtlas,
+    generator: &SharedGenerator,
+    ambient_occlusion: AmbientOcclusion,
+    packed_meshes: PackedMeshes,
+) {
+    let task_pool = AsyncComputeTaskPool::get();
+    let mut chunks: Vec<(IVec2XZ, Vec<usize>)> = Vec::new();
+    for section in sections {
+        match chunks
+            .iter_mut()
+            .find(|(position, _)| *position == section.position)
+        {
+            Some((_, indices)) if indices.contains(&section.index) => {}
+            Some((_, indices)) => indices.push(section.index),
+            None => chunks.push((section.position, vec![section.index])),
+        }
+    }
+    for (chunk_position, mut indices) in chunks {
+        let entity = match chunks_loaded.state(chunk_position) {
+            Some(ChunkState::Ready { entity } | ChunkState::Persistent { entity }) => entity,
+            _ => continue,
+        };
+        if let Ok(pending) = remeshing.get(entity) {
+            let waiting: Vec<usize> = pending
+                .0
+                .iter()
+                .copied()
+                .filter(|index| !indices.contains(index))
+                .collect();
+            indices.extend(waiting);
+        }
+        let Some(chunk_blocks) = chunk_map.chunks.get(&chunk_position).cloned() else {
+            continue;
+        };
+        let scale = chunks_loaded.scale(chunk_position).unwrap_or(1);
+        if scale > 1 {
+            let skirts = skirt_sides(chunk_position, scale, |position| {
+                chunks_loaded.scale(position)
+            });
+            let game_atlas = game_atlas.clone();
+            let generator = generator.0.clone();
+            let task = task_pool.spawn(async move {
+                let chunk_meshes = lod_chunk_meshes(
+                    &chunk_blocks,
+                    chunk_position,
+                    scale,
+                    &skirts,
+                    &game_atlas,
+                    generator.as_ref(),
+                );
+                (chunk_meshes, chunk_blocks)
+            });
+            commands
+                .entity(entity)
+                .insert((ComputeMeshTask(task), Remeshing((0..SECTIONS).collect())));
+            continue;
+        }
+        // The task gets its own copy of the neighbours, the edits can go on while it runs. The diagonal ones shade
+        // the corners of the chunk.
+        let mut neighbors = ChunkMap::default();
+        neighbors.chunks = (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |z| IVec2XZ::new(x, z)))
+            .filter(|&offset| offset != IVec2XZ::new(0, 0))
+            .map(|offset| chunk_position + offset)
+            .filter_map(|position| Some((position, chunk_map.chunks.get(&position)?.clone())))
+            .collect();
 
-            let chunk_position = IVec2XZ::new(
-                (vertices[0].x / CHUNK_SIZE as f32).floor() as i32,
-                (vertices[0].z / CHUNK_SIZE as f32).floor() as i32,
+        let game_atlas = game_atlas.clone();
+        let generator = generator.0.clone();
+        let sections = indices.clone();
+        let task = task_pool.spawn(async move {
+            let (mut chunk_meshes, chunk_blocks) = remesh_chunk(
+                chunk_position,
+                sections,
+                chunk_blocks,
+                neighbors,
+                game_atlas.clone(),
+                generator,
+                ambient_occlusion.0,
             );
+            if packed_meshes.0 {
+                pack_chunk_meshes(chunk_position, &mut chunk_meshes, &game_atlas);
+            }
+            (chunk_meshes, chunk_blocks)
+        });
+        commands
+            .entity(entity)
+            .insert((ComputeMeshTask(task), Remeshing(indices)));
+    }
+}
+
+/// Meshes every section of the chunks that were rescaled again, at their new scale.
+#[allow(clippy::too_many_arguments)]
+pub fn remesh_rescaled_chunks(
+    mut commands: Commands,
+    mut rescaled: EventReader<ChunkRescaled>,
+    remeshing: Query<&Remeshing>,
+    (chunks_loaded, chunk_map): (Res<ChunksLoaded>, Res<ChunkMap>),
+    game_atlas: Res<GameTextureAtlas>,
+    generator: Res<SharedGenerator>,
+    (ambient_occlusion, packed_meshes): (Res<AmbientOcclusion>, Res<PackedMeshes>),
+) {
+    let sections: Vec<ChunkSection> = rescaled
+        .iter()
+        .flat_map(|&ChunkRescaled(chunk_position)| ChunkSection::all(chunk_position))
+        .collect();
+    if sections.is_empty() {
+        return;
+    }
+    remesh_chunks(
+        &mut commands,
+        sections,
+        &remeshing,
+        &chunks_loaded,
+        &chunk_map,
+        &game_atlas,
+        &generator,
+        *ambient_occlusion,
+        *packed_meshes,
+    );
+}
 
-            // Check if this chunk position is even loaded
-            if !chunks_loaded.chunks.contains(&chunk_position) {
-                // warn!("Prevented crash!");
+/// Starts the catch-up budget when the generation is resumed or the window gets the focus back, and counts it down.
+pub fn catch_up_after_pauses(
+    time: Res<Time>,
+    generating: Res<Generating>,
+    mut focused: EventReader<WindowFocused>,
+    mut budget: ResMut<MeshInsertBudget>,
+) {
+    let resumed = generating.is_changed() && !generating.is_added() && generating.0;
+    let refocused = focused.iter().any(|event| event.focused);
+    if resumed || refocused {
+        budget.start_catch_up();
+    } else {
+        budget.tick(time.delta());
+    }
+}
 
-                // Despawn the entity.
-                commands.entity(entity).despawn_recursive();
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn handle_mesh_tasks(
+    mut commands: Commands,
+    mut mesh_tasks: Query<(
+        Entity,
+        &mut ChunkMesh,
+        &mut ComputeMeshTask,
+        Option<&Remeshing>,
+        Option<&Children>,
+    )>,
+    sections: Query<(&ChunkSection, Option<&Handle<Mesh>>, Option<&Children>)>,
+    (mut fluids, packed_sections): (
+        Query<(&ChunkFluid, &Handle<Mesh>, &mut Transform)>,
+        Query<&Handle<Mesh>, With<PackedSection>>,
+    ),
+    mut meshes: ResMut<Assets<Mesh>>,
+    chunk_materials: Res<ChunkMaterials>,
+    mut chunks_loaded: ResMut<ChunksLoaded>,
+    mut chunk_map: ResMut<ChunkMap>,
+    (player_state, mut budget): (Res<PlayerPos>, ResMut<MeshInsertBudget>),
+    (mut ready, mut rescaled): (EventWriter<ChunkReady>, EventWriter<ChunkRescaled>),
+) {
+    let start = Instant::now();
+    // The finished tasks that don't fit in the budget are left alone, they're still finished next frame.
+    let player = IVec2XZ::containing(player_state.pos);
+    let mut finished: Vec<(Entity, (bool, i32))> = mesh_tasks
+        .iter()
+        .filter(|(_, _, task, ..)| task.0.is_finished())
+        .map(|(entity, chunk, _, remeshing, _)| {
+            let priority = insert_priority(chunk.position, remeshing.is_some(), player);
+            (entity, priority)
+        })
+        .collect();
+    finished.sort_by_key(|&(_, priority)| priority);
+
+    let mut inserted = 0;
+    budget.backlog = 0;
+    for (index, &(entity, _)) in finished.iter().enumerate() {
+        if !budget.allows(inserted, start.elapsed()) {
+            budget.backlog = finished.len() - index;
+            break;
+        }
+        let Ok((entity, mut chunk, mut task, remeshing, children)) = mesh_tasks.get_mut(entity)
+        else {
+            continue;
+        };
+        let Some((chunk_meshes, chunk_blocks)) = future::block_on(future::poll_once(&mut task.0))
+        else {
+            continue;
+        };
+
+        // Task is complete, so remove task component from entity
+        commands.entity(entity).remove::<ComputeMeshTask>();
+
+        let remeshed = remeshing.is_some();
+        if remeshed {
+            commands
+                .entity(entity)
+                .remove::<Remeshing>()
+                .insert(Remeshed);
+        } else if !chunks_loaded.finish(chunk.position, entity) {
+            // The chunk left the render distance while it was generating.
+            commands.entity(entity).despawn_recursive();
+            continue;
+        } else if chunks_loaded.scale(chunk.position) != Some(chunk_meshes.scale) {
+            // It crossed the LOD distance while it was generating.
+            rescaled.send(ChunkRescaled(chunk.position));
+        }
+        inserted += 1;
+        chunk.scale = chunk_meshes.scale;
+
+        // A section that's there already is updated in place, the sections that are empty now go away.
+        let near = needs_collider(chunk.position, player_state.pos);
+        for section_mesh in chunk_meshes.sections {
+            let origin = ChunkSection {
+                position: chunk.position,
+                index: section_mesh.index,
+            }
+            .origin();
+            let old = children.into_iter().flatten().find_map(|&child| {
+                let (section, mesh_handle, fluid_children) = sections.get(child).ok()?;
+                (section.index == section_mesh.index).then_some((
+                    child,
+                    mesh_handle,
+                    fluid_children,
+                ))
+            });
+            if section_mesh.is_empty() {
+                if let Some((child, ..)) = old {
+                    commands.entity(child).despawn_recursive();
+                }
+                continue;
+            }
+            let (section, mesh_handle, fluid_children) = match old {
+                Some((child, mesh_handle, fluid_children)) => (child, mesh_handle, fluid_children),
+                None => {
+                    let section = commands
+                        .spawn((
+                            Name::new(format!("Section {}", section_mesh.index)),
+                            ChunkSection {
+                                position: chunk.position,
+                                index: section_mesh.index,
+                            },
+                            // The fluids are a child, they need the transform of the section even if it has no
+                            // other faces.
+                            SpatialBundle::default(),
+                        ))
+                        .id();
+                    commands.entity(entity).add_child(section);
+                    (section, None, None)
+                }
+            };
+            insert_section_mesh(
+                &mut commands,
+                (section, origin),
+                section_mesh,
+                (mesh_handle, fluid_children),
+                (&mut fluids, &packed_sections),
+                &mut meshes,
+                &chunk_materials,
+                near,
+            );
+        }
+        // The blocks of a remeshed chunk are already in the map, and may have been edited again since.
+        if !remeshed {
+            chunk_map.insert_chunk(chunk.position, chunk_blocks);
+            ready.send(ChunkReady {
+                position: chunk.position,
+                entity,
+            });
+        }
+    }
+    if inserted > 0 {
+        debug!(
+            "Finished {} mesh tasks in {:?} on the main thread, {} left for the next frame",
+            inserted,
+            start.elapsed(),
+            budget.backlog
+        );
+    }
+}
 
-                // Remove the chunk from the loaded chunks.
-                chunks_loaded.chunks.retain(|&x| x != chunk_position);
+/// Puts the meshes and the collider of a section on its entity, swapping the old ones of a remeshed section in
+/// place so they're drawn until then. A mesh that changed gets its bounds computed again, for the culling.
+///
+/// A packed solid mesh goes on a child at the `origin` of the section instead, with the bounds of the whole
+/// section. Bevy can't compute them without the positions.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn insert_section_mesh(
+    commands: &mut Commands,
+    (section, origin): (Entity, IVec3),
+    section_mesh: SectionMesh,
+    (mesh_handle, children): (Option<&Handle<Mesh>>, Option<&Children>),
+    (fluids, packed_sections): (
+        &mut Query<(&ChunkFluid, &Handle<Mesh>, &mut Transform)>,
+        &Query<&Handle<Mesh>, With<PackedSection>>,
+    ),
+    meshes: &mut Assets<Mesh>,
+    chunk_materials: &ChunkMaterials,
+    near: bool,
+) {
+    let SectionMesh {
+        solid_mesh,
+        fluid_meshes,
+        collider,
+        ..
+    } = section_mesh;
+
+    // Sections with only fluids don't have a solid mesh.
+    let (solid_mesh, packed_mesh) = match solid_mesh.count_vertices() > 0 {
+        true if is_packed(&solid_mesh) => (None, Some(solid_mesh)),
+        true => (Some(solid_mesh), None),
+        false => (None, None),
+    };
+    match (
+        mesh_handle.and_then(|handle| meshes.get_mut(handle)),
+        solid_mesh,
+    ) {
+        (Some(mesh), Some(solid_mesh)) => {
+            *mesh = solid_mesh;
+            commands.entity(section).remove::<Aabb>();
+        }
+        (Some(_), None) => {
+            commands.entity(section).remove::<(Handle<Mesh>, Aabb)>();
+        }
+        (None, Some(solid_mesh)) => {
+            commands.entity(section).insert(PbrBundle {
+                mesh: meshes.add(solid_mesh),
+                material: chunk_materials.solid.clone(),
+                ..Default::default()
+            });
+        }
+        (None, None) => {}
+    }
+    let old_packed = children.into_iter().flatten().find_map(|&child| {
+        let handle = packed_sections.get(child).ok()?;
+        Some((child, handle))
+    });
+    match (old_packed, packed_mesh) {
+        (Some((_, handle)), Some(packed_mesh)) => {
+            if let Some(mesh) = meshes.get_mut(handle) {
+                *mesh = packed_mesh;
+            }
+        }
+        (Some((child, _)), None) => {
+            commands.entity(child).despawn_recursive();
+        }
+        (None, Some(packed_mesh)) => {
+            let size = Vec3::new(CHUNK_SIZE as f32, SECTION_HEIGHT as f32, CHUNK_SIZE as f32);
+            let child = commands
+                .spawn((
+                    Name::new("Packed"),
+                    PackedSection,
+                    MaterialMeshBundle {
+                        mesh: meshes.add(packed_mesh),
+                        material: chunk_materials.packed.clone(),
+                        transform: Transform::from_translation(origin.as_vec3()),
+                        ..default()
+                    },
+                    Aabb::from_min_max(Vec3::ZERO, size),
+                ))
+                .id();
+            commands.entity(section).add_child(child);
+        }
+        (None, None) => {}
+    }
 
-                break;
-            } else {
-                commands
-                    .entity(entity)
-                    .insert(PbrBundle {
-                        mesh: chunk_mesh_handle,
-                        material: materials.add(StandardMaterial {
-                            base_color_texture: Some(texture.clone()),
-                            metallic: 1.,
-                            reflectance: 1.,
+    // Every fluid is a child of the section. A remeshed section swaps their meshes in place too.
+    let mut old_fluids: Vec<(Entity, BlockType)> = children
+        .into_iter()
+        .flatten()
+        .filter_map(|&child| Some((child, fluids.get(child).ok()?.0 .0)))
+        .collect();
+    for fluid in fluid_meshes {
+        match old_fluids
+            .iter()
+            .position(|&(_, block)| block == fluid.block)
+        {
+            Some(index) => {
+                let (child, _) = old_fluids.swap_remove(index);
+                let (_, handle, mut transform) = fluids.get_mut(child).unwrap();
+                if let Some(mesh) = meshes.get_mut(handle) {
+                    *mesh = fluid.mesh;
+                }
+                transform.translation = fluid.center;
+                commands.entity(child).remove::<Aabb>();
+            }
+            None => {
+                let child = commands
+                    .spawn((
+                        Name::new(format!("{:?}", fluid.block)),
+                        ChunkFluid(fluid.block),
+                        MaterialMeshBundle {
+                            mesh: meshes.add(fluid.mesh),
+                            material: chunk_materials.fluid(fluid.block),
+                            transform: Transform::from_translation(fluid.center),
                             ..default()
-                        }),
-                        ..Default::default()
-                    })
-                    .insert(Collider::trimesh(vertices, indices));
+                        },
+                    ))
+                    .id();
+                commands.entity(section).add_child(child);
+            }
+        }
+    }
+    // The fluids that are gone.
+    for (child, _) in old_fluids {
+        commands.entity(child).despawn_recursive();
+    }
 
-                // Task is complete, so remove task component from entity
-                commands.entity(entity).remove::<ComputeMeshTask>();
+    // Only the sections around the player get their collider, the others keep it until the player comes close.
+    match collider {
+        Some(collider) => {
+            if near {
+                commands.entity(section).insert(collider.clone());
             }
+            commands.entity(section).insert(ChunkCollider(collider));
+        }
+        None => {
+            commands
+                .entity(section)
+                .remove::<(Collider, ChunkCollider)>();
         }
     }
 }
 
-fn surface_generation(pos: IVec3, perlin: &Perlin) -> BlockType {
-    // Sample the noise function at the scaled position.
-    // The perlin noise needs a float value, so we need to cast the scaled position to a float.
-
-    // 2d perlin noise
-    // let noise_value = perlin.get([pos.x as f64 * SURFACE_SCALE, pos.z as f64 * SURFACE_SCALE]);
-    // to make the terrain even more interesting, we can add more octaves of noise
-
-    let noise_values = vec![
-        perlin.get([
-            pos.x as f64 * 2. * SURFACE_SCALE,
-            pos.z as f64 * 2. * SURFACE_SCALE,
-        ]),
-        perlin.get([
-            pos.x as f64 * 4. * SURFACE_SCALE,
-            pos.z as f64 * 4. * SURFACE_SCALE,
-        ]),
-        perlin.get([
-            pos.x as f64 * 6. * SURFACE_SCALE,
-            pos.z as f64 * 6. * SURFACE_SCALE,
-        ]),
-    ];
-    // add all the noise values together
-    let noise_value = noise_values.iter().fold(0., |acc, &x| acc + x);
-    // let noise_value32 = noise_value as f32;
-
-    // Change values (-1, 1) -> (TERRAIN_HEIGHT, MAX_HEIGHT)
-    let cieling_margin = 100; // 140 blocks from height limit
-    let max_height = CHUNK_HEIGHT - cieling_margin;
-    let height = remap(
-        noise_value as f32,
-        -1., //-1.
-        6.,  //1.
-        BLEND_HEIGHT as f32,
-        max_height as f32,
-    );
+/// The order finished chunks are put into the world in, lowest first. Remeshed chunks go first, the player is
+/// waiting to see their edit, then the closest ones.
+pub fn insert_priority(chunk_position: IVec2XZ, remeshed: bool, player: IVec2XZ) -> (bool, i32) {
+    let distance = chunk_position - player;
+    (!remeshed, distance.x * distance.x + distance.z * distance.z)
+}
 
-    // calculate block type given block position and height
-    match pos.y {
-        y if y == 0 => BlockType::Bedrock,
-        // y if y + 3 < height as i32 => BlockType::Stone,
-        y if y + 3 < height as i32 => cave_block(pos),
-        y if y < height as i32 && !(y > 63 && y < 72) && y > 64 => BlockType::Dirt,
-        y if y == height as i32 && !(y > 63 && y < 72) && y > 64 => BlockType::Grass,
-        // y if y <= height as i32 && y == 6 => cave_generation(pos, perlin, true),
-        y if !y <= height as i32 && y < 64 => BlockType::Stone,
-        y if y <= height as i32 && (y > 63 && y < 72) => BlockType::Sand,
-        y if !y <= height as i32 && y == 64 => BlockType::Sand,
-        // y if y > 64 && y <= WATER_HEIGHT as i32 => BlockType::Water,
-        y if !y <= height as i32 && y <= WATER_HEIGHT as i32 => BlockType::Water,
-        _ => BlockType::Air,
-    }
-}
-
-fn cave_generation(pos: IVec3, perlin: &Perlin) -> BlockType {
-    // Sample the noise function at the scaled position.
-    // The perlin noise needs a float value, so we need to cast the scaled position to a float.
-
-    // 3d perlin noise
-    let cave_noise_value = perlin.get([
-        pos.x as f64 * CAVE_SCALE,
-        pos.y as f64 * CAVE_SCALE,
-        pos.z as f64 * CAVE_SCALE,
-    ]);
-
-    // //
-    let noise_values = vec![
-        perlin.get([
-            pos.x as f64 * 2. * SURFACE_SCALE,
-            pos.z as f64 * 2. * SURFACE_SCALE,
-        ]),
-        perlin.get([
-            pos.x as f64 * 4. * SURFACE_SCALE,
-            pos.z as f64 * 4. * SURFACE_SCALE,
-        ]),
-        perlin.get([
-            pos.x as f64 * 6. * SURFACE_SCALE,
-            pos.z as f64 * 6. * SURFACE_SCALE,
-        ]),
-    ];
-    // add all the noise values together
-    let noise_value = noise_values.iter().fold(0., |acc, &x| acc + x);
-    // let noise_value32 = noise_value as f32;
-
-    // Change values (-1, 1) -> (TERRAIN_HEIGHT, MAX_HEIGHT)
-    let cieling_margin = 100; // 140 blocks from height limit
-    let max_height = CHUNK_HEIGHT - cieling_margin;
-    let height = remap(
-        noise_value as f32,
-        -1., //-1.
-        6.,  //1.
-        BLEND_HEIGHT as f32,
-        max_height as f32,
-    );
+/// Whether a chunk is close enough to the player to need a collider.
+pub fn needs_collider(chunk_position: IVec2XZ, player: Vec3) -> bool {
+    let distance = chunk_position - IVec2XZ::containing(player);
+    distance.x * distance.x + distance.z * distance.z <= COLLIDER_DISTANCE * COLLIDER_DISTANCE
+}
 
-    let no_ocean: bool = pos.y + 10 < height as i32;
-    // //
+/// The collider of a chunk, built from its mesh. The fluids aren't in it, and the plants are left out, so the
+/// player sinks into fluids and walks through plants. None if there's nothing to collide with.
+pub fn chunk_collider(
+    mesh: &Mesh,
+    chunk_position: IVec2XZ,
+    blocks: &ChunkBlocks,
+) -> Option<Collider> {
+    let (vertices, indices) = collider_geometry(mesh, chunk_position, blocks);
+    (!indices.is_empty()).then(|| Collider::trimesh(vertices, indices))
+}
 
-    if cave_noise_value < CAVE_THRESHOLD || !no_ocean {
-        if !(cave_noise_value < CAVE_THRESHOLD || pos.y > 62 && pos.y < 70) {
-            BlockType::Air
-        } else {
-            cave_block(pos)
+/// Gives the sections of the chunks that came close to the player the colliders their mesh tasks built, and takes
+/// them away from the chunks that are too far now. That's only looked at when the player moves into another chunk,
+/// [`handle_mesh_tasks`] gives the chunks that finish near the player theirs.
+///
+/// It runs in the same frame as the streaming, the chunk the player is in has its collider before the player is
+/// let go by [`ChunkLoader::check_ground`].
+pub fn update_chunk_colliders(
+    mut commands: Commands,
+    section_query: Query<(
+        Entity,
+        &ChunkSection,
+        Option<&ChunkCollider>,
+        Option<&Collider>,
+    )>,
+    player_state: Res<PlayerPos>,
+    mut player_chunk: Local<Option<IVec2XZ>>,
+) {
+    let center = IVec2XZ::containing(player_state.pos);
+    if *player_chunk == Some(center) {
+        return;
+    }
+    *player_chunk = Some(center);
+
+    for (entity, section, chunk_collider, collider) in &section_query {
+        let near = needs_collider(section.position, player_state.pos);
+        match (chunk_collider, collider) {
+            (_, Some(_)) if !near => {
+                commands.entity(entity).remove::<Collider>();
+            }
+            (Some(chunk_collider), None) if near => {
+                commands.entity(entity).insert(chunk_collider.0.clone());
+            }
+            _ => {}
         }
-    } else {
-        BlockType::Air
-    }
-}
-
-fn cave_block(pos: IVec3) -> BlockType {
-    let ore_perlin = Perlin::new(SEED);
-    let noise_ore_generation = ore_perlin.get([
-        pos.x as f64 * ORE_SCALE,
-        pos.y as f64 * ORE_SCALE,
-        pos.z as f64 * ORE_SCALE,
-    ]);
-
-    // Check if the noise value is above the threshhold
-    // if (0.01..0.9).contains(&noise_bedrock_generation) && pos.y <= 4 {
-    if DIAMOND_THRESHOLD.contains(&noise_ore_generation) && pos.y <= 16 {
-        BlockType::DiamondOre
-    } else if REDSTONE_THRESHOLD.contains(&noise_ore_generation) && pos.y <= 28 && pos.y >= 6 {
-        BlockType::RedstoneOre
-    } else if GOLD_THRESHOLD.contains(&noise_ore_generation) && pos.y <= 24 && pos.y >= 6 {
-        BlockType::GoldOre
-    } else if IRON_THRESHOLD.contains(&noise_ore_generation) && pos.y <= 70 && pos.y >= 8 {
-        BlockType::IronOre
-    } else if COAL_THRESHOLD.contains(&noise_ore_generation) && pos.y <= 78 && pos.y >= 24 {
-        BlockType::CoalOre
-    } else {
-        BlockType::Stone
     }
 }
 
-fn is_block(pos: IVec3, perlin: &Perlin) -> BlockType {
-    // is blocks
-
-    // limit the world size because it will start breaking at extreme distances
-    let no_border = true;
-    let border = 128; // i32::MAX
-    if (pos.x >= border || pos.x < -border || pos.z >= border || pos.z < -border) && !no_border {
-        return BlockType::Air;
-    }
+/// The materials every chunk shares, one for the solid blocks and one for each fluid. Sharing them lets the
+/// renderer batch the chunks, and there's nothing to free when a chunk is unloaded.
+#[derive(Resource, Clone)]
+pub struct ChunkMaterials {
+    pub solid: Handle<StandardMaterial>,
+    /// The solid blocks of the sections that were packed.
+    pub packed: Handle<PackedChunkMaterial>,
+    pub water: Handle<FluidMaterial>,
+    pub lava: Handle<FluidMaterial>,
+}
 
-    // Limit the world sky
-    if pos.y >= 255 {
-        return BlockType::Air;
+impl ChunkMaterials {
+    pub fn new(
+        atlas: &GameTextureAtlas,
+        materials: &mut Assets<StandardMaterial>,
+        packed_materials: &mut Assets<PackedChunkMaterial>,
+        fluid_materials: &mut Assets<FluidMaterial>,
+    ) -> Self {
+        Self {
+            solid: materials.add(solid_material(&atlas.atlas.texture)),
+            packed: packed_materials.add(PackedChunkMaterial::new(atlas)),
+            water: fluid_materials.add(fluid_material(BlockType::Water, atlas)),
+            lava: fluid_materials.add(fluid_material(BlockType::Lava, atlas)),
+        }
     }
 
-    // Set bedrock
-    if pos.y == 0 {
-        return BlockType::Bedrock;
+    /// The material of a fluid's mesh.
+    pub fn fluid(&self, block: BlockType) -> Handle<FluidMaterial> {
+        match block {
+            BlockType::Water => self.water.clone(),
+            _ => self.lava.clone(),
+        }
     }
 
-    // // Tests
-    // if pos.y == 0 {
-    //     return BlockType::Bedrock;
-    // } else {
-    //     let surface_block = surface_generation(pos, perlin);
-    //     surface_block
-    // }
-
-    // Generate the 2d surface block. If it's a block, check if a cave should be generated.
-    // Lava on air blocks below
-    let surface_block = surface_generation(pos, perlin);
-    if surface_block != BlockType::Air {
-        let cave_block = cave_generation(pos, perlin);
-        if cave_block == BlockType::Air {
-            if pos.y <= LAVA_HEIGHT as i32 {
-                BlockType::Lava
-            } else {
-                // TODO: Don't spawn caves between BLEND_HEIGHT and below WATER_HEIGHT
-                cave_block
-            }
-        } else {
-            surface_block
-        }
-    } else {
-        surface_block
+    pub fn fluids(&self) -> [&Handle<FluidMaterial>; 2] {
+        [&self.water, &self.lava]
     }
 }
 
-/// The function that is used to interpolate between the noise values.
+/// The material of the solid blocks of the chunks. The plants are cut out of their tiles.
 ///
-/// This function is used to make caves and land coexist. It's a smooth linear line from 0 to 256.
-/// TODO: Implement this into is_block in a way that makes sense.
-// fn noise_interpolation(y: i32) -> i32 {
-//     // Linear interpolation
-//     (y as f32 * 256.0 / CHUNK_HEIGHT as f32) as i32
-// }
+/// The vertex colors, the ambient occlusion and the biome tints, are multiplied into the texture. The standard
+/// material does that for every mesh that has them.
+pub fn solid_material(texture: &Handle<Image>) -> StandardMaterial {
+    StandardMaterial {
+        base_color_texture: Some(texture.clone()),
+        metallic: 1.,
+        reflectance: 1.,
+        alpha_mode: AlphaMode::Mask(0.5),
+        ..default()
+    }
+}
 
-/// Remaps a value from one range to another.
-fn remap(value: f32, from_min: f32, from_max: f32, to_min: f32, to_max: f32) -> f32 {
-    (value - from_min) / (from_max - from_min) * (to_max - to_min) + to_min
+/// The material of a fluid, scrolling through the tile of its texture. Water is see-through and tinted blue, and
+/// drawn from both sides so its surface can be seen from under water. Lava is opaque, and glows as bright as it
+/// [emits light](BlockProperties::emits_light) so it can be seen in the dark.
+pub fn fluid_material(block: BlockType, atlas: &GameTextureAtlas) -> FluidMaterial {
+    let uvs = atlas.tile_uvs(atlas.tiles(block, BlockFace::Top)[0]);
+    let glow = block.properties().emits_light.unwrap_or(0.0);
+    let material = FluidMaterial {
+        texture: atlas.atlas.texture.clone(),
+        tile: Rect::from_corners(uvs[0].into(), uvs[2].into()),
+        emissive: Color::rgb(glow, glow, glow),
+        metallic: 1.,
+        reflectance: 1.,
+        ..default()
+    };
+    match block {
+        BlockType::Water => FluidMaterial {
+            base_color: WATER_TINT,
+            scroll: WATER_SCROLL,
+            alpha_mode: AlphaMode::Blend,
+            double_sided: true,
+            ..material
+        },
+        _ => FluidMaterial {
+            scroll: LAVA_SCROLL,
+            ..material
+        },
+    }
 }
 
 // Got this from bevy discord
 // https://discord.com/channels/691052431525675048/1015147097458212864/1015147294804430848
+//
+// The positions of a packed mesh are unpacked, they're relative to the corner of its section.
 pub fn get_verts_indices(mesh: &Mesh) -> (Vec<Vec3>, Vec<[u32; 3]>) {
-    let vertices = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
-        None => panic!("Mesh does not contain vertex positions"),
-        Some(vertex_values) => match &vertex_values {
-            VertexAttributeValues::Float32x3(positions) => positions
-                .iter()
-                .map(|[x, y, z]| Vec3::new(*x, *y, *z))
-                .collect(),
-            _ => panic!("Unexpected types in {:?}", Mesh::ATTRIBUTE_POSITION),
-        },
+    let vertices = match (
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+        mesh.attribute(ATTRIBUTE_PACKED),
+    ) {
+        (Some(VertexAttributeValues::Float32x3(positions)), _) => positions
+            .iter()
+            .map(|[x, y, z]| Vec3::new(*x, *y, *z))
+            .collect(),
+        (None, Some(VertexAttributeValues::Uint32x2(vertices))) => vertices
+            .iter()
+            .map(|&vertex| PackedVertex::unpack(vertex).position.as_vec3())
+            .collect(),
+        (None, None) => panic!("Mesh does not contain vertex positions"),
+        _ => panic!("Unexpected types in {:?}", Mesh::ATTRIBUTE_POSITION),
     };
 
     let indices = match mesh.indices().unwrap() {
-        Indices::U16(_) => {
-            panic!("expected u32 indices");
-        }
+        Indices::U16(indices) => indices
+            .chunks(3)
+            .map(|chunk| [chunk[0], chunk[1], chunk[2]].map(u32::from))
+            .collect(),
         Indices::U32(indices) => indices
             .chunks(3)
             .map(|chunk| [chunk[0], chunk[1], chunk[2]])
@@ -698,3 +1747,42 @@ pub fn get_verts_indices(mesh: &Mesh) -> (Vec<Vec3>, Vec<[u32; 3]>) {
     };
     (vertices, indices)
 }
+
+/// The collider geometry of a chunk mesh: its triangles, except for the ones of blocks you can't stand on.
+pub fn collider_geometry(
+    mesh: &Mesh,
+    chunk_position: IVec2XZ,
+    blocks: &ChunkBlocks,
+) -> (Vec<Vec3>, Vec<[u32; 3]>) {
+    let (vertices, indices) = get_verts_indices(mesh);
+    let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    else {
+        panic!("Mesh does not contain vertex normals");
+    };
+
+    let origin = IVec3::new(
+        chunk_position.x * CHUNK_SIZE as i32,
+        0,
+        chunk_position.z * CHUNK_SIZE as i32,
+    );
+    let indices = indices
+        .into_iter()
+        .filter(|triangle| {
+            // Step back from the middle of the quad into the block it belongs to. The middle of the quad is halfway
+            // along the longest edge of the triangle, the diagonal. The lowered fluid tops still land in the fluid's
+            // cell, crosses in their own, and the faces of a slab or a step in the middle of a cell in that cell.
+            let corners = triangle.map(|i| vertices[i as usize]);
+            let center = (0..3)
+                .map(|i| (corners[i], corners[(i + 1) % 3]))
+                .max_by(|a, b| a.0.distance(a.1).total_cmp(&b.0.distance(b.1)))
+                .map(|(a, b)| (a + b) / 2.0)
+                .unwrap();
+            let normal = Vec3::from(normals[triangle[0] as usize]);
+            let local = (center - normal * 0.25).floor().as_ivec3() - origin;
+            blocks
+                .get(local.x as usize, local.y as usize, local.z as usize)
+                .is_solid()
+        })
+        .collect();
+    (vertices, indices)
+}
@@ -1,58 +1,849 @@
-use std::{collections::HashSet, ops::Range};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ops::RangeInclusive,
+    sync::Mutex,
+    time::Duration,
+};
 
 use bevy::{ecs::event::ManualEventReader, input::mouse::MouseMotion, tasks::Task};
+use serde::{Deserialize, Serialize};
 
+use crate::game::atlas::MISSING_TEXTURE;
 use crate::prelude::*;
 
 pub const RENDER_DISTANCE: i32 = 8;
-pub const SEED: u32 = 2137;
+/// The chunks within the load distance of the player are loaded, and they're only unloaded again past the unload
+/// distance. Walking back and forth over a chunk border doesn't unload and load the same chunks over and over.
+pub const LOAD_DISTANCE: i32 = RENDER_DISTANCE;
+pub const UNLOAD_DISTANCE: i32 = LOAD_DISTANCE + 2;
+/// How many chunks away from the player the chunks get colliders. Nothing further away can be touched.
+pub const COLLIDER_DISTANCE: i32 = 3;
+/// Past this many chunks from the player the chunks are meshed [`LOD_SCALE`] times coarser, with a block for every
+/// cube of that many blocks on a side.
+pub const LOD_DISTANCE: i32 = 5;
+pub const LOD_SCALE: usize = 2;
 
 pub const CHUNK_SIZE: usize = 16;
 pub const CHUNK_HEIGHT: usize = 256;
+/// Every chunk is meshed in sections of 16x16x16 blocks, stacked from the bottom of the world.
+pub const SECTION_HEIGHT: usize = 16;
+pub const SECTIONS: usize = CHUNK_HEIGHT / SECTION_HEIGHT;
 
-pub const SURFACE_SCALE: f64 = 0.004; //0.008
-                                      // pub const BLEND_SCALE: f64 = 0.02; //0.02
+/// The frequency of the first octave of the surface, how close together the hills are.
+pub const SURFACE_SCALE: f64 = 0.008;
+// pub const BLEND_SCALE: f64 = 0.02; //0.02
+/// The frequency of the cave noise, unless it's tuned in `WorldGenParams` like the other scales.
 pub const CAVE_SCALE: f64 = 0.06; //0.06
-pub const ORE_SCALE: f64 = 0.1;
+/// Biomes are a few hundred blocks across.
+pub const BIOME_SCALE: f64 = 0.002;
 
-pub const DIAMOND_THRESHOLD: Range<f64> = 0.0..0.002;
-pub const REDSTONE_THRESHOLD: Range<f64> = 0.45..0.48;
-pub const GOLD_THRESHOLD: Range<f64> = 0.38..0.4;
-pub const IRON_THRESHOLD: Range<f64> = 0.44..0.48;
-pub const COAL_THRESHOLD: Range<f64> = 0.58..0.7;
+/// How one ore is spread through the stone: how many veins a chunk gets, how big they are and their heights.
+pub struct OreVeins {
+    pub ore: BlockType,
+    pub veins_per_chunk: u32,
+    pub sizes: RangeInclusive<u32>,
+    pub heights: RangeInclusive<i32>,
+}
+
+/// Every ore. Where veins overlap, the first ore placed wins.
+pub const ORES: [OreVeins; 5] = [
+    OreVeins {
+        ore: BlockType::DiamondOre,
+        veins_per_chunk: 1,
+        sizes: 3..=6,
+        heights: 0..=16,
+    },
+    OreVeins {
+        ore: BlockType::RedstoneOre,
+        veins_per_chunk: 6,
+        sizes: 4..=8,
+        heights: 0..=15,
+    },
+    OreVeins {
+        ore: BlockType::GoldOre,
+        veins_per_chunk: 2,
+        sizes: 3..=8,
+        heights: 6..=24,
+    },
+    OreVeins {
+        ore: BlockType::IronOre,
+        veins_per_chunk: 12,
+        sizes: 4..=9,
+        heights: 8..=70,
+    },
+    OreVeins {
+        ore: BlockType::CoalOre,
+        veins_per_chunk: 16,
+        sizes: 6..=12,
+        heights: 24..=78,
+    },
+];
 
 pub const LAVA_HEIGHT: usize = 10;
+/// Bedrock is between 1 and this many blocks thick, depending on the column.
+pub const BEDROCK_MAX_THICKNESS: i32 = 4;
 pub const WATER_HEIGHT: usize = 69;
+/// Grass above this height is snowed over, whatever the biome. Only the highest peaks reach it, unless the world
+/// is amplified.
+pub const SNOW_HEIGHT: usize = 100;
+/// Rivers follow the lines where the river noise is zero, a few hundred blocks apart.
+pub const RIVER_SCALE: f64 = 0.0015;
+/// How far from zero the river noise carves a flat riverbed, and how much farther the valley sides reach.
+pub const RIVER_CHANNEL: f64 = 0.02;
+pub const RIVER_VALLEY: f64 = 0.06;
+/// The top of the riverbed, a few blocks below the water.
+pub const RIVER_BED_HEIGHT: usize = 65;
 pub const CAVE_THRESHOLD: f64 = 0.32; //0.32
-                                      // pub const CAVE_THRESHOLD_SURFACE: f64 = 0.2; //0.32 // TODO: Dokonczyc to
+/// The cave threshold right under the surface, lower so that some tunnels break through to the open.
+pub const CAVE_THRESHOLD_SURFACE: f64 = 0.26;
+/// How many blocks below the surface the threshold starts ramping from `CAVE_THRESHOLD` to `CAVE_THRESHOLD_SURFACE`.
+pub const CAVE_ENTRANCE_DEPTH: f32 = 16.0;
 pub const BLEND_HEIGHT: usize = 63; //85
+/// The patch noise makes blobs of gravel above `PATCH_THRESHOLD` and of dirt below `-PATCH_THRESHOLD`, in the stone.
+pub const PATCH_SCALE: f64 = 0.04;
+pub const PATCH_THRESHOLD: f64 = 0.5;
+/// Every region of `RAVINE_REGION` by `RAVINE_REGION` blocks has a ravine with a chance of `RAVINE_CHANCE`.
+pub const RAVINE_REGION: i32 = 256;
+pub const RAVINE_CHANCE: f32 = 0.25;
+/// How wide ravines get at most, at the top of their middle.
+pub const RAVINE_WIDTH: f32 = 10.0;
+/// How long ravines are, shorter than a region so they only reach into the regions right next to theirs.
+pub const RAVINE_LENGTH: RangeInclusive<f32> = 80.0..=160.0;
+/// How deep the floor in the middle of a ravine goes.
+pub const RAVINE_FLOOR: RangeInclusive<f32> = 6.0..=30.0;
+pub const POND_CHANCE: f32 = 0.05;
+/// The chance of a grass block getting tall grass, and of it getting a flower instead.
+pub const TALL_GRASS_CHANCE: f32 = 0.12;
+pub const FLOWER_CHANCE: f32 = 0.02;
+pub const LAVA_POND_CHANCE: f32 = 0.3; // Of the ponds below LAVA_POND_HEIGHT
+pub const LAVA_POND_HEIGHT: usize = 90;
+/// The floating islands are between these heights, thickest in the middle.
+pub const ISLAND_BOTTOM: i32 = 100;
+pub const ISLAND_TOP: i32 = 180;
+pub const ISLAND_SCALE: f64 = 0.025;
+/// The island noise above which there's an island, in the middle of the band. It goes up towards the top and the bottom.
+pub const ISLAND_THRESHOLD: f64 = 0.5;
+/// How many blocks of dirt are under the grass of the islands.
+pub const ISLAND_DIRT_DEPTH: i32 = 3;
+/// How far from the spawn the player is put on the nearest island, and how far apart the columns checked are.
+pub const ISLAND_SPAWN_SEARCH: i32 = 256;
+pub const ISLAND_SPAWN_STEP: i32 = 4;
+/// How much the precious ores glow, so they glint in the dark caves.
+pub const ORE_GLINT: f32 = 0.4;
 pub const FOV: f32 = 80.0;
+pub const WORLD_SPAWN: Vec3 = Vec3::new(0.0, 200.0, 0.0);
 
 pub const SPEED: f32 = 3.0;
+/// How much of the walking speed is left while sneaking.
+pub const SNEAK_SPEED_FACTOR: f32 = 0.3;
 pub const GRAVITY: f32 = 9.81;
 pub const JUMP_FORCE: f32 = 10.0;
+pub const REACH_DISTANCE: f32 = 5.0;
 
 // === COMPONENTS ===
 
+/// A column of the world, the entity the streaming loads and unloads. Its sections are its children.
 #[derive(Component)]
 pub struct ChunkMesh {
     pub position: IVec2XZ,
+    /// The scale its sections were meshed at, see [`lod_scale`](crate::game::lod::lod_scale).
+    pub scale: usize,
+}
+
+/// One of the [`SECTIONS`] of a chunk, counted from the bottom. Only the sections with faces have an entity, it
+/// has the solid mesh and the collider, and a child for every fluid.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct ChunkSection {
+    pub position: IVec2XZ,
+    pub index: usize,
+}
+
+impl ChunkSection {
+    /// Every section of a chunk, from the bottom up.
+    pub fn all(position: IVec2XZ) -> impl Iterator<Item = ChunkSection> {
+        (0..SECTIONS).map(move |index| ChunkSection { position, index })
+    }
+
+    /// The section a block of the world is in.
+    pub fn containing(pos: IVec3) -> Self {
+        let (position, local) = world_to_chunk_and_local(pos);
+        Self {
+            position,
+            index: local.y as usize / SECTION_HEIGHT,
+        }
+    }
+
+    /// The minimum corner of the section, in world space.
+    pub fn origin(self) -> IVec3 {
+        IVec3::new(
+            self.position.x * CHUNK_SIZE as i32,
+            (self.index * SECTION_HEIGHT) as i32,
+            self.position.z * CHUNK_SIZE as i32,
+        )
+    }
+}
+
+#[derive(Component)]
+pub struct ComputeMeshTask(pub Task<(ChunkMeshes, ChunkBlocks)>);
+
+/// The meshes of the sections of a chunk that were meshed. A generated chunk has all of them, a remeshed one only
+/// the sections whose blocks changed.
+pub struct ChunkMeshes {
+    pub sections: Vec<SectionMesh>,
+    /// 1 at full detail, or the scale of [`lod_chunk_meshes`](crate::game::lod::lod_chunk_meshes).
+    pub scale: usize,
+}
+
+impl ChunkMeshes {
+    #[allow(dead_code)] // Only the tests look at the meshes of a whole chunk at once
+    /// Every mesh of the chunk, each with the offset its vertices are relative to.
+    pub fn parts(&self) -> impl Iterator<Item = (&Mesh, Vec3)> {
+        self.sections.iter().flat_map(|section| {
+            std::iter::once((&section.solid_mesh, Vec3::ZERO)).chain(
+                section
+                    .fluid_meshes
+                    .iter()
+                    .map(|fluid| (&fluid.mesh, fluid.center)),
+            )
+        })
+    }
+}
+
+/// The meshes of a section. Every fluid is meshed apart from the rest, with a material of its own and no collider.
+pub struct SectionMesh {
+    pub index: usize,
+    pub solid_mesh: Mesh,
+    /// One for each fluid in the section.
+    pub fluid_meshes: Vec<FluidMesh>,
+    /// The collider of the solid mesh, built by the task so the main thread doesn't have to. None if there's
+    /// nothing to collide with.
+    pub collider: Option<Collider>,
 }
 
+impl SectionMesh {
+    /// Whether nothing in the section is drawn, because it's all air or buried. It gets no entity.
+    pub fn is_empty(&self) -> bool {
+        self.solid_mesh.count_vertices() == 0 && self.fluid_meshes.is_empty()
+    }
+}
+
+/// One fluid of a section. Its vertices are relative to the centre of the fluid, so the transparent pass sorts it
+/// by where the water is, not by the corner of the world.
+pub struct FluidMesh {
+    pub block: BlockType,
+    pub mesh: Mesh,
+    pub center: Vec3,
+}
+
+/// The collider of a section, kept while its chunk is too far from the player to need it. The [`Collider`] the
+/// sections near the player get is a copy of it, which shares the shape.
+#[derive(Component, Clone)]
+pub struct ChunkCollider(pub Collider);
+
+/// A child entity of a section that draws one of its fluids.
 #[derive(Component)]
-pub struct ComputeMeshTask(pub Task<Mesh>);
+pub struct ChunkFluid(pub BlockType);
 
+/// A child entity of a section that draws its packed solid mesh. The vertices are relative to the corner of the
+/// section, it's moved there. The section stays where it is, it has the collider.
 #[derive(Component)]
-pub struct ChunkBorder;
+pub struct PackedSection;
+
+/// Marks a [`ComputeMeshTask`] of a chunk that's already loaded, meshing the sections again after their blocks
+/// changed. Remeshing it again before the task is done drops the task, the new one meshes these sections too.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Remeshing(pub Vec<usize>);
+
+/// Marks a chunk that was meshed again since it was generated, so it isn't put in the [`MeshCache`].
+#[derive(Component)]
+pub struct Remeshed;
 
 #[derive(Component)]
 pub struct UI;
 
+// === EVENTS ===
+
+/// The streaming wants a chunk loaded now. [`spawn_chunk_tasks`](crate::game::chunk::spawn_chunk_tasks) starts its
+/// mesh task, the chunk is [`ChunkState::Requested`] until then.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct ChunkLoadRequested(pub IVec2XZ);
+
+/// A chunk finished generating and is in the world, with its blocks in the [`ChunkMap`]. Remeshing a chunk doesn't
+/// send it again.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct ChunkReady {
+    pub position: IVec2XZ,
+    pub entity: Entity,
+}
+
+/// A chunk that was ready is unloaded, its entity is despawned and its blocks are gone from the [`ChunkMap`].
+/// Chunks that were unloaded before they were ready never send it.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct ChunkUnloaded(pub IVec2XZ);
+
+/// A loaded chunk has to be meshed again at another scale, or with other skirts, because it or a neighbour moved
+/// past the [`LOD_DISTANCE`].
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct ChunkRescaled(pub IVec2XZ);
+
 // === RESOURCES ===
 
-#[derive(Resource)]
+/// Where a chunk is in its lifecycle, from being requested by the streaming to being unloaded.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChunkState {
+    /// In render distance, but its mesh task hasn't been spawned yet.
+    Requested,
+    Generating {
+        task_entity: Entity,
+    },
+    Ready {
+        entity: Entity,
+    },
+    /// A spawn chunk, which stays loaded no matter how far away the player is.
+    Persistent {
+        entity: Entity,
+    },
+    /// Left the render distance while it was still generating. The task is kept running in case the chunk is
+    /// requested again, otherwise it's despawned when it finishes.
+    Unloading {
+        task_entity: Entity,
+    },
+}
+
+/// The state of every chunk the streaming knows about. Chunks that aren't in the map aren't loaded at all.
+///
+/// It only knows the columns, the entity of a loaded chunk is its [`ChunkMesh`] and the sections go with it.
+#[derive(Resource, Default)]
 pub struct ChunksLoaded {
-    pub chunks: HashSet<IVec2XZ>,
+    chunks: HashMap<IVec2XZ, ChunkState>,
+    /// The spawn chunks. They become [`ChunkState::Persistent`] once they're generated.
+    persistent: HashSet<IVec2XZ>,
+    /// The scale every wanted chunk is meshed at, see [`lod_scale`](crate::game::lod::lod_scale).
+    scales: HashMap<IVec2XZ, usize>,
+}
+
+impl ChunksLoaded {
+    pub fn state(&self, position: IVec2XZ) -> Option<ChunkState> {
+        self.chunks.get(&position).copied()
+    }
+
+    /// Whether the chunk is (or is about to be) loaded, so it doesn't have to be requested again.
+    pub fn is_wanted(&self, position: IVec2XZ) -> bool {
+        !matches!(
+            self.state(position),
+            None | Some(ChunkState::Unloading { .. })
+        )
+    }
+
+    /// The chunks that are loaded or on their way.
+    pub fn wanted(&self) -> impl Iterator<Item = IVec2XZ> + '_ {
+        self.chunks
+            .keys()
+            .copied()
+            .filter(|&position| self.is_wanted(position))
+    }
+
+    pub fn is_persistent(&self, position: IVec2XZ) -> bool {
+        self.persistent.contains(&position)
+    }
+
+    /// The scale a chunk is meshed at, or is going to be. None if it isn't wanted.
+    pub fn scale(&self, position: IVec2XZ) -> Option<usize> {
+        self.is_wanted(position)
+            .then(|| self.scales.get(&position).copied().unwrap_or(1))
+    }
+
+    /// Works out the scale of every wanted chunk again with `scale_at`. Returns the chunks that have to be meshed
+    /// again because of it: the ones whose scale changed, and their neighbours at a lower detail, whose skirts
+    /// depend on it. The chunks that are new get their scale without being returned.
+    pub fn rescale(&mut self, scale_at: impl Fn(IVec2XZ) -> usize) -> Vec<IVec2XZ> {
+        let wanted: HashSet<IVec2XZ> = self.wanted().collect();
+        self.scales.retain(|position, _| wanted.contains(position));
+        let mut rescaled = HashSet::new();
+        for &position in &wanted {
+            let scale = scale_at(position);
+            if self
+                .scales
+                .insert(position, scale)
+                .is_some_and(|old| old != scale)
+            {
+                rescaled.insert(position);
+            }
+        }
+        let neighbors: Vec<IVec2XZ> = rescaled
+            .iter()
+            .flat_map(|&position| {
+                [(1, 0), (-1, 0), (0, 1), (0, -1)].map(|(x, z)| position + IVec2XZ::new(x, z))
+            })
+            .filter(|position| self.scales.get(position).is_some_and(|&scale| scale > 1))
+            .collect();
+        rescaled.extend(neighbors);
+        rescaled.into_iter().collect()
+    }
+
+    pub fn persistent(&self) -> impl Iterator<Item = IVec2XZ> + '_ {
+        self.persistent.iter().copied()
+    }
+
+    /// Changes the set of spawn chunks. Chunks that were already generated switch between ready and persistent,
+    /// the new spawn chunks still have to be requested.
+    pub fn set_persistent(&mut self, persistent: HashSet<IVec2XZ>) {
+        for (position, state) in self.chunks.iter_mut() {
+            *state = match *state {
+                ChunkState::Ready { entity } if persistent.contains(position) => {
+                    ChunkState::Persistent { entity }
+                }
+                ChunkState::Persistent { entity } if !persistent.contains(position) => {
+                    ChunkState::Ready { entity }
+                }
+                state => state,
+            };
+        }
+        self.persistent = persistent;
+    }
+
+    /// How many chunks are ready, how many of those are spawn chunks, and how many are still generating.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for state in self.chunks.values() {
+            match state {
+                ChunkState::Ready { .. } => counts.0 += 1,
+                ChunkState::Persistent { .. } => {
+                    counts.0 += 1;
+                    counts.1 += 1;
+                }
+                ChunkState::Requested | ChunkState::Generating { .. } => counts.2 += 1,
+                ChunkState::Unloading { .. } => {}
+            }
+        }
+        counts
+    }
+
+    /// How many chunks have a mesh task running, the ones unloaded while generating included.
+    pub fn tasks_in_flight(&self) -> usize {
+        self.chunks
+            .values()
+            .filter(|state| {
+                matches!(
+                    state,
+                    ChunkState::Generating { .. } | ChunkState::Unloading { .. }
+                )
+            })
+            .count()
+    }
+
+    /// The chunks that still need a mesh task.
+    pub fn requested(&self) -> impl Iterator<Item = IVec2XZ> + '_ {
+        self.chunks
+            .iter()
+            .filter(|(_, &state)| state == ChunkState::Requested)
+            .map(|(&position, _)| position)
+    }
+
+    /// Requests a chunk. A chunk that is still unloading picks up its running task again.
+    pub fn request(&mut self, position: IVec2XZ) {
+        match self.state(position) {
+            None => {
+                self.chunks.insert(position, ChunkState::Requested);
+            }
+            Some(ChunkState::Unloading { task_entity }) => {
+                self.chunks
+                    .insert(position, ChunkState::Generating { task_entity });
+            }
+            Some(state) => illegal_transition(position, state, "request"),
+        }
+    }
+
+    pub fn start_generating(&mut self, position: IVec2XZ, task_entity: Entity) {
+        match self.state(position) {
+            Some(ChunkState::Requested) => {
+                self.chunks
+                    .insert(position, ChunkState::Generating { task_entity });
+            }
+            Some(state) => illegal_transition(position, state, "start generating"),
+            None => untracked_chunk(position, "start generating"),
+        }
+    }
+
+    /// Called when the mesh task of a chunk finishes. Returns false if the chunk was unloaded in the meantime,
+    /// in which case the entity should be despawned.
+    pub fn finish(&mut self, position: IVec2XZ, entity: Entity) -> bool {
+        match self.state(position) {
+            Some(ChunkState::Generating { task_entity }) if task_entity == entity => {
+                let state = if self.is_persistent(position) {
+                    ChunkState::Persistent { entity }
+                } else {
+                    ChunkState::Ready { entity }
+                };
+                self.chunks.insert(position, state);
+                true
+            }
+            Some(ChunkState::Unloading { task_entity }) if task_entity == entity => {
+                self.chunks.remove(&position);
+                false
+            }
+            Some(state) => {
+                illegal_transition(position, state, "finish");
+                false
+            }
+            None => {
+                untracked_chunk(position, "finish");
+                false
+            }
+        }
+    }
+
+    /// Unloads a chunk. Returns the entity to despawn right away, if there is one.
+    pub fn unload(&mut self, position: IVec2XZ) -> Option<Entity> {
+        match self.state(position) {
+            Some(ChunkState::Requested) => {
+                self.chunks.remove(&position);
+                None
+            }
+            Some(ChunkState::Generating { task_entity }) => {
+                self.chunks
+                    .insert(position, ChunkState::Unloading { task_entity });
+                None
+            }
+            Some(ChunkState::Ready { entity }) => {
+                self.chunks.remove(&position);
+                Some(entity)
+            }
+            Some(state) => {
+                illegal_transition(position, state, "unload");
+                None
+            }
+            None => {
+                untracked_chunk(position, "unload");
+                None
+            }
+        }
+    }
+
+    /// Whether the chunk is generated, and has its mesh and collider.
+    pub fn is_ready(&self, position: IVec2XZ) -> bool {
+        matches!(
+            self.state(position),
+            Some(ChunkState::Ready { .. } | ChunkState::Persistent { .. })
+        )
+    }
+
+    /// Forgets the chunks that were unloaded while generating. Returns their task entities, despawning them
+    /// drops the tasks before they finish.
+    pub fn cancel_unloading(&mut self) -> Vec<Entity> {
+        let mut cancelled = Vec::new();
+        self.chunks.retain(|_, state| match *state {
+            ChunkState::Unloading { task_entity } => {
+                cancelled.push(task_entity);
+                false
+            }
+            _ => true,
+        });
+        cancelled
+    }
+
+    /// Forgets every chunk, but not which ones are spawn chunks. The caller is responsible for despawning the
+    /// chunk entities.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.scales.clear();
+    }
+}
+
+/// The centre chunk moving more than this many chunks along either axis in one frame is a teleport.
+pub const TELEPORT_DISTANCE: i32 = 2;
+/// How many mesh tasks are started in a frame, and how many while catching up after a teleport.
+pub const CHUNK_TASKS_PER_FRAME: usize = 8;
+pub const TELEPORT_TASKS_PER_FRAME: usize = 32;
+/// How many chunks can have a mesh task running at once. The task pool works through them in the order they were
+/// started, so keeping the queue short lets the chunks closest to the player go first when they move.
+pub const MAX_CHUNK_TASKS_IN_FLIGHT: usize = 64;
+
+/// How far to the side of where the camera looks a chunk can be and still be loaded first, as the cosine of the
+/// angle. A bit wider than the field of view, so turning a little doesn't reveal empty ground.
+pub const VIEW_CONE_COS: f32 = 0.5;
+
+/// How many finished chunks are put into the world in a frame at most, and for how long.
+pub const MESH_INSERTS_PER_FRAME: usize = 8;
+pub const MESH_INSERT_TIME: Duration = Duration::from_millis(4);
+/// For a second after the generation is resumed or the window gets the focus back, fewer chunks go in a frame. The
+/// tasks that finished in the meantime trickle in instead of hitching the frame control comes back on.
+pub const CATCH_UP_INSERTS_PER_FRAME: usize = 2;
+pub const CATCH_UP_INSERT_TIME: Duration = Duration::from_millis(2);
+pub const CATCH_UP_TIME: Duration = Duration::from_secs(1);
+
+/// Limits how much of a frame goes to putting the meshes of finished chunks into the world. Many chunks finish at
+/// once when the world loads or after a teleport, the ones that don't fit in the frame wait for the next one.
+#[derive(Resource, Debug, Clone)]
+pub struct MeshInsertBudget {
+    pub max_per_frame: usize,
+    /// The chunk that's already started always finishes, at least one is put in every frame.
+    pub max_time: Duration,
+    /// How many finished chunks were left for the next frame.
+    pub backlog: usize,
+    /// How much longer the smaller catch-up budget is used for.
+    pub catch_up: Duration,
+}
+
+impl Default for MeshInsertBudget {
+    fn default() -> Self {
+        Self {
+            max_per_frame: MESH_INSERTS_PER_FRAME,
+            max_time: MESH_INSERT_TIME,
+            backlog: 0,
+            catch_up: Duration::ZERO,
+        }
+    }
+}
+
+impl MeshInsertBudget {
+    /// Whether another chunk fits in the frame, after some were put in within the time that passed.
+    pub fn allows(&self, inserted: usize, elapsed: Duration) -> bool {
+        let (max_per_frame, max_time) = if self.is_catching_up() {
+            (
+                self.max_per_frame.min(CATCH_UP_INSERTS_PER_FRAME),
+                self.max_time.min(CATCH_UP_INSERT_TIME),
+            )
+        } else {
+            (self.max_per_frame, self.max_time)
+        };
+        inserted == 0 || inserted < max_per_frame && elapsed < max_time
+    }
+
+    /// Uses the catch-up budget for the next [`CATCH_UP_TIME`].
+    pub fn start_catch_up(&mut self) {
+        self.catch_up = CATCH_UP_TIME;
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.catch_up = self.catch_up.saturating_sub(delta);
+    }
+
+    pub fn is_catching_up(&self) -> bool {
+        !self.catch_up.is_zero()
+    }
+}
+
+/// Follows the chunk the loading is centred on, to notice teleports.
+///
+/// After a teleport the chunks around the destination come first and more tasks are started per frame, until
+/// the 3x3 chunks at the destination are ready.
+#[derive(Resource, Default, Debug)]
+pub struct ChunkLoader {
+    center: Option<IVec2XZ>,
+    /// The centre chunk of the last scan for chunks to load and unload.
+    scanned: Option<IVec2XZ>,
+    teleporting: bool,
+    /// The chunk the player is in has no collider yet, so the player is held in place instead of falling.
+    pub hold_player: bool,
+}
+
+impl ChunkLoader {
+    /// Moves the loading to this frame's centre chunk. Returns true if it was a teleport.
+    pub fn move_to(&mut self, center: IVec2XZ, chunks: &ChunksLoaded) -> bool {
+        let teleported = self.center.is_some_and(|old| {
+            let moved = center - old;
+            moved.x.abs().max(moved.z.abs()) > TELEPORT_DISTANCE
+        });
+        self.center = Some(center);
+
+        if teleported {
+            self.teleporting = true;
+        } else if self.teleporting && destination(center).all(|chunk| chunks.is_ready(chunk)) {
+            self.teleporting = false;
+        }
+        teleported
+    }
+
+    /// Whether the chunks to load and unload have to be looked for this frame, because the centre chunk changed
+    /// since the last time or a [`ChunkLoader::rescan`] was asked for.
+    pub fn take_scan(&mut self, center: IVec2XZ) -> bool {
+        let scan = self.scanned != Some(center);
+        self.scanned = Some(center);
+        scan
+    }
+
+    /// Looks for the chunks to load and unload in the next frame, even if the centre chunk stays the same. Needed
+    /// whenever the loaded chunks change outside of the streaming.
+    pub fn rescan(&mut self) {
+        self.scanned = None;
+    }
+
+    /// Holds the player while the chunk they're in has no collider, falling through it would drop them out of the world.
+    pub fn check_ground(&mut self, player: Vec3, chunks: &ChunksLoaded) {
+        self.hold_player = !chunks.is_ready(IVec2XZ::containing(player));
+    }
+
+    /// The chunk the loading is centred on, once there's been a frame.
+    pub fn center(&self) -> Option<IVec2XZ> {
+        self.center
+    }
+
+    pub fn is_teleporting(&self) -> bool {
+        self.teleporting
+    }
+
+    pub fn tasks_per_frame(&self) -> usize {
+        if self.teleporting {
+            TELEPORT_TASKS_PER_FRAME
+        } else {
+            CHUNK_TASKS_PER_FRAME
+        }
+    }
+}
+
+/// The 3x3 chunks around a centre chunk, which are generated before any other.
+pub fn destination(center: IVec2XZ) -> impl Iterator<Item = IVec2XZ> {
+    (-1..=1).flat_map(move |x| (-1..=1).map(move |z| center + IVec2XZ::new(x, z)))
+}
+
+fn illegal_transition(position: IVec2XZ, state: ChunkState, action: &str) {
+    if cfg!(debug_assertions) {
+        error!(
+            "Can't {} chunk ({}, {}) in state {:?}",
+            action, position.x, position.z, state
+        );
+    }
+}
+
+fn untracked_chunk(position: IVec2XZ, action: &str) {
+    if cfg!(debug_assertions) {
+        error!(
+            "Can't {} chunk ({}, {}), it isn't loaded",
+            action, position.x, position.z
+        );
+    }
+}
+
+/// The generated blocks of every loaded chunk.
+///
+/// The chunks are put in and taken out with [`ChunkMap::insert_chunk`] and [`ChunkMap::remove_chunk`], and their blocks
+/// changed with [`ChunkMap::set_block`], so the sky cache stays in sync with them.
+#[derive(Resource, Default)]
+pub struct ChunkMap {
+    pub chunks: HashMap<IVec2XZ, ChunkBlocks>,
+    /// The highest block that blocks the sky in every column of the chunks asked about so far, see
+    /// [`ChunkMap::sky_top`].
+    sky_tops: Mutex<HashMap<IVec2XZ, Box<[Option<i32>]>>>,
+}
+
+/// The highest block that blocks the sky in each column of a chunk, indexed by x + z * [`CHUNK_SIZE`].
+fn column_tops(blocks: &ChunkBlocks) -> Box<[Option<i32>]> {
+    (0..CHUNK_SIZE * CHUNK_SIZE)
+        .map(|i| column_top(blocks, i % CHUNK_SIZE, i / CHUNK_SIZE))
+        .collect()
+}
+
+fn column_top(blocks: &ChunkBlocks, x: usize, z: usize) -> Option<i32> {
+    let top = blocks
+        .column(x, z)
+        .iter()
+        .rposition(|block| block.blocks_sky())?;
+    Some(top as i32)
+}
+
+impl ChunkMap {
+    /// Returns the block at a world position, or None if its chunk isn't loaded.
+    pub fn get_block(&self, pos: IVec3) -> Option<BlockType> {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return None;
+        }
+        let (chunk, local) = world_to_chunk_and_local(pos);
+        let blocks = self.chunks.get(&chunk)?;
+        Some(blocks.get(local.x as usize, local.y as usize, local.z as usize))
+    }
+
+    /// Changes the block at a world position. Nothing happens if its chunk isn't loaded or it's outside the world.
+    /// The chunk isn't remeshed, that's up to the caller.
+    pub fn set_block(&mut self, pos: IVec3, block: BlockType) {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return;
+        }
+        let (chunk, local) = world_to_chunk_and_local(pos);
+        let (x, z) = (local.x as usize, local.z as usize);
+        if let Some(blocks) = self.chunks.get_mut(&chunk) {
+            blocks.set(x, local.y as usize, z, block);
+            if let Some(tops) = self.sky_tops.get_mut().unwrap().get_mut(&chunk) {
+                tops[x + z * CHUNK_SIZE] = column_top(blocks, x, z);
+            }
+        }
+    }
+
+    pub fn insert_chunk(&mut self, position: IVec2XZ, blocks: ChunkBlocks) {
+        self.sky_tops.get_mut().unwrap().remove(&position);
+        self.chunks.insert(position, blocks);
+    }
+
+    pub fn remove_chunk(&mut self, position: IVec2XZ) -> Option<ChunkBlocks> {
+        self.sky_tops.get_mut().unwrap().remove(&position);
+        self.chunks.remove(&position)
+    }
+
+    pub fn clear(&mut self) {
+        self.sky_tops.get_mut().unwrap().clear();
+        self.chunks.clear();
+    }
+
+    /// The height of the highest block of a column that [blocks the sky](BlockType::blocks_sky), None if nothing in it
+    /// does or its chunk isn't loaded.
+    /// The columns of a chunk are looked at the first time it's asked about, then kept up to date by the edits.
+    pub fn sky_top(&self, column: IVec2XZ) -> Option<i32> {
+        let (chunk, local) = world_to_chunk_and_local(IVec3::new(column.x, 0, column.z));
+        let blocks = self.chunks.get(&chunk)?;
+        let mut sky_tops = self.sky_tops.lock().unwrap();
+        let tops = sky_tops.entry(chunk).or_insert_with(|| column_tops(blocks));
+        tops[local.x as usize + local.z as usize * CHUNK_SIZE]
+    }
+
+    /// Whether nothing that blocks the sky is above a position, so it's under the open sky. Unloaded chunks count as
+    /// air.
+    pub fn sky_exposed(&self, pos: IVec3) -> bool {
+        self.sky_top(IVec2XZ::new(pos.x, pos.z))
+            .is_none_or(|top| top < pos.y)
+    }
+}
+
+/// How many of the chunks unloaded last keep their meshes, so going back and forth over a chunk border doesn't
+/// generate the same chunks again.
+pub const MESH_CACHE_CAPACITY: usize = 32;
+
+/// The meshes and blocks of the chunks unloaded last, the way their mesh task finished them. When it's full the
+/// chunk unloaded first is dropped.
+///
+/// Only the chunks the way the generator made them are kept, an edited chunk is generated again. The cache has
+/// to be cleared when the chunks would be meshed differently.
+#[derive(Resource, Default)]
+pub struct MeshCache {
+    chunks: VecDeque<(IVec2XZ, ChunkMeshes, ChunkBlocks)>,
+}
+
+impl MeshCache {
+    pub fn insert(&mut self, position: IVec2XZ, meshes: ChunkMeshes, blocks: ChunkBlocks) {
+        self.chunks.retain(|(cached, ..)| *cached != position);
+        if self.chunks.len() >= MESH_CACHE_CAPACITY {
+            self.chunks.pop_front();
+        }
+        self.chunks.push_back((position, meshes, blocks));
+    }
+
+    /// Takes a chunk out of the cache, to load it again.
+    pub fn take(&mut self, position: IVec2XZ) -> Option<(ChunkMeshes, ChunkBlocks)> {
+        let index = self
+            .chunks
+            .iter()
+            .position(|(cached, ..)| *cached == position)?;
+        let (_, meshes, blocks) = self.chunks.remove(index)?;
+        Some((meshes, blocks))
+    }
+
+    /// How many chunks are cached.
+    pub fn count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
 }
 
 #[derive(Resource, Clone, Copy)]
@@ -73,18 +864,315 @@ pub struct ChunkBorderToggled(pub bool);
 #[derive(Resource)]
 pub struct Generating(pub bool);
 
+/// Renders the chunks without lighting, to look at caves.
+#[derive(Resource, Default)]
+pub struct Fullbright(pub bool);
+
+/// Shades the corners of the block faces by the blocks around them. It's only worked out when a chunk is meshed.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct AmbientOcclusion(pub bool);
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Packs the solid meshes of the chunks into 8 bytes a vertex, instead of 48. It's only done when a chunk is
+/// meshed, like the ambient occlusion.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PackedMeshes(pub bool);
+
+/// The atlas of the block textures, and which of its tiles every block uses.
 #[derive(Resource, Clone)]
-pub struct GameTextureAtlas(pub TextureAtlas);
+pub struct GameTextureAtlas {
+    pub atlas: TextureAtlas,
+    /// How far the texture coordinates stay inside the tiles, in texels.
+    pub uv_inset: f32,
+    /// The tile of every texture, by name.
+    names: HashMap<String, usize>,
+    /// The tiles of every block, by its id.
+    blocks: Vec<FaceTiles>,
+}
+
+impl GameTextureAtlas {
+    /// Looks up the textures of every block in the atlas, by the names in the block table. The ones that aren't in
+    /// it are left out with a warning, and a face that has none of its textures uses the `missing` tile instead.
+    pub fn new(atlas: TextureAtlas, names: HashMap<String, usize>, missing: usize) -> Self {
+        let mut warned = HashSet::new();
+        let mut index = |name: &str| {
+            let index = names.get(name).copied();
+            if index.is_none() && warned.insert(name.to_string()) {
+                warn!("No texture named \"{}\"", name);
+            }
+            index
+        };
+        let mut blocks = vec![FaceTiles::default(); BlockType::ALL.len()];
+        for block in BlockType::ALL {
+            blocks[block.id() as usize] = block.properties().textures.resolve(&mut index, missing);
+        }
+        Self {
+            atlas,
+            uv_inset: 0.0,
+            names,
+            blocks,
+        }
+    }
+
+    /// The tile of a texture, by name. The textures that aren't on a block, like the cracks, are looked up with it.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// The tiles a face of a block can use. Faces with more than one pick a variant per position, see
+    /// [`pick_variant`](crate::game::chunk::pick_variant).
+    pub fn tiles(&self, block: BlockType, face: BlockFace) -> &[usize] {
+        self.blocks[block.id() as usize].get(face)
+    }
+
+    /// The texture coordinates of the corners of a tile, clockwise from the top left, [`uv_inset`](Self::uv_inset)
+    /// texels in from its edges.
+    pub fn tile_uvs(&self, index: usize) -> [[f32; 2]; 4] {
+        let tile = self.atlas.textures[index];
+        let size = self.atlas.size;
+        let (min, max) = (tile.min + self.uv_inset, tile.max - self.uv_inset);
+        [
+            [min.x / size.x, min.y / size.y],
+            [max.x / size.x, min.y / size.y],
+            [max.x / size.x, max.y / size.y],
+            [min.x / size.x, max.y / size.y],
+        ]
+    }
+}
+
+// === TYPES ===
+
+/// Mixes a 64-bit value (splitmix64 finalizer).
+pub fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Index of a block in a chunk's block storage. Columns are stored one after another (y changes fastest),
+/// so vertical neighbours are next to each other in memory.
+#[inline]
+pub fn local_index(x: usize, y: usize, z: usize) -> usize {
+    debug_assert!(
+        x < CHUNK_SIZE && y < CHUNK_HEIGHT && z < CHUNK_SIZE,
+        "local position ({}, {}, {}) is outside the chunk",
+        x,
+        y,
+        z
+    );
+    (x * CHUNK_SIZE + z) * CHUNK_HEIGHT + y
+}
+
+/// The local position of a block from its index in a chunk's block storage, the other way around from
+/// [`local_index`].
+#[inline]
+pub fn local_position(index: usize) -> UVec3 {
+    debug_assert!(
+        index < CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE,
+        "index {} is outside the chunk",
+        index
+    );
+    let column = index / CHUNK_HEIGHT;
+    UVec3::new(
+        (column / CHUNK_SIZE) as u32,
+        (index % CHUNK_HEIGHT) as u32,
+        (column % CHUNK_SIZE) as u32,
+    )
+}
+
+/// Splits a world block position into its chunk and the position inside that chunk.
+///
+/// Rounds towards negative infinity, so x -1 is the last block of chunk -1 and not the first block of chunk 0.
+/// The y has to be inside the world already, chunks span the whole height.
+#[inline]
+pub fn world_to_chunk_and_local(pos: IVec3) -> (IVec2XZ, UVec3) {
+    debug_assert!(
+        (0..CHUNK_HEIGHT as i32).contains(&pos.y),
+        "y {} is outside the world",
+        pos.y
+    );
+    let chunk = IVec2XZ::new(
+        pos.x.div_euclid(CHUNK_SIZE as i32),
+        pos.z.div_euclid(CHUNK_SIZE as i32),
+    );
+    let local = UVec3::new(
+        pos.x.rem_euclid(CHUNK_SIZE as i32) as u32,
+        pos.y as u32,
+        pos.z.rem_euclid(CHUNK_SIZE as i32) as u32,
+    );
+    (chunk, local)
+}
+
+/// The blocks of a single chunk. They're on the heap, a chunk is too big to be moved around on the stack of the
+/// mesh tasks.
+#[derive(Clone, PartialEq)]
+pub struct ChunkBlocks {
+    blocks: Box<[BlockType]>,
+}
+
+impl Default for ChunkBlocks {
+    fn default() -> Self {
+        Self {
+            blocks: vec![BlockType::Air; CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE].into_boxed_slice(),
+        }
+    }
+}
+
+impl ChunkBlocks {
+    #[inline]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockType {
+        self.blocks[local_index(x, y, z)]
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: BlockType) {
+        self.blocks[local_index(x, y, z)] = block;
+    }
+
+    /// The blocks of one column, from y = 0 up.
+    pub fn column(&self, x: usize, z: usize) -> &[BlockType] {
+        let start = local_index(x, 0, z);
+        &self.blocks[start..start + CHUNK_HEIGHT]
+    }
+
+    pub fn column_mut(&mut self, x: usize, z: usize) -> &mut [BlockType] {
+        let start = local_index(x, 0, z);
+        &mut self.blocks[start..start + CHUNK_HEIGHT]
+    }
+
+    #[allow(dead_code)] // Only the tests read every block with its position so far
+    /// Every block with its local position, in the order they're stored in.
+    pub fn iter(&self) -> impl Iterator<Item = (UVec3, BlockType)> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(index, &block)| (local_position(index), block))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (UVec3, &mut BlockType)> {
+        self.blocks
+            .iter_mut()
+            .enumerate()
+            .map(|(index, block)| (local_position(index), block))
+    }
+
+    /// What one of the [`SECTIONS`] is filled with, looked at before it's meshed.
+    pub fn section_fill(&self, index: usize) -> SectionFill {
+        let bottom = index * SECTION_HEIGHT;
+        let mut fill = None;
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for &block in &self.column(x, z)[bottom..bottom + SECTION_HEIGHT] {
+                    let block_fill = if block == BlockType::Air {
+                        SectionFill::Air
+                    } else if BlockFace::ALL
+                        .into_iter()
+                        .all(|face| block.covers_face(face))
+                    {
+                        SectionFill::Opaque
+                    } else {
+                        return SectionFill::Mixed;
+                    };
+                    if *fill.get_or_insert(block_fill) != block_fill {
+                        return SectionFill::Mixed;
+                    }
+                }
+            }
+        }
+        fill.unwrap_or(SectionFill::Air)
+    }
+}
+
+/// What the blocks of a section are, so the mesher only looks at the blocks that can have faces.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SectionFill {
+    /// Nothing but air, there are no faces.
+    Air,
+    /// Nothing but blocks that cover every face of their cell. Only the outer layer of blocks can have faces, against
+    /// the sections and chunks around it.
+    Opaque,
+    Mixed,
+}
+
+/// How a [`Spline`] gets from one control point to the next.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SplineInterpolation {
+    #[default]
+    Linear,
+    /// Eases out of every point and into the next one, so the curve is flat at the points.
+    Smoothstep,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct SplinePoint {
+    pub input: f32,
+    pub output: f32,
+}
+
+/// A curve through control points, sorted by their input. Before the first point and after the last one it stays
+/// at their output, and without any points it's the input itself.
+#[derive(Reflect, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Spline {
+    pub points: Vec<SplinePoint>,
+    pub interpolation: SplineInterpolation,
+}
+
+impl Spline {
+    pub fn new(points: &[(f32, f32)], interpolation: SplineInterpolation) -> Self {
+        Self {
+            points: points
+                .iter()
+                .map(|&(input, output)| SplinePoint { input, output })
+                .collect(),
+            interpolation,
+        }
+    }
+
+    pub fn evaluate(&self, input: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return input;
+        };
+        if input <= first.input {
+            return first.output;
+        }
+        let Some(segment) = self
+            .points
+            .windows(2)
+            .find(|segment| input <= segment[1].input)
+        else {
+            return last.output;
+        };
+        let (from, to) = (segment[0], segment[1]);
+        // Two points at the same input are a step, the curve takes the output of the second one.
+        let t = if to.input > from.input {
+            ((input - from.input) / (to.input - from.input)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let t = match self.interpolation {
+            SplineInterpolation::Linear => t,
+            SplineInterpolation::Smoothstep => t * t * (3.0 - 2.0 * t),
+        };
+        from.output + (to.output - from.output) * t
+    }
+}
 
 // === ENUMS ===
 
-#[derive(PartialEq, Copy, Clone, Default)]
+#[derive(PartialEq, Copy, Clone, Default, Debug)]
 pub enum BlockType {
     Bedrock,
     Stone,
     Dirt,
     Grass,
     Log,
+    Leaves,
     Lava,
     Water,
     DiamondOre,
@@ -93,11 +1181,677 @@ pub enum BlockType {
     IronOre,
     CoalOre,
     Sand,
+    /// Grass with a layer of snow on top, the surface of tundras.
+    SnowyGrass,
+    SpruceLog,
+    SpruceLeaves,
+    Snow,
+    /// Frozen water, on top of the water in cold biomes.
+    Ice,
+    TallGrass,
+    Poppy,
+    Dandelion,
+    Gravel,
     #[default]
     Air,
+    // New blocks go after air, so the discriminants of the others stay what they were.
+    Cobblestone,
+    Chest,
+    /// The bottom half of a stone block.
+    StoneSlab,
+    /// The top half of a stone block.
+    StoneSlabTop,
+    /// Stone stairs, named after the side their back is on. Without a side it's the back.
+    StoneStairs,
+    StoneStairsFront,
+    StoneStairsLeft,
+    StoneStairsRight,
+    /// The same stairs upside down, the step hanging under a top slab.
+    StoneStairsTop,
+    StoneStairsFrontTop,
+    StoneStairsLeftTop,
+    StoneStairsRightTop,
+}
+
+/// The stone and the dirt have a few textures, so big walls of them don't repeat the same tile. The dirt ones are
+/// also under the grass.
+const STONE_TEXTURES: &[&str] = &["stone", "stone_1", "stone_2"];
+const DIRT_TEXTURES: &[&str] = &["dirt", "dirt_1", "dirt_2"];
+
+impl BlockType {
+    pub const ALL: [BlockType; 36] = [
+        BlockType::Bedrock,
+        BlockType::Stone,
+        BlockType::Dirt,
+        BlockType::Grass,
+        BlockType::Log,
+        BlockType::Leaves,
+        BlockType::Lava,
+        BlockType::Water,
+        BlockType::DiamondOre,
+        BlockType::RedstoneOre,
+        BlockType::GoldOre,
+        BlockType::IronOre,
+        BlockType::CoalOre,
+        BlockType::Sand,
+        BlockType::SnowyGrass,
+        BlockType::SpruceLog,
+        BlockType::SpruceLeaves,
+        BlockType::Snow,
+        BlockType::Ice,
+        BlockType::TallGrass,
+        BlockType::Poppy,
+        BlockType::Dandelion,
+        BlockType::Gravel,
+        BlockType::Cobblestone,
+        BlockType::Chest,
+        BlockType::StoneSlab,
+        BlockType::StoneSlabTop,
+        BlockType::StoneStairs,
+        BlockType::StoneStairsFront,
+        BlockType::StoneStairsLeft,
+        BlockType::StoneStairsRight,
+        BlockType::StoneStairsTop,
+        BlockType::StoneStairsFrontTop,
+        BlockType::StoneStairsLeftTop,
+        BlockType::StoneStairsRightTop,
+        BlockType::Air,
+    ];
+
+    /// Everything about the block, its row in the block table. A new block needs a row here, a place in
+    /// [`BlockType::ALL`] and a tile in the atlas. A face listing more than one texture picks one per position.
+    pub const fn properties(self) -> BlockProperties {
+        use BlockProperties as Block;
+        match self {
+            BlockType::Air => Block {
+                is_solid: false,
+                is_transparent: true,
+                hardness: None,
+                ..Block::cube(0, "Air", FaceTextures::all(&[MISSING_TEXTURE]))
+            },
+            // It holds up the world.
+            BlockType::Bedrock => Block {
+                hardness: None,
+                ..Block::cube(1, "Bedrock", FaceTextures::all(&["bedrock"]))
+            },
+            BlockType::Stone => Block::cube(2, "Stone", FaceTextures::all(STONE_TEXTURES)),
+            BlockType::Dirt => Block {
+                hardness: Some(0.5),
+                ..Block::cube(3, "Dirt", FaceTextures::all(DIRT_TEXTURES))
+            },
+            BlockType::Grass => Block {
+                tint: Tint::Top,
+                hardness: Some(0.6),
+                ..Block::cube(
+                    4,
+                    "Grass Block",
+                    FaceTextures::new(
+                        &["grass_top", "grass_top_1", "grass_top_2"],
+                        &["grass_side", "grass_side_1"],
+                        DIRT_TEXTURES,
+                    ),
+                )
+            },
+            BlockType::Log => Block {
+                hardness: Some(2.0),
+                ..Block::cube(5, "Log", FaceTextures::column(&["log_top"], &["log_side"]))
+            },
+            BlockType::Leaves => Block {
+                hardness: Some(0.2),
+                ..Block::cube(6, "Leaves", FaceTextures::all(&["leaves"]))
+            },
+            BlockType::Lava => Block {
+                is_transparent: false,
+                emits_light: Some(1.0),
+                ..Block::fluid(7, "Lava", &["lava"])
+            },
+            BlockType::Water => Block::fluid(8, "Water", &["water"]),
+            BlockType::DiamondOre => Block {
+                emits_light: Some(ORE_GLINT),
+                hardness: Some(3.0),
+                ..Block::cube(9, "Diamond Ore", FaceTextures::all(&["diamond_ore"]))
+            },
+            BlockType::RedstoneOre => Block {
+                emits_light: Some(ORE_GLINT),
+                hardness: Some(3.0),
+                ..Block::cube(10, "Redstone Ore", FaceTextures::all(&["redstone_ore"]))
+            },
+            BlockType::GoldOre => Block {
+                emits_light: Some(ORE_GLINT),
+                hardness: Some(3.0),
+                ..Block::cube(11, "Gold Ore", FaceTextures::all(&["gold_ore"]))
+            },
+            BlockType::IronOre => Block {
+                hardness: Some(3.0),
+                ..Block::cube(12, "Iron Ore", FaceTextures::all(&["iron_ore"]))
+            },
+            BlockType::CoalOre => Block {
+                hardness: Some(3.0),
+                ..Block::cube(13, "Coal Ore", FaceTextures::all(&["coal_ore"]))
+            },
+            BlockType::Sand => Block {
+                hardness: Some(0.5),
+                ..Block::cube(14, "Sand", FaceTextures::all(&["sand"]))
+            },
+            BlockType::SnowyGrass => Block {
+                hardness: Some(0.6),
+                ..Block::cube(
+                    15,
+                    "Snowy Grass Block",
+                    FaceTextures::new(&["snow"], &["snowy_grass_side"], DIRT_TEXTURES),
+                )
+            },
+            BlockType::SpruceLog => Block {
+                hardness: Some(2.0),
+                ..Block::cube(
+                    16,
+                    "Spruce Log",
+                    FaceTextures::column(&["spruce_log_top"], &["spruce_log_side"]),
+                )
+            },
+            BlockType::SpruceLeaves => Block {
+                hardness: Some(0.2),
+                ..Block::cube(17, "Spruce Leaves", FaceTextures::all(&["spruce_leaves"]))
+            },
+            BlockType::Snow => Block {
+                hardness: Some(0.2),
+                ..Block::cube(18, "Snow", FaceTextures::all(&["snow"]))
+            },
+            BlockType::Ice => Block {
+                frozen_from: Some(BlockType::Water),
+                hardness: Some(0.5),
+                ..Block::cube(19, "Ice", FaceTextures::all(&["ice"]))
+            },
+            BlockType::TallGrass => Block::plant(20, "Tall Grass", &["tall_grass"]),
+            BlockType::Poppy => Block::plant(21, "Poppy", &["poppy"]),
+            BlockType::Dandelion => Block::plant(22, "Dandelion", &["dandelion"]),
+            BlockType::Gravel => Block {
+                hardness: Some(0.6),
+                ..Block::cube(23, "Gravel", FaceTextures::all(&["gravel"]))
+            },
+            BlockType::Cobblestone => Block {
+                hardness: Some(2.0),
+                ..Block::cube(24, "Cobblestone", FaceTextures::all(&["cobblestone"]))
+            },
+            BlockType::Chest => Block {
+                interactive: true,
+                hardness: Some(2.5),
+                ..Block::cube(
+                    25,
+                    "Chest",
+                    FaceTextures::column(&["chest_top"], &["chest_side"]),
+                )
+            },
+            BlockType::StoneSlab => Block::shaped(
+                26,
+                "Stone Slab",
+                BlockShape::Slab { top: false },
+                &["stone"],
+            ),
+            BlockType::StoneSlabTop => Block::shaped(
+                27,
+                "Stone Slab (Top)",
+                BlockShape::Slab { top: true },
+                &["stone"],
+            ),
+            BlockType::StoneStairs => Block::shaped(
+                28,
+                "Stone Stairs",
+                BlockShape::stairs(BlockFace::Back, false),
+                &["stone"],
+            ),
+            BlockType::StoneStairsFront => Block::shaped(
+                29,
+                "Stone Stairs (Front)",
+                BlockShape::stairs(BlockFace::Front, false),
+                &["stone"],
+            ),
+            BlockType::StoneStairsLeft => Block::shaped(
+                30,
+                "Stone Stairs (Left)",
+                BlockShape::stairs(BlockFace::Left, false),
+                &["stone"],
+            ),
+            BlockType::StoneStairsRight => Block::shaped(
+                31,
+                "Stone Stairs (Right)",
+                BlockShape::stairs(BlockFace::Right, false),
+                &["stone"],
+            ),
+            BlockType::StoneStairsTop => Block::shaped(
+                32,
+                "Stone Stairs (Top)",
+                BlockShape::stairs(BlockFace::Back, true),
+                &["stone"],
+            ),
+            BlockType::StoneStairsFrontTop => Block::shaped(
+                33,
+                "Stone Stairs (Front, Top)",
+                BlockShape::stairs(BlockFace::Front, true),
+                &["stone"],
+            ),
+            BlockType::StoneStairsLeftTop => Block::shaped(
+                34,
+                "Stone Stairs (Left, Top)",
+                BlockShape::stairs(BlockFace::Left, true),
+                &["stone"],
+            ),
+            BlockType::StoneStairsRightTop => Block::shaped(
+                35,
+                "Stone Stairs (Right, Top)",
+                BlockShape::stairs(BlockFace::Right, true),
+                &["stone"],
+            ),
+        }
+    }
+
+    /// The number the block is stored as in files. It never changes, new blocks get new numbers even if they're
+    /// put somewhere else in the list.
+    pub fn id(self) -> u8 {
+        self.properties().id
+    }
+
+    pub fn from_id(id: u8) -> Option<BlockType> {
+        Self::ALL.into_iter().find(|block| block.id() == id)
+    }
+
+    /// The human-readable name, used in the HUD.
+    pub fn display_name(self) -> &'static str {
+        self.properties().name
+    }
+
+    #[allow(dead_code)] // Nothing takes block names as input yet
+    /// Parses a block name. Case, spaces and underscores are ignored, so "Diamond Ore",
+    /// "diamond_ore" and "DiamondOre" are all the same block.
+    pub fn from_name(name: &str) -> Option<BlockType> {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| !c.is_whitespace() && *c != '_')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        };
+        let name = normalize(name);
+        Self::ALL.into_iter().find(|block| {
+            normalize(block.display_name()) == name || normalize(&format!("{:?}", block)) == name
+        })
+    }
+
+    /// Whether the block is something you can stand on (not air, a fluid or a plant).
+    pub fn is_solid(self) -> bool {
+        self.properties().is_solid
+    }
+
+    /// Whether the block is water or lava. Fluids are meshed apart from the other blocks.
+    pub fn is_fluid(self) -> bool {
+        self.properties().is_fluid
+    }
+
+    /// Whether the block is a plant growing on the grass. Plants are drawn as crosses and you walk right through them.
+    pub fn is_plant(self) -> bool {
+        self.shape() == BlockShape::Cross
+    }
+
+    /// How the block is drawn.
+    pub fn shape(self) -> BlockShape {
+        self.properties().shape
+    }
+
+    /// Whether the block is the trunk of a tree, of any species.
+    pub fn is_log(self) -> bool {
+        matches!(self, BlockType::Log | BlockType::SpruceLog)
+    }
+
+    /// Whether the block is leaves, of any species. Any log holds up any leaves.
+    pub fn is_leaves(self) -> bool {
+        matches!(self, BlockType::Leaves | BlockType::SpruceLeaves)
+    }
+
+    /// Whether the block shuts out the sky for what's under it. Only solid blocks do, and the leaves let it through
+    /// like the plants and the fluids.
+    pub fn blocks_sky(self) -> bool {
+        self.is_solid() && !self.is_leaves()
+    }
+
+    /// How many seconds the break button has to be held to break the block, None if it can't be broken.
+    pub fn hardness(self) -> Option<f32> {
+        self.properties().hardness
+    }
+
+    /// Whether right clicking the block does something with it instead of placing a block against it. Sneaking
+    /// places anyway.
+    pub fn has_interaction(self) -> bool {
+        self.properties().interactive
+    }
+
+    /// Whether this block completely covers the given face of its cell, hiding the neighbour's face behind it.
+    pub fn covers_face(self, face: BlockFace) -> bool {
+        let properties = self.properties();
+        properties.is_solid && !properties.is_transparent && properties.shape.covers(face)
+    }
+
+    /// The block a family of shaped blocks is picked as, the bottom slab or the bottom stairs with their back on
+    /// the back. Every other block is its own.
+    pub fn base(self) -> BlockType {
+        match self {
+            BlockType::StoneSlabTop => BlockType::StoneSlab,
+            BlockType::StoneStairsFront
+            | BlockType::StoneStairsLeft
+            | BlockType::StoneStairsRight
+            | BlockType::StoneStairsTop
+            | BlockType::StoneStairsFrontTop
+            | BlockType::StoneStairsLeftTop
+            | BlockType::StoneStairsRightTop => BlockType::StoneStairs,
+            block => block,
+        }
+    }
+
+    /// The block of the same family with another shape, if the family has it.
+    pub fn with_shape(self, shape: BlockShape) -> Option<BlockType> {
+        Self::ALL
+            .into_iter()
+            .find(|block| block.base() == self.base() && block.shape() == shape)
+    }
+}
+
+/// What a block is like, everything the meshing, the culling and the colliders need to know about it. It's the
+/// row of the block in [`BlockType::properties`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockProperties {
+    /// The number it's stored as, see [`BlockType::id`].
+    pub id: u8,
+    pub name: &'static str,
+    pub textures: FaceTextures,
+    pub shape: BlockShape,
+    /// Something you can stand on, it's in the collider.
+    pub is_solid: bool,
+    /// Fluids are meshed apart from the other blocks, and the faces between two blocks of the same fluid are hidden.
+    pub is_fluid: bool,
+    /// Whether what's behind the block shows through it. The faces of its neighbours are drawn.
+    pub is_transparent: bool,
+    /// How bright the block glows, from 0 to 1. The fluids glow through their own material, the solid blocks only
+    /// in the packed chunk meshes, see [`PackedVertex::glow`](crate::game::packed::PackedVertex::glow).
+    pub emits_light: Option<f32>,
+    /// Right clicking it opens it, see [`BlockType::has_interaction`].
+    pub interactive: bool,
+    /// The fluid the block is the frozen top of. Its bottom isn't drawn on that fluid, it's the same body.
+    pub frozen_from: Option<BlockType>,
+    /// The faces that are colored by the biome, see [`Biome::grass_tint`](crate::game::biome::Biome::grass_tint).
+    pub tint: Tint,
+    /// How many seconds the break button has to be held to break it, None if it can't be broken.
+    pub hardness: Option<f32>,
+}
+
+impl BlockProperties {
+    /// An opaque solid cube, like most blocks.
+    const fn cube(id: u8, name: &'static str, textures: FaceTextures) -> Self {
+        Self {
+            id,
+            name,
+            textures,
+            shape: BlockShape::Cube,
+            is_solid: true,
+            is_fluid: false,
+            is_transparent: false,
+            emits_light: None,
+            interactive: false,
+            frozen_from: None,
+            tint: Tint::None,
+            hardness: Some(1.5),
+        }
+    }
+
+    /// A see-through fluid, with the same texture on every face.
+    const fn fluid(id: u8, name: &'static str, textures: &'static [&'static str]) -> Self {
+        Self {
+            is_solid: false,
+            is_fluid: true,
+            is_transparent: true,
+            hardness: None,
+            ..Self::cube(id, name, FaceTextures::all(textures))
+        }
+    }
+
+    /// A solid block that only fills part of its cell, like a slab.
+    const fn shaped(
+        id: u8,
+        name: &'static str,
+        shape: BlockShape,
+        textures: &'static [&'static str],
+    ) -> Self {
+        Self {
+            shape,
+            ..Self::cube(id, name, FaceTextures::all(textures))
+        }
+    }
+
+    /// A plant growing on the grass, drawn as a cross you walk through.
+    const fn plant(id: u8, name: &'static str, textures: &'static [&'static str]) -> Self {
+        Self {
+            shape: BlockShape::Cross,
+            is_solid: false,
+            is_transparent: true,
+            hardness: Some(0.0),
+            ..Self::cube(id, name, FaceTextures::all(textures))
+        }
+    }
+}
+
+/// Which faces of a block are colored by the biome it's in. The color is multiplied into the texture, so one texture
+/// works for every biome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tint {
+    None,
+    Top,
+}
+
+impl Tint {
+    pub fn applies(self, face: BlockFace) -> bool {
+        match self {
+            Tint::None => false,
+            Tint::Top => face == BlockFace::Top,
+        }
+    }
+}
+
+/// The names of the textures the faces of a block can use, the files in
+/// [`BLOCK_TEXTURES_DIR`](crate::game::atlas::BLOCK_TEXTURES_DIR) without the extension. A face with more than one
+/// picks one per position, see [`pick_variant`](crate::game::chunk::pick_variant).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaceTextures {
+    pub top: &'static [&'static str],
+    pub side: &'static [&'static str],
+    pub bottom: &'static [&'static str],
+}
+
+impl FaceTextures {
+    pub const fn new(
+        top: &'static [&'static str],
+        side: &'static [&'static str],
+        bottom: &'static [&'static str],
+    ) -> Self {
+        Self { top, side, bottom }
+    }
+
+    /// The same textures on every face.
+    pub const fn all(names: &'static [&'static str]) -> Self {
+        Self::new(names, names, names)
+    }
+
+    /// The same textures on the top and the bottom, and others on the sides, like a log.
+    pub const fn column(ends: &'static [&'static str], side: &'static [&'static str]) -> Self {
+        Self::new(ends, side, ends)
+    }
+
+    /// The atlas tiles of the textures, looked up by name. The variants that aren't found are left out, and a face
+    /// without any gets the `missing` tile.
+    pub fn resolve(
+        &self,
+        mut index: impl FnMut(&str) -> Option<usize>,
+        missing: usize,
+    ) -> FaceTiles {
+        let mut tiles = |names: &[&str]| {
+            let tiles: Vec<usize> = names.iter().filter_map(|name| index(name)).collect();
+            if tiles.is_empty() {
+                vec![missing]
+            } else {
+                tiles
+            }
+        };
+        FaceTiles {
+            top: tiles(self.top),
+            side: tiles(self.side),
+            bottom: tiles(self.bottom),
+        }
+    }
+}
+
+/// The atlas tiles of the faces of a block, its [`FaceTextures`] looked up in the atlas.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FaceTiles {
+    pub top: Vec<usize>,
+    pub side: Vec<usize>,
+    pub bottom: Vec<usize>,
+}
+
+impl FaceTiles {
+    pub fn get(&self, face: BlockFace) -> &[usize] {
+        match face {
+            BlockFace::Top => &self.top,
+            BlockFace::Bottom => &self.bottom,
+            _ => &self.side,
+        }
+    }
 }
 
+/// The geometry a block is meshed as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockShape {
+    /// A unit cube, with the faces that aren't covered by the neighbours. Fluids are cubes with a lowered top.
+    Cube,
+    /// Two quads crossing diagonally through the cell, seen from both sides.
+    Cross,
+    /// The bottom half of a cube, or the `top` one. Its other side is in the middle of the cell.
+    Slab { top: bool },
+    /// A slab with a step on it. The half of the cell the back is `facing` is full, the step is the quarter of the
+    /// cell over the slab there. With `top` it's upside down, the step hangs under a top slab.
+    Stairs { facing: BlockFace, top: bool },
+}
+
+impl BlockShape {
+    /// Stairs with their back on a side of the cell. The back can't be on top or at the bottom.
+    pub const fn stairs(facing: BlockFace, top: bool) -> Self {
+        assert!(!matches!(facing, BlockFace::Top | BlockFace::Bottom));
+        BlockShape::Stairs { facing, top }
+    }
+
+    /// The boxes the shape is made of, it's meshed box by box. A cross is no boxes.
+    pub fn boxes(self) -> impl Iterator<Item = BlockBox> {
+        // The half the slab is in, and the one the step is in.
+        let halves = |top: bool| {
+            if top {
+                (BlockFace::Top, BlockFace::Bottom)
+            } else {
+                (BlockFace::Bottom, BlockFace::Top)
+            }
+        };
+        let boxes = match self {
+            BlockShape::Cube => [Some(BlockBox::CELL), None],
+            BlockShape::Cross => [None, None],
+            BlockShape::Slab { top } => [Some(BlockBox::half(halves(top).0)), None],
+            BlockShape::Stairs { facing, top } => {
+                let (slab, step) = halves(top);
+                [
+                    Some(BlockBox::half(slab)),
+                    Some(BlockBox::half(step).intersect(BlockBox::half(facing))),
+                ]
+            }
+        };
+        boxes.into_iter().flatten()
+    }
+
+    /// Whether the shape fills the whole of the given face of its cell.
+    pub fn covers(self, face: BlockFace) -> bool {
+        // The boxes don't overlap, so the ones on the face fill it if their faces add up to all of it.
+        self.boxes()
+            .filter(|part| part.touches(face))
+            .map(|part| part.area(face))
+            .sum::<f32>()
+            >= 1.0
+    }
+}
+
+/// A box in a cell, from `min` to `max` with the cell going from 0 to 1 on every axis.
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BlockBox {
+    /// The whole cell.
+    pub const CELL: BlockBox = BlockBox {
+        min: Vec3::ZERO,
+        max: Vec3::ONE,
+    };
+
+    /// The half of the cell toward one of its faces.
+    pub fn half(face: BlockFace) -> Self {
+        let (axis, positive) = face.axis();
+        let mut half = Self::CELL;
+        if positive {
+            half.min[axis] = 0.5;
+        } else {
+            half.max[axis] = 0.5;
+        }
+        half
+    }
+
+    /// The part of the cell that's in both boxes.
+    pub fn intersect(self, other: BlockBox) -> Self {
+        Self {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
+        }
+    }
+
+    /// Where the face of the box is along its axis.
+    pub fn plane(self, face: BlockFace) -> f32 {
+        let (axis, positive) = face.axis();
+        if positive {
+            self.max[axis]
+        } else {
+            self.min[axis]
+        }
+    }
+
+    /// How big the face of the box is, the cell's are 1.
+    pub fn area(self, face: BlockFace) -> f32 {
+        let (axis, _) = face.axis();
+        let size = self.max - self.min;
+        size.x * size.y * size.z / size[axis]
+    }
+
+    /// Whether the face of the box lies on the same face of its cell.
+    pub fn touches(self, face: BlockFace) -> bool {
+        self.plane(face) == Self::CELL.plane(face)
+    }
+
+    /// Whether this box's face is inside the other's, along the two axes of the face.
+    pub fn within(self, other: BlockBox, face: BlockFace) -> bool {
+        let (axis, _) = face.axis();
+        (0..3)
+            .filter(|&other_axis| other_axis != axis)
+            .all(|axis| other.min[axis] <= self.min[axis] && self.max[axis] <= other.max[axis])
+    }
+
+    /// Whether the face of the box is pressed against the other box, inside the face of it that's opposite. It's
+    /// in the middle of the shape then, where it can't be seen.
+    pub fn hidden_by(self, other: BlockBox, face: BlockFace) -> bool {
+        other.plane(face.opposite()) == self.plane(face) && self.within(other, face)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BlockFace {
     Top,
     Bottom,
@@ -107,6 +1861,57 @@ pub enum BlockFace {
     Back,
 }
 
+impl BlockFace {
+    pub const ALL: [BlockFace; 6] = [
+        BlockFace::Top,
+        BlockFace::Bottom,
+        BlockFace::Left,
+        BlockFace::Right,
+        BlockFace::Front,
+        BlockFace::Back,
+    ];
+
+    /// The direction from a block to its neighbour on this side.
+    pub fn offset(self) -> IVec3 {
+        match self {
+            BlockFace::Top => IVec3::Y,
+            BlockFace::Bottom => IVec3::NEG_Y,
+            BlockFace::Left => IVec3::NEG_X,
+            BlockFace::Right => IVec3::X,
+            BlockFace::Front => IVec3::Z,
+            BlockFace::Back => IVec3::NEG_Z,
+        }
+    }
+
+    /// The face on the side of an offset, None if it isn't one step along an axis.
+    pub fn from_offset(offset: IVec3) -> Option<Self> {
+        Self::ALL.into_iter().find(|face| face.offset() == offset)
+    }
+
+    /// The axis the face is across, 0 to 2 for x to z, and whether it's on the positive side of it.
+    pub fn axis(self) -> (usize, bool) {
+        match self {
+            BlockFace::Left => (0, false),
+            BlockFace::Right => (0, true),
+            BlockFace::Bottom => (1, false),
+            BlockFace::Top => (1, true),
+            BlockFace::Back => (2, false),
+            BlockFace::Front => (2, true),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            BlockFace::Top => BlockFace::Bottom,
+            BlockFace::Bottom => BlockFace::Top,
+            BlockFace::Left => BlockFace::Right,
+            BlockFace::Right => BlockFace::Left,
+            BlockFace::Front => BlockFace::Back,
+            BlockFace::Back => BlockFace::Front,
+        }
+    }
+}
+
 // === Macros ===
 // #[macro_export]
 // macro_rules! get_single {
@@ -127,9 +1932,27 @@ pub struct IVec2XZ {
 }
 
 impl IVec2XZ {
-    pub fn new(x: i32, z: i32) -> Self {
+    pub const fn new(x: i32, z: i32) -> Self {
         IVec2XZ { x, z }
     }
+
+    /// The chunk a world position is in.
+    pub fn containing(pos: Vec3) -> Self {
+        Self::new(
+            (pos.x / CHUNK_SIZE as f32).floor() as i32,
+            (pos.z / CHUNK_SIZE as f32).floor() as i32,
+        )
+    }
+
+    /// The chunks closer than `radius` chunks to this one. A radius of 0 is no chunks at all.
+    pub fn within(self, radius: u32) -> HashSet<IVec2XZ> {
+        let radius = radius as i32;
+        (-radius..=radius)
+            .flat_map(|x| (-radius..=radius).map(move |z| IVec2XZ::new(x, z)))
+            .filter(|offset| offset.x * offset.x + offset.z * offset.z < radius * radius)
+            .map(|offset| self + offset)
+            .collect()
+    }
 }
 
 impl std::ops::Add for IVec2XZ {
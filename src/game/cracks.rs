@@ -0,0 +1,101 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::render::mesh::VertexAttributeValues;
+
+use crate::game::atlas::MISSING_TEXTURE;
+use crate::game::interaction::BreakProgress;
+use crate::prelude::*;
+
+/// How many stages the cracks go through while a block is broken, the textures `crack_0` and up in the atlas.
+pub const CRACK_STAGES: usize = 5;
+/// How far the overlay stands out of the faces of the block, so it isn't drawn into them.
+const CRACK_INFLATE: f32 = 0.001;
+
+/// A cube for every stage of the cracks, and the see-through material they're all drawn with.
+#[derive(Resource)]
+pub struct CrackAssets {
+    meshes: Vec<Handle<Mesh>>,
+    material: Handle<StandardMaterial>,
+}
+
+/// The cracks on the block that's being broken. There's only ever one.
+#[derive(Component)]
+pub struct CrackOverlay;
+
+/// The stage of the cracks a block is at, from how far along the breaking is.
+pub fn crack_stage(fraction: f32) -> usize {
+    ((fraction * CRACK_STAGES as f32) as usize).min(CRACK_STAGES - 1)
+}
+
+/// A cube around a block with the tile of a crack stage on every face. Without the texture it's the checker.
+pub fn crack_mesh(atlas: &GameTextureAtlas, stage: usize) -> Mesh {
+    let mut mesh: Mesh = shape::Cube::new(1.0 + 2.0 * CRACK_INFLATE).into();
+    let tile = atlas
+        .index(&format!("crack_{}", stage))
+        .or_else(|| atlas.index(MISSING_TEXTURE))
+        .unwrap_or_default();
+    let [min, _, max, _] = atlas.tile_uvs(tile).map(Vec2::from);
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0) {
+        for uv in uvs {
+            *uv = (min + Vec2::from(*uv) * (max - min)).to_array();
+        }
+    }
+    mesh
+}
+
+/// The atlas is set up at startup, the cracks are cut out of it after.
+pub fn setup_crack_assets(
+    mut commands: Commands,
+    game_atlas: Res<GameTextureAtlas>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(CrackAssets {
+        meshes: (0..CRACK_STAGES)
+            .map(|stage| meshes.add(crack_mesh(&game_atlas, stage)))
+            .collect(),
+        material: materials.add(StandardMaterial {
+            base_color_texture: Some(game_atlas.atlas.texture.clone()),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+/// Puts the cracks of the stage the breaking is at over the block being broken, and takes them away once it's
+/// broken or the button is let go. The chunk isn't remeshed for them.
+pub fn update_crack_overlay(
+    mut commands: Commands,
+    progress: Res<BreakProgress>,
+    assets: Res<CrackAssets>,
+    mut overlays: Query<(Entity, &mut Transform, &mut Handle<Mesh>), With<CrackOverlay>>,
+) {
+    let crack = progress
+        .fraction()
+        .map(|(pos, fraction)| (pos.as_vec3() + Vec3::splat(0.5), crack_stage(fraction)));
+    match (overlays.get_single_mut(), crack) {
+        (Ok((_, mut transform, mut mesh)), Some((center, stage))) => {
+            transform.translation = center;
+            if *mesh != assets.meshes[stage] {
+                *mesh = assets.meshes[stage].clone();
+            }
+        }
+        (Ok((entity, ..)), None) => {
+            commands.entity(entity).despawn_recursive();
+        }
+        (Err(_), Some((center, stage))) => {
+            commands.spawn((
+                Name::new("Cracks"),
+                CrackOverlay,
+                PbrBundle {
+                    mesh: assets.meshes[stage].clone(),
+                    material: assets.material.clone(),
+                    transform: Transform::from_translation(center),
+                    ..default()
+                },
+                NotShadowCaster,
+            ));
+        }
+        (Err(_), None) => {}
+    }
+}
@@ -1,36 +1,234 @@
-use std::collections::HashSet;
-
 use bevy::window::PresentMode;
 use bevy_prototype_debug_lines::DebugLines;
 
+use crate::game::chunk::{remesh_chunks, reset_chunks, ChunkMaterials};
+use crate::game::fluid_material::FluidMaterial;
+use crate::game::generator::SharedGenerator;
+use crate::game::interaction::reach_block;
+use crate::game::packed::PackedChunkMaterial;
 use crate::prelude::*;
 
-pub fn debug_keyboard(
+/// The debug keys only work while this is held, so they can't be pressed by accident.
+pub const DEBUG_MODIFIER: KeyCode = KeyCode::F3;
+/// How long a destructive chord waits for the second press, in seconds.
+pub const CONFIRM_WINDOW: f32 = 1.0;
+/// How far the highlight of the targeted block sticks out of it on every side.
+pub const HIGHLIGHT_INFLATION: f32 = 0.002;
+/// How long the chunk borders show a chunk that was loaded or unloaded, in seconds.
+pub const CHUNK_FLASH_SECONDS: f32 = 1.0;
+
+/// Everything the debug chords can do.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugAction {
+    PauseGeneration,
+    ResetChunks,
+    ChunkBorders,
+    Fullbright,
+    VSync,
+    Freecam,
+    StreamAroundFreecam,
+    BugReport,
+    AmbientOcclusion,
+    TextureFilter,
+    PackedMeshes,
+    GrowTree,
+    PasteSchematic,
+}
+
+impl DebugAction {
+    pub const ALL: [DebugAction; 13] = [
+        DebugAction::PauseGeneration,
+        DebugAction::ResetChunks,
+        DebugAction::ChunkBorders,
+        DebugAction::Fullbright,
+        DebugAction::VSync,
+        DebugAction::Freecam,
+        DebugAction::StreamAroundFreecam,
+        DebugAction::BugReport,
+        DebugAction::AmbientOcclusion,
+        DebugAction::TextureFilter,
+        DebugAction::PackedMeshes,
+        DebugAction::GrowTree,
+        DebugAction::PasteSchematic,
+    ];
+
+    /// The key pressed together with [`DEBUG_MODIFIER`].
+    pub fn key(self) -> KeyCode {
+        match self {
+            DebugAction::PauseGeneration => KeyCode::P,
+            DebugAction::ResetChunks => KeyCode::R,
+            DebugAction::ChunkBorders => KeyCode::G,
+            DebugAction::Fullbright => KeyCode::F,
+            DebugAction::VSync => KeyCode::V,
+            DebugAction::Freecam => KeyCode::C,
+            DebugAction::StreamAroundFreecam => KeyCode::L,
+            DebugAction::BugReport => KeyCode::B,
+            DebugAction::AmbientOcclusion => KeyCode::O,
+            DebugAction::TextureFilter => KeyCode::T,
+            DebugAction::PackedMeshes => KeyCode::M,
+            DebugAction::GrowTree => KeyCode::N,
+            DebugAction::PasteSchematic => KeyCode::I,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            DebugAction::PauseGeneration => "Pause Chunk generation (far Chunks still unload)",
+            DebugAction::ResetChunks => "Reset Chunks (press twice)",
+            DebugAction::ChunkBorders => "Toggle Chunks Borders",
+            DebugAction::Fullbright => "Toggle Fullbright",
+            DebugAction::VSync => "Toggle VSync",
+            DebugAction::Freecam => "Toggle Freecam",
+            DebugAction::StreamAroundFreecam => "Stream Chunks around the Freecam",
+            DebugAction::BugReport => "Write a Bug Report",
+            DebugAction::AmbientOcclusion => "Toggle Ambient Occlusion",
+            DebugAction::TextureFilter => "Toggle Texture Filtering",
+            DebugAction::PackedMeshes => "Toggle Packed Chunk Meshes",
+            DebugAction::GrowTree => "Grow a Tree on the targeted Block",
+            DebugAction::PasteSchematic => "Paste the Schematic on the targeted Block",
+        }
+    }
+
+    /// Destructive actions only happen when their chord is pressed twice within [`CONFIRM_WINDOW`].
+    pub fn needs_confirmation(self) -> bool {
+        self == DebugAction::ResetChunks
+    }
+
+    /// The line of the controls list, like "F3 + R - Reset Chunks (press twice)".
+    pub fn control_line(self) -> String {
+        format!(
+            "{:?} + {:?} - {}",
+            DEBUG_MODIFIER,
+            self.key(),
+            self.description()
+        )
+    }
+}
+
+/// Turns the keys pressed while [`DEBUG_MODIFIER`] is held into debug actions, once per frame.
+#[derive(Resource, Default, Debug)]
+pub struct DebugChords {
+    /// A destructive action waiting for its second press, and until when it waits.
+    pending: Option<(DebugAction, f32)>,
+    triggered: Vec<DebugAction>,
+}
+
+impl DebugChords {
+    /// Reads a frame of input. Only keys pressed while the modifier is already down (or goes down the same frame)
+    /// count, pressing the modifier after the key does nothing.
+    pub fn update(
+        &mut self,
+        modifier_held: bool,
+        just_pressed: impl IntoIterator<Item = KeyCode>,
+        now: f32,
+    ) {
+        self.triggered.clear();
+        if self.pending.is_some_and(|(_, until)| now > until) {
+            self.pending = None;
+        }
+        if !modifier_held {
+            return;
+        }
+
+        for key in just_pressed {
+            let Some(action) = DebugAction::ALL
+                .into_iter()
+                .find(|action| action.key() == key)
+            else {
+                continue;
+            };
+            if !action.needs_confirmation() {
+                self.triggered.push(action);
+            } else if self.pending.is_some_and(|(pending, _)| pending == action) {
+                self.pending = None;
+                self.triggered.push(action);
+            } else {
+                self.pending = Some((action, now + CONFIRM_WINDOW));
+                info!("Press {} again to confirm", action.control_line());
+            }
+        }
+    }
+
+    /// Whether the action's chord was completed this frame.
+    pub fn triggered(&self, action: DebugAction) -> bool {
+        self.triggered.contains(&action)
+    }
+}
+
+pub fn detect_debug_chords(
     keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut chords: ResMut<DebugChords>,
+) {
+    chords.update(
+        keyboard_input.pressed(DEBUG_MODIFIER),
+        keyboard_input.get_just_pressed().copied(),
+        time.elapsed_seconds(),
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn debug_keyboard(
+    chords: Res<DebugChords>,
     mut generating: ResMut<Generating>,
     mut commands: Commands,
     chunk_query: Query<Entity, With<ChunkMesh>>,
-    mut chunks_loaded: ResMut<ChunksLoaded>,
+    (mut chunks_loaded, mut loader): (ResMut<ChunksLoaded>, ResMut<ChunkLoader>),
+    (mut chunk_map, mut mesh_cache, mut unloaded): (
+        ResMut<ChunkMap>,
+        ResMut<MeshCache>,
+        EventWriter<ChunkUnloaded>,
+    ),
     mut chunk_border_toggled: ResMut<ChunkBorderToggled>,
+    mut fullbright: ResMut<Fullbright>,
+    (mut ambient_occlusion, mut packed_meshes): (ResMut<AmbientOcclusion>, ResMut<PackedMeshes>),
+    mut settings: ResMut<Settings>,
     mut windows: Query<&mut Window>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::P) {
+    if chords.triggered(DebugAction::PauseGeneration) {
         // Toggle the generating resource.
         generating.0 = !generating.0;
     }
-    if keyboard_input.just_pressed(KeyCode::R) {
-        // Delete all chunks.
-        for entity in chunk_query.iter() {
-            commands.entity(entity).despawn_recursive();
-        }
-        chunks_loaded.chunks = HashSet::new();
+    if chords.triggered(DebugAction::ResetChunks) {
+        reset_chunks(
+            &mut commands,
+            &chunk_query,
+            &mut chunks_loaded,
+            &mut loader,
+            &mut chunk_map,
+            &mut mesh_cache,
+            &mut unloaded,
+        );
     }
-    if keyboard_input.just_pressed(KeyCode::G) {
+    if chords.triggered(DebugAction::ChunkBorders) {
         // Toggle the chunk border.
         chunk_border_toggled.0 = !chunk_border_toggled.0;
     }
+    if chords.triggered(DebugAction::Fullbright) {
+        fullbright.0 = !fullbright.0;
+    }
+    if chords.triggered(DebugAction::AmbientOcclusion) {
+        ambient_occlusion.0 = !ambient_occlusion.0;
+        info!(
+            "Ambient occlusion: {}",
+            if ambient_occlusion.0 { "on" } else { "off" }
+        );
+    }
+    if chords.triggered(DebugAction::PackedMeshes) {
+        packed_meshes.0 = !packed_meshes.0;
+        info!(
+            "Packed chunk meshes: {}",
+            if packed_meshes.0 { "on" } else { "off" }
+        );
+    }
+    if chords.triggered(DebugAction::TextureFilter) {
+        // Only until the settings file is changed, it isn't saved.
+        let graphics = &mut settings.graphics;
+        graphics.texture_filter = graphics.texture_filter.toggled();
+        info!("Texture filter: {:?}", graphics.texture_filter);
+    }
     // Toggle VSync
-    if keyboard_input.just_pressed(KeyCode::V) {
+    if chords.triggered(DebugAction::VSync) {
         let mut window = windows.single_mut();
 
         window.present_mode = if matches!(window.present_mode, PresentMode::AutoVsync) {
@@ -42,23 +240,109 @@ pub fn debug_keyboard(
     }
 }
 
+/// Meshes the loaded chunks again when the ambient occlusion or the packing is switched, they're baked into their
+/// meshes. The old meshes stay until the new ones are done, and the edits are kept. The cached meshes are thrown
+/// away.
+pub fn apply_mesh_options(
+    mut commands: Commands,
+    (ambient_occlusion, packed_meshes): (Res<AmbientOcclusion>, Res<PackedMeshes>),
+    (chunks_loaded, remeshing): (Res<ChunksLoaded>, Query<&Remeshing>),
+    (chunk_map, mut mesh_cache): (Res<ChunkMap>, ResMut<MeshCache>),
+    game_atlas: Res<GameTextureAtlas>,
+    generator: Res<SharedGenerator>,
+) {
+    let occlusion_switched = ambient_occlusion.is_changed() && !ambient_occlusion.is_added();
+    let packing_switched = packed_meshes.is_changed() && !packed_meshes.is_added();
+    if !occlusion_switched && !packing_switched {
+        return;
+    }
+    mesh_cache.clear();
+    let loaded: Vec<ChunkSection> = chunk_map
+        .chunks
+        .keys()
+        .flat_map(|&position| ChunkSection::all(position))
+        .collect();
+    remesh_chunks(
+        &mut commands,
+        loaded,
+        &remeshing,
+        &chunks_loaded,
+        &chunk_map,
+        &game_atlas,
+        &generator,
+        *ambient_occlusion,
+        *packed_meshes,
+    );
+}
+
+/// Switches the shared chunk materials between lit and unlit. Only the materials change, so nothing is remeshed.
+pub fn apply_fullbright(
+    fullbright: Res<Fullbright>,
+    chunk_materials: Res<ChunkMaterials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    (mut packed_materials, mut fluid_materials): (
+        ResMut<Assets<PackedChunkMaterial>>,
+        ResMut<Assets<FluidMaterial>>,
+    ),
+) {
+    if !fullbright.is_changed() {
+        return;
+    }
+    if let Some(material) = materials.get_mut(&chunk_materials.solid) {
+        material.unlit = fullbright.0;
+    }
+    if let Some(material) = packed_materials.get_mut(&chunk_materials.packed) {
+        material.unlit = fullbright.0;
+    }
+    for handle in chunk_materials.fluids() {
+        if let Some(material) = fluid_materials.get_mut(handle) {
+            material.unlit = fullbright.0;
+        }
+    }
+}
+
 pub fn chunk_border(
     mut lines: ResMut<DebugLines>,
     camera: Query<&Transform, With<Camera>>,
     chunk_border_toggled: Res<ChunkBorderToggled>,
+    (mut ready, mut unloaded): (EventReader<ChunkReady>, EventReader<ChunkUnloaded>),
 ) {
     // Check if the chunk border should be drawn.
     if chunk_border_toggled.0 {
+        ready.clear();
+        unloaded.clear();
         return;
     }
 
+    // The chunks that just loaded flash green for a moment and the ones that unloaded grey, at the height of the
+    // camera so they can be seen from wherever it is.
+    let height = camera.single().translation.y;
+    let flashes = ready
+        .iter()
+        .map(|chunk| (chunk.position, Color::GREEN))
+        .chain(unloaded.iter().map(|chunk| (chunk.0, Color::GRAY)));
+    for (position, color) in flashes {
+        let min = Vec3::new(
+            (position.x * CHUNK_SIZE as i32) as f32,
+            height,
+            (position.z * CHUNK_SIZE as i32) as f32,
+        );
+        let size = CHUNK_SIZE as f32;
+        let corners = [
+            min,
+            min + Vec3::new(size, 0.0, 0.0),
+            min + Vec3::new(size, 0.0, size),
+            min + Vec3::new(0.0, 0.0, size),
+        ];
+        for i in 0..4 {
+            lines.line_colored(corners[i], corners[(i + 1) % 4], CHUNK_FLASH_SECONDS, color);
+        }
+    }
+
     // Draw a "box" around the selected chunk.
     // Determine the current from the camera position
     let camera_position = camera.single().translation;
-    let current_chunk: IVec2XZ = IVec2XZ::new(
-        (camera_position.x / CHUNK_SIZE as f32).floor() as i32,
-        (camera_position.z / CHUNK_SIZE as f32).floor() as i32,
-    );
+    let current_chunk: IVec2XZ = IVec2XZ::containing(camera_position);
 
     // Draw the lines around the current chunk.
     let x1 = current_chunk.x * CHUNK_SIZE as i32;
@@ -72,40 +356,79 @@ pub fn chunk_border(
     let duration = 0.0;
     let color = Color::rgb(1.0, 0.0, 0.0);
 
-    let corners = [
-        [x1, y1, z1],
-        [x2, y1, z1],
-        [x1, y2, z1],
-        [x1, y1, z2],
-        [x2, y2, z1],
-        [x2, y1, z2],
-        [x1, y2, z2],
-        [x2, y2, z2],
-    ];
+    // Draw all of the lines.
+    let min = Vec3::new(x1 as f32, y1 as f32, z1 as f32);
+    let max = Vec3::new(x2 as f32, y2 as f32, z2 as f32);
+    for (start, end) in box_edges(min, max) {
+        lines.line_colored(start, end, duration, color);
+    }
 
-    // Get all of the lines to draw.
-    let lines_to_draw = [
-        [corners[0], corners[1]],
-        [corners[0], corners[2]],
-        [corners[0], corners[3]],
-        [corners[1], corners[4]],
-        [corners[1], corners[5]],
-        [corners[2], corners[4]],
-        [corners[2], corners[6]],
-        [corners[3], corners[5]],
-        [corners[3], corners[6]],
-        [corners[4], corners[7]],
-        [corners[5], corners[7]],
-        [corners[6], corners[7]],
-    ];
+    // And a yellow one around the section the camera is in, the closest one if it's above or below the world.
+    let (min, max) = section_box(camera_position);
+    for (start, end) in box_edges(min, max) {
+        lines.line_colored(start, end, duration, Color::YELLOW);
+    }
+}
 
-    // Draw all of the lines.
-    for line in lines_to_draw.iter() {
-        lines.line_colored(
-            Vec3::new(line[0][0] as f32, line[0][1] as f32, line[0][2] as f32),
-            Vec3::new(line[1][0] as f32, line[1][1] as f32, line[1][2] as f32),
-            duration,
-            color,
-        );
+/// The corners of the section of the chunk a position is in. Above and below the world it's the top or the bottom one.
+pub fn section_box(position: Vec3) -> (Vec3, Vec3) {
+    let chunk = IVec2XZ::containing(position);
+    let height = SECTION_HEIGHT as f32;
+    let index = (position.y / height)
+        .floor()
+        .clamp(0.0, (SECTIONS - 1) as f32);
+    let min = Vec3::new(
+        (chunk.x * CHUNK_SIZE as i32) as f32,
+        index * height,
+        (chunk.z * CHUNK_SIZE as i32) as f32,
+    );
+    (
+        min,
+        min + Vec3::new(CHUNK_SIZE as f32, height, CHUNK_SIZE as f32),
+    )
+}
+
+/// The 12 edges of a box.
+pub fn box_edges(min: Vec3, max: Vec3) -> [(Vec3, Vec3); 12] {
+    let corner = |x: bool, y: bool, z: bool| {
+        Vec3::new(
+            if x { max.x } else { min.x },
+            if y { max.y } else { min.y },
+            if z { max.z } else { min.z },
+        )
+    };
+    let mut edges = [(Vec3::ZERO, Vec3::ZERO); 12];
+    for i in 0..4 {
+        let (a, b) = (i & 1 == 1, i & 2 == 2);
+        edges[i] = (corner(false, a, b), corner(true, a, b));
+        edges[i + 4] = (corner(a, false, b), corner(a, true, b));
+        edges[i + 8] = (corner(a, b, false), corner(a, b, true));
+    }
+    edges
+}
+
+/// The box drawn around the targeted block, a bit bigger than the block so it isn't hidden in its faces.
+pub fn highlight_box(pos: IVec3) -> (Vec3, Vec3) {
+    let min = pos.as_vec3();
+    (
+        min - Vec3::splat(HIGHLIGHT_INFLATION),
+        min + Vec3::ONE + Vec3::splat(HIGHLIGHT_INFLATION),
+    )
+}
+
+/// Draws a black wireframe around the block the player can break, the same one the reach ray finds. Nothing is
+/// drawn when it's out of reach, fluids are looked through.
+pub fn highlight_targeted_block(
+    mut lines: ResMut<DebugLines>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+    chunk_map: Res<ChunkMap>,
+) {
+    let camera = camera_query.single();
+    let Some(hit) = reach_block(&chunk_map, camera.translation, camera.forward()) else {
+        return;
+    };
+    let (min, max) = highlight_box(hit.pos);
+    for (start, end) in box_edges(min, max) {
+        lines.line_colored(start, end, 0.0, Color::BLACK);
     }
 }
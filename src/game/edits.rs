@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+
+use crate::game::leaves::LeafDecay;
+use crate::prelude::*;
+
+/// How many actions can be undone. The oldest ones are forgotten first.
+pub const EDIT_HISTORY_LIMIT: usize = 1024;
+
+/// A block the player changed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EditRecord {
+    pub pos: IVec3,
+    pub old: BlockType,
+    pub new: BlockType,
+}
+
+impl EditRecord {
+    /// The edit that takes this one back.
+    pub fn inverse(self) -> Self {
+        Self {
+            pos: self.pos,
+            old: self.new,
+            new: self.old,
+        }
+    }
+}
+
+/// The block edits of the player this session, for Ctrl+Z and Ctrl+Y. Changes made by the world itself, like
+/// flowing water, are never recorded.
+///
+/// Every action is undone as a whole. A single edit is an action on its own, and the edits between
+/// [`EditHistory::begin_batch`] and [`EditHistory::end_batch`], like the blocks of a stamped structure, are one
+/// action together.
+#[derive(Resource, Default, Debug)]
+pub struct EditHistory {
+    undo: VecDeque<Vec<EditRecord>>,
+    redo: Vec<Vec<EditRecord>>,
+    batch: Option<Vec<EditRecord>>,
+}
+
+impl EditHistory {
+    /// Records an edit of the player. Anything that was undone can't be redone anymore.
+    pub fn record(&mut self, edit: EditRecord) {
+        if edit.old == edit.new {
+            return;
+        }
+        match &mut self.batch {
+            Some(batch) => batch.push(edit),
+            None => self.push(vec![edit]),
+        }
+    }
+
+    /// Groups the edits recorded from now on into one action, until [`EditHistory::end_batch`].
+    pub fn begin_batch(&mut self) {
+        self.end_batch();
+        self.batch = Some(Vec::new());
+    }
+
+    pub fn end_batch(&mut self) {
+        if let Some(batch) = self.batch.take().filter(|batch| !batch.is_empty()) {
+            self.push(batch);
+        }
+    }
+
+    fn push(&mut self, action: Vec<EditRecord>) {
+        self.redo.clear();
+        if self.undo.len() == EDIT_HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(action);
+    }
+
+    /// Takes back the last action. Returns the edits to apply, in the order to apply them.
+    pub fn undo(&mut self) -> Option<Vec<EditRecord>> {
+        self.end_batch();
+        let action = self.undo.pop_back()?;
+        let inverse = action.iter().rev().map(|edit| edit.inverse()).collect();
+        self.redo.push(action);
+        Some(inverse)
+    }
+
+    /// Does the last undone action again. Returns the edits to apply, in the order to apply them.
+    pub fn redo(&mut self) -> Option<Vec<EditRecord>> {
+        self.end_batch();
+        let action = self.redo.pop()?;
+        self.undo.push_back(action.clone());
+        Some(action)
+    }
+
+    #[allow(dead_code)] // Nothing shows whether there's anything to undo yet
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.batch.as_ref().is_some_and(|batch| !batch.is_empty())
+    }
+
+    #[allow(dead_code)]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+/// The chunk a block is in, and the neighbours whose faces it touches or shades if it's on the border.
+pub fn touched_chunks(pos: IVec3) -> Vec<IVec2XZ> {
+    let (chunk, local) = world_to_chunk_and_local(pos);
+    let mut chunks = vec![chunk];
+    let last = CHUNK_SIZE as u32 - 1;
+    let x = if local.x == 0 {
+        -1
+    } else if local.x == last {
+        1
+    } else {
+        0
+    };
+    let z = if local.z == 0 {
+        -1
+    } else if local.z == last {
+        1
+    } else {
+        0
+    };
+    if x != 0 {
+        chunks.push(chunk + IVec2XZ::new(x, 0));
+    }
+    if z != 0 {
+        chunks.push(chunk + IVec2XZ::new(0, z));
+    }
+    // A block in the corner shades the corner of the chunk across the diagonal too.
+    if x != 0 && z != 0 {
+        chunks.push(chunk + IVec2XZ::new(x, z));
+    }
+    chunks
+}
+
+/// The section a block is in, and the sections next to it whose faces it touches or shades if it's on the border of
+/// the section, in the same chunk or across the border of the chunk.
+pub fn touched_sections(pos: IVec3) -> Vec<ChunkSection> {
+    let section = ChunkSection::containing(pos);
+    let y = pos.y as usize % SECTION_HEIGHT;
+    let mut indices = vec![section.index];
+    if y == 0 && section.index > 0 {
+        indices.push(section.index - 1);
+    }
+    if y == SECTION_HEIGHT - 1 && section.index < SECTIONS - 1 {
+        indices.push(section.index + 1);
+    }
+    touched_chunks(pos)
+        .into_iter()
+        .flat_map(|position| {
+            indices
+                .iter()
+                .map(move |&index| ChunkSection { position, index })
+        })
+        .collect()
+}
+
+/// Applies undone or redone edits to the loaded chunks. Returns the sections that need a new mesh, the ones that
+/// changed and their neighbours next to the edits on a border.
+///
+/// Edits in chunks that have been unloaded since are skipped with a warning, the chunk is generated from scratch
+/// when it comes back anyway.
+pub fn apply_edits(
+    chunk_map: &mut ChunkMap,
+    decay: &mut LeafDecay,
+    edits: &[EditRecord],
+) -> Vec<ChunkSection> {
+    let mut changed = Vec::new();
+    for edit in edits {
+        if edit.pos.y < 0 || edit.pos.y >= CHUNK_HEIGHT as i32 {
+            continue;
+        }
+        let (chunk, _) = world_to_chunk_and_local(edit.pos);
+        if !chunk_map.chunks.contains_key(&chunk) {
+            warn!(
+                "Skipping the edit at {} in the unloaded chunk ({}, {})",
+                edit.pos, chunk.x, chunk.z
+            );
+            continue;
+        }
+        chunk_map.set_block(edit.pos, edit.new);
+        decay.edited(edit, |pos| chunk_map.get_block(pos));
+        for section in touched_sections(edit.pos) {
+            if !changed.contains(&section) {
+                changed.push(section);
+            }
+        }
+    }
+    changed
+}
@@ -0,0 +1,297 @@
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::game::biome::{biome_at, Biome};
+use crate::game::camera::distance_fog;
+use crate::game::chunk::FLUID_SURFACE;
+use crate::game::rules::WorldSeed;
+use crate::game::suffocation::eye_cell;
+use crate::prelude::*;
+
+/// Seconds between two samples of the environment.
+pub const ENVIRONMENT_SAMPLE_INTERVAL: f32 = 1.0;
+/// How far around the camera lava is looked for, in blocks on each axis.
+pub const LAVA_SCAN_RADIUS: i32 = 6;
+/// Above this height it's cold in any biome, and freezing 40 blocks higher.
+pub const FROST_ALTITUDE: f32 = 140.0;
+/// How many blocks of ground have to be overhead before lava makes the air shimmer.
+pub const HEAT_DEPTH: i32 = 8;
+/// How fast the effects fade in and out, in strength per second.
+const EFFECT_FADE_SPEED: f32 = 0.5;
+const OVERLAY_TEXTURE_SIZE: usize = 128;
+/// Under water the fog is thick and blue, close enough that the surface can still be seen from below.
+pub const UNDERWATER_FOG_COLOR: Color = Color::rgb(0.04, 0.16, 0.32);
+pub const UNDERWATER_FOG: FogFalloff = FogFalloff::Linear {
+    start: 0.0,
+    end: 24.0,
+};
+
+/// What the air around the camera is like, sampled once per second. The screen effects read it, and anything else
+/// that depends on the surroundings can too.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct EnvironmentSample {
+    pub biome: Biome,
+    pub altitude: f32,
+    /// How many blocks the highest block overhead that blocks the sky is above the camera, 0 under the open sky.
+    pub depth: i32,
+    /// How far the closest lava within [`LAVA_SCAN_RADIUS`] is.
+    pub lava_distance: Option<f32>,
+    /// The y of the direction towards the sun, below zero at night.
+    pub sun_height: f32,
+}
+
+impl Default for EnvironmentSample {
+    fn default() -> Self {
+        Self {
+            biome: Biome::Plains,
+            altitude: WORLD_SPAWN.y,
+            depth: 0,
+            lava_distance: None,
+            sun_height: 1.0,
+        }
+    }
+}
+
+impl EnvironmentSample {
+    /// Samples the environment at a position, under the highest block of its column that blocks the sky
+    /// ([`ChunkMap::sky_top`]). Unloaded blocks count as air.
+    pub fn at(
+        position: Vec3,
+        biome: Biome,
+        sun_height: f32,
+        sky_top: Option<i32>,
+        block_at: impl Fn(IVec3) -> Option<BlockType>,
+    ) -> Self {
+        let cell = position.floor().as_ivec3();
+        let depth = sky_top.map_or(0, |top| (top - cell.y).max(0));
+
+        let radius = LAVA_SCAN_RADIUS;
+        let lava_distance = (-radius..=radius)
+            .flat_map(|x| {
+                (-radius..=radius)
+                    .flat_map(move |y| (-radius..=radius).map(move |z| IVec3::new(x, y, z)))
+            })
+            .map(|offset| cell + offset)
+            .filter(|&pos| block_at(pos) == Some(BlockType::Lava))
+            .map(|pos| (pos.as_vec3() + 0.5).distance(position))
+            .min_by(f32::total_cmp);
+
+        Self {
+            biome,
+            altitude: position.y,
+            depth,
+            lava_distance,
+            sun_height,
+        }
+    }
+
+    /// How cold the air is, from 0 to 1. Cold biomes at night and high up, but not under a roof.
+    pub fn frost(&self) -> f32 {
+        if self.depth > 0 {
+            return 0.0;
+        }
+        let night = (-self.sun_height / 0.2).clamp(0.0, 1.0);
+        let cold_night = if self.biome.is_cold() { night } else { 0.0 };
+        let altitude = ((self.altitude - FROST_ALTITUDE) / 40.0).clamp(0.0, 1.0);
+        cold_night.max(altitude)
+    }
+
+    /// How hot the air is, from 0 to 1. Only deep underground, the closer to lava the hotter.
+    pub fn heat(&self) -> f32 {
+        match self.lava_distance {
+            Some(distance) if self.depth >= HEAT_DEPTH => {
+                (1.0 - distance / (LAVA_SCAN_RADIUS as f32 + 1.0)).clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+pub fn update_environment_sample(
+    time: Res<Time>,
+    seed: Res<WorldSeed>,
+    chunk_map: Res<ChunkMap>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+    sun_query: Query<&Transform, With<DirectionalLight>>,
+    mut sample: ResMut<EnvironmentSample>,
+    mut since_sample: Local<Option<f32>>,
+) {
+    let elapsed = since_sample.map_or(ENVIRONMENT_SAMPLE_INTERVAL, |since| {
+        since + time.delta_seconds()
+    });
+    if elapsed < ENVIRONMENT_SAMPLE_INTERVAL {
+        *since_sample = Some(elapsed);
+        return;
+    }
+    *since_sample = Some(0.0);
+
+    let position = camera_query.single().translation;
+    // The light shines away from the sun.
+    let sun_height = sun_query.get_single().map_or(1.0, |sun| -sun.forward().y);
+    let biome = biome_at(
+        IVec2XZ::new(position.x.floor() as i32, position.z.floor() as i32),
+        seed.0,
+    );
+    let cell = position.floor().as_ivec3();
+    let sky_top = chunk_map.sky_top(IVec2XZ::new(cell.x, cell.z));
+    *sample = EnvironmentSample::at(position, biome, sun_height, sky_top, |pos| {
+        chunk_map.get_block(pos)
+    });
+}
+
+/// Frost creeping in from the edges of the screen in the cold.
+#[derive(Component)]
+pub struct FrostOverlay;
+
+/// Wavy bands of hot air near lava. There is no post processing, the overlay wobbles instead of distorting the view.
+#[derive(Component)]
+pub struct HeatOverlay;
+
+/// A texture with the given alpha for each pixel, from its position in `0.0..1.0` on both axes.
+fn overlay_texture(alpha: impl Fn(f32, f32) -> f32) -> Image {
+    let size = OVERLAY_TEXTURE_SIZE;
+    let mut data = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let (u, v) = (
+                (x as f32 + 0.5) / size as f32,
+                (y as f32 + 0.5) / size as f32,
+            );
+            let alpha = (alpha(u, v).clamp(0.0, 1.0) * 255.0) as u8;
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size as u32,
+            height: size as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+pub fn setup_environment_overlays(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    // Clear in the middle, thickening towards the edges and the corners.
+    let vignette = images.add(overlay_texture(|u, v| {
+        let edge = Vec2::new(u - 0.5, v - 0.5).abs().max_element() * 2.0;
+        let corner = Vec2::new(u - 0.5, v - 0.5).length() * 2.0;
+        ((edge.max(corner * 0.85) - 0.6) / 0.4).powi(2)
+    }));
+    let ripples = images.add(overlay_texture(|u, v| {
+        let wave = (v * 40.0 + (u * 9.0).sin() * 2.0).sin() * 0.5 + 0.5;
+        wave * wave * 0.5
+    }));
+
+    let overlay = |image: Handle<Image>| ImageBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        image: UiImage::new(image),
+        visibility: Visibility::Hidden,
+        // Behind the rest of the HUD, like the other overlays.
+        z_index: ZIndex::Global(-1),
+        ..default()
+    };
+    commands.spawn((
+        Name::new("Frost Overlay"),
+        overlay(vignette),
+        FrostOverlay,
+        UI,
+    ));
+    let mut heat = overlay(ripples);
+    // Bigger than the screen, so the edges never show when it sways.
+    heat.style.width = Val::Percent(104.0);
+    heat.style.height = Val::Percent(104.0);
+    commands.spawn((Name::new("Heat Overlay"), heat, HeatOverlay, UI));
+}
+
+/// Fades the frost and the heat overlays towards what the environment sample calls for.
+#[allow(clippy::type_complexity)]
+pub fn update_environment_effects(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    sample: Res<EnvironmentSample>,
+    mut strengths: Local<(f32, f32)>,
+    mut frost_query: Query<
+        (&mut BackgroundColor, &mut Visibility),
+        (With<FrostOverlay>, Without<HeatOverlay>),
+    >,
+    mut heat_query: Query<
+        (&mut BackgroundColor, &mut Visibility, &mut Style),
+        (With<HeatOverlay>, Without<FrostOverlay>),
+    >,
+) {
+    let fade = |current: f32, target: f32| {
+        let step = EFFECT_FADE_SPEED * time.delta_seconds();
+        current + (target - current).clamp(-step, step)
+    };
+    let graphics = &settings.graphics;
+    let frost_target = if graphics.frost_effect {
+        sample.frost()
+    } else {
+        0.0
+    };
+    let heat_target = if graphics.heat_effect {
+        sample.heat()
+    } else {
+        0.0
+    };
+    let (frost, heat) = (
+        fade(strengths.0, frost_target),
+        fade(strengths.1, heat_target),
+    );
+    *strengths = (frost, heat);
+
+    let shown = |strength: f32| {
+        if strength > 0.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    };
+    for (mut color, mut visibility) in frost_query.iter_mut() {
+        *color = Color::rgba(0.85, 0.93, 1.0, frost * 0.8).into();
+        visibility.set_if_neq(shown(frost));
+    }
+    let seconds = time.elapsed_seconds();
+    for (mut color, mut visibility, mut style) in heat_query.iter_mut() {
+        *color = Color::rgba(1.0, 0.6, 0.3, heat * (0.12 + 0.04 * (seconds * 3.0).sin())).into();
+        visibility.set_if_neq(shown(heat));
+        // A slow sway of a few pixels makes the bands look like rising air.
+        style.top = Val::Px((seconds * 2.3).sin() * 3.0 - 6.0);
+        style.left = Val::Px((seconds * 1.7).cos() * 2.0 - 6.0);
+    }
+}
+
+/// The fluid the camera is in. Unloaded blocks count as air, and so does the top of a fluid's surface block
+/// above [`FLUID_SURFACE`].
+pub fn eye_fluid(eye: Vec3, block_at: impl Fn(IVec3) -> Option<BlockType>) -> Option<BlockType> {
+    let cell = eye_cell(eye);
+    let fluid = block_at(cell).filter(|block| block.is_fluid())?;
+    let surface = block_at(cell + IVec3::Y) != Some(fluid);
+    (!surface || eye.y - cell.y as f32 <= FLUID_SURFACE).then_some(fluid)
+}
+
+/// Puts the camera in the underwater fog while it's in water, and back in the distance fog once it's out. It runs
+/// after the sky, which colours the distance fog.
+pub fn update_underwater_fog(
+    chunk_map: Res<ChunkMap>,
+    mut camera_query: Query<(&Transform, &mut FogSettings), With<Camera3d>>,
+    mut was_underwater: Local<bool>,
+) {
+    let (camera, mut fog) = camera_query.single_mut();
+    let underwater =
+        eye_fluid(camera.translation, |pos| chunk_map.get_block(pos)) == Some(BlockType::Water);
+    if underwater {
+        fog.color = UNDERWATER_FOG_COLOR;
+        fog.falloff = UNDERWATER_FOG;
+    } else if *was_underwater {
+        fog.falloff = distance_fog();
+    }
+    *was_underwater = underwater;
+}
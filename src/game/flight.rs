@@ -0,0 +1,153 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+
+use crate::prelude::*;
+
+/// Tuning for the player's flight.
+#[derive(Resource, Clone, Debug)]
+pub struct PlayerPhysicsConfig {
+    /// Flying speed at a 1x multiplier, in blocks per second.
+    pub fly_speed: f32,
+    /// Time constant of the velocity smoothing. After three of these the player is at 95% of the target speed.
+    pub fly_smoothing: f32,
+    pub min_fly_multiplier: f32,
+    pub max_fly_multiplier: f32,
+    /// How much one scroll notch multiplies (or divides) the fly speed.
+    pub fly_multiplier_step: f32,
+    /// Two jumps within this many seconds toggle flying.
+    pub double_tap_window: f32,
+    /// How long the fly speed stays on screen after changing it.
+    pub fly_speed_text_duration: f32,
+}
+
+impl Default for PlayerPhysicsConfig {
+    fn default() -> Self {
+        Self {
+            fly_speed: 10.0,
+            fly_smoothing: 0.1,
+            min_fly_multiplier: 0.25,
+            max_fly_multiplier: 8.0,
+            fly_multiplier_step: 1.25,
+            double_tap_window: 0.3,
+            fly_speed_text_duration: 1.5,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Flight {
+    pub flying: bool,
+    pub multiplier: f32,
+    /// Seconds since startup of the last jump press, for the double tap.
+    last_jump: Option<f32>,
+    /// Seconds since the fly speed was last changed.
+    speed_changed_for: Option<f32>,
+}
+
+impl Default for Flight {
+    fn default() -> Self {
+        Self {
+            flying: false,
+            multiplier: 1.0,
+            last_jump: None,
+            speed_changed_for: None,
+        }
+    }
+}
+
+impl Flight {
+    /// Registers a jump press. Returns true if it was the second one of a double tap, which toggles flying.
+    pub fn jump_pressed(&mut self, now: f32, config: &PlayerPhysicsConfig) -> bool {
+        let double_tap = self
+            .last_jump
+            .is_some_and(|last| now - last <= config.double_tap_window);
+        // A third tap starts a new double tap instead of toggling again.
+        self.last_jump = if double_tap { None } else { Some(now) };
+        if double_tap {
+            self.flying = !self.flying;
+        }
+        double_tap
+    }
+
+    /// Scales the fly speed by a number of scroll notches, positive is faster.
+    pub fn scroll(&mut self, notches: f32, config: &PlayerPhysicsConfig) {
+        self.multiplier = (self.multiplier * config.fly_multiplier_step.powf(notches))
+            .clamp(config.min_fly_multiplier, config.max_fly_multiplier);
+        self.speed_changed_for = Some(0.0);
+    }
+}
+
+/// Moves a velocity towards the target, independent of the frame rate.
+pub fn approach_velocity(current: Vec3, target: Vec3, smoothing: f32, delta: f32) -> Vec3 {
+    target + (current - target) * (-delta / smoothing).exp()
+}
+
+/// Ctrl + scroll changes the fly speed.
+pub fn adjust_fly_speed(
+    mut scroll: EventReader<MouseWheel>,
+    keys: Res<Input<KeyCode>>,
+    config: Res<PlayerPhysicsConfig>,
+    mut flight: ResMut<Flight>,
+) {
+    let notches: f32 = scroll
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // Touchpads scroll in pixels, roughly 20 per line.
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+    if notches == 0.0
+        || !flight.flying
+        || !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+    {
+        return;
+    }
+
+    flight.scroll(notches, &config);
+}
+
+#[derive(Component)]
+pub struct FlySpeedText;
+
+pub fn setup_fly_speed_text(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Fly Speed Text"),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        }),
+        FlySpeedText,
+        UI,
+    ));
+}
+
+/// Briefly shows the fly speed after it changes.
+pub fn update_fly_speed_text(
+    time: Res<Time>,
+    config: Res<PlayerPhysicsConfig>,
+    mut flight: ResMut<Flight>,
+    mut query: Query<&mut Text, With<FlySpeedText>>,
+) {
+    let Some(shown_for) = flight.speed_changed_for else {
+        return;
+    };
+    let shown_for = shown_for + time.delta_seconds();
+
+    let mut text = query.single_mut();
+    if shown_for > config.fly_speed_text_duration {
+        flight.speed_changed_for = None;
+        text.sections[0].value.clear();
+    } else {
+        flight.speed_changed_for = Some(shown_for);
+        text.sections[0].value = format!("Fly speed: {:.2}x", flight.multiplier);
+    }
+}
@@ -0,0 +1,85 @@
+// The fragment shader of the fluids. The same lighting as the standard material, with the texture scrolled
+// through its tile of the atlas, and glowing by the emissive colour.
+
+#import bevy_pbr::pbr_functions as pbr_functions
+#import bevy_pbr::pbr_types as pbr_types
+#import bevy_pbr::mesh_vertex_output MeshVertexOutput
+#import bevy_pbr::mesh_bindings mesh
+#import bevy_pbr::mesh_view_bindings view, fog
+#import bevy_pbr::mesh_view_types FOG_MODE_OFF
+#import bevy_core_pipeline::tonemapping screen_space_dither, powsafe, tone_mapping
+
+struct FluidMaterial {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    // The top left and the bottom right corner of the tile.
+    tile: vec4<f32>,
+    // How far the texture has scrolled, in tiles.
+    offset: vec2<f32>,
+    metallic: f32,
+    reflectance: f32,
+    flags: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: FluidMaterial;
+@group(1) @binding(1)
+var fluid_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var fluid_sampler: sampler;
+
+@fragment
+fn fragment(
+    in: MeshVertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> @location(0) vec4<f32> {
+    // Where the fragment is in its tile, moved along and wrapped around, so it never reads the neighbouring tiles.
+    let tile_size = material.tile.zw - material.tile.xy;
+    let in_tile = fract((in.uv - material.tile.xy) / tile_size + material.offset);
+    let texel = textureSampleBias(fluid_texture, fluid_sampler, material.tile.xy + in_tile * tile_size, view.mip_bias);
+
+    var output_color = material.base_color * texel;
+#ifdef VERTEX_COLORS
+    output_color = output_color * in.color;
+#endif
+
+    var pbr_input = pbr_functions::pbr_input_new();
+    pbr_input.material.base_color = output_color;
+    pbr_input.material.emissive = vec4(material.emissive.rgb * texel.rgb, 1.0);
+    pbr_input.material.metallic = material.metallic;
+    pbr_input.material.reflectance = material.reflectance;
+    pbr_input.material.flags = material.flags;
+
+    if (material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+        pbr_input.frag_coord = in.position;
+        pbr_input.world_position = in.world_position;
+        pbr_input.world_normal = pbr_functions::prepare_world_normal(
+            in.world_normal,
+            (material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT) != 0u,
+            is_front,
+        );
+        pbr_input.N = normalize(pbr_input.world_normal);
+        pbr_input.is_orthographic = view.projection[3].w == 1.0;
+        pbr_input.V = pbr_functions::calculate_view(in.world_position, pbr_input.is_orthographic);
+        pbr_input.flags = mesh.flags;
+        output_color = pbr_functions::pbr(pbr_input);
+    } else {
+        output_color = pbr_functions::alpha_discard(pbr_input.material, output_color);
+    }
+
+    if fog.mode != FOG_MODE_OFF && (material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_FOG_ENABLED_BIT) != 0u {
+        output_color = pbr_functions::apply_fog(fog, output_color, in.world_position.xyz, view.world_position.xyz);
+    }
+
+#ifdef TONEMAP_IN_SHADER
+    output_color = tone_mapping(output_color, view.color_grading);
+#ifdef DEBAND_DITHER
+    var output_rgb = output_color.rgb;
+    output_rgb = powsafe(output_rgb, 1.0 / 2.2);
+    output_rgb = output_rgb + screen_space_dither(in.position.xy);
+    output_rgb = powsafe(output_rgb, 2.2);
+    output_color = vec4(output_rgb, output_color.a);
+#endif
+#endif
+    return output_color;
+}
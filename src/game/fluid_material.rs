@@ -0,0 +1,172 @@
+use bevy::asset::load_internal_asset;
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey, StandardMaterialFlags};
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    AsBindGroup, AsBindGroupShaderType, RenderPipelineDescriptor, ShaderRef,
+    SpecializedMeshPipelineError,
+};
+
+use crate::prelude::*;
+
+/// The shader of the fluids, built into the game so it doesn't depend on the assets folder.
+pub const FLUID_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 0x5f1d_3c6a_94e2_7b08);
+
+/// The material of the fluids. It's lit like the standard material, but the texture scrolls through its tile so
+/// the fluids look like they flow, and it can glow.
+///
+/// Every face of a fluid wraps around the same [`tile`](Self::tile), so a fluid can only have one texture.
+#[derive(AsBindGroup, TypeUuid, TypePath, Clone, Debug)]
+#[uuid = "8d3e6f0a-2c41-4b7e-9a55-1f6c0e2d7b93"]
+#[bind_group_data(FluidMaterialKey)]
+#[uniform(0, FluidMaterialUniform)]
+pub struct FluidMaterial {
+    pub base_color: Color,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Handle<Image>,
+    /// How bright the texture glows, whatever the light is. Black doesn't glow.
+    pub emissive: Color,
+    /// The tile of the texture in the atlas, in texture coordinates.
+    pub tile: Rect,
+    /// How fast the texture scrolls, in tiles per second.
+    pub scroll: Vec2,
+    /// How far the texture has scrolled, in tiles. Kept between 0 and 1 by [`scroll_fluids`].
+    pub offset: Vec2,
+    pub metallic: f32,
+    pub reflectance: f32,
+    pub alpha_mode: AlphaMode,
+    /// Drawn from both sides, with the back lit like the front.
+    pub double_sided: bool,
+    pub unlit: bool,
+}
+
+impl Default for FluidMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Color::WHITE,
+            texture: Handle::default(),
+            emissive: Color::BLACK,
+            tile: Rect::new(0.0, 0.0, 1.0, 1.0),
+            scroll: Vec2::ZERO,
+            offset: Vec2::ZERO,
+            metallic: 0.0,
+            reflectance: 0.5,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+            unlit: false,
+        }
+    }
+}
+
+pub use uniform::FluidMaterialUniform;
+
+// The derive checks every field of the uniform with a function nothing calls.
+#[allow(dead_code)]
+mod uniform {
+    use bevy::render::render_resource::ShaderType;
+
+    use crate::prelude::*;
+
+    /// What the shader gets of a [`FluidMaterial`](super::FluidMaterial). The flags are the standard material's,
+    /// the shader passes them on to the lighting.
+    #[derive(ShaderType, Clone, Copy, Debug)]
+    pub struct FluidMaterialUniform {
+        pub base_color: Vec4,
+        pub emissive: Vec4,
+        /// The corners of the tile, the top left and then the bottom right.
+        pub tile: Vec4,
+        pub offset: Vec2,
+        pub metallic: f32,
+        pub reflectance: f32,
+        pub flags: u32,
+    }
+}
+
+impl AsBindGroupShaderType<FluidMaterialUniform> for FluidMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> FluidMaterialUniform {
+        let mut flags = StandardMaterialFlags::FOG_ENABLED;
+        flags |= match self.alpha_mode {
+            AlphaMode::Blend => StandardMaterialFlags::ALPHA_MODE_BLEND,
+            _ => StandardMaterialFlags::ALPHA_MODE_OPAQUE,
+        };
+        if self.double_sided {
+            flags |= StandardMaterialFlags::DOUBLE_SIDED;
+        }
+        if self.unlit {
+            flags |= StandardMaterialFlags::UNLIT;
+        }
+        FluidMaterialUniform {
+            base_color: self.base_color.as_linear_rgba_f32().into(),
+            emissive: self.emissive.as_linear_rgba_f32().into(),
+            tile: self
+                .tile
+                .min
+                .extend(self.tile.max.x)
+                .extend(self.tile.max.y),
+            offset: self.offset,
+            metallic: self.metallic,
+            reflectance: self.reflectance,
+            flags: flags.bits(),
+        }
+    }
+}
+
+/// What the pipeline of a [`FluidMaterial`] is specialized on.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FluidMaterialKey {
+    double_sided: bool,
+}
+
+impl From<&FluidMaterial> for FluidMaterialKey {
+    fn from(material: &FluidMaterial) -> Self {
+        Self {
+            double_sided: material.double_sided,
+        }
+    }
+}
+
+impl Material for FluidMaterial {
+    fn fragment_shader() -> ShaderRef {
+        FLUID_SHADER_HANDLE.typed().into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if key.bind_group_data.double_sided {
+            descriptor.primitive.cull_mode = None;
+        }
+        Ok(())
+    }
+}
+
+/// Draws the fluids with their [`FluidMaterial`] and scrolls their textures.
+pub struct FluidMaterialPlugin;
+
+impl Plugin for FluidMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, FLUID_SHADER_HANDLE, "fluid.wgsl", Shader::from_wgsl);
+        app.add_plugins(MaterialPlugin::<FluidMaterial>::default())
+            .add_systems(Update, scroll_fluids);
+    }
+}
+
+/// Moves the textures of the fluids along, at the speed of each.
+pub fn scroll_fluids(time: Res<Time>, mut materials: ResMut<Assets<FluidMaterial>>) {
+    let elapsed = time.elapsed_seconds_wrapped();
+    for (_, material) in materials.iter_mut() {
+        if material.scroll != Vec2::ZERO {
+            material.offset = (material.scroll * elapsed).fract();
+        }
+    }
+}
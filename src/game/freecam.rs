@@ -0,0 +1,98 @@
+use bevy::{input::mouse::MouseMotion, window::CursorGrabMode};
+
+use crate::game::debug::{DebugAction, DebugChords};
+use crate::game::flight::*;
+use crate::prelude::*;
+
+/// Whether the camera follows the player, or flies around on its own for debugging.
+#[derive(Resource, Default, Debug)]
+pub struct CameraMode {
+    /// The camera from before the freecam was turned on, put back when it's turned off.
+    saved: Option<Transform>,
+    /// Whether the chunks stream around the freecam instead of the player.
+    pub stream_from_camera: bool,
+}
+
+impl CameraMode {
+    pub fn is_freecam(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Turns the freecam on or off. Turning it off restores the camera exactly as it was.
+    pub fn toggle(&mut self, camera: &mut Transform) {
+        match self.saved.take() {
+            Some(saved) => {
+                *camera = saved;
+                self.stream_from_camera = false;
+            }
+            None => self.saved = Some(*camera),
+        }
+    }
+
+    /// The position the chunks are loaded around. The player's, unless the freecam took over the streaming.
+    pub fn streaming_center(&self, camera: Vec3, player: Vec3) -> Vec3 {
+        if self.is_freecam() && !self.stream_from_camera {
+            player
+        } else {
+            camera
+        }
+    }
+}
+
+/// F3 + C toggles the freecam, F3 + L makes the chunks follow it instead of the player.
+pub fn toggle_freecam(
+    chords: Res<DebugChords>,
+    mut mode: ResMut<CameraMode>,
+    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    mut windows: Query<&mut Window>,
+    mut state: ResMut<InputState>,
+    motion: Res<Events<MouseMotion>>,
+) {
+    if chords.triggered(DebugAction::Freecam) {
+        mode.toggle(&mut camera_query.single_mut());
+        info!("Freecam: {}", mode.is_freecam());
+
+        if !mode.is_freecam() {
+            // Grab the mouse again, and drop the motion from before so the player doesn't turn around.
+            let mut window = windows.single_mut();
+            window.cursor.grab_mode = CursorGrabMode::Confined;
+            window.cursor.visible = false;
+            state.reader_motion.clear(&motion);
+        }
+    }
+
+    if chords.triggered(DebugAction::StreamAroundFreecam) && mode.is_freecam() {
+        mode.stream_from_camera = !mode.stream_from_camera;
+        info!("Chunks follow the freecam: {}", mode.stream_from_camera);
+    }
+}
+
+/// Flies the freecam where it's looking, Space and Shift go straight up and down.
+pub fn move_freecam(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    config: Res<PlayerPhysicsConfig>,
+    flight: Res<Flight>,
+    mode: Res<CameraMode>,
+    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+) {
+    if !mode.is_freecam() {
+        return;
+    }
+
+    let mut transform = camera_query.single_mut();
+    let mut wish = Vec3::ZERO;
+    for key in keys.get_pressed() {
+        match key {
+            KeyCode::W => wish += transform.forward(),
+            KeyCode::S => wish -= transform.forward(),
+            KeyCode::A => wish -= transform.right(),
+            KeyCode::D => wish += transform.right(),
+            KeyCode::Space => wish += Vec3::Y,
+            KeyCode::ShiftLeft => wish -= Vec3::Y,
+            _ => (),
+        }
+    }
+    transform.translation +=
+        wish.normalize_or_zero() * config.fly_speed * flight.multiplier * time.delta_seconds();
+}
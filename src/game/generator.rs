@@ -0,0 +1,890 @@
+use std::sync::Arc;
+
+use noise::{Perlin, Seedable};
+
+use crate::game::biome::{Biome, BiomeNoise};
+use crate::game::hash::{feature_seed, hash_pos};
+use crate::game::ores::{place_veins, vein_block_at, veins_near, OreVein};
+use crate::game::plants::{place_plants, plant_for_column};
+use crate::game::ponds::{carve_pond, pond_for_chunk};
+use crate::game::ravines::{carve_ravine, is_carvable, ravine_fill, ravines_near};
+use crate::game::rules::{WorldGenParams, WorldRules, WorldSeed, WorldType};
+use crate::game::trees::{
+    place_trees, tree_block_at, trees_around, trees_near, Tree, CANOPY_RADIUS,
+};
+use crate::prelude::*;
+
+/// Decides which block is where. Chunks are generated and meshed from it, on the async task pool.
+pub trait WorldGenerator: Send + Sync {
+    /// The block at a world position.
+    fn block_at(&self, pos: IVec3) -> BlockType;
+
+    /// Where the player starts. By default it's high above the spawn, the player falls down onto the ground.
+    fn spawn_point(&self) -> Vec3 {
+        WORLD_SPAWN
+    }
+
+    /// The biome of a block column, it tints the grass. Worlds without biomes are all plains.
+    fn biome_at(&self, _column: IVec2XZ) -> Biome {
+        Biome::Plains
+    }
+
+    /// All the blocks of a chunk. The default samples [`WorldGenerator::block_at`] for every block.
+    ///
+    /// Generators can override this to generate faster, or to add features that don't fit into `block_at`.
+    /// Those features have to stay away from the outermost columns, the neighbouring chunks only see `block_at`.
+    fn generate_chunk(&self, chunk_position: IVec2XZ) -> ChunkBlocks {
+        let mut blocks = ChunkBlocks::default();
+        let origin = chunk_origin(chunk_position);
+        for (local, block) in blocks.iter_mut() {
+            *block = self.block_at(origin + local.as_ivec3());
+        }
+        blocks
+    }
+
+    /// The blocks of a chunk with the margin around it, for the faces on its border. The default samples
+    /// [`WorldGenerator::block_at`] for every block of the margin.
+    fn pad_chunk(&self, chunk_position: IVec2XZ, chunk_blocks: &ChunkBlocks) -> PaddedBlocks {
+        let origin = chunk_origin(chunk_position);
+        PaddedBlocks::new(chunk_blocks, |local| self.block_at(origin + local))
+    }
+}
+
+/// How many blocks a [`PaddedBlocks`] is wide and high.
+pub const PADDED_SIZE: usize = CHUNK_SIZE + 2;
+pub const PADDED_HEIGHT: usize = CHUNK_HEIGHT + 2;
+
+/// Index of a block in the storage of a [`PaddedBlocks`], from its position relative to the chunk's first block.
+/// It's laid out like [`local_index`], one block further out on every side.
+///
+/// The position has to be in the margin. Unlike `local_index` it isn't checked again here, the mesher asks for
+/// every block several times and [`PaddedBlocks::get`] already checks.
+#[inline]
+pub fn padded_index(local: IVec3) -> usize {
+    let padded = (local + IVec3::ONE).as_uvec3();
+    (padded.x as usize * PADDED_SIZE + padded.z as usize) * PADDED_HEIGHT + padded.y as usize
+}
+
+/// The blocks of a chunk with a margin of one block all around it, 18x258x18. The mesher reads every block it
+/// looks at from here, the ones across the border of the chunk included, so both sides of a border are decided
+/// from the same blocks.
+///
+/// The margin below and above the world is air.
+pub struct PaddedBlocks {
+    blocks: Box<[BlockType]>,
+}
+
+impl PaddedBlocks {
+    /// Copies the blocks of the chunk and samples the columns around it, corners included. `margin` takes positions
+    /// relative to the chunk's first block.
+    pub fn new(chunk_blocks: &ChunkBlocks, margin: impl Fn(IVec3) -> BlockType) -> Self {
+        let mut blocks =
+            vec![BlockType::Air; PADDED_SIZE * PADDED_HEIGHT * PADDED_SIZE].into_boxed_slice();
+        let size = CHUNK_SIZE as i32;
+        for x in -1..=size {
+            for z in -1..=size {
+                let start = padded_index(IVec3::new(x, 0, z));
+                let column = &mut blocks[start..start + CHUNK_HEIGHT];
+                if (0..size).contains(&x) && (0..size).contains(&z) {
+                    column.copy_from_slice(chunk_blocks.column(x as usize, z as usize));
+                } else {
+                    for (y, block) in column.iter_mut().enumerate() {
+                        *block = margin(IVec3::new(x, y as i32, z));
+                    }
+                }
+            }
+        }
+        Self { blocks }
+    }
+
+    /// The block at a position relative to the chunk's first block. Anything past the margin is air.
+    pub fn get(&self, local: IVec3) -> BlockType {
+        let size = CHUNK_SIZE as i32;
+        if !(-1..=size).contains(&local.x)
+            || !(-1..=size).contains(&local.z)
+            || !(-1..=CHUNK_HEIGHT as i32).contains(&local.y)
+        {
+            return BlockType::Air;
+        }
+        self.blocks[padded_index(local)]
+    }
+}
+
+/// The generator of the world, shared with the mesh tasks.
+#[derive(Resource, Clone)]
+pub struct SharedGenerator(pub Arc<dyn WorldGenerator>);
+
+/// The world position of a chunk's first block.
+pub fn chunk_origin(chunk_position: IVec2XZ) -> IVec3 {
+    IVec3::new(
+        chunk_position.x * CHUNK_SIZE as i32,
+        0,
+        chunk_position.z * CHUNK_SIZE as i32,
+    )
+}
+
+/// How many columns around a chunk its heightmap reaches. The trees growing into the chunk and into the ring of
+/// blocks around it stand this far out at most.
+const HEIGHTMAP_MARGIN: i32 = CANOPY_RADIUS + 1;
+const HEIGHTMAP_SIZE: usize = CHUNK_SIZE + 2 * HEIGHTMAP_MARGIN as usize;
+
+/// The terrain heights of a chunk's columns and the columns around it, worked out once per chunk. A height takes
+/// the octaves of the surface noise and the river noise, sampling it again for every block was most of the
+/// generation time.
+pub struct ChunkHeightmap {
+    min: IVec2XZ,
+    heights: [[f32; HEIGHTMAP_SIZE]; HEIGHTMAP_SIZE],
+}
+
+impl ChunkHeightmap {
+    pub fn new(chunk_position: IVec2XZ, height_at: impl Fn(IVec2XZ) -> f32) -> Self {
+        let origin = chunk_origin(chunk_position);
+        let min = IVec2XZ::new(origin.x - HEIGHTMAP_MARGIN, origin.z - HEIGHTMAP_MARGIN);
+        let mut heights = [[0.0; HEIGHTMAP_SIZE]; HEIGHTMAP_SIZE];
+        for (x, row) in heights.iter_mut().enumerate() {
+            for (z, height) in row.iter_mut().enumerate() {
+                *height = height_at(IVec2XZ::new(min.x + x as i32, min.z + z as i32));
+            }
+        }
+        Self { min, heights }
+    }
+
+    /// The height of a column, if the heightmap reaches it.
+    pub fn get(&self, column: IVec2XZ) -> Option<f32> {
+        let (x, z) = (column.x - self.min.x, column.z - self.min.z);
+        let inside = |i: i32| (0..HEIGHTMAP_SIZE as i32).contains(&i);
+        (inside(x) && inside(z)).then(|| self.heights[x as usize][z as usize])
+    }
+}
+
+/// The trees and ore veins that can reach into a chunk and the ring of blocks around it, collected once instead of
+/// for every block.
+struct ChunkFeatures {
+    trees: Vec<Tree>,
+    veins: Vec<OreVein>,
+}
+
+/// The normal terrain: Perlin noise hills, caves, ores and biomes, with ponds and trees on top.
+#[derive(Clone)]
+pub struct DefaultGenerator {
+    perlin: Perlin,
+    biome_noise: BiomeNoise,
+    rules: WorldRules,
+    params: WorldGenParams,
+}
+
+impl DefaultGenerator {
+    #[allow(dead_code)] // Only the tests use the default parameters without the resource
+    pub fn new(seed: u32, rules: WorldRules) -> Self {
+        Self::with_params(seed, rules, WorldGenParams::default())
+    }
+
+    pub fn with_params(seed: u32, rules: WorldRules, params: WorldGenParams) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            biome_noise: BiomeNoise::with_scale(seed, params.biome_scale),
+            rules,
+            params,
+        }
+    }
+
+    /// The height of the terrain in a column, before caves, ponds and trees. From the heightmap if it reaches the
+    /// column.
+    fn surface_at(&self, column: IVec2XZ, heights: Option<&ChunkHeightmap>) -> f32 {
+        heights
+            .and_then(|heights| heights.get(column))
+            .unwrap_or_else(|| {
+                surface_height(
+                    IVec3::new(column.x, 0, column.z),
+                    &self.perlin,
+                    &self.rules,
+                    &self.params,
+                )
+            })
+    }
+
+    /// The y of the top block of the terrain in a column, before caves, ponds and trees.
+    #[allow(dead_code)] // Only the tests look at the surface from outside
+    pub fn surface_y(&self, column: IVec2XZ) -> i32 {
+        self.surface_at(column, None) as i32
+    }
+
+    /// What a block of the terrain turns into if a ravine cuts through it, like in `generate_chunk`.
+    fn ravine_block(
+        &self,
+        pos: IVec3,
+        block: BlockType,
+        biome: Biome,
+        heights: Option<&ChunkHeightmap>,
+    ) -> Option<BlockType> {
+        // Most blocks can be ruled out before looking for ravines, flooded or not.
+        let water_height = self.params.water_height;
+        if !self.rules.generate_caves || !is_carvable(block, pos, water_height, true) {
+            return None;
+        }
+        let (chunk, _) = world_to_chunk_and_local(pos);
+        let ravine = ravines_near(chunk, self.perlin.seed())
+            .into_iter()
+            .find(|ravine| ravine.carves(pos))?;
+        let flooded = ravine.floods(water_height, |column| self.surface_at(column, heights));
+        is_carvable(block, pos, water_height, flooded)
+            .then(|| ravine_fill(pos, water_height, flooded, biome.is_cold()))
+    }
+
+    /// The y of the grass block a tree in the column would grow from, if it's grass.
+    fn tree_ground(&self, column: IVec2XZ, heights: Option<&ChunkHeightmap>) -> Option<i32> {
+        let height = self.surface_at(column, heights);
+        let y = height as i32;
+        let ground = IVec3::new(column.x, y, column.z);
+        let biome = self.biome_noise.biome_at(column);
+        let block = is_block(
+            ground,
+            height,
+            &self.perlin,
+            &self.rules,
+            &self.params,
+            biome,
+        );
+        let carved = self.ravine_block(ground, block, biome, heights).is_some();
+        (matches!(block, BlockType::Grass | BlockType::SnowyGrass) && !carved).then_some(y)
+    }
+
+    /// Whether a column is in the middle of a river, where the riverbed is flat.
+    #[allow(dead_code)] // Only the tests look for rivers so far
+    pub fn is_river(&self, column: IVec2XZ) -> bool {
+        river_noise(IVec3::new(column.x, 0, column.z), &self.perlin) < RIVER_CHANNEL
+    }
+
+    /// The trees growing in and right around a chunk, on the grass of the generated terrain.
+    fn chunk_trees(&self, chunk_position: IVec2XZ, heights: Option<&ChunkHeightmap>) -> Vec<Tree> {
+        trees_near(
+            chunk_position,
+            self.perlin.seed(),
+            &self.biome_noise,
+            |column| self.tree_ground(column, heights),
+        )
+    }
+
+    /// The block at a world position, like `block_at`. The heights come from the heightmap where it reaches. The
+    /// trees and the ore veins come from `features` if it's given, else the ones around the position are looked for.
+    fn sample(
+        &self,
+        pos: IVec3,
+        heights: Option<&ChunkHeightmap>,
+        features: Option<&ChunkFeatures>,
+    ) -> BlockType {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return BlockType::Air;
+        }
+        let column = IVec2XZ::new(pos.x, pos.z);
+        let biome = self.biome_noise.biome_at(column);
+        let height = self.surface_at(column, heights);
+        let block = is_block(pos, height, &self.perlin, &self.rules, &self.params, biome);
+        if let Some(carved) = self.ravine_block(pos, block, biome, heights) {
+            return carved;
+        }
+        match block {
+            BlockType::Air if self.rules.generate_structures => {
+                let tree_block = match features {
+                    Some(features) => tree_block_at(&features.trees, pos),
+                    None => {
+                        let trees =
+                            trees_around(column, self.perlin.seed(), &self.biome_noise, |column| {
+                                self.tree_ground(column, heights)
+                            });
+                        tree_block_at(&trees, pos)
+                    }
+                };
+                tree_block
+                    .or_else(|| {
+                        // Grass is only ever at the surface height, which is much quicker to check.
+                        plant_for_column(column, self.perlin.seed()).filter(|_| {
+                            pos.y - 1 == height as i32
+                                && self.sample(pos - IVec3::Y, heights, features)
+                                    == BlockType::Grass
+                        })
+                    })
+                    .unwrap_or(BlockType::Air)
+            }
+            BlockType::Stone if self.rules.generate_ores => match features {
+                Some(features) => vein_block_at(&features.veins, pos),
+                None => {
+                    let (chunk, _) = world_to_chunk_and_local(pos);
+                    vein_block_at(&veins_near(chunk, self.perlin.seed()), pos)
+                }
+            }
+            .unwrap_or(BlockType::Stone),
+            block => block,
+        }
+    }
+}
+
+impl WorldGenerator for DefaultGenerator {
+    /// The terrain, the ravines, the ore veins, the trees and the plants. Ponds are left out, they never touch the outermost columns of their chunk.
+    fn block_at(&self, pos: IVec3) -> BlockType {
+        self.sample(pos, None, None)
+    }
+
+    fn biome_at(&self, column: IVec2XZ) -> Biome {
+        self.biome_noise.biome_at(column)
+    }
+
+    /// Generates the blocks of a 16x256x16 chunk, including the per-chunk features like ponds.
+    ///
+    /// The features are seeded with the seed of the Perlin noise.
+    fn generate_chunk(&self, chunk_position: IVec2XZ) -> ChunkBlocks {
+        let (perlin, rules, params) = (&self.perlin, &self.rules, &self.params);
+        let mut chunk_blocks = ChunkBlocks::default();
+        let heights = ChunkHeightmap::new(chunk_position, |column| self.surface_at(column, None));
+
+        // Fill the chunk column by column.
+        // Remember to offset the position by the chunk position.
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                // Scale the position down by the chunk size.
+                let scaled_x = x as i32 + (chunk_position.x * CHUNK_SIZE as i32);
+                let scaled_z = z as i32 + (chunk_position.z * CHUNK_SIZE as i32);
+
+                // Everything above both the surface and the water is air, which the blocks already are.
+                let height = self.surface_at(IVec2XZ::new(scaled_x, scaled_z), Some(&heights));
+                let top = (height as i32)
+                    .max(params.water_height as i32)
+                    .min(CHUNK_HEIGHT as i32 - 1) as usize;
+
+                let biome = self.biome_noise.biome_at(IVec2XZ::new(scaled_x, scaled_z));
+
+                let column = chunk_blocks.column_mut(x, z);
+                for (y, block) in column.iter_mut().enumerate().take(top + 1) {
+                    // Sample the noise function at the scaled position.
+                    *block = is_block(
+                        IVec3::new(scaled_x, y as i32, scaled_z),
+                        height,
+                        perlin,
+                        rules,
+                        params,
+                        biome,
+                    );
+                }
+            }
+        }
+
+        // Ore veins go into the stone before anything else is carved, the ones from the neighbours included.
+        if rules.generate_ores {
+            let veins = veins_near(chunk_position, perlin.seed());
+            place_veins(chunk_position, &veins, &mut chunk_blocks);
+        }
+
+        // Ravines cut through everything below them, ores included, and across the chunk borders.
+        if rules.generate_caves {
+            for ravine in ravines_near(chunk_position, perlin.seed()) {
+                let flooded = ravine.floods(params.water_height, |column| {
+                    self.surface_at(column, Some(&heights))
+                });
+                carve_ravine(
+                    chunk_position,
+                    &ravine,
+                    params.water_height,
+                    flooded,
+                    |column| self.biome_noise.biome_at(column).is_cold(),
+                    &mut chunk_blocks,
+                );
+            }
+        }
+
+        // Ponds never touch the outermost columns, so the neighbouring chunks can keep sampling `block_at` at the border.
+        if let Some(pond) =
+            pond_for_chunk(chunk_position, perlin.seed()).filter(|_| rules.generate_structures)
+        {
+            carve_pond(&pond, &mut chunk_blocks);
+        }
+
+        // Trees go last, they grow out of the finished surface and can reach in from the neighbouring chunks.
+        if rules.generate_structures {
+            let trees = self.chunk_trees(chunk_position, Some(&heights));
+            place_trees(chunk_position, &trees, &mut chunk_blocks);
+            // The plants grow on whatever grass the trees left open.
+            place_plants(chunk_position, perlin.seed(), &mut chunk_blocks);
+        }
+
+        chunk_blocks
+    }
+
+    /// The blocks around the chunk from the same heightmap and trees, instead of looking them up again for every
+    /// block like `block_at` has to.
+    fn pad_chunk(&self, chunk_position: IVec2XZ, chunk_blocks: &ChunkBlocks) -> PaddedBlocks {
+        let heights = ChunkHeightmap::new(chunk_position, |column| self.surface_at(column, None));
+        let features = ChunkFeatures {
+            trees: if self.rules.generate_structures {
+                self.chunk_trees(chunk_position, Some(&heights))
+            } else {
+                Vec::new()
+            },
+            // A vein only reaches 2 blocks from its origin, so the veins of the chunk and its neighbours are all the
+            // ones that can reach the ring. They're in the same order `veins_near` puts them in for the neighbours.
+            veins: veins_near(chunk_position, self.perlin.seed()),
+        };
+        let origin = chunk_origin(chunk_position);
+        PaddedBlocks::new(chunk_blocks, |local| {
+            self.sample(origin + local, Some(&heights), Some(&features))
+        })
+    }
+}
+
+/// A flat world made of layers, from the bottom up. Everything above them is air.
+///
+/// There are no caves, ores or structures, so a chunk is just a few copies of its layers.
+#[derive(Clone, Debug)]
+pub struct SuperflatGenerator {
+    pub layers: Vec<(BlockType, u32)>,
+}
+
+impl Default for SuperflatGenerator {
+    /// Bedrock, two layers of dirt and grass.
+    fn default() -> Self {
+        Self {
+            layers: vec![
+                (BlockType::Bedrock, 1),
+                (BlockType::Dirt, 2),
+                (BlockType::Grass, 1),
+            ],
+        }
+    }
+}
+
+impl WorldGenerator for SuperflatGenerator {
+    fn block_at(&self, pos: IVec3) -> BlockType {
+        if pos.y < 0 {
+            return BlockType::Air;
+        }
+        let mut top = 0;
+        for &(block, thickness) in &self.layers {
+            top += thickness as i32;
+            if pos.y < top {
+                return block;
+            }
+        }
+        BlockType::Air
+    }
+
+    /// Stacks the layers into every column, the rest of the chunk is already air.
+    fn generate_chunk(&self, _chunk_position: IVec2XZ) -> ChunkBlocks {
+        let column: Vec<BlockType> = self
+            .layers
+            .iter()
+            .flat_map(|&(block, thickness)| std::iter::repeat_n(block, thickness as usize))
+            .take(CHUNK_HEIGHT)
+            .collect();
+
+        let mut blocks = ChunkBlocks::default();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                blocks.column_mut(x, z)[..column.len()].copy_from_slice(&column);
+            }
+        }
+        blocks
+    }
+}
+
+/// Floating islands: 3D noise terrain in a band of the sky between `ISLAND_BOTTOM` and `ISLAND_TOP`, with nothing
+/// under it. The caves and the ores of the normal terrain go into the islands too, the ores as high above the bottom
+/// of the band as they'd be above the bottom of the world.
+#[derive(Clone)]
+pub struct FloatingIslandsGenerator {
+    perlin: Perlin,
+    rules: WorldRules,
+}
+
+impl FloatingIslandsGenerator {
+    pub fn new(seed: u32, rules: WorldRules) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            rules,
+        }
+    }
+
+    /// Whether there's island at a position, with the caves carved out of it.
+    fn is_island(&self, pos: IVec3) -> bool {
+        let falloff = island_falloff(pos.y);
+        if falloff <= 0.0 {
+            return false;
+        }
+        // The islands are flatter than they are wide, and they're clustered by the 2D noise.
+        let density = self.perlin.get([
+            pos.x as f64 * ISLAND_SCALE + 3000.5,
+            pos.y as f64 * ISLAND_SCALE * 2.0,
+            pos.z as f64 * ISLAND_SCALE - 3000.5,
+        ]) + 0.5
+            * self.perlin.get([
+                pos.x as f64 * ISLAND_SCALE * 0.25 - 3000.5,
+                pos.z as f64 * ISLAND_SCALE * 0.25 + 3000.5,
+            ]);
+        if density < ISLAND_THRESHOLD + (1.0 - falloff) {
+            return false;
+        }
+        !(self.rules.generate_caves && cave_noise(pos, &self.perlin, CAVE_SCALE) >= CAVE_THRESHOLD)
+    }
+
+    /// The veins of the ores around a chunk, moved up from the bottom of the world to the bottom of the islands.
+    fn veins_near(&self, chunk_position: IVec2XZ) -> Vec<OreVein> {
+        let mut veins = veins_near(chunk_position, self.perlin.seed());
+        for vein in &mut veins {
+            vein.origin.y += ISLAND_BOTTOM;
+        }
+        veins
+    }
+
+    /// The y of the top block of the highest island in a column.
+    fn top_at(&self, column: IVec2XZ) -> Option<i32> {
+        (ISLAND_BOTTOM..=ISLAND_TOP)
+            .rev()
+            .find(|&y| self.is_island(IVec3::new(column.x, y, column.z)))
+    }
+}
+
+/// How far a height is into the band of the islands, 1 in the middle and 0 at the top and the bottom.
+fn island_falloff(y: i32) -> f64 {
+    let half = (ISLAND_TOP - ISLAND_BOTTOM) as f64 / 2.0;
+    let from_middle = (y as f64 - (ISLAND_BOTTOM as f64 + half)) / half;
+    1.0 - from_middle * from_middle
+}
+
+/// The block of an island that has air this many blocks above it, or `None` if there's no air in reach.
+fn island_layer(air_above: Option<i32>) -> BlockType {
+    match air_above {
+        Some(1) => BlockType::Grass,
+        Some(depth) if depth <= ISLAND_DIRT_DEPTH + 1 => BlockType::Dirt,
+        _ => BlockType::Stone,
+    }
+}
+
+impl WorldGenerator for FloatingIslandsGenerator {
+    /// The islands with their grass and dirt on top, and the ores.
+    fn block_at(&self, pos: IVec3) -> BlockType {
+        if !self.is_island(pos) {
+            return BlockType::Air;
+        }
+        let air_above =
+            (1..=ISLAND_DIRT_DEPTH + 1).find(|&depth| !self.is_island(pos + IVec3::Y * depth));
+        match island_layer(air_above) {
+            BlockType::Stone if self.rules.generate_ores => {
+                let (chunk, _) = world_to_chunk_and_local(pos);
+                vein_block_at(&self.veins_near(chunk), pos).unwrap_or(BlockType::Stone)
+            }
+            block => block,
+        }
+    }
+
+    /// Finds the top of the island nearest to the spawn, the player would fall into the void anywhere else.
+    fn spawn_point(&self) -> Vec3 {
+        let center = IVec2XZ::containing(WORLD_SPAWN);
+        let center = IVec2XZ::new(
+            center.x * CHUNK_SIZE as i32 + CHUNK_SIZE as i32 / 2,
+            center.z * CHUNK_SIZE as i32 + CHUNK_SIZE as i32 / 2,
+        );
+        // The columns are searched in rings around the spawn, a few blocks apart.
+        let ring = |radius: i32| {
+            (-radius..=radius).flat_map(move |x| {
+                (-radius..=radius)
+                    .filter(move |&z| x.abs() == radius || z.abs() == radius)
+                    .map(move |z| {
+                        center + IVec2XZ::new(x * ISLAND_SPAWN_STEP, z * ISLAND_SPAWN_STEP)
+                    })
+            })
+        };
+        (0..=ISLAND_SPAWN_SEARCH / ISLAND_SPAWN_STEP)
+            .flat_map(ring)
+            .find_map(|column| {
+                self.top_at(column).map(|top| {
+                    Vec3::new(
+                        column.x as f32 + 0.5,
+                        top as f32 + 2.5,
+                        column.z as f32 + 0.5,
+                    )
+                })
+            })
+            .unwrap_or(WORLD_SPAWN)
+    }
+
+    /// Fills in the islands column by column, then puts the grass on the blocks with air above them and the dirt
+    /// under it.
+    fn generate_chunk(&self, chunk_position: IVec2XZ) -> ChunkBlocks {
+        let mut chunk_blocks = ChunkBlocks::default();
+        let origin = chunk_origin(chunk_position);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let column = chunk_blocks.column_mut(x, z);
+                for y in ISLAND_BOTTOM..=ISLAND_TOP {
+                    if self.is_island(origin + IVec3::new(x as i32, y, z as i32)) {
+                        column[y as usize] = BlockType::Stone;
+                    }
+                }
+
+                // Going down from the top, counting the blocks since the last air.
+                let mut air_above = Some(0);
+                for y in (ISLAND_BOTTOM..=ISLAND_TOP).rev() {
+                    let block = &mut column[y as usize];
+                    if *block == BlockType::Air {
+                        air_above = Some(0);
+                        continue;
+                    }
+                    air_above = air_above.map(|depth| depth + 1);
+                    *block =
+                        island_layer(air_above.filter(|&depth| depth <= ISLAND_DIRT_DEPTH + 1));
+                }
+            }
+        }
+
+        if self.rules.generate_ores {
+            place_veins(
+                chunk_position,
+                &self.veins_near(chunk_position),
+                &mut chunk_blocks,
+            );
+        }
+        chunk_blocks
+    }
+}
+
+/// Builds the generator from the type, the rules and the seed of the world.
+pub fn setup_world_generator(
+    mut commands: Commands,
+    world_type: Res<WorldType>,
+    seed: Res<WorldSeed>,
+    rules: Res<WorldRules>,
+    params: Res<WorldGenParams>,
+) {
+    commands.insert_resource(SharedGenerator(
+        world_type.generator(seed.0, *rules, &params),
+    ));
+}
+
+/// The terrain height of a column, before caves and water.
+fn surface_height(pos: IVec3, perlin: &Perlin, rules: &WorldRules, params: &WorldGenParams) -> f32 {
+    let noise_value = fbm(
+        perlin,
+        pos.x as f64 * params.frequency,
+        pos.z as f64 * params.frequency,
+        params.octaves,
+        params.lacunarity,
+        params.persistence,
+    );
+    let height = params.height_spline.evaluate(noise_value as f32);
+
+    let height = if rules.amplified {
+        // Double the height above the blend height, the ceiling is only 10 blocks away now.
+        let amplified_cieling_margin = 10;
+        (BLEND_HEIGHT as f32 + (height - BLEND_HEIGHT as f32) * 2.)
+            .min((CHUNK_HEIGHT - amplified_cieling_margin) as f32)
+    } else {
+        height
+    };
+
+    carve_river(height, river_noise(pos, perlin))
+}
+
+/// Ridged noise for the rivers, zero in the middle of a river. Only depends on x and z, like the surface.
+fn river_noise(pos: IVec3, perlin: &Perlin) -> f64 {
+    // Far away from the surface noise, so the rivers don't follow the hills.
+    perlin
+        .get([
+            pos.x as f64 * RIVER_SCALE + 1000.5,
+            pos.z as f64 * RIVER_SCALE - 1000.5,
+        ])
+        .abs()
+}
+
+/// Lowers the surface into a river valley. The middle of the river is a flat riverbed under the water, and the
+/// sides rise smoothly back to the surface.
+///
+/// The surface is never raised, so where a river reaches terrain that's already below the water it just opens
+/// into the ocean. The riverbanks get their sand from the same rule as the beaches.
+pub fn carve_river(height: f32, river: f64) -> f32 {
+    let t = ((river - RIVER_CHANNEL) / RIVER_VALLEY).clamp(0.0, 1.0) as f32;
+    let carved =
+        RIVER_BED_HEIGHT as f32 + (height - RIVER_BED_HEIGHT as f32) * t * t * (3.0 - 2.0 * t);
+    carved.min(height)
+}
+
+/// The block of the surface at a position, with the water and the beaches around `water_height`.
+fn surface_generation(pos: IVec3, height: f32, biome: Biome, water_height: usize) -> BlockType {
+    let (mut top_block, filler_block) = biome.surface_blocks();
+    if height as usize > SNOW_HEIGHT
+        && matches!(top_block, BlockType::Grass | BlockType::SnowyGrass)
+    {
+        top_block = BlockType::Snow;
+    }
+
+    // The beaches are from 5 blocks under the water to 2 blocks over it.
+    let water = water_height as i32;
+    let beach = |y: i32| y > water - 6 && y < water + 3;
+
+    // calculate block type given block position and height
+    match pos.y {
+        // y if y + 3 < height as i32 => BlockType::Stone,
+        y if y + 3 < height as i32 => BlockType::Stone,
+        y if y < height as i32 && !beach(y) && y > water - 5 => filler_block,
+        y if y == height as i32 && !beach(y) && y > water - 5 => top_block,
+        // y if y <= height as i32 && y == 6 => cave_generation(pos, perlin, true),
+        y if !y <= height as i32 && y < water - 5 => BlockType::Stone,
+        y if y <= height as i32 && beach(y) => BlockType::Sand,
+        y if !y <= height as i32 && y == water - 5 => BlockType::Sand,
+        // y if y > 64 && y <= WATER_HEIGHT as i32 => BlockType::Water,
+        y if y == water && biome.is_cold() => BlockType::Ice,
+        y if !y <= height as i32 && y <= water => BlockType::Water,
+        _ => BlockType::Air,
+    }
+}
+
+/// The cave noise above which a block is carved out, at this many blocks below the surface.
+///
+/// Deep down it's `deep`, and towards the surface it goes down by the gap between `CAVE_THRESHOLD` and
+/// `CAVE_THRESHOLD_SURFACE`.
+pub fn cave_threshold(depth: f32, deep: f64) -> f64 {
+    let t = (1.0 - depth / CAVE_ENTRANCE_DEPTH).clamp(0.0, 1.0) as f64;
+    deep + (CAVE_THRESHOLD_SURFACE - CAVE_THRESHOLD) * t
+}
+
+/// The 3D noise that carves the caves wherever it's above the cave threshold, at the frequency `scale`.
+fn cave_noise(pos: IVec3, perlin: &Perlin, scale: f64) -> f64 {
+    perlin.get([
+        pos.x as f64 * scale,
+        pos.y as f64 * scale,
+        pos.z as f64 * scale,
+    ])
+}
+
+fn cave_generation(pos: IVec3, height: f32, perlin: &Perlin, params: &WorldGenParams) -> BlockType {
+    // Sample the noise function at the scaled position.
+    // The perlin noise needs a float value, so we need to cast the scaled position to a float.
+
+    let cave_noise_value = cave_noise(pos, perlin, params.cave_scale);
+
+    let no_ocean: bool = pos.y + 10 < height as i32;
+    let threshold = cave_threshold(height - pos.y as f32, params.cave_threshold);
+
+    // Caves don't cut into the beaches, from 7 blocks under the water to the block over it.
+    let water = params.water_height as i32;
+    if cave_noise_value < threshold || !no_ocean {
+        if !(cave_noise_value < threshold || pos.y > water - 7 && pos.y < water + 1) {
+            BlockType::Air
+        } else {
+            BlockType::Stone
+        }
+    } else {
+        BlockType::Air
+    }
+}
+
+/// The gravel or dirt that replaces the stone at a position, if it's in a patch.
+///
+/// The noise is sampled far away from the other channels, so the patches don't follow the caves or the terrain.
+pub fn patch_block(pos: IVec3, perlin: &Perlin) -> Option<BlockType> {
+    let patch_noise = perlin.get([
+        pos.x as f64 * PATCH_SCALE - 2000.5,
+        pos.y as f64 * PATCH_SCALE + 500.5,
+        pos.z as f64 * PATCH_SCALE + 2000.5,
+    ]);
+    if patch_noise > PATCH_THRESHOLD {
+        Some(BlockType::Gravel)
+    } else if patch_noise < -PATCH_THRESHOLD {
+        Some(BlockType::Dirt)
+    } else {
+        None
+    }
+}
+
+/// The block of the terrain at a position, for the surface height of its column.
+fn is_block(
+    pos: IVec3,
+    height: f32,
+    perlin: &Perlin,
+    rules: &WorldRules,
+    params: &WorldGenParams,
+    biome: Biome,
+) -> BlockType {
+    // is blocks
+
+    // limit the world size because it will start breaking at extreme distances
+    let no_border = true;
+    let border = 128; // i32::MAX
+    if (pos.x >= border || pos.x < -border || pos.z >= border || pos.z < -border) && !no_border {
+        return BlockType::Air;
+    }
+
+    // Limit the world sky
+    if pos.y >= 255 {
+        return BlockType::Air;
+    }
+
+    // Set bedrock. It comes before the caves, so they can't cut through it.
+    let bedrock_top = if rules.bedrock_flat {
+        0
+    } else {
+        bedrock_thickness(IVec2XZ::new(pos.x, pos.z), perlin.seed()) - 1
+    };
+    if pos.y <= bedrock_top {
+        return BlockType::Bedrock;
+    }
+
+    // // Tests
+    // if pos.y == 0 {
+    //     return BlockType::Bedrock;
+    // } else {
+    //     let surface_block = surface_generation(pos, perlin);
+    //     surface_block
+    // }
+
+    // Generate the 2d surface block. If it's a block, check if a cave should be generated.
+    // Lava on air blocks below
+    let surface_block = surface_generation(pos, height, biome, params.water_height);
+    if surface_block != BlockType::Air && rules.generate_caves {
+        let cave_block = cave_generation(pos, height, perlin, params);
+        if cave_block == BlockType::Air {
+            if pos.y <= LAVA_HEIGHT as i32 {
+                BlockType::Lava
+            } else {
+                // TODO: Don't spawn caves between BLEND_HEIGHT and below WATER_HEIGHT
+                cave_block
+            }
+        } else {
+            patched(surface_block, pos, perlin, rules)
+        }
+    } else {
+        patched(surface_block, pos, perlin, rules)
+    }
+}
+
+/// The block with the gravel and dirt patches mixed into the stone. They come with the ores.
+fn patched(block: BlockType, pos: IVec3, perlin: &Perlin, rules: &WorldRules) -> BlockType {
+    match block {
+        BlockType::Stone if rules.generate_ores => patch_block(pos, perlin).unwrap_or(block),
+        block => block,
+    }
+}
+
+const BEDROCK_SALT: u32 = 0xbed;
+
+/// How many blocks of bedrock a column has at the bottom of the world, from 1 to `BEDROCK_MAX_THICKNESS`.
+pub fn bedrock_thickness(column: IVec2XZ, seed: u32) -> i32 {
+    let hash = hash_pos(
+        feature_seed(seed, BEDROCK_SALT),
+        IVec3::new(column.x, 0, column.z),
+    );
+    1 + (hash % BEDROCK_MAX_THICKNESS as u64) as i32
+}
+
+/// Fractal Brownian motion, octaves of the noise added together. Every octave has `lacunarity` times the frequency
+/// and `persistence` times the amplitude of the one before.
+///
+/// The sum is divided by the amplitudes, so it stays in -1..1 like the noise itself whatever the parameters are.
+pub fn fbm(noise: &Perlin, x: f64, z: f64, octaves: u32, lacunarity: f64, persistence: f64) -> f64 {
+    let (mut sum, mut amplitudes) = (0.0, 0.0);
+    let (mut frequency, mut amplitude) = (1.0, 1.0);
+    for _ in 0..octaves.max(1) {
+        sum += noise.get([x * frequency, z * frequency]) * amplitude;
+        amplitudes += f64::abs(amplitude);
+        frequency *= lacunarity;
+        amplitude *= persistence;
+    }
+    sum / amplitudes
+}
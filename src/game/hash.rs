@@ -0,0 +1,74 @@
+use crate::prelude::*;
+
+/// The seed of one kind of feature in a world. Every feature gets its own salt, so for example the ores don't line
+/// up with the trees.
+pub fn feature_seed(world_seed: u32, salt: u32) -> u64 {
+    ((world_seed as u64) << 32) | salt as u64
+}
+
+/// A well mixed 64-bit hash of a seed and a position. Neighbouring positions get unrelated values.
+///
+/// Everything in this module is built on it, and worlds are generated from it. It must never change: the same seed
+/// and position give the same value in every version and on every platform, or existing seeds turn into new worlds.
+pub fn hash_pos(seed: u64, pos: IVec3) -> u64 {
+    let mut hash = mix(seed);
+    for coordinate in pos.to_array() {
+        hash = mix(hash ^ coordinate as u32 as u64);
+    }
+    hash
+}
+
+/// A value in `0.0..1.0` for a seed and a position.
+pub fn rand01(seed: u64, pos: IVec3) -> f32 {
+    unit(hash_pos(seed, pos))
+}
+
+/// Whether something with chance `p` happens at a position.
+#[allow(dead_code)] // No feature is on it yet
+pub fn chance(seed: u64, pos: IVec3, p: f32) -> bool {
+    rand01(seed, pos) < p
+}
+
+/// A stream of random values for a seed and a position, for features that need more than one.
+///
+/// World generation must only take its randomness from this module, never from `rand::thread_rng` or the clock, so
+/// two runs with the same seed produce byte-identical chunks no matter in which order they're generated.
+pub fn rng_for(seed: u64, pos: IVec3) -> WorldRng {
+    WorldRng {
+        state: hash_pos(seed, pos),
+    }
+}
+
+/// The random values of a feature placed once per chunk, like the ore veins and the ponds. The chunk gets the same
+/// sequence every time it's generated, and its neighbours get unrelated ones.
+pub fn chunk_rng(seed: u64, chunk: IVec2XZ) -> WorldRng {
+    rng_for(seed, IVec3::new(chunk.x, 0, chunk.z))
+}
+
+/// The top 24 bits of a hash as a float in `0.0..1.0`, all of them representable exactly.
+fn unit(hash: u64) -> f32 {
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// A splitmix64 generator. Unlike the generators of the `rand` crate it's guaranteed to never change.
+#[derive(Debug, Clone)]
+pub struct WorldRng {
+    state: u64,
+}
+
+impl WorldRng {
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+
+    /// A value in `0..n`. The modulo bias is negligible for the small ranges the generation uses.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// A value in `0.0..1.0`.
+    pub fn rand01(&mut self) -> f32 {
+        unit(self.next_u64())
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::window::CursorGrabMode;
+
+use crate::prelude::*;
+
+/// How long a hint stays on screen, including the fades.
+const HINT_DURATION: f32 = 4.0;
+const HINT_FADE: f32 = 0.4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Hint {
+    ReleaseCursor,
+    Sprint,
+    ChunkBorders,
+    EmptyChest,
+}
+
+impl Hint {
+    pub fn message(self) -> &'static str {
+        match self {
+            Hint::ReleaseCursor => "Esc to release the mouse and show the controls",
+            Hint::Sprint => "Hold Left Shift to sprint",
+            Hint::ChunkBorders => "F3 + G to show the chunk borders",
+            Hint::EmptyChest => "The chest is empty, hold C to sneak and place a block on it",
+        }
+    }
+}
+
+/// Hints that were already shown this session.
+#[derive(Resource, Default)]
+pub struct SeenHints(pub HashSet<Hint>);
+
+#[derive(Resource, Default)]
+pub struct HintQueue {
+    queue: VecDeque<Hint>,
+    current: Option<(Hint, Timer)>,
+}
+
+impl HintQueue {
+    /// Queues a hint, unless it was already shown.
+    pub fn push(&mut self, hint: Hint, seen: &mut SeenHints) {
+        if seen.0.insert(hint) {
+            self.queue.push_back(hint);
+        }
+    }
+
+    pub fn current(&self) -> Option<Hint> {
+        self.current.as_ref().map(|(hint, _)| *hint)
+    }
+
+    /// Advances the timer of the current hint, moving on to the next one when it runs out.
+    pub fn tick(&mut self, delta: std::time::Duration) {
+        if let Some((_, timer)) = &mut self.current {
+            if timer.tick(delta).finished() {
+                self.current = None;
+            }
+        }
+        if self.current.is_none() {
+            self.current = self
+                .queue
+                .pop_front()
+                .map(|hint| (hint, Timer::from_seconds(HINT_DURATION, TimerMode::Once)));
+        }
+    }
+
+    /// Opacity of the current hint, fading in and out at the ends.
+    fn alpha(&self) -> f32 {
+        match &self.current {
+            Some((_, timer)) => {
+                let elapsed = timer.elapsed_secs();
+                let fade_in = elapsed / HINT_FADE;
+                let fade_out = (HINT_DURATION - elapsed) / HINT_FADE;
+                fade_in.min(fade_out).clamp(0.0, 1.0)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct HintText;
+
+pub fn setup_hints(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("Hint Root Node"),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Percent(25.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            UI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Name::new("Hint Text"),
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                ),
+                HintText,
+            ));
+        });
+}
+
+/// Queues hints when they become relevant.
+pub fn trigger_hints(
+    keys: Res<Input<KeyCode>>,
+    windows: Query<&Window>,
+    mut hints: ResMut<HintQueue>,
+    mut seen: ResMut<SeenHints>,
+) {
+    let grabbed = windows
+        .get_single()
+        .is_ok_and(|window| window.cursor.grab_mode != CursorGrabMode::None);
+    if !grabbed {
+        return;
+    }
+
+    hints.push(Hint::ReleaseCursor, &mut seen);
+    if keys.any_pressed([KeyCode::W, KeyCode::A, KeyCode::S, KeyCode::D]) {
+        hints.push(Hint::Sprint, &mut seen);
+        hints.push(Hint::ChunkBorders, &mut seen);
+    }
+}
+
+/// Shows the current hint in the hint text, fading it in and out.
+pub fn update_hint_text(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut hints: ResMut<HintQueue>,
+    mut query: Query<&mut Text, With<HintText>>,
+) {
+    hints.tick(time.delta());
+
+    let mut text = query.single_mut();
+    let section = &mut text.sections[0];
+    let message = hints.current().map_or("", Hint::message);
+    if section.value != message {
+        section.value = message.to_string();
+    }
+    let color = settings.hud.text_color;
+    section.style.color = color.with_a(color.a() * hints.alpha());
+}
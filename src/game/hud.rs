@@ -1,22 +1,94 @@
+use crate::game::biome::biome_at;
+use crate::game::budget::{EntityBudget, EntityCategory};
+use crate::game::debug::DebugAction;
+use crate::game::freecam::CameraMode;
+use crate::game::hints::HintText;
+use crate::game::interaction::SelectedBlock;
+use crate::game::packed::MeshStats;
+use crate::game::rules::WorldSeed;
+use crate::game::status::{Burning, Health, StatusEffect};
+use crate::game::suffocation::Suffocating;
+use crate::game::target::TargetedBlock;
 use crate::prelude::*;
 use bevy::diagnostic::DiagnosticsStore;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::diagnostic::SystemInformationDiagnosticsPlugin;
+use bevy::window::CursorGrabMode;
 use bevy::window::PresentMode;
 
+const MIB: f32 = 1024.0 * 1024.0;
+
 // For FPS counter
 #[derive(Component)]
 pub struct TextChanges;
 
+// Both crosshair bars
+#[derive(Component)]
+pub struct Crosshair;
+
+// Name of the targeted block, next to the crosshair
+#[derive(Component)]
+pub struct TargetTooltip;
+
+/// How many chunks were loaded and unloaded since the game started, counted from the chunk events.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq)]
+pub struct ChunkActivity {
+    pub loaded: usize,
+    pub unloaded: usize,
+}
+
+pub fn count_chunk_activity(
+    mut ready: EventReader<ChunkReady>,
+    mut unloaded: EventReader<ChunkUnloaded>,
+    mut activity: ResMut<ChunkActivity>,
+) {
+    activity.loaded += ready.len();
+    activity.unloaded += unloaded.len();
+    ready.clear();
+    unloaded.clear();
+}
+
+/// Adds up the meshes of the sections and their fluids into the [`MeshStats`] the HUD shows.
+#[allow(clippy::type_complexity)]
+pub fn count_chunk_meshes(
+    meshes: Res<Assets<Mesh>>,
+    query: Query<&Handle<Mesh>, Or<(With<ChunkSection>, With<ChunkFluid>, With<PackedSection>)>>,
+    mut stats: ResMut<MeshStats>,
+) {
+    let mut counted = MeshStats::default();
+    for mesh in query.iter().filter_map(|handle| meshes.get(handle)) {
+        counted.add(mesh);
+    }
+    *stats = counted;
+}
+
+// Keybinding reference, shown while the mouse is released
+#[derive(Component)]
+pub struct ControlsText;
+
 /// Updates the UI text.
 ///
 /// Information about the FPS, coordinates and direction is displayed.
+#[allow(clippy::too_many_arguments)]
 pub fn update_text(
     diagnostics: Res<DiagnosticsStore>,
     mut query: Query<&mut Text, With<TextChanges>>,
     camera_query: Query<&Transform, With<Camera>>,
-    chunk_query: Query<&ChunkMesh>,
+    (chunks_loaded, activity, mesh_stats): (Res<ChunksLoaded>, Res<ChunkActivity>, Res<MeshStats>),
+    (loader, mesh_cache, insert_budget, generating): (
+        Res<ChunkLoader>,
+        Res<MeshCache>,
+        Res<MeshInsertBudget>,
+        Res<Generating>,
+    ),
     windows: Query<&Window>,
+    budget: Res<EntityBudget>,
+    targeted: Res<TargetedBlock>,
+    selected: Res<SelectedBlock>,
+    seed: Res<WorldSeed>,
+    camera_mode: Res<CameraMode>,
+    player_state: Res<PlayerPos>,
+    player_query: Query<(&Health, Option<&Burning>, Option<&Suffocating>)>,
 ) {
     // Update the FPS counter.
     let mut fps_text = query.single_mut();
@@ -42,14 +114,24 @@ pub fn update_text(
         }
     }
 
-    let chunks_loaded = chunk_query.iter().count();
+    let (chunks_ready, spawn_chunks, chunks_generating) = chunks_loaded.counts();
+    let tasks_in_flight = chunks_loaded.tasks_in_flight();
+    let catching_up = if !generating.0 {
+        " (generation paused)"
+    } else if loader.is_teleporting() {
+        " (catching up after a teleport)"
+    } else if insert_budget.is_catching_up() {
+        " (catching up after a pause)"
+    } else {
+        ""
+    };
+
+    let entity_counts = EntityCategory::ALL
+        .map(|category| format!("{}/{}", budget.count(category), budget.limits(category).cap));
 
     // Update the coordinates and direction.
     let camera_transform = camera_query.single();
-    let camera_transform_chunks: IVec2XZ = IVec2XZ::new(
-        (camera_transform.translation.x / CHUNK_SIZE as f32).floor() as i32,
-        (camera_transform.translation.z / CHUNK_SIZE as f32).floor() as i32,
-    );
+    let camera_transform_chunks: IVec2XZ = IVec2XZ::containing(camera_transform.translation);
 
     let camera_position = camera_transform.translation;
     // Determine if the camera is looking towards +X, -X, +Z or -Z.
@@ -73,32 +155,184 @@ pub fn update_text(
         "-Z"
     };
 
+    let biome = biome_at(
+        IVec2XZ::new(
+            camera_position.x.floor() as i32,
+            camera_position.z.floor() as i32,
+        ),
+        seed.0,
+    );
+
+    let target = match targeted.target {
+        Some((pos, block)) => format!("{} ({}, {}, {})", block.display_name(), pos.x, pos.y, pos.z),
+        None => "Nothing".to_string(),
+    };
+
+    // The freecam shows where it left the player too.
+    let (position_label, freecam) = if camera_mode.is_freecam() {
+        let following = if camera_mode.stream_from_camera {
+            "camera"
+        } else {
+            "player"
+        };
+        let player = player_state.pos;
+        (
+            "Camera",
+            format!(
+                "FREECAM (chunks follow the {})\nPlayer: XYZ ({:.2}, {:.2}, {:.2})\n",
+                following, player.x, player.y, player.z
+            ),
+        )
+    } else {
+        ("Position", String::new())
+    };
+
+    // New status effects go in the player query and this list.
+    let (health, burning, suffocating) = player_query.single();
+    let effects: Vec<String> = [
+        burning.map(StatusEffect::label),
+        suffocating.map(StatusEffect::label),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let effects = if effects.is_empty() {
+        String::new()
+    } else {
+        format!(", {}", effects.join(", "))
+    };
+
     let window = windows.single();
 
     let vsync = match window.present_mode {
         PresentMode::AutoNoVsync => "Disabled",
         PresentMode::AutoVsync => "Enabled",
         PresentMode::Fifo => "Enabled",
+        PresentMode::FifoRelaxed => "Adaptive",
         PresentMode::Mailbox => "Triple buffered",
         PresentMode::Immediate => "Immediate",
     };
 
     fps_text.sections[0].value = format!(
-        "FPS: {:.2} (VSync {})\nCPU: {:.2}%, RAM: {:.2}%\nChunks loaded: {}\n\nPosition: XYZ ({:.2}, {:.2}, {:.2}) Chunk: XZ ({}, {})\nDirection: {}",
+        "FPS: {:.2} (VSync {})\nCPU: {:.2}%, RAM: {:.2}%\nChunks loaded: {} ({} spawn), generating: {} ({}/{} tasks, {} waiting), cached: {}/{}, streamed: +{} -{}{}\nChunk meshes: {} vertices, {} triangles, {:.1} MiB ({:.1} MiB unpacked)\nItems: {}, Particles: {}, Mobs: {}\n\n{}{}: XYZ ({:.2}, {:.2}, {:.2}) Chunk: XZ ({}, {})\nBiome: {}, Seed: {}\nDirection: {}\nLooking at: {}, Holding: {}\nHealth: {}/{}{}",
         fps,
         vsync,
         cpu,
         ram,
-        chunks_loaded,
+        chunks_ready,
+        spawn_chunks,
+        chunks_generating,
+        tasks_in_flight,
+        MAX_CHUNK_TASKS_IN_FLIGHT,
+        insert_budget.backlog,
+        mesh_cache.count(),
+        MESH_CACHE_CAPACITY,
+        activity.loaded,
+        activity.unloaded,
+        catching_up,
+        mesh_stats.vertices,
+        mesh_stats.triangles,
+        mesh_stats.bytes as f32 / MIB,
+        mesh_stats.unpacked_bytes as f32 / MIB,
+        entity_counts[0],
+        entity_counts[1],
+        entity_counts[2],
+        freecam,
+        position_label,
         camera_position.x,
         camera_position.y,
         camera_position.z,
         camera_transform_chunks.x,
         camera_transform_chunks.z,
-        direction
+        biome.display_name(),
+        seed.0,
+        direction,
+        target,
+        selected.0.display_name(),
+        health.current,
+        health.max,
+        effects
     );
 }
 
+/// Applies the HUD palette from the settings.
+///
+/// There is no way to read back the pixels under the crosshair, so the contrast mode guesses instead:
+/// under a roof it's dark (white crosshair), under the open sky it's sky or lit terrain (black crosshair).
+pub fn apply_hud_palette(
+    settings: Res<Settings>,
+    chunk_map: Res<ChunkMap>,
+    camera_query: Query<&Transform, With<Camera>>,
+    mut crosshair_query: Query<&mut BackgroundColor, (With<Crosshair>, Without<TextChanges>)>,
+    mut panel_query: Query<&mut BackgroundColor, (With<TextChanges>, Without<Crosshair>)>,
+    mut text_query: Query<&mut Text, (Without<HintText>, Without<TargetTooltip>)>,
+) {
+    let palette = &settings.hud;
+
+    let crosshair_color = match palette.crosshair {
+        CrosshairColor::Fixed(color) => color,
+        CrosshairColor::Contrast => {
+            let camera = camera_query.single().translation.floor().as_ivec3();
+            if !chunk_map.sky_exposed(camera) {
+                Color::WHITE
+            } else {
+                Color::BLACK
+            }
+        }
+    };
+    for mut background in crosshair_query.iter_mut() {
+        if background.0 != crosshair_color {
+            background.0 = crosshair_color;
+        }
+    }
+
+    let panel_color = if palette.text_panel {
+        palette.text_panel_color
+    } else {
+        Color::NONE
+    };
+    for mut background in panel_query.iter_mut() {
+        if background.0 != panel_color {
+            background.0 = panel_color;
+        }
+    }
+
+    // Only the value of the text is rebuilt every frame, its style stays. The colour only needs setting again when
+    // the palette changed.
+    if settings.is_changed() {
+        for mut text in text_query.iter_mut() {
+            for section in text.sections.iter_mut() {
+                section.style.color = palette.text_color;
+            }
+        }
+    }
+}
+
+/// Shows the name of the targeted block after it has been targeted for a moment.
+pub fn update_target_tooltip(
+    targeted: Res<TargetedBlock>,
+    settings: Res<Settings>,
+    mut query: Query<&mut Text, With<TargetTooltip>>,
+) {
+    const DELAY: f32 = 0.5;
+    const FADE: f32 = 0.25;
+
+    let mut text = query.single_mut();
+    let section = &mut text.sections[0];
+
+    let name = targeted
+        .target
+        .map_or("", |(_, block)| block.display_name());
+    if section.value != name {
+        section.value = name.to_string();
+    }
+
+    // Hidden straight away when the target changes, since steady_for starts over.
+    let alpha = ((targeted.steady_for - DELAY) / FADE).clamp(0.0, 1.0);
+    let color = settings.hud.text_color;
+    section.style.color = color.with_a(color.a() * alpha);
+}
+
 pub fn setup_hud(mut commands: Commands) {
     // Manual implementation of the crosshair.
     // root node
@@ -132,9 +366,10 @@ pub fn setup_hud(mut commands: Commands) {
 
                         ..default()
                     },
-                    background_color: BackgroundColor(Color::rgba(1.0, 0.0, 0.0, 1.0)),
+                    background_color: BackgroundColor(Color::WHITE),
                     ..default()
                 },
+                Crosshair,
             ));
             parent.spawn((
                 Name::new("Crosshair X"),
@@ -148,9 +383,40 @@ pub fn setup_hud(mut commands: Commands) {
                         position_type: PositionType::Absolute,
                         ..default()
                     },
-                    background_color: BackgroundColor(Color::rgba(1.0, 0.0, 0.0, 1.0)),
+                    background_color: BackgroundColor(Color::WHITE),
+                    ..default()
+                },
+                Crosshair,
+            ));
+        });
+
+    // Tooltip with the targeted block, just below the crosshair
+    commands
+        .spawn((
+            Name::new("Tooltip Root Node"),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(52.0),
+                    justify_content: JustifyContent::Center,
                     ..default()
                 },
+                ..default()
+            },
+            UI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Name::new("Tooltip Text"),
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                ),
+                TargetTooltip,
             ));
         });
 
@@ -168,6 +434,7 @@ pub fn setup_hud(mut commands: Commands) {
             position_type: PositionType::Absolute,
             bottom: Val::Px(20.0),
             left: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(4.0)),
             ..default()
         }),
         TextChanges,
@@ -177,17 +444,50 @@ pub fn setup_hud(mut commands: Commands) {
     commands.spawn((
         Name::new("Controls Text"),
         TextBundle::from_section(
-        "P - Pause Chunk generation\nR - Reset Chunks\nG - Toggle Chunks Borders\nV - Toggle VSync"
-            .to_string(),
-        TextStyle {
-            font_size: 20.0,
+            controls_text(),
+            TextStyle {
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            right: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(4.0)),
             ..default()
-        },
-    )
-    .with_style(Style {
-        position_type: PositionType::Absolute,
-        bottom: Val::Px(20.0),
-        right: Val::Px(10.0),
-        ..default()
-    }),));
+        }),
+        ControlsText,
+    ));
+}
+
+/// The keybinding reference, the debug chords included.
+pub fn controls_text() -> String {
+    let mut text = "WASD - Move\nLeft Shift - Sprint\nC - Sneak\nSpace - Jump\nDouble Space - Toggle flying\nCtrl + Scroll - Fly speed\nQ - Throw a rock\nF2 - Screenshot without the HUD\nLeft Click - Grab mouse, then hold to break blocks\nRight Click - Place a block\nScroll - Pick the block to place\nCtrl + Z / Ctrl + Y - Undo / redo\nEsc - Release mouse\n".to_string();
+    for action in DebugAction::ALL {
+        text.push('\n');
+        text.push_str(&action.control_line());
+    }
+    text
+}
+
+/// Only shows the keybinding reference while the mouse is released, like a pause screen.
+pub fn toggle_controls_text(
+    windows: Query<&Window>,
+    mut query: Query<&mut Visibility, With<ControlsText>>,
+) {
+    let released = windows
+        .get_single()
+        .is_ok_and(|window| window.cursor.grab_mode == CursorGrabMode::None);
+    let visibility = if released {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for mut current in query.iter_mut() {
+        if *current != visibility {
+            *current = visibility;
+        }
+    }
 }
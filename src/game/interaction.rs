@@ -0,0 +1,433 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+
+use crate::game::chunk::remesh_chunks;
+use crate::game::debug::DEBUG_MODIFIER;
+use crate::game::edits::{apply_edits, touched_sections, EditHistory, EditRecord};
+use crate::game::freecam::CameraMode;
+use crate::game::generator::SharedGenerator;
+use crate::game::hints::{Hint, HintQueue, SeenHints};
+use crate::game::leaves::LeafDecay;
+use crate::game::placement::WorldAabb;
+use crate::game::target::{raycast_blocks, BlockHit};
+use crate::prelude::*;
+
+pub const BREAK_BUTTON: MouseButton = MouseButton::Left;
+pub const PLACE_BUTTON: MouseButton = MouseButton::Right;
+/// Held to sneak, see [`Sneaking`]. Left Shift already sprints and flies down, sneaking gets a key of its own.
+pub const SNEAK_KEY: KeyCode = KeyCode::C;
+
+/// Whether the player sneaks. A sneaking player walks slowly, can't sprint and doesn't walk off the edges of the
+/// blocks they stand on, and places blocks against the ones that would otherwise be interacted with.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq)]
+pub struct Sneaking(pub bool);
+
+/// Sneaks while [`SNEAK_KEY`] is held. Not while `F3` is, `F3` + `C` toggles the freecam, and the freecam has no
+/// body to sneak with.
+pub fn update_sneaking(
+    keys: Res<Input<KeyCode>>,
+    camera_mode: Res<CameraMode>,
+    mut sneaking: ResMut<Sneaking>,
+) {
+    let held =
+        keys.pressed(SNEAK_KEY) && !keys.pressed(DEBUG_MODIFIER) && !camera_mode.is_freecam();
+    if sneaking.0 != held {
+        sneaking.0 = held;
+    }
+}
+
+/// A right click on a block that has an interaction, see [`BlockType::has_interaction`].
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct BlockInteracted {
+    pub pos: IVec3,
+    pub block: BlockType,
+}
+
+/// How far the player got breaking a block, holding the break button on it.
+#[derive(Resource, Default, Debug, PartialEq)]
+pub struct BreakProgress {
+    /// The block being broken, and for how many seconds the button was held on it.
+    breaking: Option<(IVec3, BlockType, f32)>,
+}
+
+impl BreakProgress {
+    /// Holds the button on `target` for `delta` seconds more, looking at another block starts over. Returns the
+    /// block once it was held on for its [`hardness`](BlockType::hardness).
+    pub fn hold(&mut self, target: Option<(IVec3, BlockType)>, delta: f32) -> Option<IVec3> {
+        let Some((pos, block, hardness)) =
+            target.and_then(|(pos, block)| Some((pos, block, block.hardness()?)))
+        else {
+            self.breaking = None;
+            return None;
+        };
+        let held = match self.breaking {
+            Some((breaking, old, held)) if breaking == pos && old == block => held + delta,
+            _ => 0.0,
+        };
+        if held >= hardness {
+            self.breaking = None;
+            return Some(pos);
+        }
+        self.breaking = Some((pos, block, held));
+        None
+    }
+
+    /// The button was let go, the block is whole again.
+    pub fn release(&mut self) {
+        self.breaking = None;
+    }
+
+    /// The block being broken, and how far along it is from 0 to 1.
+    pub fn fraction(&self) -> Option<(IVec3, f32)> {
+        let (pos, block, held) = self.breaking?;
+        let hardness = block.hardness().filter(|&hardness| hardness > 0.0)?;
+        Some((pos, (held / hardness).min(1.0)))
+    }
+}
+
+/// What a right click did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockUse {
+    Interacted(BlockInteracted),
+    Placed(EditRecord),
+}
+
+/// The block the player places, picked with the scroll wheel.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct SelectedBlock(pub BlockType);
+
+impl Default for SelectedBlock {
+    fn default() -> Self {
+        Self(BlockType::Stone)
+    }
+}
+
+impl SelectedBlock {
+    /// The blocks that can be placed, in the order the scroll wheel goes through them. Plants need grass under them
+    /// and bedrock couldn't be broken again, so they're left out.
+    pub fn choices() -> impl Iterator<Item = BlockType> {
+        BlockType::ALL.into_iter().filter(|&block| {
+            block != BlockType::Air
+                && block != BlockType::Bedrock
+                && !block.is_plant()
+                && block.base() == block
+        })
+    }
+
+    /// Moves the selection a number of blocks along the choices, wrapping around at the ends.
+    pub fn cycle(&mut self, steps: i32) {
+        let choices: Vec<BlockType> = Self::choices().collect();
+        let current = choices
+            .iter()
+            .position(|&block| block == self.0)
+            .unwrap_or(0);
+        let next = (current as i32 + steps).rem_euclid(choices.len() as i32);
+        self.0 = choices[next as usize];
+    }
+}
+
+/// The block the player can reach along a ray, walking through the loaded blocks. Air and fluids are looked through,
+/// plants can be hit.
+pub fn reach_block(chunk_map: &ChunkMap, origin: Vec3, direction: Vec3) -> Option<BlockHit> {
+    raycast_blocks(origin, direction, REACH_DISTANCE, |pos| {
+        chunk_map
+            .get_block(pos)
+            .is_some_and(|block| block.is_solid() || block.is_plant())
+    })
+}
+
+/// Turns a loaded block into air. The blocks without a [`hardness`](BlockType::hardness), like bedrock, can't be
+/// broken. Returns the edit, for the history.
+pub fn break_block(
+    chunk_map: &mut ChunkMap,
+    decay: &mut LeafDecay,
+    pos: IVec3,
+) -> Option<EditRecord> {
+    let old = chunk_map
+        .get_block(pos)
+        .filter(|&block| block.hardness().is_some())?;
+    chunk_map.set_block(pos, BlockType::Air);
+    let edit = EditRecord {
+        pos,
+        old,
+        new: BlockType::Air,
+    };
+    decay.edited(&edit, |pos| chunk_map.get_block(pos));
+    Some(edit)
+}
+
+/// Places a block against the face the ray came in through. The cell has to be empty or hold a fluid, and a block
+/// you can stand on can't go where the player is. Returns the edit, for the history.
+pub fn place_block(
+    chunk_map: &mut ChunkMap,
+    decay: &mut LeafDecay,
+    hit: BlockHit,
+    block: BlockType,
+    player: WorldAabb,
+) -> Option<EditRecord> {
+    // The ray started inside the block, there's no face to place against.
+    if hit.normal == IVec3::ZERO {
+        return None;
+    }
+    let pos = hit.pos + hit.normal;
+    let block = placed_shape(block, hit);
+    let old = chunk_map
+        .get_block(pos)
+        .filter(|&old| old != block && !old.is_solid() && !old.is_plant())?;
+    if block.is_solid() && player.overlaps_block(pos) {
+        return None;
+    }
+    chunk_map.set_block(pos, block);
+    let edit = EditRecord {
+        pos,
+        old,
+        new: block,
+    };
+    decay.edited(&edit, |pos| chunk_map.get_block(pos));
+    Some(edit)
+}
+
+/// The block that goes against the hit face, a slab or stairs turned for where the face was hit. They go in the
+/// bottom half on a top face and in the top half under a bottom face, on a side in the half that was hit. Stairs
+/// placed on a side have their back on the hit block, on a top or bottom face they climb away from the edge closest
+/// to where it was hit, so they go up away from whoever looks at them.
+pub fn placed_shape(block: BlockType, hit: BlockHit) -> BlockType {
+    let inside = hit.point - (hit.pos + hit.normal).as_vec3();
+    let top = match hit.normal.y {
+        1 => false,
+        -1 => true,
+        _ => inside.y > 0.5,
+    };
+    let shape = match block.shape() {
+        BlockShape::Slab { .. } => BlockShape::Slab { top },
+        BlockShape::Stairs { facing, .. } => {
+            let facing = if hit.normal.y != 0 {
+                let (x, z) = (inside.x - 0.5, inside.z - 0.5);
+                match (x.abs() > z.abs(), x > 0.0, z > 0.0) {
+                    (true, true, _) => BlockFace::Left,
+                    (true, false, _) => BlockFace::Right,
+                    (false, _, true) => BlockFace::Back,
+                    (false, _, false) => BlockFace::Front,
+                }
+            } else {
+                BlockFace::from_offset(-hit.normal).unwrap_or(facing)
+            };
+            BlockShape::Stairs { facing, top }
+        }
+        _ => return block,
+    };
+    block.with_shape(shape).unwrap_or(block)
+}
+
+/// A right click on the hit block. Blocks with an interaction take the click, unless the player sneaks, then the
+/// block is placed against them like against any other.
+pub fn use_block(
+    chunk_map: &mut ChunkMap,
+    decay: &mut LeafDecay,
+    hit: BlockHit,
+    block: BlockType,
+    player: WorldAabb,
+    sneaking: bool,
+) -> Option<BlockUse> {
+    let target = chunk_map.get_block(hit.pos)?;
+    if target.has_interaction() && !sneaking {
+        return Some(BlockUse::Interacted(BlockInteracted {
+            pos: hit.pos,
+            block: target,
+        }));
+    }
+    place_block(chunk_map, decay, hit, block, player).map(BlockUse::Placed)
+}
+
+/// Breaks the block under the crosshair once the left button was held on it for its hardness, see
+/// [`BreakProgress`]. The click that grabs the mouse doesn't break anything, so this runs before
+/// [`cursor_grab_system`](crate::game::camera::cursor_grab_system).
+#[allow(clippy::too_many_arguments)]
+pub fn break_targeted_block(
+    mut commands: Commands,
+    (time, button): (Res<Time>, Res<Input<MouseButton>>),
+    (mut progress, mut armed): (ResMut<BreakProgress>, Local<bool>),
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+    (mut chunk_map, mut decay): (ResMut<ChunkMap>, ResMut<LeafDecay>),
+    mut history: ResMut<EditHistory>,
+    (chunks_loaded, remeshing): (Res<ChunksLoaded>, Query<&Remeshing>),
+    (game_atlas, generator, ambient_occlusion, packed_meshes): (
+        Res<GameTextureAtlas>,
+        Res<SharedGenerator>,
+        Res<AmbientOcclusion>,
+        Res<PackedMeshes>,
+    ),
+) {
+    let grabbed = window_query
+        .get_single()
+        .is_ok_and(|window| window.cursor.grab_mode != CursorGrabMode::None);
+    if button.just_pressed(BREAK_BUTTON) {
+        *armed = grabbed;
+    }
+    if !button.pressed(BREAK_BUTTON) || !grabbed || !*armed {
+        progress.release();
+        return;
+    }
+
+    let camera = camera_query.single();
+    let target = reach_block(&chunk_map, camera.translation, camera.forward())
+        .and_then(|hit| Some((hit.pos, chunk_map.get_block(hit.pos)?)));
+    let Some(pos) = progress.hold(target, time.delta_seconds()) else {
+        return;
+    };
+    let Some(edit) = break_block(&mut chunk_map, &mut decay, pos) else {
+        return;
+    };
+    history.record(edit);
+    remesh_chunks(
+        &mut commands,
+        touched_sections(pos),
+        &remeshing,
+        &chunks_loaded,
+        &chunk_map,
+        &game_atlas,
+        &generator,
+        *ambient_occlusion,
+        *packed_meshes,
+    );
+}
+
+/// Uses the targeted block on a right click, while the mouse is grabbed: interacts with it, or places the selected
+/// block against the targeted face.
+#[allow(clippy::too_many_arguments)]
+pub fn place_targeted_block(
+    mut commands: Commands,
+    (button, sneaking): (Res<Input<MouseButton>>, Res<Sneaking>),
+    mut interactions: EventWriter<BlockInteracted>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+    player_query: Query<(&Collider, &Transform), With<KinematicCharacterController>>,
+    selected: Res<SelectedBlock>,
+    (mut chunk_map, mut history, mut decay): (
+        ResMut<ChunkMap>,
+        ResMut<EditHistory>,
+        ResMut<LeafDecay>,
+    ),
+    (chunks_loaded, remeshing): (Res<ChunksLoaded>, Query<&Remeshing>),
+    (game_atlas, generator, ambient_occlusion, packed_meshes): (
+        Res<GameTextureAtlas>,
+        Res<SharedGenerator>,
+        Res<AmbientOcclusion>,
+        Res<PackedMeshes>,
+    ),
+) {
+    let grabbed = window_query
+        .get_single()
+        .is_ok_and(|window| window.cursor.grab_mode != CursorGrabMode::None);
+    if !button.just_pressed(PLACE_BUTTON) || !grabbed {
+        return;
+    }
+
+    let camera = camera_query.single();
+    let Some(hit) = reach_block(&chunk_map, camera.translation, camera.forward()) else {
+        return;
+    };
+    let (collider, transform) = player_query.single();
+    let player = WorldAabb::of_collider(collider, transform);
+    let edit = match use_block(
+        &mut chunk_map,
+        &mut decay,
+        hit,
+        selected.0,
+        player,
+        sneaking.0,
+    ) {
+        Some(BlockUse::Placed(edit)) => edit,
+        Some(BlockUse::Interacted(interaction)) => {
+            interactions.send(interaction);
+            return;
+        }
+        None => return,
+    };
+    history.record(edit);
+    remesh_chunks(
+        &mut commands,
+        touched_sections(edit.pos),
+        &remeshing,
+        &chunks_loaded,
+        &chunk_map,
+        &game_atlas,
+        &generator,
+        *ambient_occlusion,
+        *packed_meshes,
+    );
+}
+
+/// Opens the blocks that were interacted with. Chests can't hold anything yet, so opening one only says so.
+pub fn open_interacted_blocks(
+    mut interactions: EventReader<BlockInteracted>,
+    mut hints: ResMut<HintQueue>,
+    mut seen: ResMut<SeenHints>,
+) {
+    for interaction in interactions.iter() {
+        if interaction.block == BlockType::Chest {
+            hints.push(Hint::EmptyChest, &mut seen);
+        }
+    }
+}
+
+/// The scroll wheel picks the block to place. With Ctrl held it changes the fly speed instead.
+pub fn cycle_selected_block(
+    mut scroll: EventReader<MouseWheel>,
+    keys: Res<Input<KeyCode>>,
+    mut selected: ResMut<SelectedBlock>,
+) {
+    let lines: f32 = scroll
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // Touchpads scroll in pixels, roughly 20 per line.
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+    if lines == 0.0 || keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    // Scrolling down goes forward through the blocks, like a hotbar.
+    selected.cycle(-lines.signum() as i32);
+}
+
+/// Ctrl+Z takes back the last edit of the player, Ctrl+Y does it again.
+#[allow(clippy::too_many_arguments)]
+pub fn undo_edits(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    (mut chunk_map, mut decay): (ResMut<ChunkMap>, ResMut<LeafDecay>),
+    mut history: ResMut<EditHistory>,
+    (chunks_loaded, remeshing): (Res<ChunksLoaded>, Query<&Remeshing>),
+    game_atlas: Res<GameTextureAtlas>,
+    generator: Res<SharedGenerator>,
+    (ambient_occlusion, packed_meshes): (Res<AmbientOcclusion>, Res<PackedMeshes>),
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let edits = if keys.just_pressed(KeyCode::Z) {
+        history.undo()
+    } else if keys.just_pressed(KeyCode::Y) {
+        history.redo()
+    } else {
+        return;
+    };
+    let Some(edits) = edits else {
+        return;
+    };
+    let changed = apply_edits(&mut chunk_map, &mut decay, &edits);
+    remesh_chunks(
+        &mut commands,
+        changed,
+        &remeshing,
+        &chunks_loaded,
+        &chunk_map,
+        &game_atlas,
+        &generator,
+        *ambient_occlusion,
+        *packed_meshes,
+    );
+}
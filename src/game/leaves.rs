@@ -0,0 +1,169 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::game::chunk::remesh_chunks;
+use crate::game::edits::{touched_sections, EditRecord};
+use crate::game::generator::SharedGenerator;
+use crate::prelude::*;
+
+/// How many steps through leaves a leaf may be from a log and still hold on.
+pub const LEAF_REACH: i32 = 4;
+/// The most leaves checked in one tick, so felling a big tree doesn't spike a frame.
+pub const DECAY_CHECKS_PER_TICK: usize = 64;
+/// Unsupported leaves break after this many ticks, plus up to [`DECAY_JITTER`] more.
+pub const DECAY_DELAY: u64 = 20;
+pub const DECAY_JITTER: u64 = 40;
+
+/// Whether a leaf is within [`LEAF_REACH`] of a log, walking through leaves only. The species don't matter.
+///
+/// `block_at` returns None for unloaded blocks. Leaves next to them are kept, the log might be there.
+pub fn is_supported(pos: IVec3, block_at: impl Fn(IVec3) -> Option<BlockType>) -> bool {
+    let mut seen = HashSet::from([pos]);
+    let mut frontier = vec![pos];
+    for _ in 0..LEAF_REACH {
+        let mut next = Vec::new();
+        for current in frontier {
+            for face in BlockFace::ALL {
+                let neighbor = current + face.offset();
+                if !seen.insert(neighbor) {
+                    continue;
+                }
+                match block_at(neighbor) {
+                    None => return true,
+                    Some(block) if block.is_log() => return true,
+                    Some(block) if block.is_leaves() => next.push(neighbor),
+                    Some(_) => {}
+                }
+            }
+        }
+        frontier = next;
+    }
+    false
+}
+
+/// Leaves waiting for their decay check, ordered by the tick they're due.
+#[derive(Resource, Default)]
+pub struct LeafDecay {
+    due: BinaryHeap<Reverse<(u64, [i32; 3])>>,
+    queued: HashSet<IVec3>,
+    /// The fixed ticks [`decay_leaves`] ran so far.
+    ticks: u64,
+}
+
+impl LeafDecay {
+    /// Schedules a check of a leaf, after a delay picked from its position. Also the random tick hook.
+    pub fn schedule(&mut self, pos: IVec3, tick: u64) {
+        if !self.queued.insert(pos) {
+            return;
+        }
+        let hash = mix(((pos.x as u32 as u64) << 40)
+            ^ ((pos.y as u32 as u64) << 20)
+            ^ pos.z as u32 as u64);
+        let due = tick + DECAY_DELAY + hash % (DECAY_JITTER + 1);
+        self.due.push(Reverse((due, pos.to_array())));
+    }
+
+    /// The neighbour-changed hook: after a log or leaves block changed, checks every leaf that could have hung on it.
+    pub fn block_changed(
+        &mut self,
+        pos: IVec3,
+        tick: u64,
+        block_at: impl Fn(IVec3) -> Option<BlockType>,
+    ) {
+        for x in -LEAF_REACH..=LEAF_REACH {
+            for y in -LEAF_REACH..=LEAF_REACH {
+                for z in -LEAF_REACH..=LEAF_REACH {
+                    let offset = IVec3::new(x, y, z);
+                    if offset.abs().to_array().iter().sum::<i32>() > LEAF_REACH {
+                        continue;
+                    }
+                    if block_at(pos + offset).is_some_and(BlockType::is_leaves) {
+                        self.schedule(pos + offset, tick);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the leaves around an edit that took away or added a log or leaves, as of the current tick.
+    pub fn edited(&mut self, edit: &EditRecord, block_at: impl Fn(IVec3) -> Option<BlockType>) {
+        let holds_leaves = |block: BlockType| block.is_log() || block.is_leaves();
+        if holds_leaves(edit.old) || holds_leaves(edit.new) {
+            self.block_changed(edit.pos, self.ticks, block_at);
+        }
+    }
+
+    /// How many leaves are waiting for their check.
+    pub fn pending(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Runs up to [`DECAY_CHECKS_PER_TICK`] of the checks that are due and returns the leaves that break.
+    ///
+    /// The caller removes them, and reports each removal with [`LeafDecay::block_changed`] so the decay spreads.
+    pub fn tick(&mut self, tick: u64, block_at: impl Fn(IVec3) -> Option<BlockType>) -> Vec<IVec3> {
+        let mut decayed = Vec::new();
+        for _ in 0..DECAY_CHECKS_PER_TICK {
+            match self.due.peek() {
+                Some(&Reverse((due, _))) if due <= tick => {}
+                _ => break,
+            }
+            let Some(Reverse((_, pos))) = self.due.pop() else {
+                break;
+            };
+            let pos = IVec3::from_array(pos);
+            self.queued.remove(&pos);
+            // The leaf may be gone already, broken by the player or by an earlier check.
+            if block_at(pos).is_some_and(BlockType::is_leaves) && !is_supported(pos, &block_at) {
+                decayed.push(pos);
+            }
+        }
+        decayed
+    }
+}
+
+/// Breaks the leaves whose checks are due, once per fixed tick. The decay isn't an edit, it stays out of the history.
+#[allow(clippy::too_many_arguments)]
+pub fn decay_leaves(
+    mut commands: Commands,
+    (mut chunk_map, mut decay): (ResMut<ChunkMap>, ResMut<LeafDecay>),
+    (chunks_loaded, remeshing): (Res<ChunksLoaded>, Query<&Remeshing>),
+    (game_atlas, generator, ambient_occlusion, packed_meshes): (
+        Res<GameTextureAtlas>,
+        Res<SharedGenerator>,
+        Res<AmbientOcclusion>,
+        Res<PackedMeshes>,
+    ),
+) {
+    decay.ticks += 1;
+    if decay.pending() == 0 {
+        return;
+    }
+    let tick = decay.ticks;
+    let decayed = decay.tick(tick, |pos| chunk_map.get_block(pos));
+    if decayed.is_empty() {
+        return;
+    }
+
+    let mut changed = Vec::new();
+    for pos in decayed {
+        chunk_map.set_block(pos, BlockType::Air);
+        decay.block_changed(pos, tick, |pos| chunk_map.get_block(pos));
+        for section in touched_sections(pos) {
+            if !changed.contains(&section) {
+                changed.push(section);
+            }
+        }
+    }
+    remesh_chunks(
+        &mut commands,
+        changed,
+        &remeshing,
+        &chunks_loaded,
+        &chunk_map,
+        &game_atlas,
+        &generator,
+        *ambient_occlusion,
+        *packed_meshes,
+    );
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use crate::game::chunk::{create_face, mesh_block_parts, ChunkMeshData};
+use crate::game::generator::{chunk_origin, WorldGenerator};
+use crate::prelude::*;
+
+/// How close two vertices of a low detail mesh have to be to get welded, in blocks.
+const LOD_WELD_EPSILON: f32 = 0.01;
+/// How far the skirts hang down below the edges of a low detail mesh, in blocks.
+pub const LOD_SKIRT_DEPTH: f32 = 4.0;
+/// The sides of a chunk, the faces towards each of its neighbours.
+pub const CHUNK_SIDES: [BlockFace; 4] = [
+    BlockFace::Left,
+    BlockFace::Right,
+    BlockFace::Front,
+    BlockFace::Back,
+];
+
+/// The scale a chunk `offset` chunks away from the centre of the streaming is meshed at: full detail up to the
+/// [`LOD_DISTANCE`], [`LOD_SCALE`] past it.
+pub fn lod_scale(offset: IVec2XZ) -> usize {
+    if offset.x * offset.x + offset.z * offset.z > LOD_DISTANCE * LOD_DISTANCE {
+        LOD_SCALE
+    } else {
+        1
+    }
+}
+
+/// The meshes of a chunk past the [`LOD_DISTANCE`], meshed with [`mesh_lod`] at `scale`. All of it goes in the
+/// first section and the other sections are empty, so a chunk that was at full detail loses them. There's no
+/// collider, nothing that far away can be touched.
+pub fn lod_chunk_meshes(
+    chunk_blocks: &ChunkBlocks,
+    chunk_position: IVec2XZ,
+    scale: usize,
+    skirts: &[BlockFace],
+    atlas: &GameTextureAtlas,
+    generator: &dyn WorldGenerator,
+) -> ChunkMeshes {
+    let (solid, fluids) = mesh_lod(
+        chunk_blocks,
+        chunk_position,
+        scale,
+        atlas,
+        true,
+        |column| generator.biome_at(column).grass_tint(),
+        skirts,
+    );
+    let surface = SectionMesh {
+        index: 0,
+        solid_mesh: solid.into_mesh(),
+        fluid_meshes: fluids
+            .into_iter()
+            .map(|(block, mut fluid)| {
+                let center = fluid.recenter();
+                FluidMesh {
+                    block,
+                    mesh: fluid.into_mesh(),
+                    center,
+                }
+            })
+            .collect(),
+        collider: None,
+    };
+    let empty = (1..SECTIONS).map(|index| SectionMesh {
+        index,
+        solid_mesh: ChunkMeshData::default().into_mesh(),
+        fluid_meshes: Vec::new(),
+        collider: None,
+    });
+    ChunkMeshes {
+        sections: std::iter::once(surface).chain(empty).collect(),
+        scale,
+    }
+}
+
+/// Meshes a chunk at a lower resolution, for terrain far away. Every cube of `scale` blocks on a side becomes
+/// one block, so `scale` has to divide the chunk size and height. The fluids come apart from the rest, like in
+/// [`mesh_block_parts`], and the tinted faces get the color `tint_at` gives for the world column of their cell.
+///
+/// With `smooth` the vertices the faces share are welded together, see [`weld_vertices`]. The full detail chunks
+/// always stay flat shaded.
+///
+/// The `skirts` sides get a curtain hanging down from the edges of the surface, so the gaps between this mesh and a
+/// neighbour at another detail show the skirt instead of the sky. See [`skirt_sides`].
+pub fn mesh_lod(
+    chunk_blocks: &ChunkBlocks,
+    chunk_position: IVec2XZ,
+    scale: usize,
+    atlas: &GameTextureAtlas,
+    smooth: bool,
+    tint_at: impl Fn(IVec2XZ) -> [f32; 3],
+    skirts: &[BlockFace],
+) -> (ChunkMeshData, Vec<(BlockType, ChunkMeshData)>) {
+    let size = UVec3::new(CHUNK_SIZE as u32, CHUNK_HEIGHT as u32, CHUNK_SIZE as u32) / scale as u32;
+    let cells = downsample(chunk_blocks, scale);
+    let size_cells = size.as_ivec3();
+    let cell_at = |pos: IVec3| {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(size_cells).any() {
+            return BlockType::Air;
+        }
+        let pos = pos.as_uvec3();
+        cells[((pos.x * size.z + pos.z) * size.y + pos.y) as usize]
+    };
+    // The neighbouring chunks get their own low detail meshes. Repeating the cells at the border hides the faces
+    // towards them, instead of walling the chunk in.
+    let corner = chunk_origin(chunk_position);
+    let (mut data, mut fluids) = mesh_block_parts(
+        size,
+        IVec3::ZERO,
+        atlas,
+        false,
+        |cell| {
+            tint_at(IVec2XZ::new(
+                corner.x + cell.x * scale as i32,
+                corner.z + cell.z * scale as i32,
+            ))
+        },
+        |pos| {
+            cell_at(IVec3::new(
+                pos.x.clamp(0, size_cells.x - 1),
+                pos.y,
+                pos.z.clamp(0, size_cells.z - 1),
+            ))
+        },
+    );
+
+    let mut skirt_data = mesh_skirts(size, skirts, LOD_SKIRT_DEPTH / scale as f32, atlas, cell_at);
+
+    let origin = Vec3::new(
+        (chunk_position.x * CHUNK_SIZE as i32) as f32,
+        0.0,
+        (chunk_position.z * CHUNK_SIZE as i32) as f32,
+    );
+    let to_world = |data: &mut ChunkMeshData| {
+        for position in &mut data.positions {
+            *position = (Vec3::from(*position) * scale as f32 + origin).to_array();
+        }
+    };
+    to_world(&mut data);
+    for (_, fluid) in &mut fluids {
+        to_world(fluid);
+    }
+
+    let mut data = if smooth {
+        weld_vertices(data, LOD_WELD_EPSILON)
+    } else {
+        data
+    };
+
+    // The skirts aren't welded, they'd bend the normals of the surface along the edges.
+    to_world(&mut skirt_data);
+    data.append(skirt_data);
+    (data, fluids)
+}
+
+/// The sides of a chunk that need skirts, the ones towards neighbours that are (or may be) meshed at another
+/// scale. `scale_at` is the scale a chunk is meshed at, `None` if it's not known yet.
+pub fn skirt_sides(
+    chunk_position: IVec2XZ,
+    scale: usize,
+    scale_at: impl Fn(IVec2XZ) -> Option<usize>,
+) -> Vec<BlockFace> {
+    CHUNK_SIDES
+        .into_iter()
+        .filter(|side| {
+            let offset = side.offset();
+            scale_at(chunk_position + IVec2XZ::new(offset.x, offset.z)) != Some(scale)
+        })
+        .collect()
+}
+
+/// The skirts along the sides of a chunk of cells: a quad facing out of the chunk under every top edge of its surface
+/// on those sides, `depth` cells tall and with the side texture of the cell.
+fn mesh_skirts(
+    size: UVec3,
+    sides: &[BlockFace],
+    depth: f32,
+    atlas: &GameTextureAtlas,
+    cell_at: impl Fn(IVec3) -> BlockType,
+) -> ChunkMeshData {
+    let mut data = ChunkMeshData::default();
+    let size = size.as_ivec3();
+    for &side in sides {
+        // The cells along the side, going along the other horizontal axis.
+        let border = |along: i32| match side {
+            BlockFace::Left => IVec3::new(0, 0, along),
+            BlockFace::Right => IVec3::new(size.x - 1, 0, along),
+            BlockFace::Back => IVec3::new(along, 0, 0),
+            _ => IVec3::new(along, 0, size.z - 1),
+        };
+        let length = if matches!(side, BlockFace::Left | BlockFace::Right) {
+            size.z
+        } else {
+            size.x
+        };
+        for along in 0..length {
+            for y in 0..size.y {
+                let cell = border(along) + IVec3::Y * y;
+                let block = cell_at(cell);
+                if !block.is_solid() || cell_at(cell + IVec3::Y).is_solid() {
+                    continue;
+                }
+                create_face(
+                    &mut data,
+                    cell.as_vec3().to_array(),
+                    side,
+                    block,
+                    atlas,
+                    None,
+                    None,
+                );
+                // The side of the cell, stretched down from the top of it.
+                let face = data.positions.len() - 4;
+                for position in &mut data.positions[face..] {
+                    if position[1] == y as f32 {
+                        position[1] = (y + 1) as f32 - depth;
+                    }
+                }
+            }
+        }
+    }
+    data
+}
+
+/// The block of every cell of a chunk, in the same order as the chunk stores its blocks. A cell is the block most
+/// of it is made of, or air if less than half of it is filled.
+fn downsample(chunk_blocks: &ChunkBlocks, scale: usize) -> Vec<BlockType> {
+    let mut cells = Vec::new();
+    for x in (0..CHUNK_SIZE).step_by(scale) {
+        for z in (0..CHUNK_SIZE).step_by(scale) {
+            for y in (0..CHUNK_HEIGHT).step_by(scale) {
+                let mut counts: Vec<(BlockType, usize)> = Vec::new();
+                for dx in 0..scale {
+                    for dz in 0..scale {
+                        for &block in &chunk_blocks.column(x + dx, z + dz)[y..y + scale] {
+                            // The plants are too small to show from far away.
+                            if block == BlockType::Air || block.is_plant() {
+                                continue;
+                            }
+                            match counts.iter_mut().find(|(counted, _)| *counted == block) {
+                                Some((_, count)) => *count += 1,
+                                None => counts.push((block, 1)),
+                            }
+                        }
+                    }
+                }
+                let filled: usize = counts.iter().map(|(_, count)| count).sum();
+                let cell = counts
+                    .into_iter()
+                    .max_by_key(|&(_, count)| count)
+                    .filter(|_| filled * 2 >= scale.pow(3))
+                    .map_or(BlockType::Air, |(block, _)| block);
+                cells.push(cell);
+            }
+        }
+    }
+    cells
+}
+
+/// Merges the vertices that are at the same position, and averages their normals so the mesh gets smooth shaded.
+///
+/// Positions are snapped to a grid `epsilon` wide to find the ones that coincide. A merged vertex keeps the
+/// position, UV and color of the first of them, so textures smear across the faces a vertex is shared by. That's only
+/// good enough for terrain far away.
+pub fn weld_vertices(data: ChunkMeshData, epsilon: f32) -> ChunkMeshData {
+    let mut welded = ChunkMeshData::default();
+    let mut cells: HashMap<IVec3, u32> = HashMap::new();
+    // The vertex every welded one started out as.
+    let mut firsts = Vec::new();
+    let remap: Vec<u32> = data
+        .positions
+        .iter()
+        .enumerate()
+        .map(|(i, &position)| {
+            let cell = (Vec3::from(position) / epsilon).round().as_ivec3();
+            let index = *cells.entry(cell).or_insert_with(|| {
+                welded.positions.push(position);
+                welded.normals.push([0.0; 3]);
+                welded.uvs.push(data.uvs[i]);
+                if !data.colors.is_empty() {
+                    welded
+                        .colors
+                        .push(data.colors.get(i).copied().unwrap_or([1.0; 4]));
+                }
+                firsts.push(i);
+                welded.positions.len() as u32 - 1
+            });
+            let normal = &mut welded.normals[index as usize];
+            *normal = (Vec3::from(*normal) + Vec3::from(data.normals[i])).to_array();
+            index
+        })
+        .collect();
+
+    // Normals pointing opposite ways cancel out, those vertices keep the first one.
+    for (normal, &first) in welded.normals.iter_mut().zip(&firsts) {
+        *normal = Vec3::from(*normal)
+            .try_normalize()
+            .map_or(data.normals[first], |average| average.to_array());
+    }
+    welded.indices = data.indices.iter().map(|&i| remap[i as usize]).collect();
+    welded
+}
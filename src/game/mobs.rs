@@ -0,0 +1,155 @@
+use crate::game::budget::{EntityBudget, EntityCategory};
+use crate::game::environment::EnvironmentSample;
+use crate::game::hash::rng_for;
+use crate::game::status::Health;
+use crate::prelude::*;
+
+/// Hostiles only spawn in the dark. The sky light doesn't reach this many blocks under the ground, and at night the
+/// open sky is dark too. There's no light data in the chunks, so that's all a spawn looks at.
+pub const HOSTILE_SPAWN_DEPTH: i32 = 8;
+/// How far from the player hostiles spawn, in blocks along each axis, and how close they never do.
+pub const HOSTILE_SPAWN_RADIUS: i32 = 24;
+pub const HOSTILE_MIN_SPAWN_DISTANCE: f32 = 12.0;
+/// Seconds between two spawns, and how many cells are tried for each.
+pub const HOSTILE_SPAWN_INTERVAL: f32 = 2.0;
+pub const HOSTILE_SPAWN_TRIES: usize = 16;
+pub const HOSTILE_HEALTH: f32 = 10.0;
+const HOSTILE_SIZE: Vec3 = Vec3::new(0.6, 1.8, 0.6);
+
+/// Mobs that attack the player. These are removed when the difficulty is set to peaceful.
+#[derive(Component)]
+pub struct Hostile;
+
+#[derive(Resource)]
+pub struct HostileAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+pub fn setup_hostile_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(HostileAssets {
+        mesh: meshes.add(shape::Box::new(HOSTILE_SIZE.x, HOSTILE_SIZE.y, HOSTILE_SIZE.z).into()),
+        material: materials.add(Color::rgb(0.25, 0.45, 0.2).into()),
+    });
+}
+
+/// Whether a hostile can spawn with its feet in a cell: on top of a full block, with room for its head, and in the
+/// dark, [`HOSTILE_SPAWN_DEPTH`] under the highest block of the column or under the open sky at night.
+pub fn is_spawn_candidate(chunk_map: &ChunkMap, cell: IVec3, night: bool) -> bool {
+    let free = |pos| {
+        chunk_map
+            .get_block(pos)
+            .is_some_and(|block| !block.is_solid() && !block.is_fluid())
+    };
+    let ground = chunk_map
+        .get_block(cell - IVec3::Y)
+        .is_some_and(|block| block.covers_face(BlockFace::Top));
+    if !ground || !free(cell) || !free(cell + IVec3::Y) {
+        return false;
+    }
+    if chunk_map.sky_exposed(cell) {
+        return night;
+    }
+    let depth = chunk_map.sky_top(IVec2XZ::new(cell.x, cell.z)).unwrap_or(0) - cell.y;
+    depth >= HOSTILE_SPAWN_DEPTH
+}
+
+/// Picks a cell around the player for the next hostile, out of [`HOSTILE_SPAWN_TRIES`] random ones. `seed` gives
+/// the cells to try.
+pub fn find_spawn_cell(
+    chunk_map: &ChunkMap,
+    player: Vec3,
+    seed: u64,
+    night: bool,
+) -> Option<IVec3> {
+    let center = player.floor().as_ivec3();
+    let mut rng = rng_for(seed, center);
+    let side = (HOSTILE_SPAWN_RADIUS * 2 + 1) as u64;
+    (0..HOSTILE_SPAWN_TRIES)
+        .map(|_| {
+            let offset = [(); 3].map(|_| rng.below(side) as i32 - HOSTILE_SPAWN_RADIUS);
+            center + IVec3::from(offset)
+        })
+        .filter(|cell| cell.as_vec3().distance(player) >= HOSTILE_MIN_SPAWN_DISTANCE)
+        .find(|&cell| is_spawn_candidate(chunk_map, cell, night))
+}
+
+/// Spawns a hostile in the dark around the player every [`HOSTILE_SPAWN_INTERVAL`], unless it's peaceful or the mob
+/// budget is full.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_hostiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    (settings, chunk_map, sample, player): (
+        Res<Settings>,
+        Res<ChunkMap>,
+        Res<EnvironmentSample>,
+        Res<PlayerPos>,
+    ),
+    assets: Res<HostileAssets>,
+    mut budget: ResMut<EntityBudget>,
+    mut since_spawn: Local<f32>,
+    mut spawns: Local<u64>,
+) {
+    *since_spawn += time.delta_seconds();
+    if *since_spawn < HOSTILE_SPAWN_INTERVAL {
+        return;
+    }
+    *since_spawn = 0.0;
+    if !settings.difficulty.allows_hostile_spawns() || !budget.can_spawn(EntityCategory::Mob) {
+        return;
+    }
+
+    *spawns += 1;
+    let night = sample.sun_height < 0.0;
+    let Some(cell) = find_spawn_cell(&chunk_map, player.pos, *spawns, night) else {
+        return;
+    };
+    let feet = cell.as_vec3() + Vec3::new(0.5, 0.0, 0.5);
+    commands.spawn((
+        Name::new("Hostile"),
+        PbrBundle {
+            mesh: assets.mesh.clone(),
+            material: assets.material.clone(),
+            transform: Transform::from_translation(feet + Vec3::Y * HOSTILE_SIZE.y / 2.0),
+            ..default()
+        },
+        Hostile,
+        Health {
+            current: HOSTILE_HEALTH,
+            max: HOSTILE_HEALTH,
+        },
+        budget.track(EntityCategory::Mob),
+    ));
+}
+
+/// Hostiles are gone once they're out of health.
+pub fn despawn_dead_hostiles(
+    mut commands: Commands,
+    hostiles: Query<(Entity, &Health), With<Hostile>>,
+) {
+    for (entity, health) in hostiles.iter() {
+        if health.current <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Despawns all hostile mobs while the difficulty is peaceful.
+pub fn despawn_peaceful_hostiles(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    hostiles: Query<Entity, With<Hostile>>,
+) {
+    if settings.difficulty.allows_hostile_spawns() {
+        return;
+    }
+
+    for entity in hostiles.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,5 +1,41 @@
+pub mod atlas;
+pub mod biome;
+pub mod budget;
 pub mod camera;
+pub mod capture;
 pub mod chunk;
 pub mod common;
+pub mod cracks;
 pub mod debug;
+pub mod edits;
+pub mod environment;
+pub mod flight;
+pub mod fluid_material;
+pub mod freecam;
+pub mod generator;
+pub mod hash;
+pub mod hints;
 pub mod hud;
+pub mod interaction;
+pub mod leaves;
+pub mod lod;
+pub mod mobs;
+pub mod ores;
+pub mod packed;
+pub mod placement;
+pub mod plants;
+pub mod ponds;
+pub mod projectiles;
+pub mod ravines;
+pub mod report;
+pub mod rules;
+pub mod schematic;
+pub mod settings;
+pub mod sky;
+pub mod soak;
+pub mod stamp;
+pub mod status;
+pub mod suffocation;
+pub mod target;
+pub mod trees;
+pub mod tune;
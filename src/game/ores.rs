@@ -0,0 +1,114 @@
+use crate::game::hash::{chunk_rng, feature_seed};
+use crate::prelude::*;
+
+const ORE_SALT: u32 = 0x0e5e;
+/// How far a vein can wander from its origin on each axis.
+pub const VEIN_REACH: i32 = 2;
+
+/// A blob of ore, grown from its origin by a random walk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OreVein {
+    pub ore: BlockType,
+    pub origin: IVec3,
+    /// How many steps the walk takes. The vein has at most this many blocks, fewer if the walk crosses itself.
+    pub size: u32,
+    walk: u64,
+}
+
+impl OreVein {
+    /// The world positions of the vein's blocks, in the order they're placed. Always connected.
+    pub fn blocks(&self) -> Vec<IVec3> {
+        let mut hash = self.walk;
+        let mut offset = IVec3::ZERO;
+        let mut blocks = vec![self.origin];
+        while (blocks.len() as u32) < self.size {
+            hash = mix(hash);
+            let step = offset + BlockFace::ALL[(hash % 6) as usize].offset();
+            // Steps out of reach are skipped, there's room for far bigger veins than any ore has.
+            if step.abs().max_element() > VEIN_REACH {
+                continue;
+            }
+            offset = step;
+            let pos = self.origin + offset;
+            if !blocks.contains(&pos) {
+                blocks.push(pos);
+            }
+        }
+        blocks
+    }
+}
+
+/// The veins whose origins are in this chunk, ore by ore in the order of [`ORES`].
+pub fn veins_in_chunk(chunk_position: IVec2XZ, seed: u32) -> Vec<OreVein> {
+    let mut rng = chunk_rng(feature_seed(seed, ORE_SALT), chunk_position);
+    let mut roll = |range: u64| rng.below(range);
+
+    let mut veins = Vec::new();
+    for ore in &ORES {
+        for _ in 0..ore.veins_per_chunk {
+            let origin = IVec3::new(
+                chunk_position.x * CHUNK_SIZE as i32 + roll(CHUNK_SIZE as u64) as i32,
+                ore.heights.start()
+                    + roll((ore.heights.end() - ore.heights.start() + 1) as u64) as i32,
+                chunk_position.z * CHUNK_SIZE as i32 + roll(CHUNK_SIZE as u64) as i32,
+            );
+            let size =
+                ore.sizes.start() + roll((ore.sizes.end() - ore.sizes.start() + 1) as u64) as u32;
+            veins.push(OreVein {
+                ore: ore.ore,
+                origin,
+                size,
+                walk: roll(u64::MAX),
+            });
+        }
+    }
+    veins
+}
+
+/// The veins that can put blocks into a chunk: its own, and the ones reaching in from the neighbours.
+///
+/// The order is the same for every block of the chunk, so overlapping veins are settled the same way everywhere.
+pub fn veins_near(chunk_position: IVec2XZ, seed: u32) -> Vec<OreVein> {
+    let mut veins = Vec::new();
+    for dx in -1..=1 {
+        for dz in -1..=1 {
+            veins.extend(veins_in_chunk(chunk_position + IVec2XZ::new(dx, dz), seed));
+        }
+    }
+    veins
+}
+
+/// Writes the parts of the veins that are inside the chunk. Veins only replace stone.
+///
+/// The rest of a vein crossing the border is written when the neighbour generates, from the same veins.
+pub fn place_veins(chunk_position: IVec2XZ, veins: &[OreVein], chunk_blocks: &mut ChunkBlocks) {
+    let origin = IVec3::new(
+        chunk_position.x * CHUNK_SIZE as i32,
+        0,
+        chunk_position.z * CHUNK_SIZE as i32,
+    );
+    for vein in veins {
+        for pos in vein.blocks() {
+            let local = pos - origin;
+            if !(0..CHUNK_SIZE as i32).contains(&local.x)
+                || !(0..CHUNK_SIZE as i32).contains(&local.z)
+                || !(0..CHUNK_HEIGHT as i32).contains(&local.y)
+            {
+                continue;
+            }
+            let (x, y, z) = (local.x as usize, local.y as usize, local.z as usize);
+            if chunk_blocks.get(x, y, z) == BlockType::Stone {
+                chunk_blocks.set(x, y, z, vein.ore);
+            }
+        }
+    }
+}
+
+/// The ore a stone block was turned into, if any. The first vein that reaches it wins, like in [`place_veins`].
+pub fn vein_block_at(veins: &[OreVein], pos: IVec3) -> Option<BlockType> {
+    veins
+        .iter()
+        .filter(|vein| (vein.origin - pos).abs().max_element() <= VEIN_REACH)
+        .find(|vein| vein.blocks().contains(&pos))
+        .map(|vein| vein.ore)
+}
@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+
+use bevy::asset::load_internal_asset;
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey, StandardMaterialFlags};
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::render::mesh::{
+    Indices, MeshVertexAttribute, MeshVertexBufferLayout, VertexAttributeValues,
+};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    AsBindGroup, AsBindGroupShaderType, PrimitiveTopology, RenderPipelineDescriptor, ShaderRef,
+    SpecializedMeshPipelineError, VertexFormat,
+};
+
+use crate::prelude::*;
+
+/// The only attribute of a packed chunk mesh, 8 bytes a vertex. The first word is a [`PackedVertex`] without its
+/// color, the second one is the color, 8 bits a channel.
+pub const ATTRIBUTE_PACKED: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Packed", 0x5041_434b, VertexFormat::Uint32x2);
+
+/// How many tiles of the atlas the packed vertices can use. The material has a table of them.
+pub const MAX_PACKED_TILES: usize = 256;
+/// How far from the corner of its section a packed vertex can be, on every axis.
+pub const MAX_PACKED_POSITION: u32 = 31;
+/// The brightest glow of a packed vertex, the glow of a block that [emits light](BlockProperties::emits_light) at 1.
+pub const MAX_PACKED_GLOW: u8 = 15;
+/// How big a vertex of an unpacked chunk mesh is, with its position, normal, texture coordinates and color.
+pub const UNPACKED_VERTEX_SIZE: usize = 48;
+
+const POSITION_BITS: u32 = 5;
+const FACE_SHIFT: u32 = 3 * POSITION_BITS;
+const CORNER_SHIFT: u32 = FACE_SHIFT + 3;
+const TILE_SHIFT: u32 = CORNER_SHIFT + 2;
+const GLOW_SHIFT: u32 = TILE_SHIFT + 8;
+
+/// The shaders of the packed chunks, built into the game so they don't depend on the assets folder. The types
+/// are imported by the other two.
+pub const PACKED_CHUNK_TYPES_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 0x2b7c_90de_13f4_6a85);
+pub const PACKED_CHUNK_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 0x2b7c_90de_13f4_6a86);
+pub const PACKED_CHUNK_PREPASS_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 0x2b7c_90de_13f4_6a87);
+
+/// A vertex of a chunk, with everything the mesh needs to know about it. The faces are axis aligned with their
+/// corners on whole blocks, so little of it is needed: where it is in its section, which way its face points,
+/// which corner of which tile it is, and how much its block glows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PackedVertex {
+    /// In blocks from the minimum corner of the section, up to [`MAX_PACKED_POSITION`].
+    pub position: UVec3,
+    /// The face the normal points out of. The crosses point up, like their normals.
+    pub face: BlockFace,
+    /// Whether the vertex is at the far side of its tile, along the texture coordinates.
+    pub corner: BVec2,
+    /// The tile of the atlas, below [`MAX_PACKED_TILES`].
+    pub tile: usize,
+    /// How bright the colors of the texture glow in the dark, up to [`MAX_PACKED_GLOW`].
+    pub glow: u8,
+    pub color: [u8; 4],
+}
+
+impl PackedVertex {
+    pub fn pack(self) -> [u32; 2] {
+        let face = BlockFace::ALL
+            .iter()
+            .position(|&face| face == self.face)
+            .unwrap() as u32;
+        let word = self.position.x
+            | self.position.y << POSITION_BITS
+            | self.position.z << (2 * POSITION_BITS)
+            | face << FACE_SHIFT
+            | (self.corner.x as u32) << CORNER_SHIFT
+            | (self.corner.y as u32) << (CORNER_SHIFT + 1)
+            | (self.tile as u32) << TILE_SHIFT
+            | (self.glow as u32) << GLOW_SHIFT;
+        [word, u32::from_le_bytes(self.color)]
+    }
+
+    pub fn unpack([word, color]: [u32; 2]) -> Self {
+        let axis = |shift: u32| word >> shift & MAX_PACKED_POSITION;
+        Self {
+            position: UVec3::new(axis(0), axis(POSITION_BITS), axis(2 * POSITION_BITS)),
+            face: BlockFace::ALL[(word >> FACE_SHIFT & 0b111) as usize],
+            corner: BVec2::new(
+                word >> CORNER_SHIFT & 1 != 0,
+                word >> (CORNER_SHIFT + 1) & 1 != 0,
+            ),
+            tile: (word >> TILE_SHIFT & 0xff) as usize,
+            glow: (word >> GLOW_SHIFT) as u8,
+            color: color.to_le_bytes(),
+        }
+    }
+}
+
+/// The tiles the packed vertices can use, by the corners of their texture coordinates.
+fn packed_tiles(atlas: &GameTextureAtlas) -> HashMap<[u32; 4], usize> {
+    (0..atlas.atlas.len().min(MAX_PACKED_TILES))
+        .map(|tile| {
+            let uvs = atlas.tile_uvs(tile);
+            let corners = [uvs[0][0], uvs[0][1], uvs[2][0], uvs[2][1]];
+            (corners.map(f32::to_bits), tile)
+        })
+        .collect()
+}
+
+/// How much the blocks that use a tile glow. The ores have tiles of their own, so it's the glow of their block.
+fn packed_glows(atlas: &GameTextureAtlas) -> HashMap<usize, u8> {
+    let mut glows = HashMap::new();
+    for block in BlockType::ALL {
+        let Some(glow) = block.properties().emits_light else {
+            continue;
+        };
+        let glow = (glow.clamp(0.0, 1.0) * MAX_PACKED_GLOW as f32).round() as u8;
+        for face in BlockFace::ALL {
+            for &tile in atlas.tiles(block, face) {
+                glows.insert(tile, glow);
+            }
+        }
+    }
+    glows
+}
+
+/// Packs a chunk mesh into [`ATTRIBUTE_PACKED`], with its positions relative to `origin`. The indices are 16-bit
+/// if there are few enough vertices. The colors are rounded to 8 bits, the glow of the blocks is looked up by their
+/// tile.
+///
+/// The vertices have to come in quads with the texture coordinates of a whole tile. None if anything doesn't fit,
+/// the mesh is drawn as it is then.
+pub fn pack_mesh(mesh: &Mesh, origin: Vec3, atlas: &GameTextureAtlas) -> Option<Mesh> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    else {
+        return None;
+    };
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        return None;
+    };
+    let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) => Some(colors),
+        None => None,
+        Some(_) => return None,
+    };
+    if positions.len() % 4 != 0 {
+        return None;
+    }
+
+    let tiles = packed_tiles(atlas);
+    let glows = packed_glows(atlas);
+    let mut vertices = Vec::with_capacity(positions.len());
+    for quad in (0..positions.len()).step_by(4) {
+        let corners = &uvs[quad..quad + 4];
+        let min = corners.iter().fold([f32::MAX; 2], |min, uv| {
+            [min[0].min(uv[0]), min[1].min(uv[1])]
+        });
+        let max = corners.iter().fold([f32::MIN; 2], |max, uv| {
+            [max[0].max(uv[0]), max[1].max(uv[1])]
+        });
+        let tile = *tiles.get(&[min[0], min[1], max[0], max[1]].map(f32::to_bits))?;
+
+        for vertex in quad..quad + 4 {
+            let local = Vec3::from(positions[vertex]) - origin;
+            let in_range = local.cmpge(Vec3::ZERO).all()
+                && local.cmple(Vec3::splat(MAX_PACKED_POSITION as f32)).all();
+            if !in_range || local != local.round() {
+                return None;
+            }
+            let normal = Vec3::from(normals[vertex]);
+            let face = BlockFace::ALL
+                .into_iter()
+                .find(|face| face.offset().as_vec3() == normal)?;
+            let [u, v] = uvs[vertex];
+            if !(u == min[0] || u == max[0]) || !(v == min[1] || v == max[1]) {
+                return None;
+            }
+            let color = colors.map_or([u8::MAX; 4], |colors| {
+                colors[vertex].map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+            });
+            vertices.push(
+                PackedVertex {
+                    position: local.as_uvec3(),
+                    face,
+                    corner: BVec2::new(u == max[0], v == max[1]),
+                    tile,
+                    glow: glows.get(&tile).copied().unwrap_or(0),
+                    color,
+                }
+                .pack(),
+            );
+        }
+    }
+
+    let indices = match mesh.indices()? {
+        Indices::U32(indices) if vertices.len() <= u16::MAX as usize + 1 => {
+            Indices::U16(indices.iter().map(|&index| index as u16).collect())
+        }
+        indices => indices.clone(),
+    };
+    let mut packed = Mesh::new(PrimitiveTopology::TriangleList);
+    packed.insert_attribute(ATTRIBUTE_PACKED, VertexAttributeValues::Uint32x2(vertices));
+    packed.set_indices(Some(indices));
+    Some(packed)
+}
+
+/// Packs the solid meshes of the sections of a chunk, relative to the corner of each section. Their colliders
+/// were already built from the unpacked meshes. A section that doesn't fit stays unpacked.
+pub fn pack_chunk_meshes(
+    chunk_position: IVec2XZ,
+    chunk_meshes: &mut ChunkMeshes,
+    atlas: &GameTextureAtlas,
+) {
+    for section in &mut chunk_meshes.sections {
+        if section.solid_mesh.count_vertices() == 0 {
+            continue;
+        }
+        let origin = ChunkSection {
+            position: chunk_position,
+            index: section.index,
+        }
+        .origin();
+        if let Some(packed) = pack_mesh(&section.solid_mesh, origin.as_vec3(), atlas) {
+            section.solid_mesh = packed;
+        }
+    }
+}
+
+/// Whether a mesh was packed by [`pack_mesh`].
+pub fn is_packed(mesh: &Mesh) -> bool {
+    mesh.attribute(ATTRIBUTE_PACKED).is_some()
+}
+
+/// How many vertices and triangles some meshes have, and how much memory they take on the GPU.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq)]
+pub struct MeshStats {
+    pub vertices: usize,
+    pub triangles: usize,
+    pub bytes: usize,
+    /// How much they would take if none of them were packed, with 32-bit indices.
+    pub unpacked_bytes: usize,
+}
+
+impl MeshStats {
+    pub fn add(&mut self, mesh: &Mesh) {
+        let vertices = mesh.count_vertices();
+        let (indices, index_bytes) = match mesh.indices() {
+            Some(Indices::U16(indices)) => (indices.len(), indices.len() * 2),
+            Some(Indices::U32(indices)) => (indices.len(), indices.len() * 4),
+            None => (vertices, 0),
+        };
+        let vertex_size: u64 = mesh
+            .attributes()
+            .map(|(_, values)| VertexFormat::from(values).size())
+            .sum();
+        let bytes = vertices * vertex_size as usize + index_bytes;
+        self.vertices += vertices;
+        self.triangles += indices / 3;
+        self.bytes += bytes;
+        self.unpacked_bytes += if is_packed(mesh) {
+            vertices * UNPACKED_VERTEX_SIZE + indices * 4
+        } else {
+            bytes
+        };
+    }
+}
+
+/// The material of the packed solid blocks of the chunks. It's lit and cut out like their standard material, but
+/// the vertex shader unpacks the vertices first.
+#[derive(AsBindGroup, TypeUuid, TypePath, Clone, Debug)]
+#[uuid = "4a9e27c3-5d18-4f6b-b0e2-83c71d5a9f40"]
+#[uniform(0, PackedChunkMaterialUniform)]
+pub struct PackedChunkMaterial {
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Handle<Image>,
+    /// The tiles of the atlas, in texture coordinates. Only the first [`MAX_PACKED_TILES`] are used.
+    pub tiles: Vec<Rect>,
+    pub alpha_cutoff: f32,
+    pub metallic: f32,
+    pub reflectance: f32,
+    pub unlit: bool,
+}
+
+impl PackedChunkMaterial {
+    /// Draws the packed meshes of an atlas, with the tiles [`pack_mesh`] looks up in it.
+    pub fn new(atlas: &GameTextureAtlas) -> Self {
+        Self {
+            texture: atlas.atlas.texture.clone(),
+            tiles: (0..atlas.atlas.len().min(MAX_PACKED_TILES))
+                .map(|tile| {
+                    let uvs = atlas.tile_uvs(tile);
+                    Rect::from_corners(uvs[0].into(), uvs[2].into())
+                })
+                .collect(),
+            alpha_cutoff: 0.5,
+            metallic: 1.,
+            reflectance: 1.,
+            unlit: false,
+        }
+    }
+}
+
+pub use uniform::PackedChunkMaterialUniform;
+
+// The derive checks every field of the uniform with a function nothing calls.
+#[allow(dead_code)]
+mod uniform {
+    use bevy::render::render_resource::ShaderType;
+
+    use super::MAX_PACKED_TILES;
+    use crate::prelude::*;
+
+    /// What the shaders get of a [`PackedChunkMaterial`](super::PackedChunkMaterial). The flags are the standard
+    /// material's, the shader passes them on to the lighting.
+    #[derive(ShaderType, Clone, Copy, Debug)]
+    pub struct PackedChunkMaterialUniform {
+        /// The corners of every tile, the top left and then the bottom right.
+        pub tiles: [Vec4; MAX_PACKED_TILES],
+        pub alpha_cutoff: f32,
+        pub metallic: f32,
+        pub reflectance: f32,
+        pub flags: u32,
+    }
+}
+
+impl AsBindGroupShaderType<PackedChunkMaterialUniform> for PackedChunkMaterial {
+    fn as_bind_group_shader_type(
+        &self,
+        _images: &RenderAssets<Image>,
+    ) -> PackedChunkMaterialUniform {
+        let mut flags = StandardMaterialFlags::FOG_ENABLED | StandardMaterialFlags::ALPHA_MODE_MASK;
+        if self.unlit {
+            flags |= StandardMaterialFlags::UNLIT;
+        }
+        let mut tiles = [Vec4::ZERO; MAX_PACKED_TILES];
+        for (corners, tile) in tiles.iter_mut().zip(&self.tiles) {
+            *corners = tile.min.extend(tile.max.x).extend(tile.max.y);
+        }
+        PackedChunkMaterialUniform {
+            tiles,
+            alpha_cutoff: self.alpha_cutoff,
+            metallic: self.metallic,
+            reflectance: self.reflectance,
+            flags: flags.bits(),
+        }
+    }
+}
+
+impl Material for PackedChunkMaterial {
+    fn vertex_shader() -> ShaderRef {
+        PACKED_CHUNK_SHADER_HANDLE.typed().into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        PACKED_CHUNK_SHADER_HANDLE.typed().into()
+    }
+
+    fn prepass_vertex_shader() -> ShaderRef {
+        PACKED_CHUNK_PREPASS_SHADER_HANDLE.typed().into()
+    }
+
+    fn prepass_fragment_shader() -> ShaderRef {
+        PACKED_CHUNK_PREPASS_SHADER_HANDLE.typed().into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Mask(self.alpha_cutoff)
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // The mesh pipelines only look for the standard attributes, a packed mesh has none of them.
+        descriptor.vertex.buffers =
+            vec![layout.get_layout(&[ATTRIBUTE_PACKED.at_shader_location(0)])?];
+        Ok(())
+    }
+}
+
+/// Draws the packed chunk meshes with their [`PackedChunkMaterial`].
+pub struct PackedMeshPlugin;
+
+impl Plugin for PackedMeshPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            PACKED_CHUNK_TYPES_HANDLE,
+            "packed_chunk_types.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            PACKED_CHUNK_SHADER_HANDLE,
+            "packed_chunk.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            PACKED_CHUNK_PREPASS_SHADER_HANDLE,
+            "packed_chunk_prepass.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(MaterialPlugin::<PackedChunkMaterial>::default());
+    }
+}
@@ -0,0 +1,79 @@
+// The packed chunks. The vertex shader unpacks the vertices, the fragment shader lights them like the standard
+// material does the unpacked ones, with the plants cut out of their tiles. The blocks that glow, like the ores, glint
+// where their texture is colorful, the grey stone around the gems stays dark.
+
+#import voxel::packed_chunk as packed_chunk
+#import bevy_pbr::mesh_functions as mesh_functions
+#import bevy_pbr::pbr_functions as pbr_functions
+#import bevy_pbr::pbr_types as pbr_types
+#import bevy_pbr::mesh_bindings mesh
+#import bevy_pbr::mesh_view_bindings view, fog
+#import bevy_pbr::mesh_view_types FOG_MODE_OFF
+#import bevy_core_pipeline::tonemapping screen_space_dither, powsafe, tone_mapping
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) glow: f32,
+};
+
+@vertex
+fn vertex(vertex: packed_chunk::Vertex) -> VertexOutput {
+    let unpacked = packed_chunk::unpack_vertex(vertex);
+
+    var out: VertexOutput;
+    out.world_position = mesh_functions::mesh_position_local_to_world(mesh.model, vec4(unpacked.position, 1.0));
+    out.position = mesh_functions::mesh_position_world_to_clip(out.world_position);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(unpacked.normal);
+    out.uv = unpacked.uv;
+    out.color = unpacked.color;
+    out.glow = unpacked.glow;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var output_color = textureSampleBias(packed_chunk::chunk_texture, packed_chunk::chunk_sampler, in.uv, view.mip_bias);
+    output_color = output_color * in.color;
+
+    var pbr_input = pbr_functions::pbr_input_new();
+    pbr_input.material.base_color = output_color;
+    pbr_input.material.metallic = packed_chunk::material.metallic;
+    pbr_input.material.reflectance = packed_chunk::material.reflectance;
+    pbr_input.material.alpha_cutoff = packed_chunk::material.alpha_cutoff;
+    pbr_input.material.flags = packed_chunk::material.flags;
+    let chroma = max(output_color.r, max(output_color.g, output_color.b)) - min(output_color.r, min(output_color.g, output_color.b));
+    pbr_input.material.emissive = vec4(output_color.rgb * chroma * in.glow, 1.0);
+
+    if (packed_chunk::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+        pbr_input.frag_coord = in.position;
+        pbr_input.world_position = in.world_position;
+        pbr_input.world_normal = normalize(in.world_normal);
+        pbr_input.N = pbr_input.world_normal;
+        pbr_input.is_orthographic = view.projection[3].w == 1.0;
+        pbr_input.V = pbr_functions::calculate_view(in.world_position, pbr_input.is_orthographic);
+        pbr_input.flags = mesh.flags;
+        output_color = pbr_functions::pbr(pbr_input);
+    } else {
+        output_color = pbr_functions::alpha_discard(pbr_input.material, output_color);
+    }
+
+    if fog.mode != FOG_MODE_OFF && (packed_chunk::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_FOG_ENABLED_BIT) != 0u {
+        output_color = pbr_functions::apply_fog(fog, output_color, in.world_position.xyz, view.world_position.xyz);
+    }
+
+#ifdef TONEMAP_IN_SHADER
+    output_color = tone_mapping(output_color, view.color_grading);
+#ifdef DEBAND_DITHER
+    var output_rgb = output_color.rgb;
+    output_rgb = powsafe(output_rgb, 1.0 / 2.2);
+    output_rgb = output_rgb + screen_space_dither(in.position.xy);
+    output_rgb = powsafe(output_rgb, 2.2);
+    output_color = vec4(output_rgb, output_color.a);
+#endif
+#endif
+    return output_color;
+}
@@ -0,0 +1,90 @@
+// The prepass of the packed chunks, the shadows included. The plants are cut out of their tiles here too, like the
+// prepass of the standard material does it.
+
+#import voxel::packed_chunk as packed_chunk
+#import bevy_pbr::mesh_functions as mesh_functions
+#import bevy_pbr::mesh_bindings mesh
+#import bevy_pbr::prepass_bindings as prepass_bindings
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+#ifdef NORMAL_PREPASS
+    @location(1) world_normal: vec3<f32>,
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    @location(3) world_position: vec4<f32>,
+    @location(4) previous_world_position: vec4<f32>,
+#endif
+#ifdef DEPTH_CLAMP_ORTHO
+    @location(5) clip_position_unclamped: vec4<f32>,
+#endif
+};
+
+@vertex
+fn vertex(vertex: packed_chunk::Vertex) -> VertexOutput {
+    let unpacked = packed_chunk::unpack_vertex(vertex);
+    let position = vec4(unpacked.position, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = mesh_functions::mesh_position_local_to_clip(mesh.model, position);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.clip_position;
+    out.clip_position.z = min(out.clip_position.z, 1.0);
+#endif
+    out.uv = unpacked.uv;
+#ifdef NORMAL_PREPASS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(unpacked.normal);
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    out.world_position = mesh_functions::mesh_position_local_to_world(mesh.model, position);
+    out.previous_world_position = mesh_functions::mesh_position_local_to_world(mesh.previous_model, position);
+#endif
+    return out;
+}
+
+// The vertex colors don't change the alpha, only the texture is cut out.
+fn alpha_discard(in: VertexOutput) {
+    let texel = textureSampleBias(packed_chunk::chunk_texture, packed_chunk::chunk_sampler, in.uv, prepass_bindings::view.mip_bias);
+    if texel.a < packed_chunk::material.alpha_cutoff {
+        discard;
+    }
+}
+
+#ifdef PREPASS_FRAGMENT
+struct FragmentOutput {
+#ifdef NORMAL_PREPASS
+    @location(0) normal: vec4<f32>,
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    @location(1) motion_vector: vec2<f32>,
+#endif
+#ifdef DEPTH_CLAMP_ORTHO
+    @builtin(frag_depth) frag_depth: f32,
+#endif
+};
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    alpha_discard(in);
+
+    var out: FragmentOutput;
+#ifdef NORMAL_PREPASS
+    out.normal = vec4(normalize(in.world_normal) * 0.5 + vec3(0.5), 1.0);
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    let clip_position_t = prepass_bindings::view.unjittered_view_proj * in.world_position;
+    let previous_clip_position_t = prepass_bindings::previous_view_proj * in.previous_world_position;
+    out.motion_vector = (clip_position_t.xy / clip_position_t.w - previous_clip_position_t.xy / previous_clip_position_t.w) * vec2(0.5, -0.5);
+#endif
+#ifdef DEPTH_CLAMP_ORTHO
+    out.frag_depth = in.clip_position_unclamped.z;
+#endif
+    return out;
+}
+#else
+@fragment
+fn fragment(in: VertexOutput) {
+    alpha_discard(in);
+}
+#endif
@@ -0,0 +1,63 @@
+// The material of the packed chunks, and how their vertices are unpacked. Shared by the main pass and the prepass.
+
+#define_import_path voxel::packed_chunk
+
+struct PackedChunkMaterial {
+    // The top left and the bottom right corner of every tile.
+    tiles: array<vec4<f32>, 256>,
+    alpha_cutoff: f32,
+    metallic: f32,
+    reflectance: f32,
+    flags: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: PackedChunkMaterial;
+@group(1) @binding(1)
+var chunk_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var chunk_sampler: sampler;
+
+struct Vertex {
+    @location(0) packed: vec2<u32>,
+};
+
+struct UnpackedVertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    uv: vec2<f32>,
+    color: vec4<f32>,
+    glow: f32,
+};
+
+// The normals of the faces, in the order of `BlockFace::ALL`.
+fn face_normal(face: u32) -> vec3<f32> {
+    var normals = array<vec3<f32>, 6>(
+        vec3(0.0, 1.0, 0.0),
+        vec3(0.0, -1.0, 0.0),
+        vec3(-1.0, 0.0, 0.0),
+        vec3(1.0, 0.0, 0.0),
+        vec3(0.0, 0.0, 1.0),
+        vec3(0.0, 0.0, -1.0),
+    );
+    return normals[min(face, 5u)];
+}
+
+// The bits are laid out like `PackedVertex::pack` does it: 5 for each axis of the position, 3 for the face,
+// 2 for the corner of the tile, 8 for the tile and the last 4 for the glow. The second word is the color.
+fn unpack_vertex(vertex: Vertex) -> UnpackedVertex {
+    let word = vertex.packed.x;
+    let tile = material.tiles[(word >> 20u) & 255u];
+    let corner = (word >> 18u) & 3u;
+
+    var out: UnpackedVertex;
+    out.position = vec3(f32(word & 31u), f32((word >> 5u) & 31u), f32((word >> 10u) & 31u));
+    out.normal = face_normal((word >> 15u) & 7u);
+    out.uv = vec2(
+        select(tile.x, tile.z, (corner & 1u) != 0u),
+        select(tile.y, tile.w, (corner & 2u) != 0u),
+    );
+    out.color = unpack4x8unorm(vertex.packed.y);
+    out.glow = f32(word >> 28u) / 15.0;
+    return out;
+}
@@ -0,0 +1,31 @@
+use crate::prelude::*;
+
+/// How far a block may reach into an entity's bounding box before it counts as overlapping.
+/// This lets you place blocks right next to yourself, like under your feet at the top of a jump.
+pub const PLACEMENT_EPSILON: f32 = 0.001;
+
+/// An axis-aligned bounding box in world space.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WorldAabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl WorldAabb {
+    /// The bounding box of a collider. Rotation is ignored, the player only turns around the y axis.
+    pub fn of_collider(collider: &Collider, transform: &Transform) -> Self {
+        let aabb = collider.raw.compute_local_aabb();
+        Self {
+            min: transform.translation + Vec3::from(aabb.mins),
+            max: transform.translation + Vec3::from(aabb.maxs),
+        }
+    }
+
+    /// Whether the unit cube of a block overlaps the box by more than [`PLACEMENT_EPSILON`].
+    pub fn overlaps_block(&self, block: IVec3) -> bool {
+        let block_min = block.as_vec3();
+        let block_max = block_min + Vec3::ONE;
+        (self.min + PLACEMENT_EPSILON).cmplt(block_max).all()
+            && (self.max - PLACEMENT_EPSILON).cmpgt(block_min).all()
+    }
+}
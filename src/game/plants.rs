@@ -0,0 +1,42 @@
+use crate::game::hash::{feature_seed, rand01};
+use crate::prelude::*;
+
+const PLANT_SALT: u32 = 0x91a7;
+
+/// The plant a column grows if it's topped with grass, whatever the height of the grass.
+pub fn plant_for_column(column: IVec2XZ, seed: u32) -> Option<BlockType> {
+    let roll = rand01(
+        feature_seed(seed, PLANT_SALT),
+        IVec3::new(column.x, 0, column.z),
+    );
+    if roll < FLOWER_CHANCE / 2.0 {
+        Some(BlockType::Poppy)
+    } else if roll < FLOWER_CHANCE {
+        Some(BlockType::Dandelion)
+    } else if roll < FLOWER_CHANCE + TALL_GRASS_CHANCE {
+        Some(BlockType::TallGrass)
+    } else {
+        None
+    }
+}
+
+/// Puts the plants on the grass blocks of a chunk with air above them.
+pub fn place_plants(chunk_position: IVec2XZ, seed: u32, chunk_blocks: &mut ChunkBlocks) {
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let column = IVec2XZ::new(
+                chunk_position.x * CHUNK_SIZE as i32 + x as i32,
+                chunk_position.z * CHUNK_SIZE as i32 + z as i32,
+            );
+            let Some(plant) = plant_for_column(column, seed) else {
+                continue;
+            };
+            let blocks = chunk_blocks.column_mut(x, z);
+            for y in 1..CHUNK_HEIGHT {
+                if blocks[y] == BlockType::Air && blocks[y - 1] == BlockType::Grass {
+                    blocks[y] = plant;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::game::hash::{chunk_rng, feature_seed};
+use crate::prelude::*;
+
+const POND_SALT: u32 = 0x90d;
+
+/// How many blocks are kept free between a pond (including its scorch ring) and the chunk border.
+const POND_MARGIN: usize = 3;
+const SCORCH_RADIUS: f32 = 2.0;
+
+/// A small lake carved into the surface of a single chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pond {
+    /// Local x and z of the centre column.
+    pub center: (usize, usize),
+    pub radius_x: usize,
+    pub radius_z: usize,
+    pub depth: usize,
+    /// Whether the pond turns into lava if it's low enough.
+    pub lava: bool,
+}
+
+/// Returns the pond placed in this chunk, if there is one.
+pub fn pond_for_chunk(chunk_position: IVec2XZ, seed: u32) -> Option<Pond> {
+    let mut rng = chunk_rng(feature_seed(seed, POND_SALT), chunk_position);
+    let mut roll = |range: u64| rng.below(range);
+
+    if roll(10_000) as f32 >= POND_CHANCE * 10_000.0 {
+        return None;
+    }
+
+    let radius_x = 2 + roll(3) as usize;
+    let radius_z = 2 + roll(3) as usize;
+    let depth = 2 + roll(2) as usize;
+    let lava = (roll(10_000) as f32) < LAVA_POND_CHANCE * 10_000.0;
+
+    // Keep the pond and its scorch ring away from the chunk border.
+    let min_x = radius_x + POND_MARGIN;
+    let min_z = radius_z + POND_MARGIN;
+    let center = (
+        min_x + roll((CHUNK_SIZE - 2 * min_x) as u64) as usize,
+        min_z + roll((CHUNK_SIZE - 2 * min_z) as u64) as usize,
+    );
+
+    Some(Pond {
+        center,
+        radius_x,
+        radius_z,
+        depth,
+        lava,
+    })
+}
+
+impl Pond {
+    /// Whether a local column is in the pond or its scorch ring.
+    pub fn near(&self, x: usize, z: usize) -> bool {
+        pond_distance(self, x, z, SCORCH_RADIUS) <= 1.0
+    }
+}
+
+/// How far a column is from the pond centre, where 1.0 is the edge of the ellipse grown by `padding` blocks.
+fn pond_distance(pond: &Pond, x: usize, z: usize, padding: f32) -> f32 {
+    let dx = (x as f32 - pond.center.0 as f32) / (pond.radius_x as f32 + padding);
+    let dz = (z as f32 - pond.center.1 as f32) / (pond.radius_z as f32 + padding);
+    (dx * dx + dz * dz).sqrt()
+}
+
+/// The highest non-air block in a column.
+fn surface_y(blocks: &ChunkBlocks, x: usize, z: usize) -> Option<usize> {
+    blocks
+        .column(x, z)
+        .iter()
+        .rposition(|&block| block != BlockType::Air)
+}
+
+/// Carves the pond into the chunk blocks. Returns false (and leaves the blocks untouched) if it doesn't fit,
+/// for example because it would be placed in the ocean or hang over a cave opening.
+pub fn carve_pond(pond: &Pond, blocks: &mut ChunkBlocks) -> bool {
+    let footprint: Vec<(usize, usize)> = (0..CHUNK_SIZE)
+        .flat_map(|x| (0..CHUNK_SIZE).map(move |z| (x, z)))
+        .filter(|&(x, z)| pond_distance(pond, x, z, 0.0) <= 1.0)
+        .collect();
+
+    // The water level is the lowest surface inside the pond, so the water never stands above the ground.
+    let mut level = CHUNK_HEIGHT;
+    for &(x, z) in &footprint {
+        let Some(y) = surface_y(blocks, x, z) else {
+            return false;
+        };
+        if !matches!(
+            blocks.get(x, y, z),
+            BlockType::Grass | BlockType::SnowyGrass | BlockType::Dirt | BlockType::Sand
+        ) {
+            return false;
+        }
+        level = level.min(y);
+    }
+    if level < pond.depth {
+        return false;
+    }
+
+    let (fluid, lining) = if pond.lava && level < LAVA_POND_HEIGHT {
+        (BlockType::Lava, BlockType::Stone)
+    } else {
+        (BlockType::Water, BlockType::Sand)
+    };
+
+    // The bowl is an ellipsoid, deepest in the middle and at least one block deep at the edge.
+    let mut fluid_cells = Vec::new();
+    for &(x, z) in &footprint {
+        let distance = pond_distance(pond, x, z, 0.0);
+        let depth =
+            ((pond.depth as f32 * (1.0 - distance * distance).sqrt()).round() as usize).max(1);
+        for y in (level + 1 - depth)..=level {
+            fluid_cells.push((x, y, z));
+        }
+    }
+    let is_fluid_cell = |x: usize, y: usize, z: usize| fluid_cells.contains(&(x, y, z));
+
+    // Every cell around the fluid (except above) has to be solid, otherwise the pond would leak into a cave.
+    let mut lining_cells = Vec::new();
+    for &(x, y, z) in &fluid_cells {
+        for (nx, ny, nz) in [
+            (x - 1, y, z),
+            (x + 1, y, z),
+            (x, y, z - 1),
+            (x, y, z + 1),
+            (x, y - 1, z),
+        ] {
+            if is_fluid_cell(nx, ny, nz) {
+                continue;
+            }
+            if !blocks.get(nx, ny, nz).is_solid() {
+                return false;
+            }
+            lining_cells.push((nx, ny, nz));
+        }
+    }
+
+    // Clear the terrain above the water level.
+    for &(x, z) in &footprint {
+        blocks.column_mut(x, z)[(level + 1)..].fill(BlockType::Air);
+    }
+    for (x, y, z) in fluid_cells.iter().copied() {
+        blocks.set(x, y, z, fluid);
+    }
+    for (x, y, z) in lining_cells {
+        if blocks.get(x, y, z) != BlockType::Bedrock {
+            blocks.set(x, y, z, lining);
+        }
+    }
+
+    // Lava scorches the grass around it.
+    if fluid == BlockType::Lava {
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                if pond_distance(pond, x, z, 0.0) <= 1.0
+                    || pond_distance(pond, x, z, SCORCH_RADIUS) > 1.0
+                {
+                    continue;
+                }
+                if let Some(y) = surface_y(blocks, x, z) {
+                    if matches!(
+                        blocks.get(x, y, z),
+                        BlockType::Grass | BlockType::SnowyGrass
+                    ) {
+                        blocks.set(x, y, z, BlockType::Dirt);
+                    }
+                }
+            }
+        }
+    }
+
+    true
+}
@@ -0,0 +1,260 @@
+use std::f32::consts::TAU;
+
+use bevy::math::Vec3Swizzles;
+
+use crate::game::budget::{EntityBudget, EntityCategory};
+use crate::game::mobs::Hostile;
+use crate::game::status::{DamageCause, DamageEvent};
+use crate::game::target::{raycast_blocks, BlockHit};
+use crate::prelude::*;
+
+pub const THROW_KEY: KeyCode = KeyCode::Q;
+/// The projectiles move in steps of this many seconds, whatever the frame rate, so a throw always lands on the same
+/// block.
+pub const PROJECTILE_STEP: f32 = 1.0 / 60.0;
+pub const THROW_SPEED: f32 = 20.0;
+/// Seconds before a projectile that never hit anything is despawned.
+pub const PROJECTILE_LIFETIME: f32 = 10.0;
+pub const PROJECTILE_DAMAGE: f32 = 2.0;
+/// How hard a mob that was hit is pushed away, in blocks per second.
+pub const PROJECTILE_KNOCKBACK: f32 = 6.0;
+/// How close to the centre of a mob a projectile has to get to hit it.
+pub const MOB_HIT_RADIUS: f32 = 0.6;
+const PROJECTILE_SIZE: f32 = 0.2;
+const IMPACT_PARTICLES: usize = 8;
+const PARTICLE_SIZE: f32 = 0.08;
+const PARTICLE_LIFETIME: f32 = 0.6;
+
+/// A thrown rock. It isn't a rapier body, it falls and collides with the blocks by itself in fixed steps.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Projectile {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Seconds since the throw, in whole steps.
+    pub age: f32,
+    /// Time of the frames that didn't add up to a whole step yet.
+    unsimulated: f32,
+}
+
+/// What a projectile ran into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Impact {
+    Block(BlockHit),
+    Entity(Entity),
+}
+
+impl Projectile {
+    pub fn new(position: Vec3, velocity: Vec3) -> Self {
+        Self {
+            position,
+            velocity,
+            age: 0.0,
+            unsimulated: 0.0,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.age >= PROJECTILE_LIFETIME
+    }
+
+    /// Moves the projectile by one step. If it runs into a block on the way, it stops where the block starts.
+    pub fn step(&mut self, solid: impl Fn(IVec3) -> bool) -> Option<BlockHit> {
+        self.age += PROJECTILE_STEP;
+        self.velocity.y -= GRAVITY * PROJECTILE_STEP;
+        let motion = self.velocity * PROJECTILE_STEP;
+
+        let hit = raycast_blocks(self.position, motion, motion.length(), solid);
+        match hit {
+            Some(hit) => self.position += motion.normalize_or_zero() * hit.distance,
+            None => self.position += motion,
+        }
+        hit
+    }
+
+    /// Runs the whole steps that fit into `delta` seconds, and the time left over the next time. Stops at the first
+    /// block it hits, or the first entity `target_at` finds at its position.
+    pub fn advance(
+        &mut self,
+        delta: f32,
+        solid: impl Fn(IVec3) -> bool,
+        target_at: impl Fn(Vec3) -> Option<Entity>,
+    ) -> Option<Impact> {
+        self.unsimulated += delta;
+        while self.unsimulated >= PROJECTILE_STEP && !self.is_expired() {
+            self.unsimulated -= PROJECTILE_STEP;
+            if let Some(hit) = self.step(&solid) {
+                return Some(Impact::Block(hit));
+            }
+            if let Some(entity) = target_at(self.position) {
+                return Some(Impact::Entity(entity));
+            }
+        }
+        None
+    }
+}
+
+/// A speck of dust flying off where a projectile hit a block.
+#[derive(Component, Debug)]
+pub struct ImpactParticle {
+    velocity: Vec3,
+    remaining: f32,
+}
+
+#[derive(Resource)]
+pub struct ProjectileAssets {
+    rock_mesh: Handle<Mesh>,
+    rock_material: Handle<StandardMaterial>,
+    particle_mesh: Handle<Mesh>,
+    particle_material: Handle<StandardMaterial>,
+}
+
+pub fn setup_projectile_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ProjectileAssets {
+        rock_mesh: meshes.add(shape::Cube::new(PROJECTILE_SIZE).into()),
+        rock_material: materials.add(Color::rgb(0.45, 0.45, 0.45).into()),
+        particle_mesh: meshes.add(shape::Cube::new(PARTICLE_SIZE).into()),
+        particle_material: materials.add(Color::rgb(0.55, 0.5, 0.45).into()),
+    });
+}
+
+/// Throws a rock the way the camera looks.
+pub fn throw_projectile(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    assets: Res<ProjectileAssets>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+) {
+    if !keys.just_pressed(THROW_KEY) {
+        return;
+    }
+    let camera = camera_query.single();
+    // From just in front of the eyes, so it doesn't start inside the player.
+    let position = camera.translation + camera.forward() * 0.5;
+    commands.spawn((
+        Name::new("Projectile"),
+        PbrBundle {
+            mesh: assets.rock_mesh.clone(),
+            material: assets.rock_material.clone(),
+            transform: Transform::from_translation(position),
+            ..default()
+        },
+        Projectile::new(position, camera.forward() * THROW_SPEED),
+    ));
+}
+
+/// Moves the projectiles, and makes them hurt the mobs and crumble on the blocks they hit. They're despawned once
+/// they're too old or leave the loaded chunks.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    chunk_map: Res<ChunkMap>,
+    assets: Res<ProjectileAssets>,
+    mut budget: ResMut<EntityBudget>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+    mut mobs: Query<
+        (Entity, &Transform, Option<&mut Velocity>),
+        (With<Hostile>, Without<Projectile>),
+    >,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    let targets: Vec<(Entity, Vec3)> = mobs
+        .iter()
+        .map(|(entity, transform, _)| (entity, transform.translation))
+        .collect();
+    let solid = |pos| chunk_map.get_block(pos).is_some_and(BlockType::is_solid);
+    let target_at = |position: Vec3| {
+        targets
+            .iter()
+            .find(|(_, center)| center.distance_squared(position) < MOB_HIT_RADIUS * MOB_HIT_RADIUS)
+            .map(|&(entity, _)| entity)
+    };
+
+    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+        let impact = projectile.advance(time.delta_seconds(), solid, target_at);
+        transform.translation = projectile.position;
+
+        match impact {
+            Some(Impact::Block(hit)) => {
+                spawn_impact_particles(
+                    &mut commands,
+                    &mut budget,
+                    &assets,
+                    projectile.position,
+                    hit.normal.as_vec3(),
+                );
+            }
+            Some(Impact::Entity(mob)) => {
+                damage.send(DamageEvent {
+                    entity: mob,
+                    amount: PROJECTILE_DAMAGE,
+                    cause: DamageCause::Projectile,
+                });
+                if let Ok((_, _, Some(mut velocity))) = mobs.get_mut(mob) {
+                    let away = projectile.velocity.xz().normalize_or_zero();
+                    velocity.linvel += (Vec3::new(away.x, 0.5, away.y)) * PROJECTILE_KNOCKBACK;
+                }
+            }
+            None => {
+                let loaded = chunk_map
+                    .chunks
+                    .contains_key(&IVec2XZ::containing(projectile.position));
+                if loaded && !projectile.is_expired() {
+                    continue;
+                }
+            }
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// A ring of dust flying off the side of the block that was hit.
+fn spawn_impact_particles(
+    commands: &mut Commands,
+    budget: &mut EntityBudget,
+    assets: &ProjectileAssets,
+    position: Vec3,
+    normal: Vec3,
+) {
+    let normal = normal.try_normalize().unwrap_or(Vec3::Y);
+    let (side, up) = normal.any_orthonormal_pair();
+    for i in 0..IMPACT_PARTICLES {
+        let angle = i as f32 / IMPACT_PARTICLES as f32 * TAU;
+        let velocity = (side * angle.cos() + up * angle.sin()) * 1.5 + normal * 2.0;
+        commands.spawn((
+            Name::new("Impact Particle"),
+            PbrBundle {
+                mesh: assets.particle_mesh.clone(),
+                material: assets.particle_material.clone(),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            ImpactParticle {
+                velocity,
+                remaining: PARTICLE_LIFETIME,
+            },
+            budget.track(EntityCategory::Particle),
+        ));
+    }
+}
+
+pub fn update_impact_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut ImpactParticle, &mut Transform)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform) in particles.iter_mut() {
+        particle.remaining -= delta;
+        if particle.remaining <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        particle.velocity.y -= GRAVITY * delta;
+        transform.translation += particle.velocity * delta;
+    }
+}
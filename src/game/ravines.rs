@@ -0,0 +1,218 @@
+use std::f32::consts::TAU;
+
+use crate::game::hash::{feature_seed, rand01, rng_for};
+use crate::prelude::*;
+
+const RAVINE_SALT: u32 = 0x7a57;
+/// How many straight pieces the path of a ravine is made of.
+const RAVINE_SEGMENTS: usize = 16;
+/// How high above its floor a ravine reaches its full width.
+const RAVINE_FLARE: f32 = 16.0;
+/// How much shallower the ends of a ravine are than its middle.
+const RAVINE_END_RISE: f32 = 24.0;
+/// How much wider or narrower than the rest a layer of the walls can be.
+const RAVINE_LEDGE: f32 = 0.15;
+
+/// A long, narrow cut through the terrain, along a curved path.
+///
+/// Ravines are much longer than a chunk, so every chunk works out the ones reaching into it from the seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ravine {
+    /// Points along the middle of the ravine, from one end to the other.
+    pub path: Vec<Vec2>,
+    /// Half of the width in the middle of the ravine, at the top.
+    pub half_width: f32,
+    /// The y of the floor in the middle of the ravine.
+    pub floor: f32,
+    /// Seeds the ledges that make the walls uneven.
+    ledges: u64,
+}
+
+impl Ravine {
+    /// The corners of the area the ravine can carve, in the XZ plane.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let (min, max) = self
+            .path
+            .iter()
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), &point| {
+                (min.min(point), max.max(point))
+            });
+        (min - self.reach(), max + self.reach())
+    }
+
+    /// How far from the middle the ravine carves at most, on the widest ledge.
+    fn reach(&self) -> f32 {
+        self.half_width * (1.0 + RAVINE_LEDGE)
+    }
+
+    /// The distance to the middle of the ravine, and how far along the ravine the closest point is, from 0 to 1.
+    fn closest(&self, point: Vec2) -> (f32, f32) {
+        let mut closest = (f32::MAX, 0.0);
+        for (i, segment) in self.path.windows(2).enumerate() {
+            let (from, to) = (segment[0], segment[1]);
+            let along =
+                ((point - from).dot(to - from) / (to - from).length_squared()).clamp(0.0, 1.0);
+            let distance = point.distance(from.lerp(to, along));
+            if distance < closest.0 {
+                closest = (distance, (i as f32 + along) / RAVINE_SEGMENTS as f32);
+            }
+        }
+        closest
+    }
+
+    /// Whether the block is inside the ravine. Ravines are narrow at the floor and open up towards the top, and
+    /// narrow down and get shallower towards their ends.
+    pub fn carves(&self, pos: IVec3) -> bool {
+        self.column(pos.x, pos.z)
+            .is_some_and(|closest| self.carves_height(closest, pos.y))
+    }
+
+    /// Where a column is relative to the path, like [`Ravine::closest`]. None if it's out of reach of the ravine.
+    fn column(&self, x: i32, z: i32) -> Option<(f32, f32)> {
+        let point = Vec2::new(x as f32 + 0.5, z as f32 + 0.5);
+        let (min, max) = self.bounds();
+        if point.cmplt(min).any() || point.cmpgt(max).any() {
+            return None;
+        }
+        Some(self.closest(point)).filter(|&(distance, _)| distance < self.reach())
+    }
+
+    fn carves_height(&self, (distance, t): (f32, f32), y: i32) -> bool {
+        // 1 in the middle of the ravine, 0 at the ends.
+        let middle = 1.0 - (2.0 * t - 1.0).powi(2);
+        let floor = self.floor + (1.0 - middle) * RAVINE_END_RISE;
+        if (y as f32) < floor {
+            return false;
+        }
+        let flare = ((y as f32 - floor) / RAVINE_FLARE).clamp(0.0, 1.0);
+        let ledge = 1.0 + RAVINE_LEDGE * (2.0 * rand01(self.ledges, IVec3::new(0, y, 0)) - 1.0);
+        distance < self.half_width * middle.sqrt() * (0.3 + 0.7 * flare) * ledge
+    }
+
+    /// Whether the ravine reaches under the water at `water_height` somewhere, going by the surface height of the
+    /// terrain. Those ravines are filled with water up to the water level, all along.
+    pub fn floods(&self, water_height: usize, surface_height: impl Fn(IVec2XZ) -> f32) -> bool {
+        self.path.iter().any(|point| {
+            let column = IVec2XZ::new(point.x.floor() as i32, point.y.floor() as i32);
+            surface_height(column) < water_height as f32
+        })
+    }
+}
+
+/// The ravine of a region, if it has one. The region is `RAVINE_REGION` blocks wide, the ravine starts in it but
+/// can reach into the regions around it.
+pub fn ravine_in_region(region: IVec2XZ, seed: u32) -> Option<Ravine> {
+    let mut rng = rng_for(
+        feature_seed(seed, RAVINE_SALT),
+        IVec3::new(region.x, 0, region.z),
+    );
+    if rng.rand01() >= RAVINE_CHANCE {
+        return None;
+    }
+    let mut between = |low: f32, high: f32| low + (high - low) * rng.rand01();
+
+    let region_size = RAVINE_REGION as f32;
+    let start = Vec2::new(region.x as f32, region.z as f32) * region_size
+        + Vec2::new(between(0.0, region_size), between(0.0, region_size));
+    let direction = Vec2::from_angle(between(0.0, TAU));
+    let length = between(*RAVINE_LENGTH.start(), *RAVINE_LENGTH.end());
+    let end = start + direction * length;
+    // The path bends towards a point off to the side of its middle.
+    let bend = (start + end) / 2.0 + direction.perp() * between(-0.3, 0.3) * length;
+
+    let path = (0..=RAVINE_SEGMENTS)
+        .map(|i| {
+            let t = i as f32 / RAVINE_SEGMENTS as f32;
+            start.lerp(bend, t).lerp(bend.lerp(end, t), t)
+        })
+        .collect();
+    Some(Ravine {
+        path,
+        half_width: RAVINE_WIDTH / 2.0 * between(0.6, 1.0),
+        floor: between(*RAVINE_FLOOR.start(), *RAVINE_FLOOR.end()),
+        ledges: rng.next_u64(),
+    })
+}
+
+/// The ravines that reach into a chunk, from its own region and the ones around it.
+pub fn ravines_near(chunk_position: IVec2XZ, seed: u32) -> Vec<Ravine> {
+    let chunk_min = Vec2::new(chunk_position.x as f32, chunk_position.z as f32) * CHUNK_SIZE as f32;
+    let chunk_max = chunk_min + CHUNK_SIZE as f32;
+    let region = IVec2XZ::new(
+        (chunk_position.x * CHUNK_SIZE as i32).div_euclid(RAVINE_REGION),
+        (chunk_position.z * CHUNK_SIZE as i32).div_euclid(RAVINE_REGION),
+    );
+
+    let mut ravines = Vec::new();
+    for dx in -1..=1 {
+        for dz in -1..=1 {
+            let Some(ravine) = ravine_in_region(region + IVec2XZ::new(dx, dz), seed) else {
+                continue;
+            };
+            let (min, max) = ravine.bounds();
+            if min.cmplt(chunk_max).all() && max.cmpgt(chunk_min).all() {
+                ravines.push(ravine);
+            }
+        }
+    }
+    ravines
+}
+
+/// What a block carved out by a ravine turns into: water if the ravine is flooded, lava at the bottom of the world
+/// like in the caves, air otherwise. The water freezes over in cold biomes, like the ocean around it.
+pub fn ravine_fill(pos: IVec3, water_height: usize, flooded: bool, cold: bool) -> BlockType {
+    if flooded && pos.y == water_height as i32 && cold {
+        BlockType::Ice
+    } else if flooded && pos.y <= water_height as i32 {
+        BlockType::Water
+    } else if pos.y <= LAVA_HEIGHT as i32 {
+        BlockType::Lava
+    } else {
+        BlockType::Air
+    }
+}
+
+/// Whether a ravine can cut through a block. Bedrock and fluids stay. Flooded ravines also fill the caves they cut
+/// through below the water level, the rest of the air is left alone.
+pub fn is_carvable(block: BlockType, pos: IVec3, water_height: usize, flooded: bool) -> bool {
+    match block {
+        BlockType::Bedrock => false,
+        BlockType::Air => flooded && pos.y <= water_height as i32,
+        block => block.is_solid(),
+    }
+}
+
+/// Carves the part of a ravine that's inside the chunk, flooded up to `water_height` if it floods. `is_cold` tells
+/// whether a column is in a cold biome.
+pub fn carve_ravine(
+    chunk_position: IVec2XZ,
+    ravine: &Ravine,
+    water_height: usize,
+    flooded: bool,
+    is_cold: impl Fn(IVec2XZ) -> bool,
+    chunk_blocks: &mut ChunkBlocks,
+) {
+    let origin = IVec3::new(
+        chunk_position.x * CHUNK_SIZE as i32,
+        0,
+        chunk_position.z * CHUNK_SIZE as i32,
+    );
+    let floor = ravine.floor.floor().max(0.0) as usize;
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let column = IVec2XZ::new(origin.x + x as i32, origin.z + z as i32);
+            let Some(closest) = ravine.column(column.x, column.z) else {
+                continue;
+            };
+            let cold = flooded && is_cold(column);
+            for y in floor..CHUNK_HEIGHT {
+                let pos = origin + IVec3::new(x as i32, y as i32, z as i32);
+                if is_carvable(chunk_blocks.get(x, y, z), pos, water_height, flooded)
+                    && ravine.carves_height(closest, y as i32)
+                {
+                    chunk_blocks.set(x, y, z, ravine_fill(pos, water_height, flooded, cold));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::tasks::IoTaskPool;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::game::debug::DebugAction;
+use crate::game::debug::DebugChords;
+use crate::game::flight::Flight;
+use crate::game::rules::{arg_value, WorldRules, WorldSeed, WorldType};
+use crate::prelude::*;
+
+/// Where the bug reports are written, relative to the working directory. Every report gets a directory of its own.
+pub const REPORT_DIR: &str = "reports";
+/// The file in a report directory with everything but the blocks.
+pub const REPORT_FILE: &str = "report.ron";
+pub const REPORT_SETTINGS_FILE: &str = "settings.ron";
+pub const REPORT_SCREENSHOT_FILE: &str = "screenshot.png";
+
+/// What the game looked like when a bug report was written, enough to get back to the same view with
+/// `--load-report <dir>`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BugReport {
+    pub seed: u32,
+    /// The world type as it's given to `--world-type`.
+    pub world_type: String,
+    pub rules: WorldRules,
+    pub position: [f32; 3],
+    /// Where the camera looks, in radians. The yaw turns around the vertical, the pitch up and down.
+    pub yaw: f32,
+    pub pitch: f32,
+    /// The same as the yaw and the pitch, for reading.
+    pub look_direction: [f32; 3],
+    pub flying: bool,
+    /// The chunk the player is in and the 8 around it.
+    pub chunks: Vec<ChunkRecord>,
+}
+
+/// Where a chunk of a report was in its lifecycle.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChunkRecord {
+    pub position: [i32; 2],
+    /// The [`ChunkState`], like `Ready { entity: 12v0 }`, or `Unloaded` if the streaming doesn't know the chunk.
+    pub state: String,
+    /// The file in the report directory with the blocks of the chunk, if they were loaded.
+    pub blocks: Option<String>,
+}
+
+/// The blocks of a chunk as they're written to a file, using the stable [`BlockType::id`]s.
+///
+/// The blocks go column by column, x first and then z, each column from y = 0 up. A run of the same block is
+/// written once, with how many there are.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChunkExport {
+    pub position: [i32; 2],
+    pub runs: Vec<(u8, u32)>,
+}
+
+impl ChunkExport {
+    pub fn new(position: IVec2XZ, blocks: &ChunkBlocks) -> Self {
+        let mut runs: Vec<(u8, u32)> = Vec::new();
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                for &block in blocks.column(x, z) {
+                    match runs.last_mut() {
+                        Some((id, count)) if *id == block.id() => *count += 1,
+                        _ => runs.push((block.id(), 1)),
+                    }
+                }
+            }
+        }
+        Self {
+            position: [position.x, position.z],
+            runs,
+        }
+    }
+
+    #[allow(dead_code)] // Only the tests read the blocks of a report back so far
+    /// The blocks back. Fails if there are unknown ids or the runs don't fill the chunk exactly.
+    pub fn blocks(&self) -> Result<ChunkBlocks, String> {
+        let total = CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE;
+        let mut blocks = Vec::with_capacity(total);
+        for &(id, count) in &self.runs {
+            let block = BlockType::from_id(id).ok_or_else(|| format!("unknown block id {}", id))?;
+            if blocks.len() + count as usize > total {
+                return Err(format!("more than {} blocks", total));
+            }
+            blocks.resize(blocks.len() + count as usize, block);
+        }
+        if blocks.len() != total {
+            return Err(format!("expected {} blocks, got {}", total, blocks.len()));
+        }
+        let mut chunk = ChunkBlocks::default();
+        for (i, column) in blocks.chunks(CHUNK_HEIGHT).enumerate() {
+            chunk
+                .column_mut(i % CHUNK_SIZE, i / CHUNK_SIZE)
+                .copy_from_slice(column);
+        }
+        Ok(chunk)
+    }
+
+    /// The name of the file the chunk is written to in a report.
+    pub fn file_name(position: IVec2XZ) -> String {
+        format!("chunk_{}_{}.ron", position.x, position.z)
+    }
+}
+
+impl BugReport {
+    /// The report from the `--load-report` argument, if there is one.
+    pub fn from_args(args: &[String]) -> Option<Result<Self, String>> {
+        arg_value(args, "--load-report").map(|dir| Self::load(Path::new(dir)))
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(REPORT_FILE);
+        let contents =
+            fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        ron::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Writes the report, the settings and the blocks of the chunks into the directory. The screenshot is taken
+    /// separately, by the renderer.
+    pub fn write(
+        &self,
+        dir: &Path,
+        settings: &Settings,
+        chunks: &[(IVec2XZ, ChunkBlocks)],
+    ) -> Result<(), String> {
+        let write = |name: &str, contents: Result<String, ron::Error>| {
+            let path = dir.join(name);
+            contents
+                .map_err(|err| err.to_string())
+                .and_then(|contents| fs::write(&path, contents).map_err(|err| err.to_string()))
+                .map_err(|err| format!("{}: {}", path.display(), err))
+        };
+        let pretty = ron::ser::PrettyConfig::default();
+        fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+        write(
+            REPORT_FILE,
+            ron::ser::to_string_pretty(self, pretty.clone()),
+        )?;
+        write(
+            REPORT_SETTINGS_FILE,
+            ron::ser::to_string_pretty(settings, pretty),
+        )?;
+        for (position, blocks) in chunks {
+            // The runs would be one per line pretty printed, the blocks stay on a single line.
+            write(
+                &ChunkExport::file_name(*position),
+                ron::to_string(&ChunkExport::new(*position, blocks)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The rotation of the camera.
+    pub fn look(&self) -> Quat {
+        Quat::from_axis_angle(Vec3::Y, self.yaw) * Quat::from_axis_angle(Vec3::X, self.pitch)
+    }
+}
+
+/// The report the world was started from with `--load-report`.
+#[derive(Resource, Clone, Debug)]
+pub struct LoadedReport(pub BugReport);
+
+/// Writes a bug report of the chunks around the player when its debug chord is pressed. The blocks are only copied
+/// in the frame, they're encoded and written on the IO pool.
+#[allow(clippy::too_many_arguments)]
+pub fn write_bug_report(
+    chords: Res<DebugChords>,
+    seed: Res<WorldSeed>,
+    world_type: Res<WorldType>,
+    rules: Res<WorldRules>,
+    settings: Res<Settings>,
+    player_state: Res<PlayerPos>,
+    flight: Res<Flight>,
+    (chunks_loaded, chunk_map): (Res<ChunksLoaded>, Res<ChunkMap>),
+    camera_query: Query<&Transform, With<Camera3d>>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+) {
+    if !chords.triggered(DebugAction::BugReport) {
+        return;
+    }
+
+    let center = IVec2XZ::containing(player_state.pos);
+    let mut records = Vec::new();
+    let mut chunks = Vec::new();
+    for z in -1..=1 {
+        for x in -1..=1 {
+            let position = center + IVec2XZ::new(x, z);
+            let blocks = chunk_map.chunks.get(&position).cloned();
+            records.push(ChunkRecord {
+                position: [position.x, position.z],
+                state: chunks_loaded
+                    .state(position)
+                    .map_or("Unloaded".to_string(), |state| format!("{:?}", state)),
+                blocks: blocks.as_ref().map(|_| ChunkExport::file_name(position)),
+            });
+            chunks.extend(blocks.map(|blocks| (position, blocks)));
+        }
+    }
+
+    let rotation = camera_query
+        .iter()
+        .next()
+        .map_or(player_state.rot, |transform| transform.rotation);
+    let (yaw, pitch, _) = rotation.to_euler(EulerRot::YXZ);
+    let report = BugReport {
+        seed: seed.0,
+        world_type: world_type.to_arg(),
+        rules: *rules,
+        position: player_state.pos.to_array(),
+        yaw,
+        pitch,
+        look_direction: (rotation * Vec3::NEG_Z).to_array(),
+        flying: flight.flying,
+        chunks: records,
+    };
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let dir = PathBuf::from(format!("{}/{}", REPORT_DIR, seconds));
+    // The renderer saves the screenshot on its own, the directory has to be there before it does.
+    let screenshot = fs::create_dir_all(&dir)
+        .map_err(|err| err.to_string())
+        .and_then(|_| window_query.get_single().map_err(|err| err.to_string()))
+        .and_then(|window| {
+            screenshots
+                .save_screenshot_to_disk(window, dir.join(REPORT_SCREENSHOT_FILE))
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = screenshot {
+        warn!("Failed to take the screenshot of the bug report: {}", err);
+    }
+
+    let settings = settings.clone();
+    IoTaskPool::get()
+        .spawn(async move {
+            match report.write(&dir, &settings, &chunks) {
+                Ok(()) => info!("Wrote a bug report to {}", dir.display()),
+                Err(err) => warn!("Failed to write the bug report: {}", err),
+            }
+        })
+        .detach();
+}
+
+/// Starts the world with the seed, the world type and the rules of the loaded report.
+pub fn apply_loaded_report(
+    report: Res<LoadedReport>,
+    mut seed: ResMut<WorldSeed>,
+    mut world_type: ResMut<WorldType>,
+    mut rules: ResMut<WorldRules>,
+) {
+    let report = &report.0;
+    seed.0 = report.seed;
+    *rules = report.rules;
+    match WorldType::parse(&report.world_type) {
+        Ok(loaded) => *world_type = loaded,
+        Err(err) => warn!("Ignoring the world type of the report: {}", err),
+    }
+    info!(
+        "Loaded a bug report: seed {}, world type {:?}",
+        seed.0, *world_type
+    );
+}
+
+/// Puts the player where the report was written, looking the same way.
+pub fn move_to_report_position(
+    report: Res<LoadedReport>,
+    mut player_state: ResMut<PlayerPos>,
+    mut flight: ResMut<Flight>,
+    mut bodies: Query<&mut Transform, (With<KinematicCharacterController>, Without<Camera3d>)>,
+    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+) {
+    let report = &report.0;
+    let position = Vec3::from_array(report.position);
+    for mut transform in bodies.iter_mut() {
+        transform.translation = position;
+    }
+    for mut transform in camera_query.iter_mut() {
+        transform.rotation = report.look();
+    }
+    player_state.pos = position;
+    player_state.rot = Quat::from_axis_angle(Vec3::Y, report.yaw);
+    flight.flying = report.flying;
+}
@@ -0,0 +1,380 @@
+use std::{
+    fs,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::chunk::reset_chunks;
+use crate::game::generator::{
+    DefaultGenerator, FloatingIslandsGenerator, SharedGenerator, SuperflatGenerator, WorldGenerator,
+};
+use crate::prelude::*;
+
+/// Where the world rules are stored, relative to the working directory.
+pub const LEVEL_PATH: &str = "level.ron";
+
+/// Rules chosen when the world is created, each one switching or tuning a stage of the generation.
+///
+/// They're stored in `level.ron` and can be overridden on the command line with
+/// `--rules caves=false,amplified=true`.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct WorldRules {
+    pub generate_caves: bool,
+    /// Ore veins, and the patches of gravel and dirt in the stone.
+    pub generate_ores: bool,
+    /// Ponds, trees and plants.
+    pub generate_structures: bool,
+    /// Doubles the height of the terrain and lets it get closer to the height limit.
+    pub amplified: bool,
+    /// A single layer of bedrock, instead of one that's between 1 and `BEDROCK_MAX_THICKNESS` blocks thick in every
+    /// column.
+    pub bedrock_flat: bool,
+}
+
+impl Default for WorldRules {
+    fn default() -> Self {
+        Self {
+            generate_caves: true,
+            generate_ores: true,
+            generate_structures: true,
+            amplified: false,
+            bedrock_flat: false,
+        }
+    }
+}
+
+impl WorldRules {
+    /// Applies a comma separated list of `rule=true|false` overrides, like `caves=false,amplified=true`.
+    /// Nothing is changed if any of them is invalid.
+    pub fn apply_overrides(&mut self, overrides: &str) -> Result<(), String> {
+        let mut rules = *self;
+        for rule in overrides.split(',').filter(|rule| !rule.is_empty()) {
+            let (name, value) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("expected rule=value, got \"{}\"", rule))?;
+            let value: bool = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("expected true or false for {}, got \"{}\"", name, value))?;
+            let flag = match name.trim() {
+                "caves" => &mut rules.generate_caves,
+                "ores" => &mut rules.generate_ores,
+                "structures" => &mut rules.generate_structures,
+                "amplified" => &mut rules.amplified,
+                "bedrock_flat" => &mut rules.bedrock_flat,
+                name => return Err(format!("unknown rule \"{}\"", name)),
+            };
+            *flag = value;
+        }
+        *self = rules;
+        Ok(())
+    }
+
+    /// Finds the `--rules` argument, either as `--rules <list>` or `--rules=<list>`.
+    pub fn overrides_from_args(args: &[String]) -> Option<&str> {
+        arg_value(args, "--rules")
+    }
+
+    /// Reads the rules of the world, applies the command line overrides and writes the result back.
+    pub fn load(args: &[String]) -> Self {
+        let mut rules = match fs::read_to_string(LEVEL_PATH) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Invalid {}, using the default rules: {}", LEVEL_PATH, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+
+        if let Some(overrides) = Self::overrides_from_args(args) {
+            if let Err(err) = rules.apply_overrides(overrides) {
+                warn!("Ignoring --rules: {}", err);
+            }
+        }
+
+        rules.save();
+        rules
+    }
+
+    pub fn save(&self) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!("Failed to serialize the world rules: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = fs::write(LEVEL_PATH, contents) {
+            warn!("Failed to write {}: {}", LEVEL_PATH, err);
+        }
+    }
+}
+
+/// Finds the value of a command line argument, either as `<name> <value>` or `<name>=<value>`.
+pub fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == name {
+            args.get(i + 1).map(String::as_str)
+        } else {
+            arg.strip_prefix(name)?.strip_prefix('=')
+        }
+    })
+}
+
+/// The seed all of the terrain noise and the per-chunk features are derived from.
+///
+/// It's random unless it's given on the command line with `--seed <number>`.
+/// Changing it (for example in the inspector) regenerates the world.
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Debug)]
+#[reflect(Resource)]
+pub struct WorldSeed(pub u32);
+
+impl WorldSeed {
+    /// The seed from the `--seed` argument, if there is one and it's a valid number.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        let seed = arg_value(args, "--seed")?;
+        match seed.trim().parse() {
+            Ok(seed) => Some(Self(seed)),
+            Err(_) => {
+                warn!(
+                    "Ignoring --seed, expected a number from 0 to {}, got \"{}\"",
+                    u32::MAX,
+                    seed
+                );
+                None
+            }
+        }
+    }
+}
+
+/// A new world gets a random seed.
+impl Default for WorldSeed {
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self(mix(nanos) as u32)
+    }
+}
+
+pub fn load_world_seed(mut commands: Commands) {
+    let args: Vec<String> = std::env::args().collect();
+    let seed = WorldSeed::from_args(&args).unwrap_or_default();
+    info!("World seed: {}", seed.0);
+    commands.insert_resource(seed);
+}
+
+/// Tunes the normal terrain: the shape of the surface, the octaves of its noise and the heights they're mapped to,
+/// the caves, the water and the size of the biomes.
+///
+/// They can be changed in the inspector or with the sliders of `--tune`, which regenerates the world like a new seed.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct WorldGenParams {
+    /// How many octaves of noise make up the surface. The later ones add the smaller bumps.
+    pub octaves: u32,
+    /// How much higher the frequency of every octave is than that of the one before.
+    pub lacunarity: f64,
+    /// How much of the amplitude of the octave before every octave keeps.
+    pub persistence: f64,
+    /// The frequency of the first octave.
+    pub frequency: f64,
+    /// Turns the noise, from -1 to 1, into the height of the surface.
+    pub height_spline: Spline,
+    /// The frequency of the cave noise, how big the caves are.
+    pub cave_scale: f64,
+    /// The cave noise above which the stone is carved out deep down. It's lower right under the surface.
+    pub cave_threshold: f64,
+    /// The top of the oceans, ponds aside. The beaches are around it.
+    pub water_height: usize,
+    /// The frequency of the temperature and humidity noise, how close together the biomes are.
+    pub biome_scale: f64,
+}
+
+impl Default for WorldGenParams {
+    fn default() -> Self {
+        // The noise is between -0.4 and 0.4 almost everywhere. The lowest quarter is the sea, then the plains take
+        // up most of the land around y 70, and the top few percent rise steeply into mountains near y 200.
+        Self {
+            octaves: 3,
+            lacunarity: 2.0,
+            persistence: 1.0,
+            frequency: SURFACE_SCALE,
+            height_spline: Spline::new(
+                &[
+                    (-1.0, 40.0),
+                    (-0.4, 56.0),
+                    (-0.2, 66.0),
+                    (-0.1, 70.0),
+                    (0.2, 74.0),
+                    (0.35, 110.0),
+                    (0.55, 200.0),
+                    (1.0, 215.0),
+                ],
+                SplineInterpolation::Linear,
+            ),
+            cave_scale: CAVE_SCALE,
+            cave_threshold: CAVE_THRESHOLD,
+            water_height: WATER_HEIGHT,
+            biome_scale: BIOME_SCALE,
+        }
+    }
+}
+
+/// The kind of terrain the world is made of, chosen at startup.
+///
+/// `--world-type flat` makes a superflat world, the layers can be given from the bottom up like
+/// `--world-type flat:bedrock,3*dirt,grass`. `--world-type floating_islands` makes islands in the sky, with nothing
+/// under them.
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub enum WorldType {
+    #[default]
+    Normal,
+    Flat {
+        layers: Vec<(BlockType, u32)>,
+    },
+    FloatingIslands,
+}
+
+impl WorldType {
+    /// Parses `normal`, `floating_islands`, `flat` or `flat:<layers>`, where each layer is a block name with an optional `<count>*` in front.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (name, layers) = match value.trim().split_once(':') {
+            Some((name, layers)) => (name, Some(layers)),
+            None => (value.trim(), None),
+        };
+        match (name, layers) {
+            ("normal", None) => Ok(WorldType::Normal),
+            ("floating_islands", None) => Ok(WorldType::FloatingIslands),
+            ("flat", None) => Ok(WorldType::Flat {
+                layers: SuperflatGenerator::default().layers,
+            }),
+            ("flat", Some(layers)) => {
+                let layers = layers
+                    .split(',')
+                    .map(|layer| {
+                        let (count, block) = match layer.split_once('*') {
+                            Some((count, block)) => (
+                                count
+                                    .trim()
+                                    .parse()
+                                    .map_err(|_| format!("invalid layer count in \"{}\"", layer))?,
+                                block,
+                            ),
+                            None => (1, layer),
+                        };
+                        let block = BlockType::from_name(block)
+                            .ok_or_else(|| format!("unknown block \"{}\"", block.trim()))?;
+                        Ok((block, count))
+                    })
+                    .collect::<Result<Vec<(BlockType, u32)>, String>>()?;
+                let height: u32 = layers.iter().map(|&(_, count)| count).sum();
+                if height == 0 || height > CHUNK_HEIGHT as u32 {
+                    return Err(format!(
+                        "the layers must be 1 to {} blocks high, got {}",
+                        CHUNK_HEIGHT, height
+                    ));
+                }
+                Ok(WorldType::Flat { layers })
+            }
+            _ => Err(format!("unknown world type \"{}\"", value)),
+        }
+    }
+
+    /// What to pass to `--world-type` to get this world type, what [`WorldType::parse`] reads.
+    pub fn to_arg(&self) -> String {
+        match self {
+            WorldType::Normal => "normal".to_string(),
+            WorldType::FloatingIslands => "floating_islands".to_string(),
+            WorldType::Flat { layers } => {
+                let layers: Vec<String> = layers
+                    .iter()
+                    .map(|(block, count)| format!("{}*{:?}", count, block))
+                    .collect();
+                format!("flat:{}", layers.join(","))
+            }
+        }
+    }
+
+    /// The world type from the `--world-type` argument, if there is one and it's valid.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        let value = arg_value(args, "--world-type")?;
+        Self::parse(value)
+            .map_err(|err| warn!("Ignoring --world-type: {}", err))
+            .ok()
+    }
+
+    /// The generator for the world type. The flat world doesn't use the seed or the rules, and only the normal
+    /// terrain uses the parameters.
+    pub fn generator(
+        &self,
+        seed: u32,
+        rules: WorldRules,
+        params: &WorldGenParams,
+    ) -> Arc<dyn WorldGenerator> {
+        match self {
+            WorldType::Normal => {
+                Arc::new(DefaultGenerator::with_params(seed, rules, params.clone()))
+            }
+            WorldType::Flat { layers } => Arc::new(SuperflatGenerator {
+                layers: layers.clone(),
+            }),
+            WorldType::FloatingIslands => Arc::new(FloatingIslandsGenerator::new(seed, rules)),
+        }
+    }
+}
+
+pub fn load_world_type(mut commands: Commands) {
+    let args: Vec<String> = std::env::args().collect();
+    let world_type = WorldType::from_args(&args).unwrap_or_default();
+    info!("World type: {:?}", world_type);
+    commands.insert_resource(world_type);
+}
+
+/// Throws the chunks away when the seed or the generation parameters change, so they're generated again with the
+/// new ones.
+#[allow(clippy::too_many_arguments)]
+pub fn regenerate_on_seed_change(
+    seed: Res<WorldSeed>,
+    rules: Res<WorldRules>,
+    params: Res<WorldGenParams>,
+    world_type: Res<WorldType>,
+    mut generator: ResMut<SharedGenerator>,
+    mut commands: Commands,
+    chunk_query: Query<Entity, With<ChunkMesh>>,
+    (mut chunks_loaded, mut loader): (ResMut<ChunksLoaded>, ResMut<ChunkLoader>),
+    (mut chunk_map, mut mesh_cache, mut unloaded): (
+        ResMut<ChunkMap>,
+        ResMut<MeshCache>,
+        EventWriter<ChunkUnloaded>,
+    ),
+) {
+    if seed.is_added() || !seed.is_changed() && !params.is_changed() {
+        return;
+    }
+    if seed.is_changed() {
+        info!("World seed changed to {}, regenerating", seed.0);
+    } else {
+        info!("World generation parameters changed, regenerating");
+    }
+    *generator = SharedGenerator(world_type.generator(seed.0, *rules, &params));
+    reset_chunks(
+        &mut commands,
+        &chunk_query,
+        &mut chunks_loaded,
+        &mut loader,
+        &mut chunk_map,
+        &mut mesh_cache,
+        &mut unloaded,
+    );
+}
+
+pub fn load_world_rules(mut commands: Commands) {
+    let args: Vec<String> = std::env::args().collect();
+    let rules = WorldRules::load(&args);
+    info!("World rules: {:?}", rules);
+    commands.insert_resource(rules);
+}
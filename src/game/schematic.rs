@@ -0,0 +1,126 @@
+use std::{fmt, fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::game::rules::arg_value;
+use crate::prelude::*;
+
+/// A structure that can be pasted into the world, stored as a small RON file:
+///
+/// ```ron
+/// (
+///     size: (2, 1, 2),
+///     palette: ["Air", "Stone"],
+///     blocks: [1, 0, 0, 1],
+/// )
+/// ```
+///
+/// `blocks` indexes into the palette, with x changing fastest, then z, then y (like Sponge schematics).
+#[derive(Debug, Deserialize)]
+pub struct Schematic {
+    pub size: (u32, u32, u32),
+    pub palette: Vec<String>,
+    pub blocks: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub enum SchematicError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    /// The size has more blocks than can be counted.
+    TooLarge {
+        size: (u32, u32, u32),
+    },
+    WrongBlockCount {
+        expected: usize,
+        found: usize,
+    },
+    BadPaletteIndex(usize),
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchematicError::Io(err) => write!(f, "couldn't read the schematic: {}", err),
+            SchematicError::Parse(err) => write!(f, "invalid schematic: {}", err),
+            SchematicError::TooLarge { size: (x, y, z) } => {
+                write!(f, "a schematic of {}x{}x{} blocks is too large", x, y, z)
+            }
+            SchematicError::WrongBlockCount { expected, found } => {
+                write!(f, "expected {} blocks, found {}", expected, found)
+            }
+            SchematicError::BadPaletteIndex(index) => {
+                write!(f, "block {} isn't in the palette", index)
+            }
+        }
+    }
+}
+
+/// The schematic from the `--schematic` argument, pasted with a debug chord.
+#[derive(Resource, Debug)]
+pub struct LoadedSchematic(pub Schematic);
+
+impl Schematic {
+    /// The schematic from the `--schematic` argument, if there is one.
+    pub fn from_args(args: &[String]) -> Option<Result<Self, SchematicError>> {
+        arg_value(args, "--schematic").map(Self::load)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SchematicError> {
+        let contents = fs::read_to_string(path).map_err(SchematicError::Io)?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, SchematicError> {
+        let schematic: Schematic = ron::from_str(contents).map_err(SchematicError::Parse)?;
+
+        let (x, y, z) = schematic.size;
+        let expected = (x as usize)
+            .checked_mul(y as usize)
+            .and_then(|blocks| blocks.checked_mul(z as usize))
+            .ok_or(SchematicError::TooLarge {
+                size: schematic.size,
+            })?;
+        if schematic.blocks.len() != expected {
+            return Err(SchematicError::WrongBlockCount {
+                expected,
+                found: schematic.blocks.len(),
+            });
+        }
+        if let Some(&index) = schematic
+            .blocks
+            .iter()
+            .find(|&&index| index >= schematic.palette.len())
+        {
+            return Err(SchematicError::BadPaletteIndex(index));
+        }
+
+        Ok(schematic)
+    }
+
+    /// The palette as blocks. Names that aren't blocks become stone.
+    pub fn palette_blocks(&self) -> Vec<BlockType> {
+        self.palette
+            .iter()
+            .map(|name| BlockType::from_name(name).unwrap_or(BlockType::Stone))
+            .collect()
+    }
+
+    /// Palette names that aren't blocks, so the caller can warn about them.
+    pub fn unknown_names(&self) -> impl Iterator<Item = &str> {
+        self.palette
+            .iter()
+            .map(String::as_str)
+            .filter(|name| BlockType::from_name(name).is_none())
+    }
+
+    /// Every cell with its offset from the schematic's origin corner, air included.
+    pub fn cells(&self) -> impl Iterator<Item = (UVec3, BlockType)> + '_ {
+        let palette = self.palette_blocks();
+        let (size_x, size_z) = (self.size.0 as usize, self.size.2 as usize);
+        self.blocks.iter().enumerate().map(move |(i, &index)| {
+            let offset = [i % size_x, i / (size_x * size_z), i / size_x % size_z];
+            (UVec3::from(offset.map(|axis| axis as u32)), palette[index])
+        })
+    }
+}
@@ -0,0 +1,215 @@
+use std::{fs, time::SystemTime};
+
+use bevy::render::texture::ImageSampler;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Where the settings are read from, relative to the working directory.
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+/// How often the settings file is checked for changes, in seconds.
+const SETTINGS_POLL_INTERVAL: f32 = 1.0;
+
+/// User settings, stored in `settings.ron`.
+///
+/// Missing fields fall back to their defaults, so old files keep working when new options are added.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Settings {
+    pub hud: HudPalette,
+    pub graphics: GraphicsSettings,
+    pub difficulty: Difficulty,
+    /// Chunks closer than this to the world spawn are never unloaded. 0 turns the spawn chunks off.
+    pub spawn_chunk_radius: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            hud: HudPalette::default(),
+            graphics: GraphicsSettings::default(),
+            difficulty: Difficulty::default(),
+            spawn_chunk_radius: 2,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub sky: SkyMode,
+    /// Frost at the edges of the screen in the cold.
+    pub frost_effect: bool,
+    /// Shimmering air near lava deep underground.
+    pub heat_effect: bool,
+    pub texture_filter: TextureFilter,
+    /// How far the texture coordinates of the blocks stay inside their tiles, in texels. The tiles have a gutter
+    /// of their edge pixels around them, so 0 doesn't show the neighbouring tiles. Read at startup.
+    pub texture_inset: f32,
+    /// Draws the solid blocks of the chunks with a vertex format 6 times smaller. Read at startup.
+    pub packed_meshes: bool,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            sky: SkyMode::default(),
+            frost_effect: true,
+            heat_effect: true,
+            texture_filter: TextureFilter::default(),
+            texture_inset: 0.0,
+            packed_meshes: false,
+        }
+    }
+}
+
+/// How the sky is drawn. Can be switched while the game is running.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SkyMode {
+    /// The scattering sky of `bevy_atmosphere`. It looks the best, but costs more and has no stars.
+    #[default]
+    Atmosphere,
+    /// A cheap colour gradient, with stars at night and a sun and moon.
+    Gradient,
+}
+
+/// How the block textures are sampled. Can be switched while the game is running.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextureFilter {
+    /// Sharp texels, like the textures are drawn.
+    #[default]
+    Nearest,
+    /// Blends the texels together, smoother in the distance but blurry up close.
+    Linear,
+}
+
+impl TextureFilter {
+    pub fn sampler(self) -> ImageSampler {
+        match self {
+            TextureFilter::Nearest => ImageSampler::nearest(),
+            TextureFilter::Linear => ImageSampler::linear(),
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            TextureFilter::Nearest => TextureFilter::Linear,
+            TextureFilter::Linear => TextureFilter::Nearest,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Difficulty {
+    /// No hostile mobs. Switching to it removes the existing ones.
+    Peaceful,
+    #[default]
+    Normal,
+}
+
+impl Difficulty {
+    pub fn allows_hostile_spawns(self) -> bool {
+        self != Difficulty::Peaceful
+    }
+}
+
+/// Colours used by the HUD.
+#[derive(Reflect, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HudPalette {
+    pub crosshair: CrosshairColor,
+    pub text_color: Color,
+    /// Draw a translucent panel behind the debug text so it stays readable over bright terrain.
+    pub text_panel: bool,
+    pub text_panel_color: Color,
+}
+
+impl Default for HudPalette {
+    fn default() -> Self {
+        Self {
+            crosshair: CrosshairColor::Contrast,
+            text_color: Color::WHITE,
+            text_panel: true,
+            text_panel_color: Color::rgba(0.0, 0.0, 0.0, 0.5),
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum CrosshairColor {
+    Fixed(Color),
+    /// Picks black or white depending on what is most likely behind the crosshair.
+    Contrast,
+}
+
+impl Settings {
+    /// Reads the settings file, writing the defaults if it doesn't exist yet.
+    pub fn load() -> Self {
+        match fs::read_to_string(SETTINGS_PATH) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Invalid {}, using defaults: {}", SETTINGS_PATH, err);
+                Self::default()
+            }),
+            Err(_) => {
+                let settings = Self::default();
+                settings.save();
+                settings
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!("Failed to serialize settings: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = fs::write(SETTINGS_PATH, contents) {
+            warn!("Failed to write {}: {}", SETTINGS_PATH, err);
+        }
+    }
+}
+
+fn settings_modified() -> Option<SystemTime> {
+    fs::metadata(SETTINGS_PATH).and_then(|m| m.modified()).ok()
+}
+
+pub fn load_settings(mut commands: Commands) {
+    commands.insert_resource(Settings::load());
+}
+
+pub struct SettingsWatcher {
+    timer: Timer,
+    modified: Option<SystemTime>,
+}
+
+impl Default for SettingsWatcher {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(SETTINGS_POLL_INTERVAL, TimerMode::Repeating),
+            modified: settings_modified(),
+        }
+    }
+}
+
+/// Reloads the settings when the file is changed on disk, so edits take effect without a restart.
+pub fn watch_settings(
+    time: Res<Time>,
+    mut watcher: Local<SettingsWatcher>,
+    mut settings: ResMut<Settings>,
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modified = settings_modified();
+    if modified != watcher.modified {
+        watcher.modified = modified;
+        *settings = Settings::load();
+        info!("Reloaded {}", SETTINGS_PATH);
+    }
+}
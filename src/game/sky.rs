@@ -0,0 +1,339 @@
+use bevy::{
+    pbr::NotShadowCaster,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::{Face, PrimitiveTopology},
+    },
+};
+use bevy_atmosphere::prelude::AtmosphereCamera;
+
+use crate::prelude::*;
+
+/// How far away the gradient sky is drawn, inside the far plane of the camera and well past the fog.
+const SKY_RADIUS: f32 = 500.0;
+const STAR_COUNT: usize = 1200;
+const STAR_SEED: u64 = 0x57a2;
+/// How fast the stars turn around the pole, in radians per second.
+const STAR_SPEED: f32 = 0.004;
+const SUN_SIZE: f32 = 40.0;
+const MOON_SIZE: f32 = 28.0;
+
+/// The colours the gradient sky blends between, and that the fog takes on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SkyColors {
+    pub horizon: Color,
+    pub zenith: Color,
+}
+
+const NIGHT: SkyColors = SkyColors {
+    horizon: Color::rgb(0.03, 0.04, 0.09),
+    zenith: Color::rgb(0.0, 0.0, 0.02),
+};
+const SUNSET: SkyColors = SkyColors {
+    horizon: Color::rgb(0.92, 0.5, 0.25),
+    zenith: Color::rgb(0.2, 0.26, 0.5),
+};
+const DAY: SkyColors = SkyColors {
+    horizon: Color::rgb(0.72, 0.83, 0.95),
+    zenith: Color::rgb(0.24, 0.48, 0.9),
+};
+
+fn mix_colors(a: Color, b: Color, t: f32) -> Color {
+    let mixed = Vec4::from_array(a.as_rgba_f32()).lerp(Vec4::from_array(b.as_rgba_f32()), t);
+    Color::rgba(mixed.x, mixed.y, mixed.z, mixed.w)
+}
+
+/// The sky for a sun at this height, the y of the direction towards it. Night below -0.2, day above 0.3.
+pub fn sky_colors(sun_height: f32) -> SkyColors {
+    let (from, to, t) = if sun_height < 0.0 {
+        (NIGHT, SUNSET, (sun_height + 0.2) / 0.2)
+    } else {
+        (SUNSET, DAY, sun_height / 0.3)
+    };
+    let t = t.clamp(0.0, 1.0);
+    SkyColors {
+        horizon: mix_colors(from.horizon, to.horizon, t),
+        zenith: mix_colors(from.zenith, to.zenith, t),
+    }
+}
+
+/// How bright the stars are, fading in while the sun sets.
+pub fn star_brightness(sun_height: f32) -> f32 {
+    (-sun_height / 0.2).clamp(0.0, 1.0)
+}
+
+/// Directions to the stars, spread evenly over the sky. The same every time.
+pub fn star_directions(count: usize) -> Vec<Vec3> {
+    let mut hash = STAR_SEED;
+    let mut roll = || {
+        hash = mix(hash);
+        (hash >> 11) as f32 / (1u64 << 53) as f32
+    };
+    (0..count)
+        .map(|_| {
+            let y = roll() * 2.0 - 1.0;
+            let angle = roll() * std::f32::consts::TAU;
+            let across = (1.0 - y * y).sqrt();
+            Vec3::new(across * angle.cos(), y, across * angle.sin())
+        })
+        .collect()
+}
+
+/// The gradient sky, its stars, sun and moon. It follows the camera around and is hidden in the atmosphere mode.
+#[derive(Component)]
+pub struct Sky;
+
+#[derive(Component)]
+pub struct SkyStars;
+
+#[derive(Component)]
+pub struct SkyBody {
+    /// The moon is on the opposite side of the sky.
+    moon: bool,
+}
+
+/// Everything that makes up the gradient sky, so queries for the camera and the sun can leave it out.
+#[derive(Component)]
+pub struct SkyPart;
+
+#[derive(Resource)]
+pub struct SkyAssets {
+    dome: Handle<Mesh>,
+    stars: Handle<StandardMaterial>,
+}
+
+/// A material that ignores the light and the fog, for the things in the sky.
+fn sky_material(color: Color, alpha_mode: AlphaMode) -> StandardMaterial {
+    StandardMaterial {
+        base_color: color,
+        unlit: true,
+        fog_enabled: false,
+        cull_mode: None,
+        alpha_mode,
+        ..default()
+    }
+}
+
+fn stars_mesh() -> Mesh {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    for (i, direction) in star_directions(STAR_COUNT).into_iter().enumerate() {
+        let center = direction * SKY_RADIUS * 0.95;
+        let right = direction.any_orthonormal_vector();
+        let up = direction.cross(right);
+        // Some stars are bigger and brighter than others.
+        let size = 0.8 + (i % 3) as f32 * 0.4;
+        let brightness = 0.5 + (i % 5) as f32 * 0.125;
+
+        let first = positions.len() as u32;
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            positions.push((center + (right * x + up * y) * size).to_array());
+            colors.push([brightness, brightness, brightness, 1.0]);
+        }
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+pub fn setup_sky(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // The dome is seen from the inside, and gets its colours from `update_sky`.
+    let mut dome = Mesh::from(shape::UVSphere {
+        radius: SKY_RADIUS,
+        sectors: 32,
+        stacks: 16,
+    });
+    let vertex_count = dome.count_vertices();
+    dome.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1.0; 4]; vertex_count]);
+    let dome = meshes.add(dome);
+    let stars = materials.add(sky_material(Color::WHITE, AlphaMode::Blend));
+
+    let mut dome_material = sky_material(Color::WHITE, AlphaMode::Opaque);
+    dome_material.cull_mode = Some(Face::Front);
+    let dome_material = materials.add(dome_material);
+    let sun_material = materials.add(sky_material(Color::rgb(1.0, 0.95, 0.7), AlphaMode::Opaque));
+    let moon_material = materials.add(sky_material(Color::rgb(0.8, 0.82, 0.88), AlphaMode::Opaque));
+
+    commands
+        .spawn((
+            Name::new("Sky"),
+            Sky,
+            SkyPart,
+            SpatialBundle::HIDDEN_IDENTITY,
+        ))
+        .with_children(|sky| {
+            sky.spawn((
+                Name::new("Sky Dome"),
+                SkyPart,
+                PbrBundle {
+                    mesh: dome.clone(),
+                    material: dome_material,
+                    ..default()
+                },
+                NotShadowCaster,
+            ));
+            sky.spawn((
+                Name::new("Stars"),
+                SkyStars,
+                SkyPart,
+                PbrBundle {
+                    mesh: meshes.add(stars_mesh()),
+                    material: stars.clone(),
+                    ..default()
+                },
+                NotShadowCaster,
+            ));
+            for (name, moon, size, material) in [
+                ("Sun", false, SUN_SIZE, sun_material),
+                ("Moon", true, MOON_SIZE, moon_material),
+            ] {
+                sky.spawn((
+                    Name::new(name),
+                    SkyBody { moon },
+                    SkyPart,
+                    PbrBundle {
+                        mesh: meshes.add(shape::Quad::new(Vec2::splat(size)).into()),
+                        material,
+                        ..default()
+                    },
+                    NotShadowCaster,
+                ));
+            }
+        });
+
+    commands.insert_resource(SkyAssets { dome, stars });
+}
+
+/// Swaps between the atmosphere and the gradient sky when the setting changes.
+pub fn apply_sky_mode(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    camera_query: Query<(Entity, Option<&AtmosphereCamera>), With<Camera3d>>,
+    mut sky_query: Query<&mut Visibility, With<Sky>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let sky = settings.graphics.sky;
+    let (camera, atmosphere) = camera_query.single();
+    match (sky, atmosphere.is_some()) {
+        (SkyMode::Atmosphere, false) => {
+            commands.entity(camera).insert(AtmosphereCamera::default());
+        }
+        // Removing the component makes `bevy_atmosphere` despawn its skybox.
+        (SkyMode::Gradient, true) => {
+            commands.entity(camera).remove::<AtmosphereCamera>();
+        }
+        _ => return,
+    }
+    *sky_query.single_mut() = match sky {
+        SkyMode::Atmosphere => Visibility::Hidden,
+        SkyMode::Gradient => Visibility::Visible,
+    };
+    info!("Sky: {:?}", sky);
+}
+
+/// Colours the sky for where the sun is, and fades the terrain into its horizon with the fog.
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+pub fn update_sky(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    assets: Res<SkyAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    sun_query: Query<&Transform, (With<DirectionalLight>, Without<SkyPart>)>,
+    mut camera_query: Query<(&Transform, &mut FogSettings), (With<Camera3d>, Without<SkyPart>)>,
+    mut sky_query: Query<
+        &mut Transform,
+        (
+            With<Sky>,
+            With<SkyPart>,
+            Without<SkyStars>,
+            Without<SkyBody>,
+        ),
+    >,
+    mut stars_query: Query<
+        (&mut Transform, &mut Visibility),
+        (With<SkyStars>, With<SkyPart>, Without<Sky>),
+    >,
+    mut bodies_query: Query<
+        (&mut Transform, &SkyBody),
+        (With<SkyPart>, Without<Sky>, Without<SkyStars>),
+    >,
+    mut last_sun: Local<Option<Vec3>>,
+) {
+    let Ok(sun) = sun_query.get_single() else {
+        return;
+    };
+    // The light shines away from the sun.
+    let towards_sun = -sun.forward();
+    let colors = sky_colors(towards_sun.y);
+
+    let (camera, mut fog) = camera_query.single_mut();
+    // The atmosphere's horizon is close enough to the gradient's, so both use the same fog.
+    fog.color = colors.horizon;
+
+    if settings.graphics.sky != SkyMode::Gradient {
+        return;
+    }
+    sky_query.single_mut().translation = camera.translation;
+
+    let (mut stars, mut visibility) = stars_query.single_mut();
+    let pole = Vec3::new(0.0, 0.8, 0.6).normalize();
+    stars.rotation = Quat::from_axis_angle(pole, time.elapsed_seconds() * STAR_SPEED);
+    let brightness = star_brightness(towards_sun.y);
+    *visibility = if brightness > 0.0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for (mut transform, body) in bodies_query.iter_mut() {
+        let direction = if body.moon { -towards_sun } else { towards_sun };
+        let up = if direction.y.abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        *transform =
+            Transform::from_translation(direction * SKY_RADIUS * 0.9).looking_at(Vec3::ZERO, up);
+    }
+
+    // The colours only have to be worked out again when the sun moved.
+    if *last_sun == Some(towards_sun) {
+        return;
+    }
+    *last_sun = Some(towards_sun);
+
+    if let Some(material) = materials.get_mut(&assets.stars) {
+        material.base_color = Color::rgba(1.0, 1.0, 1.0, brightness);
+    }
+    let Some(dome) = meshes.get_mut(&assets.dome) else {
+        return;
+    };
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        dome.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return;
+    };
+    // Straight from the horizon colour to the zenith colour, below the horizon it stays the horizon colour.
+    let vertex_colors: Vec<[f32; 4]> = positions
+        .iter()
+        .map(|position| {
+            let height = (position[1] / SKY_RADIUS).max(0.0).sqrt();
+            mix_colors(colors.horizon, colors.zenith, height).as_linear_rgba_f32()
+        })
+        .collect();
+    dome.insert_attribute(Mesh::ATTRIBUTE_COLOR, vertex_colors);
+}
@@ -0,0 +1,332 @@
+use std::collections::HashSet;
+use std::f32::consts::TAU;
+use std::mem::size_of;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bevy::app::AppExit;
+use bevy::asset::HandleId;
+
+use crate::game::chunk::ChunkMaterials;
+use crate::game::rules::arg_value;
+use crate::prelude::*;
+
+/// The height the soak test flies at, above the highest terrain.
+const SOAK_HEIGHT: f32 = 240.0;
+/// How long the streaming gets to catch up after the flight, before the checks.
+const SOAK_SETTLE: f32 = 120.0;
+/// How many bytes the blocks of a chunk take up.
+const CHUNK_BYTES: usize = CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE * size_of::<BlockType>();
+
+/// Panics on any thread since the counter was installed, the mesh tasks included.
+static PANICS: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs the game without a window, flying the player along a large figure eight to put the chunk streaming under
+/// load, and checks that nothing leaked at the end.
+///
+/// It's started with `--soak minutes=N`. The speed, the size of the loops and the memory budget can be given too,
+/// like `--soak minutes=5,speed=200,size=2048,memory_mb=32`.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct SoakTest {
+    /// How long to fly for, in seconds.
+    pub duration: f32,
+    /// How fast to fly, in blocks per second.
+    pub speed: f32,
+    /// How far the loops of the figure eight reach from the spawn, in blocks.
+    pub size: f32,
+    /// The most memory the blocks of the loaded chunks may take up, in megabytes. By default it's what the chunks up
+    /// to the unload distance and the spawn chunks need.
+    pub memory_mb: Option<f32>,
+    elapsed: f32,
+    frames: u64,
+    peak_chunks: usize,
+}
+
+impl Default for SoakTest {
+    fn default() -> Self {
+        Self {
+            duration: 5.0 * 60.0,
+            speed: 100.0,
+            size: 1024.0,
+            memory_mb: None,
+            elapsed: 0.0,
+            frames: 0,
+            peak_chunks: 0,
+        }
+    }
+}
+
+impl SoakTest {
+    /// The soak test from the `--soak` argument, if there is one.
+    pub fn from_args(args: &[String]) -> Option<Result<Self, String>> {
+        arg_value(args, "--soak").map(Self::parse)
+    }
+
+    /// Parses a comma separated list of `option=value`, like `minutes=5,speed=200`.
+    pub fn parse(options: &str) -> Result<Self, String> {
+        let mut soak = Self::default();
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (name, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("expected option=value, got \"{}\"", option))?;
+            let value: f32 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|value: &f32| value.is_finite() && *value > 0.0)
+                .ok_or_else(|| {
+                    format!("expected a positive number for {}, got \"{}\"", name, value)
+                })?;
+            match name.trim() {
+                "minutes" => soak.duration = value * 60.0,
+                "speed" => soak.speed = value,
+                "size" => soak.size = value,
+                "memory_mb" => soak.memory_mb = Some(value),
+                name => return Err(format!("unknown soak option \"{}\"", name)),
+            }
+        }
+        Ok(soak)
+    }
+
+    /// Where the player is a number of seconds into the flight. The figure eight is centred on the spawn, it
+    /// crosses itself there.
+    pub fn position(&self, seconds: f32) -> Vec3 {
+        // The eight is about 6 times the size of the loops long, that's how fast the angle has to go round.
+        let angle = seconds.min(self.duration) * self.speed / (6.0 * self.size) * TAU;
+        Vec3::new(
+            WORLD_SPAWN.x + self.size * angle.sin(),
+            SOAK_HEIGHT,
+            WORLD_SPAWN.z + self.size * angle.sin() * angle.cos(),
+        )
+    }
+}
+
+/// Counts the panics on every thread, on top of what the panic hook did before.
+pub fn count_panics() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        PANICS.fetch_add(1, Ordering::SeqCst);
+        previous(info);
+    }));
+}
+
+/// What the soak test found at the end.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SoakReport {
+    pub frames: u64,
+    /// Whether every chunk was done generating before the checks.
+    pub settled: bool,
+    pub ready_chunks: usize,
+    pub peak_chunks: usize,
+    /// The most chunks that can be loaded at once, the unload distance and the spawn chunks.
+    pub max_chunks: usize,
+    /// The chunks in the load distance of where the flight stopped that aren't loaded.
+    pub missing_chunks: usize,
+    /// The sections of the chunks with a solid mesh, and the meshes that aren't used by any entity.
+    pub section_meshes: usize,
+    pub leaked_meshes: usize,
+    pub leaked_materials: usize,
+    /// Chunks whose blocks are still kept, but that are unloaded or on their way out.
+    pub stale_blocks: Vec<IVec2XZ>,
+    pub memory_bytes: usize,
+    pub memory_budget_bytes: usize,
+    pub panics: usize,
+}
+
+impl SoakReport {
+    /// Everything that went wrong, empty if the soak test passed.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.settled {
+            problems.push(format!(
+                "the streaming didn't settle in {} seconds",
+                SOAK_SETTLE
+            ));
+        }
+        if self.peak_chunks > self.max_chunks {
+            problems.push(format!(
+                "{} chunks were loaded at once, at most {} should be",
+                self.peak_chunks, self.max_chunks
+            ));
+        }
+        if self.missing_chunks > 0 {
+            problems.push(format!(
+                "{} chunks around the player aren't loaded",
+                self.missing_chunks
+            ));
+        }
+        if self.leaked_meshes > 0 || self.leaked_materials > 0 {
+            problems.push(format!(
+                "{} meshes and {} materials leaked",
+                self.leaked_meshes, self.leaked_materials
+            ));
+        }
+        if !self.stale_blocks.is_empty() {
+            problems.push(format!(
+                "the blocks of unloaded chunks are still kept: {:?}",
+                self.stale_blocks
+            ));
+        }
+        if self.memory_bytes > self.memory_budget_bytes {
+            problems.push(format!(
+                "the chunks take up {:.1} MB, over the budget of {:.1} MB",
+                megabytes(self.memory_bytes),
+                megabytes(self.memory_budget_bytes)
+            ));
+        }
+        if self.panics > 0 {
+            problems.push(format!("{} panics", self.panics));
+        }
+        problems
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Soak test: {} frames, {} chunks loaded ({} at the peak, {} at most), {} section meshes, \
+             {:.1} of {:.1} MB of chunk data",
+            self.frames,
+            self.ready_chunks,
+            self.peak_chunks,
+            self.max_chunks,
+            self.section_meshes,
+            megabytes(self.memory_bytes),
+            megabytes(self.memory_budget_bytes)
+        )
+    }
+}
+
+/// How many assets no entity uses. The default handle is left out, the renderer keeps it around for itself.
+fn unused<T: bevy::asset::Asset>(
+    assets: &Assets<T>,
+    chunks: &HashSet<HandleId>,
+    others: &HashSet<HandleId>,
+) -> usize {
+    let default = Handle::<T>::default().id();
+    assets
+        .ids()
+        .filter(|id| *id != default && !chunks.contains(id) && !others.contains(id))
+        .count()
+}
+
+/// The chunks within a distance of a centre chunk.
+fn circle(center: IVec2XZ, radius: i32) -> impl Iterator<Item = IVec2XZ> {
+    (-radius..=radius)
+        .flat_map(move |x| (-radius..=radius).map(move |z| IVec2XZ::new(x, z)))
+        .filter(move |offset| offset.x * offset.x + offset.z * offset.z <= radius * radius)
+        .map(move |offset| center + offset)
+}
+
+fn megabytes(bytes: usize) -> f32 {
+    bytes as f32 / (1024.0 * 1024.0)
+}
+
+/// Flies the player along the figure eight, and keeps it at the end while the streaming settles.
+pub fn fly_soak_path(
+    time: Res<Time>,
+    mut soak: ResMut<SoakTest>,
+    mut player_state: ResMut<PlayerPos>,
+    mut bodies: Query<(&mut Transform, &mut Velocity), With<KinematicCharacterController>>,
+) {
+    soak.elapsed += time.delta_seconds();
+    soak.frames += 1;
+    let position = soak.position(soak.elapsed);
+    for (mut transform, mut velocity) in bodies.iter_mut() {
+        transform.translation = position;
+        velocity.linvel = Vec3::ZERO;
+    }
+    player_state.pos = position;
+}
+
+/// Checks the streaming once the flight is over and every chunk is done generating, prints the report and quits.
+/// The process fails if anything went wrong.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn finish_soak_test(
+    mut soak: ResMut<SoakTest>,
+    chunks_loaded: Res<ChunksLoaded>,
+    chunk_map: Res<ChunkMap>,
+    loader: Res<ChunkLoader>,
+    meshes: Res<Assets<Mesh>>,
+    (materials, shared_materials): (Res<Assets<StandardMaterial>>, Res<ChunkMaterials>),
+    section_query: Query<
+        (Option<&Handle<Mesh>>, Option<&Handle<StandardMaterial>>),
+        Or<(With<ChunkSection>, With<PackedSection>)>,
+    >,
+    other_query: Query<
+        (Option<&Handle<Mesh>>, Option<&Handle<StandardMaterial>>),
+        (Without<ChunkSection>, Without<PackedSection>),
+    >,
+    mut exit: EventWriter<AppExit>,
+) {
+    let (ready, persistent, generating) = chunks_loaded.counts();
+    soak.peak_chunks = soak.peak_chunks.max(ready);
+    let settling = soak.elapsed - soak.duration;
+    if settling < 0.0 || generating > 0 && settling < SOAK_SETTLE {
+        return;
+    }
+
+    // The same circles the streaming loads and unloads. Where the flight stops can be right on the border of a
+    // chunk, so they're centred on the chunk the loader picked.
+    let center = loader
+        .center()
+        .unwrap_or_else(|| IVec2XZ::containing(soak.position(soak.elapsed)));
+    let in_range: Vec<IVec2XZ> = circle(center, LOAD_DISTANCE).collect();
+    let max_chunks = circle(center, UNLOAD_DISTANCE).count() + persistent;
+    let missing_chunks = in_range
+        .iter()
+        .filter(|&&position| !chunks_loaded.is_ready(position))
+        .count();
+
+    let used = |query: Vec<(Option<&Handle<Mesh>>, Option<&Handle<StandardMaterial>>)>| {
+        let meshes: HashSet<HandleId> = query
+            .iter()
+            .filter_map(|(mesh, _)| mesh.map(Handle::id))
+            .collect();
+        let materials: HashSet<HandleId> = query
+            .iter()
+            .filter_map(|(_, material)| material.map(Handle::id))
+            .collect();
+        (meshes, materials)
+    };
+    let (section_meshes, section_materials) = used(section_query.iter().collect());
+    let (other_meshes, mut other_materials) = used(other_query.iter().collect());
+    // The chunks share their material, it's kept while none of them is loaded. The fluids aren't drawn with a
+    // standard material, so they aren't counted here.
+    other_materials.insert(shared_materials.solid.id());
+
+    let stale_blocks = chunk_map
+        .chunks
+        .keys()
+        .copied()
+        .filter(|&position| !chunks_loaded.is_ready(position))
+        .collect();
+    let memory_budget_bytes = soak.memory_mb.map_or(max_chunks * CHUNK_BYTES, |mb| {
+        (mb * 1024.0 * 1024.0) as usize
+    });
+
+    let report = SoakReport {
+        frames: soak.frames,
+        settled: generating == 0,
+        ready_chunks: ready,
+        peak_chunks: soak.peak_chunks,
+        max_chunks,
+        missing_chunks,
+        section_meshes: section_meshes.len(),
+        leaked_meshes: unused(&meshes, &section_meshes, &other_meshes),
+        leaked_materials: unused(&materials, &section_materials, &other_materials),
+        stale_blocks,
+        memory_bytes: chunk_map.chunks.len() * CHUNK_BYTES,
+        memory_budget_bytes,
+        panics: PANICS.load(Ordering::SeqCst),
+    };
+
+    info!("{}", report.summary());
+    let problems = report.problems();
+    if !problems.is_empty() {
+        for problem in &problems {
+            error!("Soak test failed: {}", problem);
+        }
+        std::process::exit(1);
+    }
+    info!("Soak test passed");
+    exit.send(AppExit);
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+
+use crate::game::chunk::remesh_chunks;
+use crate::game::debug::{DebugAction, DebugChords};
+use crate::game::edits::{touched_sections, EditHistory, EditRecord};
+use crate::game::generator::SharedGenerator;
+use crate::game::schematic::{LoadedSchematic, Schematic};
+use crate::game::target::TargetedBlock;
+use crate::game::trees::{Tree, TreeSpecies};
+use crate::prelude::*;
+
+/// A structure couldn't be stamped, some of the chunks it reaches into aren't loaded.
+#[derive(Debug, PartialEq)]
+pub struct MissingChunks(pub Vec<IVec2XZ>);
+
+impl fmt::Display for MissingChunks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the chunks")?;
+        for (i, chunk) in self.0.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(f, "{} ({}, {})", separator, chunk.x, chunk.z)?;
+        }
+        write!(f, " aren't loaded")
+    }
+}
+
+/// Places the blocks of a structure, like a tree, as one action of the [`EditHistory`]. `replaces` decides which of
+/// the blocks already there give way to it. Blocks above or below the world are left out.
+///
+/// Nothing is placed if any of the chunks the structure reaches into isn't loaded, the error lists them. Returns the
+/// sections that need a new mesh.
+pub fn stamp_blocks(
+    chunk_map: &mut ChunkMap,
+    history: &mut EditHistory,
+    blocks: impl IntoIterator<Item = (IVec3, BlockType)>,
+    replaces: impl Fn(BlockType, BlockType) -> bool,
+) -> Result<Vec<ChunkSection>, MissingChunks> {
+    let blocks: Vec<(IVec3, BlockType)> = blocks
+        .into_iter()
+        .filter(|(pos, _)| (0..CHUNK_HEIGHT as i32).contains(&pos.y))
+        .collect();
+    let mut missing: Vec<IVec2XZ> = blocks
+        .iter()
+        .map(|&(pos, _)| world_to_chunk_and_local(pos).0)
+        .filter(|chunk| !chunk_map.chunks.contains_key(chunk))
+        .collect();
+    missing.sort_by_key(|chunk| (chunk.x, chunk.z));
+    missing.dedup();
+    if !missing.is_empty() {
+        return Err(MissingChunks(missing));
+    }
+
+    let mut changed = Vec::new();
+    history.begin_batch();
+    for (pos, new) in blocks {
+        let Some(old) = chunk_map.get_block(pos).filter(|&old| replaces(old, new)) else {
+            continue;
+        };
+        chunk_map.set_block(pos, new);
+        history.record(EditRecord { pos, old, new });
+        for section in touched_sections(pos) {
+            if !changed.contains(&section) {
+                changed.push(section);
+            }
+        }
+    }
+    history.end_batch();
+    Ok(changed)
+}
+
+/// Grows a tree on top of a block right away, like bonemeal on a sapling. The tree is as tall as the middle of its
+/// species' range.
+pub fn grow_tree(
+    chunk_map: &mut ChunkMap,
+    history: &mut EditHistory,
+    ground: IVec3,
+    species: TreeSpecies,
+) -> Result<Vec<ChunkSection>, MissingChunks> {
+    let (shortest, tallest) = species.trunk_heights();
+    let tree = Tree {
+        base: ground + IVec3::Y,
+        trunk_height: (shortest + tallest) / 2,
+        species,
+    };
+    // Like the generated trees, logs go through leaves, leaves only grow into air. Plants are pushed aside.
+    stamp_blocks(chunk_map, history, tree.blocks(), |old, new| {
+        old == BlockType::Air || old.is_plant() || new.is_log() && old.is_leaves()
+    })
+}
+
+/// Grows a tree on the targeted block on the debug chord. Spruces grow on snow, oaks everywhere else.
+#[allow(clippy::too_many_arguments)]
+pub fn grow_targeted_tree(
+    mut commands: Commands,
+    chords: Res<DebugChords>,
+    targeted: Res<TargetedBlock>,
+    (mut chunk_map, mut history): (ResMut<ChunkMap>, ResMut<EditHistory>),
+    (chunks_loaded, remeshing): (Res<ChunksLoaded>, Query<&Remeshing>),
+    (game_atlas, generator, ambient_occlusion, packed_meshes): (
+        Res<GameTextureAtlas>,
+        Res<SharedGenerator>,
+        Res<AmbientOcclusion>,
+        Res<PackedMeshes>,
+    ),
+) {
+    if !chords.triggered(DebugAction::GrowTree) {
+        return;
+    }
+    let Some((ground, block)) = targeted.target else {
+        return;
+    };
+    let species = match block {
+        BlockType::SnowyGrass | BlockType::Snow => TreeSpecies::Spruce,
+        _ => TreeSpecies::Oak,
+    };
+    match grow_tree(&mut chunk_map, &mut history, ground, species) {
+        Ok(changed) => remesh_chunks(
+            &mut commands,
+            changed,
+            &remeshing,
+            &chunks_loaded,
+            &chunk_map,
+            &game_atlas,
+            &generator,
+            *ambient_occlusion,
+            *packed_meshes,
+        ),
+        Err(missing) => warn!("Couldn't grow a tree at {}: {}", ground, missing),
+    }
+}
+
+/// Pastes a schematic with its origin corner at `origin`. Everything but bedrock gives way to it, air included, so a
+/// hollow building is hollow even when pasted into a hill.
+pub fn paste_schematic(
+    chunk_map: &mut ChunkMap,
+    history: &mut EditHistory,
+    schematic: &Schematic,
+    origin: IVec3,
+) -> Result<Vec<ChunkSection>, MissingChunks> {
+    let blocks = schematic
+        .cells()
+        .map(|(offset, block)| (origin + offset.as_ivec3(), block));
+    stamp_blocks(chunk_map, history, blocks, |old, new| {
+        old != new && old != BlockType::Bedrock
+    })
+}
+
+/// Pastes the `--schematic` on top of the targeted block on the debug chord.
+#[allow(clippy::too_many_arguments)]
+pub fn paste_targeted_schematic(
+    mut commands: Commands,
+    chords: Res<DebugChords>,
+    targeted: Res<TargetedBlock>,
+    schematic: Option<Res<LoadedSchematic>>,
+    (mut chunk_map, mut history): (ResMut<ChunkMap>, ResMut<EditHistory>),
+    (chunks_loaded, remeshing): (Res<ChunksLoaded>, Query<&Remeshing>),
+    (game_atlas, generator, ambient_occlusion, packed_meshes): (
+        Res<GameTextureAtlas>,
+        Res<SharedGenerator>,
+        Res<AmbientOcclusion>,
+        Res<PackedMeshes>,
+    ),
+) {
+    if !chords.triggered(DebugAction::PasteSchematic) {
+        return;
+    }
+    let Some(LoadedSchematic(schematic)) = schematic.as_deref() else {
+        warn!("No schematic to paste, pass one with --schematic <file>");
+        return;
+    };
+    let Some((ground, _)) = targeted.target else {
+        return;
+    };
+    for name in schematic.unknown_names() {
+        warn!(
+            "The schematic has an unknown block {:?}, it's pasted as stone",
+            name
+        );
+    }
+    let origin = ground + IVec3::Y;
+    match paste_schematic(&mut chunk_map, &mut history, schematic, origin) {
+        Ok(changed) => remesh_chunks(
+            &mut commands,
+            changed,
+            &remeshing,
+            &chunks_loaded,
+            &chunk_map,
+            &game_atlas,
+            &generator,
+            *ambient_occlusion,
+            *packed_meshes,
+        ),
+        Err(missing) => warn!("Couldn't paste the schematic at {}: {}", origin, missing),
+    }
+}
@@ -0,0 +1,245 @@
+use crate::prelude::*;
+
+/// How long the player keeps burning after leaving the lava, in seconds.
+pub const BURN_DURATION: f32 = 4.0;
+pub const BURN_DAMAGE: f32 = 1.0;
+/// Seconds between two hits of burning damage.
+pub const BURN_DAMAGE_INTERVAL: f32 = 0.5;
+pub const MAX_HEALTH: f32 = 20.0;
+
+/// Half the size of the player's body, the box around [`player_collider`](crate::game::camera::player_collider).
+const BODY_HALF_EXTENTS: Vec3 = Vec3::new(0.5, 1.0, 0.5);
+
+/// An effect on an entity that wears off with time, like burning.
+///
+/// Every effect is its own component, counted down by [`tick_status_effect`] and removed when it's over.
+pub trait StatusEffect: Component {
+    /// What the damage of the effect is put down to.
+    const CAUSE: DamageCause;
+
+    /// Counts the effect down by `delta` seconds. Returns the damage it dealt in that time.
+    fn tick(&mut self, delta: f32) -> f32;
+    fn is_over(&self) -> bool;
+    /// How the effect is shown in the HUD.
+    fn label(&self) -> String;
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            current: MAX_HEALTH,
+            max: MAX_HEALTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageCause {
+    Fire,
+    Projectile,
+    Suffocation,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub entity: Entity,
+    pub amount: f32,
+    pub cause: DamageCause,
+}
+
+/// Set on fire by lava. Keeps hurting for a while after getting out, unless the fire is put out with water.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Burning {
+    /// Seconds until the fire goes out.
+    pub remaining: f32,
+    /// Seconds since the last hit, the first one lands right away.
+    since_damage: f32,
+}
+
+impl Default for Burning {
+    fn default() -> Self {
+        Self {
+            remaining: BURN_DURATION,
+            since_damage: BURN_DAMAGE_INTERVAL,
+        }
+    }
+}
+
+impl Burning {
+    /// Still in the lava, the fire starts over. The damage keeps its rhythm.
+    pub fn rekindle(&mut self) {
+        self.remaining = BURN_DURATION;
+    }
+}
+
+impl StatusEffect for Burning {
+    const CAUSE: DamageCause = DamageCause::Fire;
+
+    fn tick(&mut self, delta: f32) -> f32 {
+        let delta = delta.min(self.remaining);
+        self.remaining -= delta;
+        self.since_damage += delta;
+
+        let hits = (self.since_damage / BURN_DAMAGE_INTERVAL).floor();
+        self.since_damage -= hits * BURN_DAMAGE_INTERVAL;
+        hits * BURN_DAMAGE
+    }
+
+    fn is_over(&self) -> bool {
+        self.remaining <= 0.0
+    }
+
+    fn label(&self) -> String {
+        format!("Burning ({:.1}s)", self.remaining)
+    }
+}
+
+/// The blocks overlapping a body of the player's size centered here. Unloaded blocks are left out.
+pub fn body_blocks(center: Vec3, block_at: impl Fn(IVec3) -> Option<BlockType>) -> Vec<BlockType> {
+    let min = (center - BODY_HALF_EXTENTS).floor().as_ivec3();
+    // A body exactly on a block border doesn't reach into the next block.
+    let max = (center + BODY_HALF_EXTENTS).ceil().as_ivec3() - IVec3::ONE;
+
+    let mut blocks = Vec::new();
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                blocks.extend(block_at(IVec3::new(x, y, z)));
+            }
+        }
+    }
+    blocks
+}
+
+/// Sets the player on fire while they touch lava, and puts the fire out in water.
+#[allow(clippy::type_complexity)]
+pub fn burn_in_lava(
+    mut commands: Commands,
+    chunk_map: Res<ChunkMap>,
+    mut player_query: Query<
+        (Entity, &Transform, Option<&mut Burning>),
+        With<KinematicCharacterController>,
+    >,
+) {
+    for (entity, transform, burning) in player_query.iter_mut() {
+        let blocks = body_blocks(transform.translation, |pos| chunk_map.get_block(pos));
+        if blocks.contains(&BlockType::Water) {
+            if burning.is_some() {
+                commands.entity(entity).remove::<Burning>();
+            }
+        } else if blocks.contains(&BlockType::Lava) {
+            match burning {
+                Some(mut burning) => burning.rekindle(),
+                None => {
+                    commands.entity(entity).insert(Burning::default());
+                }
+            }
+        }
+    }
+}
+
+/// Counts an effect down, sends the damage it deals and removes it once it's over.
+pub fn tick_status_effect<T: StatusEffect>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut effects: Query<(Entity, &mut T)>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for (entity, mut effect) in effects.iter_mut() {
+        let amount = effect.tick(time.delta_seconds());
+        if amount > 0.0 {
+            damage.send(DamageEvent {
+                entity,
+                amount,
+                cause: T::CAUSE,
+            });
+        }
+        if effect.is_over() {
+            commands.entity(entity).remove::<T>();
+        }
+    }
+}
+
+/// Takes the damage off the health. There is no dying yet, the health stops at zero.
+pub fn apply_damage(mut events: EventReader<DamageEvent>, mut health_query: Query<&mut Health>) {
+    for event in events.iter() {
+        let Ok(mut health) = health_query.get_mut(event.entity) else {
+            continue;
+        };
+        health.current = (health.current - event.amount).max(0.0);
+        debug!(
+            "{:?} damage: {} ({:?})",
+            event.cause, event.amount, event.entity
+        );
+    }
+}
+
+/// Flames at the bottom of the screen and an orange tint, while the player burns.
+#[derive(Component)]
+pub struct FireOverlay;
+
+pub fn setup_fire_overlay(
+    mut commands: Commands,
+    game_atlas: Res<GameTextureAtlas>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    commands
+        .spawn((
+            Name::new("Fire Overlay"),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::FlexEnd,
+                    ..default()
+                },
+                background_color: Color::rgba(1.0, 0.45, 0.1, 0.15).into(),
+                visibility: Visibility::Hidden,
+                // Behind the rest of the HUD, like the eye overlay.
+                z_index: ZIndex::Global(-1),
+                ..default()
+            },
+            FireOverlay,
+            UI,
+        ))
+        .with_children(|overlay| {
+            // There is no fire texture, the lava tile stands in for the flames.
+            overlay.spawn((
+                Name::new("Flames"),
+                AtlasImageBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(25.0),
+                        ..default()
+                    },
+                    texture_atlas: atlases.add(game_atlas.atlas.clone()),
+                    texture_atlas_image: UiTextureAtlasImage {
+                        index: game_atlas.tiles(BlockType::Lava, BlockFace::Front)[0],
+                        ..default()
+                    },
+                    background_color: Color::rgba(1.0, 0.8, 0.5, 0.6).into(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+pub fn update_fire_overlay(
+    burning_query: Query<(), (With<Burning>, With<KinematicCharacterController>)>,
+    mut overlay_query: Query<&mut Visibility, With<FireOverlay>>,
+) {
+    let visibility = if burning_query.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    overlay_query.single_mut().set_if_neq(visibility);
+}
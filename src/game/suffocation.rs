@@ -0,0 +1,167 @@
+use crate::game::freecam::CameraMode;
+use crate::game::status::{DamageCause, StatusEffect};
+use crate::prelude::*;
+
+pub const SUFFOCATION_DAMAGE: f32 = 1.0;
+/// Seconds between two hits of suffocation damage.
+pub const SUFFOCATION_DAMAGE_INTERVAL: f32 = 0.5;
+
+/// Fullscreen image of the block the camera is stuck in, drawn instead of the chunk's backfaces.
+#[derive(Component)]
+pub struct EyeOverlay;
+
+/// The cell the camera is in.
+pub fn eye_cell(eye: Vec3) -> IVec3 {
+    eye.floor().as_ivec3()
+}
+
+/// The closest loaded, non-solid cell next to the one the eye is stuck in.
+///
+/// Only the direct neighbours and the cell two blocks up are checked, this is a nudge and not a search.
+pub fn free_cell_near(chunk_map: &ChunkMap, eye: Vec3) -> Option<IVec3> {
+    let cell = eye_cell(eye);
+    BlockFace::ALL
+        .iter()
+        .map(|face| cell + face.offset())
+        .chain([cell + IVec3::Y * 2])
+        .filter(|&candidate| {
+            chunk_map
+                .get_block(candidate)
+                .is_some_and(|block| !block.is_solid())
+        })
+        .min_by(|a, b| {
+            let distance = |cell: &IVec3| (cell.as_vec3() + 0.5).distance_squared(eye);
+            distance(a).total_cmp(&distance(b))
+        })
+}
+
+/// Stuck with the head in a solid block. Hurts until the head is free again, it doesn't wear off.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Suffocating {
+    /// Seconds since the last hit, the first one lands after a whole interval.
+    since_damage: f32,
+}
+
+impl StatusEffect for Suffocating {
+    const CAUSE: DamageCause = DamageCause::Suffocation;
+
+    fn tick(&mut self, delta: f32) -> f32 {
+        self.since_damage += delta;
+        let hits = (self.since_damage / SUFFOCATION_DAMAGE_INTERVAL).floor();
+        self.since_damage -= hits * SUFFOCATION_DAMAGE_INTERVAL;
+        hits * SUFFOCATION_DAMAGE
+    }
+
+    fn is_over(&self) -> bool {
+        false
+    }
+
+    fn label(&self) -> String {
+        "Suffocating".to_string()
+    }
+}
+
+pub fn setup_eye_overlay(
+    mut commands: Commands,
+    game_atlas: Res<GameTextureAtlas>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    commands.spawn((
+        Name::new("Eye Overlay"),
+        AtlasImageBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            texture_atlas: atlases.add(game_atlas.atlas.clone()),
+            // Darken the tile, the player is inside of it.
+            background_color: Color::rgb(0.1, 0.1, 0.1).into(),
+            visibility: Visibility::Hidden,
+            // Behind the rest of the HUD.
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        EyeOverlay,
+        UI,
+    ));
+}
+
+/// Pushes the player out of a solid block their head ended up in, for example when a chunk loaded around them.
+pub fn push_out_of_blocks(
+    chunk_map: Res<ChunkMap>,
+    camera_query: Query<&Transform, (With<Camera3d>, Without<KinematicCharacterController>)>,
+    mut player_query: Query<&mut Transform, With<KinematicCharacterController>>,
+    camera_mode: Res<CameraMode>,
+) {
+    // The freecam isn't the player's head.
+    if camera_mode.is_freecam() {
+        return;
+    }
+    let eye = camera_query.single().translation;
+    let stuck = chunk_map
+        .get_block(eye_cell(eye))
+        .is_some_and(BlockType::is_solid);
+    if !stuck {
+        return;
+    }
+
+    if let Some(cell) = free_cell_near(&chunk_map, eye) {
+        for mut transform in player_query.iter_mut() {
+            transform.translation += cell.as_vec3() + 0.5 - eye;
+        }
+    }
+}
+
+/// Starts suffocating the player while their head is in a solid block, after [`push_out_of_blocks`] found nowhere
+/// to push it to, and stops when it's free again.
+pub fn suffocate_in_blocks(
+    mut commands: Commands,
+    chunk_map: Res<ChunkMap>,
+    camera_query: Query<&Transform, (With<Camera3d>, Without<KinematicCharacterController>)>,
+    player_query: Query<(Entity, Option<&Suffocating>), With<KinematicCharacterController>>,
+    camera_mode: Res<CameraMode>,
+) {
+    let eye = camera_query.single().translation;
+    let stuck = !camera_mode.is_freecam()
+        && chunk_map
+            .get_block(eye_cell(eye))
+            .is_some_and(BlockType::is_solid);
+    for (entity, suffocating) in player_query.iter() {
+        let suffocating = suffocating.is_some();
+        if stuck && !suffocating {
+            commands.entity(entity).insert(Suffocating::default());
+        } else if !stuck && suffocating {
+            commands.entity(entity).remove::<Suffocating>();
+        }
+    }
+}
+
+/// Shows the tile of the block the camera is in, if it's in one.
+pub fn update_eye_overlay(
+    chunk_map: Res<ChunkMap>,
+    game_atlas: Res<GameTextureAtlas>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+    mut overlay_query: Query<(&mut Visibility, &mut UiTextureAtlasImage), With<EyeOverlay>>,
+) {
+    let eye = camera_query.single().translation;
+    let block = chunk_map
+        .get_block(eye_cell(eye))
+        .filter(|block| block.is_solid());
+
+    let (mut visibility, mut image) = overlay_query.single_mut();
+    match block {
+        Some(block) => {
+            // The same tile everywhere, the variant would change while moving through the block.
+            let index = game_atlas.tiles(block, BlockFace::Front)[0];
+            if image.index != index {
+                image.index = index;
+            }
+            visibility.set_if_neq(Visibility::Inherited);
+        }
+        None => {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::prelude::*;
+
+/// The block under the crosshair, if it's within reach.
+#[derive(Resource, Default)]
+pub struct TargetedBlock {
+    pub target: Option<(IVec3, BlockType)>,
+    /// How long the same block has been targeted, in seconds.
+    pub steady_for: f32,
+}
+
+/// Walks a ray from the camera through the loaded blocks to find the targeted block. Only the solid blocks are hit,
+/// like the chunk colliders would be, but the chunks away from the player have none.
+pub fn update_targeted_block(
+    time: Res<Time>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+    chunk_map: Res<ChunkMap>,
+    mut targeted: ResMut<TargetedBlock>,
+) {
+    let camera = camera_query.single();
+
+    let target = raycast_blocks(
+        camera.translation,
+        camera.forward(),
+        REACH_DISTANCE,
+        |pos| chunk_map.get_block(pos).is_some_and(BlockType::is_solid),
+    )
+    .and_then(|hit| chunk_map.get_block(hit.pos).map(|block| (hit.pos, block)));
+
+    if target == targeted.target {
+        targeted.steady_for += time.delta_seconds();
+    } else {
+        targeted.target = target;
+        targeted.steady_for = 0.0;
+    }
+}
+
+/// A block a ray ran into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockHit {
+    pub pos: IVec3,
+    /// The side of the block the ray came in through, zero if it started inside the block.
+    pub normal: IVec3,
+    /// How far along the ray the block starts.
+    pub distance: f32,
+    /// Where the ray comes into the block, on the face it came in through. The start of the ray if it's inside.
+    pub point: Vec3,
+}
+
+/// Walks a ray through the block grid one block at a time (a DDA, like Amanatides and Woo) and returns the first
+/// block `hits` is true for, within `max_distance`. Unlike the rapier ray cast it needs no colliders, so it also
+/// works in chunks whose collider isn't built yet.
+pub fn raycast_blocks(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    hits: impl Fn(IVec3) -> bool,
+) -> Option<BlockHit> {
+    let mut pos = origin.floor().as_ivec3();
+    if hits(pos) {
+        return Some(BlockHit {
+            pos,
+            normal: IVec3::ZERO,
+            distance: 0.0,
+            point: origin,
+        });
+    }
+    let direction = direction.try_normalize()?;
+
+    let step = direction.signum().as_ivec3();
+    // How far along the ray one block is on each axis, and how far the next block border is.
+    let delta = direction.abs().recip();
+    let mut next = Vec3::ZERO;
+    for axis in 0..3 {
+        next[axis] = if direction[axis] > 0.0 {
+            (pos[axis] as f32 + 1.0 - origin[axis]) * delta[axis]
+        } else if direction[axis] < 0.0 {
+            (origin[axis] - pos[axis] as f32) * delta[axis]
+        } else {
+            f32::INFINITY
+        };
+    }
+
+    loop {
+        let axis = if next.x <= next.y && next.x <= next.z {
+            0
+        } else if next.y <= next.z {
+            1
+        } else {
+            2
+        };
+        let distance = next[axis];
+        if distance > max_distance {
+            return None;
+        }
+        pos[axis] += step[axis];
+        next[axis] += delta[axis];
+        if hits(pos) {
+            let mut normal = IVec3::ZERO;
+            normal[axis] = -step[axis];
+            return Some(BlockHit {
+                pos,
+                normal,
+                distance,
+                point: origin + direction * distance,
+            });
+        }
+    }
+}
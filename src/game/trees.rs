@@ -0,0 +1,246 @@
+use crate::game::biome::{Biome, BiomeNoise};
+use crate::game::hash::{feature_seed, rng_for};
+use crate::game::ponds::pond_for_chunk;
+use crate::prelude::*;
+
+/// How far the leaves reach from the trunk.
+pub const CANOPY_RADIUS: i32 = 2;
+/// Mixed into the column hash, so trees don't line up with the other per-column features.
+const TREE_SALT: u32 = 0x7ee5;
+
+/// The kinds of trees, each with its own blocks and shape. Biomes pick theirs with [`Biome::tree_species`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeSpecies {
+    /// A short trunk with a round blob of leaves on top.
+    Oak,
+    /// A tall trunk in a cone of leaves, for the cold biomes.
+    Spruce,
+}
+
+impl TreeSpecies {
+    pub fn log(self) -> BlockType {
+        match self {
+            TreeSpecies::Oak => BlockType::Log,
+            TreeSpecies::Spruce => BlockType::SpruceLog,
+        }
+    }
+
+    pub fn leaves(self) -> BlockType {
+        match self {
+            TreeSpecies::Oak => BlockType::Leaves,
+            TreeSpecies::Spruce => BlockType::SpruceLeaves,
+        }
+    }
+
+    /// The shortest and the tallest trunk the species grows.
+    pub fn trunk_heights(self) -> (i32, i32) {
+        match self {
+            TreeSpecies::Oak => (4, 6),
+            TreeSpecies::Spruce => (6, 8),
+        }
+    }
+
+    /// Whether the canopy has leaves at an offset from the lowest log. `top` is the offset of the highest log.
+    ///
+    /// Every canopy stays within [`CANOPY_RADIUS`] of the trunk and at most two blocks above it.
+    fn canopy(self, offset: IVec3, top: i32, pos: IVec3) -> bool {
+        match self {
+            TreeSpecies::Oak => oak_canopy(offset, top, pos),
+            TreeSpecies::Spruce => spruce_canopy(offset, top),
+        }
+    }
+}
+
+/// Two layers of radius 2 around the top of the trunk and two layers of radius 1 above it.
+/// The very top is a plus, the other corners are trimmed at random.
+fn oak_canopy(offset: IVec3, top: i32, pos: IVec3) -> bool {
+    let layer = offset.y - top;
+    let radius = match layer {
+        -1 | 0 => CANOPY_RADIUS,
+        1 | 2 => 1,
+        _ => return false,
+    };
+    let (dx, dz) = (offset.x.abs(), offset.z.abs());
+    if dx > radius || dz > radius {
+        return false;
+    }
+    if dx == radius && dz == radius {
+        let column = ((pos.x as u32 as u64) << 32) | pos.z as u32 as u64;
+        if layer == 2 || mix(mix(column) ^ pos.y as u64) & 1 == 0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// A single leaf above the trunk and a plus around its top, then rings of radius 1 and 2 taking turns
+/// down to the third log. The corners of the wide rings are cut off.
+fn spruce_canopy(offset: IVec3, top: i32) -> bool {
+    let layer = offset.y - top;
+    let (dx, dz) = (offset.x.abs(), offset.z.abs());
+    match layer {
+        1 => dx == 0 && dz == 0,
+        0 => dx + dz <= 1,
+        _ if layer < 0 && offset.y >= 2 => {
+            if layer % 2 == 0 {
+                dx <= CANOPY_RADIUS && dz <= CANOPY_RADIUS && dx + dz < 2 * CANOPY_RADIUS
+            } else {
+                dx <= 1 && dz <= 1
+            }
+        }
+        _ => false,
+    }
+}
+
+/// A tree growing out of a grass block. Its blocks can reach into the neighbouring chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tree {
+    /// World position of the lowest log.
+    pub base: IVec3,
+    pub trunk_height: i32,
+    pub species: TreeSpecies,
+}
+
+impl Tree {
+    /// The block the tree puts at a world position, if any.
+    pub fn block_at(&self, pos: IVec3) -> Option<BlockType> {
+        let offset = pos - self.base;
+        let top = self.trunk_height - 1;
+        if offset.x == 0 && offset.z == 0 && (0..=top).contains(&offset.y) {
+            return Some(self.species.log());
+        }
+        self.species
+            .canopy(offset, top, pos)
+            .then_some(self.species.leaves())
+    }
+
+    /// Every block of the tree, with its world position.
+    pub fn blocks(&self) -> impl Iterator<Item = (IVec3, BlockType)> + '_ {
+        let radius = CANOPY_RADIUS;
+        (-radius..=radius)
+            .flat_map(move |x| (-radius..=radius).map(move |z| (x, z)))
+            .flat_map(move |(x, z)| (0..=self.trunk_height + 1).map(move |y| IVec3::new(x, y, z)))
+            .filter_map(move |offset| {
+                let pos = self.base + offset;
+                self.block_at(pos).map(|block| (pos, block))
+            })
+    }
+}
+
+/// Rolls for a tree in a column: a number below 10 000 to compare with the chance, and one for the trunk height.
+fn tree_roll(column: IVec2XZ, seed: u32) -> (u64, u64) {
+    let mut rng = rng_for(
+        feature_seed(seed, TREE_SALT),
+        IVec3::new(column.x, 0, column.z),
+    );
+    let chance_roll = rng.below(10_000);
+    (chance_roll, rng.next_u64())
+}
+
+/// Whether a column is in (or right next to) the pond of its chunk. Trees would float over the water.
+fn near_pond(column: IVec2XZ, seed: u32) -> bool {
+    let (chunk, local) = world_to_chunk_and_local(IVec3::new(column.x, 0, column.z));
+    pond_for_chunk(chunk, seed).is_some_and(|pond| pond.near(local.x as usize, local.z as usize))
+}
+
+/// The trees starting in a square of columns.
+fn trees_in(
+    min: IVec2XZ,
+    width: i32,
+    seed: u32,
+    biome_noise: &BiomeNoise,
+    ground: impl Fn(IVec2XZ) -> Option<i32>,
+) -> Vec<Tree> {
+    let beats = |chance_roll: u64, chance: f32| (chance_roll as f32) < chance * 10_000.0;
+    let max_chance = Biome::max_tree_chance();
+
+    (min.x..min.x + width)
+        .flat_map(|x| (min.z..min.z + width).map(move |z| IVec2XZ::new(x, z)))
+        .filter_map(|column| {
+            let (chance_roll, height_roll) = tree_roll(column, seed);
+            // Most columns are ruled out before sampling the biome.
+            if !beats(chance_roll, max_chance) {
+                return None;
+            }
+            let biome = biome_noise.biome_at(column);
+            let species = biome.tree_species()?;
+            if !beats(chance_roll, biome.tree_chance()) || near_pond(column, seed) {
+                return None;
+            }
+            let y = ground(column)?;
+            let (shortest, tallest) = species.trunk_heights();
+            Some(Tree {
+                base: IVec3::new(column.x, y + 1, column.z),
+                trunk_height: shortest + (height_roll % (tallest - shortest + 1) as u64) as i32,
+                species,
+            })
+        })
+        .collect()
+}
+
+/// The trees that put blocks into a chunk, or into the ring of blocks right around it.
+///
+/// `ground` returns the y of the grass block a tree in the column would grow from, or None if it can't grow there.
+pub fn trees_near(
+    chunk_position: IVec2XZ,
+    seed: u32,
+    biome_noise: &BiomeNoise,
+    ground: impl Fn(IVec2XZ) -> Option<i32>,
+) -> Vec<Tree> {
+    // One more than the canopy, so the mesher can see the leaves just past the border.
+    let margin = CANOPY_RADIUS + 1;
+    let min = IVec2XZ::new(
+        chunk_position.x * CHUNK_SIZE as i32 - margin,
+        chunk_position.z * CHUNK_SIZE as i32 - margin,
+    );
+    trees_in(
+        min,
+        CHUNK_SIZE as i32 + 2 * margin,
+        seed,
+        biome_noise,
+        ground,
+    )
+}
+
+/// The trees that can put blocks into a column.
+pub fn trees_around(
+    column: IVec2XZ,
+    seed: u32,
+    biome_noise: &BiomeNoise,
+    ground: impl Fn(IVec2XZ) -> Option<i32>,
+) -> Vec<Tree> {
+    let min = column - IVec2XZ::new(CANOPY_RADIUS, CANOPY_RADIUS);
+    trees_in(min, 2 * CANOPY_RADIUS + 1, seed, biome_noise, ground)
+}
+
+/// The block the trees put at a world position, if any. Logs win over leaves.
+pub fn tree_block_at(trees: &[Tree], pos: IVec3) -> Option<BlockType> {
+    trees
+        .iter()
+        .filter_map(|tree| tree.block_at(pos))
+        .max_by_key(|&block| block.is_log())
+}
+
+/// Writes the trees into the chunk. Logs replace air and leaves, leaves only replace air,
+/// so the order the trees are placed in doesn't matter.
+pub fn place_trees(chunk_position: IVec2XZ, trees: &[Tree], blocks: &mut ChunkBlocks) {
+    let origin = IVec3::new(
+        chunk_position.x * CHUNK_SIZE as i32,
+        0,
+        chunk_position.z * CHUNK_SIZE as i32,
+    );
+    let size = IVec3::new(CHUNK_SIZE as i32, CHUNK_HEIGHT as i32, CHUNK_SIZE as i32);
+    for tree in trees {
+        for (pos, block) in tree.blocks() {
+            let local = pos - origin;
+            if local.cmplt(IVec3::ZERO).any() || local.cmpge(size).any() {
+                continue;
+            }
+            let (x, y, z) = (local.x as usize, local.y as usize, local.z as usize);
+            let current = blocks.get(x, y, z);
+            if current == BlockType::Air || block.is_log() && current.is_leaves() {
+                blocks.set(x, y, z, block);
+            }
+        }
+    }
+}
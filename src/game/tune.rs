@@ -0,0 +1,156 @@
+use std::{collections::BTreeMap, fs};
+
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::game::freecam::CameraMode;
+use crate::game::rules::{WorldGenParams, WorldSeed};
+use crate::prelude::*;
+
+/// Where the presets of the generation parameters are stored, relative to the working directory.
+pub const PRESETS_PATH: &str = "presets.ron";
+
+/// `--tune` opens the world in the freecam, with a side panel of sliders for the generation parameters. The world
+/// around the camera is generated again whenever one of them moves.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TuneMode;
+
+impl TuneMode {
+    /// Whether the `--tune` flag is given.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        args.iter().any(|arg| arg == "--tune").then_some(Self)
+    }
+}
+
+/// Named snapshots of the generation parameters, kept in `presets.ron`.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(transparent)]
+pub struct Presets(pub BTreeMap<String, WorldGenParams>);
+
+impl Presets {
+    /// Reads the presets, there are none if the file is missing or invalid.
+    pub fn load() -> Self {
+        match fs::read_to_string(PRESETS_PATH) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!(
+                    "Invalid {}, starting without presets: {}",
+                    PRESETS_PATH, err
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!("Failed to serialize the presets: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = fs::write(PRESETS_PATH, contents) {
+            warn!("Failed to write {}: {}", PRESETS_PATH, err);
+        }
+    }
+}
+
+/// The state of the side panel between frames.
+#[derive(Resource, Default, Debug)]
+pub struct TunePanel {
+    /// The name the current parameters are saved under.
+    pub preset_name: String,
+    /// Whether the mouse is over the panel, so clicking it doesn't grab the cursor.
+    pub pointer_over: bool,
+}
+
+pub fn load_presets(mut commands: Commands) {
+    let presets = Presets::load();
+    info!("Presets: {}", presets.0.len());
+    commands.insert_resource(presets);
+}
+
+/// Hands the camera over to the freecam, with the chunks streaming around it. The player stays where it spawned.
+pub fn start_tuning(
+    mut mode: ResMut<CameraMode>,
+    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+) {
+    if !mode.is_freecam() {
+        mode.toggle(&mut camera_query.single_mut());
+    }
+    mode.stream_from_camera = true;
+}
+
+/// The sliders for the generation parameters, and the buttons for the seed and the presets. Only a change to the
+/// parameters touches the resource, so the world is regenerated when something actually moved.
+pub fn tune_panel(
+    mut contexts: EguiContexts,
+    (mut params, mut seed): (ResMut<WorldGenParams>, ResMut<WorldSeed>),
+    (mut presets, mut panel): (ResMut<Presets>, ResMut<TunePanel>),
+) {
+    let ctx = contexts.ctx_mut();
+    let mut tuned = params.clone();
+    let panel = &mut *panel;
+    egui::SidePanel::left("tune").show(ctx, |ui| {
+        ui.heading("World generation");
+        ui.horizontal(|ui| {
+            ui.label(format!("Seed: {}", seed.0));
+            if ui.button("Randomize").clicked() {
+                *seed = WorldSeed::default();
+            }
+        });
+        ui.separator();
+
+        ui.add(
+            egui::Slider::new(&mut tuned.frequency, 0.001..=0.05)
+                .logarithmic(true)
+                .text("Surface scale"),
+        );
+        ui.add(egui::Slider::new(&mut tuned.octaves, 1..=8).text("Octaves"));
+        ui.add(egui::Slider::new(&mut tuned.persistence, 0.1..=1.5).text("Persistence"));
+        ui.add(egui::Slider::new(&mut tuned.lacunarity, 1.0..=4.0).text("Lacunarity"));
+        ui.add(
+            egui::Slider::new(&mut tuned.cave_scale, 0.01..=0.2)
+                .logarithmic(true)
+                .text("Cave scale"),
+        );
+        ui.add(egui::Slider::new(&mut tuned.cave_threshold, 0.0..=1.0).text("Cave threshold"));
+        ui.add(
+            egui::Slider::new(&mut tuned.water_height, 1..=CHUNK_HEIGHT - 1).text("Water level"),
+        );
+        ui.add(
+            egui::Slider::new(&mut tuned.biome_scale, 0.0002..=0.02)
+                .logarithmic(true)
+                .text("Biome scale"),
+        );
+        if ui.button("Reset to the defaults").clicked() {
+            tuned = WorldGenParams::default();
+        }
+        ui.separator();
+
+        ui.heading("Presets");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut panel.preset_name);
+            let name = panel.preset_name.trim();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                .clicked()
+            {
+                presets.0.insert(name.to_string(), tuned.clone());
+                presets.save();
+            }
+        });
+        for (name, preset) in &presets.0 {
+            if ui.button(format!("Load {}", name)).clicked() {
+                tuned = preset.clone();
+                panel.preset_name = name.clone();
+            }
+        }
+    });
+    panel.pointer_over = ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+
+    if tuned != *params {
+        *params = tuned;
+    }
+}
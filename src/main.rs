@@ -1,56 +1,192 @@
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::diagnostic::SystemInformationDiagnosticsPlugin;
 use bevy::prelude::*;
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
 use bevy_atmosphere::prelude::*;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_prototype_debug_lines::*;
 use bevy_rapier3d::prelude::*;
-use color_eyre::eyre::Result;
-use std::collections::HashSet;
+use color_eyre::eyre::{eyre, Result};
 
 mod prelude;
 
 mod game;
+use game::atlas::{apply_texture_filter, load_block_atlas};
+use game::budget::*;
 use game::camera::*;
+use game::capture::clean_capture;
 use game::chunk::chunk_system;
-use game::chunk::handle_mesh_tasks;
+use game::chunk::spawn_chunk_tasks;
+use game::chunk::update_chunk_colliders;
+use game::chunk::update_spawn_chunks;
+use game::chunk::ChunkMaterials;
+use game::chunk::{catch_up_after_pauses, handle_mesh_tasks, remesh_rescaled_chunks};
 use game::common::*;
+use game::cracks::{setup_crack_assets, update_crack_overlay};
+use game::debug::apply_fullbright;
+use game::debug::apply_mesh_options;
 use game::debug::chunk_border;
 use game::debug::debug_keyboard;
+use game::debug::detect_debug_chords;
+use game::debug::highlight_targeted_block;
+use game::debug::DebugChords;
+use game::edits::EditHistory;
+use game::environment::*;
+use game::flight::*;
+use game::fluid_material::{FluidMaterial, FluidMaterialPlugin};
+use game::freecam::*;
+use game::generator::setup_world_generator;
+use game::hints::*;
+use game::hud::apply_hud_palette;
+use game::hud::count_chunk_activity;
+use game::hud::count_chunk_meshes;
 use game::hud::setup_hud;
+use game::hud::toggle_controls_text;
+use game::hud::update_target_tooltip;
 use game::hud::update_text;
+use game::hud::ChunkActivity;
+use game::interaction::*;
+use game::leaves::{decay_leaves, LeafDecay};
+use game::mobs::*;
+use game::packed::{MeshStats, PackedChunkMaterial, PackedMeshPlugin};
+use game::projectiles::*;
+use game::report::*;
+use game::rules::{
+    load_world_rules, load_world_seed, load_world_type, regenerate_on_seed_change, WorldGenParams,
+    WorldSeed,
+};
+use game::schematic::*;
+use game::settings::*;
+use game::sky::*;
+use game::soak::*;
+use game::stamp::*;
+use game::status::*;
+use game::suffocation::*;
+use game::target::*;
+use game::tune::*;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    let window = WindowPlugin {
-        primary_window: Some(Window {
-            title: "Bevy - Voxel game".into(),
-            resolution: (1280., 720.).into(),
-            resizable: true,
-            mode: bevy::window::WindowMode::Windowed,
-            ..default()
-        }),
-        ..default()
-    };
+    let args: Vec<String> = std::env::args().collect();
+    let soak = SoakTest::from_args(&args)
+        .transpose()
+        .map_err(|err| eyre!("Invalid --soak: {}", err))?;
+    let report = BugReport::from_args(&args)
+        .transpose()
+        .map_err(|err| eyre!("Invalid --load-report: {}", err))?;
+    let schematic = Schematic::from_args(&args)
+        .transpose()
+        .map_err(|err| eyre!("Invalid --schematic: {}", err))?;
+    let tune = TuneMode::from_args(&args);
 
-    App::new()
-        .insert_resource(Msaa::Sample2)
+    let mut app = App::new();
+    app.insert_resource(Msaa::Sample2)
         .insert_resource(PlayerPos {
             pos: Vec3::new(0.0, 0.0, 0.0),
             rot: Quat::IDENTITY,
-        })
-        .add_plugins(
+        });
+    if let Some(report) = report {
+        app.insert_resource(LoadedReport(report));
+    }
+    if let Some(schematic) = schematic {
+        app.insert_resource(LoadedSchematic(schematic));
+    }
+
+    if let Some(soak) = soak {
+        // Nothing is drawn and the frames run back to back. The window is never opened without winit, it's
+        // only there for the systems that read its settings.
+        count_panics();
+        let headless = WindowPlugin {
+            primary_window: Some(default()),
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        };
+        let no_gpu = RenderPlugin {
+            wgpu_settings: WgpuSettings {
+                backends: None,
+                ..default()
+            },
+        };
+        app.add_plugins(
+            DefaultPlugins
+                .set(ImagePlugin::default_nearest())
+                .set(headless)
+                .set(no_gpu)
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
+        .insert_resource(soak)
+        .add_systems(Update, fly_soak_path.before(update_camera))
+        // After the chunks that finished this frame got their meshes.
+        .add_systems(PostUpdate, finish_soak_test);
+    } else {
+        let window = WindowPlugin {
+            primary_window: Some(Window {
+                title: "Bevy - Voxel game".into(),
+                resolution: (1280., 720.).into(),
+                resizable: true,
+                mode: bevy::window::WindowMode::Windowed,
+                ..default()
+            }),
+            ..default()
+        };
+        app.add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
                 .set(window),
         )
-        // == Plugins ==
         .add_plugins(WorldInspectorPlugin::new())
-        .add_plugins(FrameTimeDiagnosticsPlugin)
-        .add_plugins(SystemInformationDiagnosticsPlugin)
         .add_plugins(DebugLinesPlugin::with_depth_test(true))
         .add_plugins(AtmospherePlugin)
+        // Nobody throws anything or fights in the soak test, and the shared meshes would count as leaked there.
+        .add_systems(Startup, (setup_projectile_assets, setup_hostile_assets))
+        .add_systems(PostStartup, setup_crack_assets)
+        .add_systems(
+            Update,
+            (
+                (throw_projectile, move_projectiles, update_impact_particles)
+                    .chain()
+                    .after(update_camera),
+                clean_capture,
+                write_bug_report.after(detect_debug_chords),
+                // Nothing is edited in the soak test, it only checks the streaming.
+                (
+                    (break_targeted_block, place_targeted_block)
+                        .before(cursor_grab_system)
+                        .after(update_sneaking),
+                    open_interacted_blocks.after(place_targeted_block),
+                    update_crack_overlay.after(break_targeted_block),
+                    undo_edits,
+                    cycle_selected_block,
+                ),
+                highlight_targeted_block.after(update_camera),
+                (grow_targeted_tree, paste_targeted_schematic)
+                    .after(detect_debug_chords)
+                    .after(update_targeted_block),
+                (spawn_hostiles, despawn_dead_hostiles).before(despawn_peaceful_hostiles),
+            ),
+        );
+        if let Some(tune) = tune {
+            // The panel is drawn with the egui of the inspector.
+            app.insert_resource(tune)
+                .init_resource::<TunePanel>()
+                .add_systems(Startup, load_presets)
+                .add_systems(PostStartup, start_tuning.after(move_to_spawn_point))
+                .add_systems(Update, tune_panel.before(cursor_grab_system));
+        }
+    }
+
+    app
+        // == Plugins ==
+        .add_plugins(FrameTimeDiagnosticsPlugin)
+        .add_plugins(SystemInformationDiagnosticsPlugin)
         // .add_plugins(RapierDebugRenderPlugin
         //     {
         //         enabled: true,
@@ -60,29 +196,151 @@ fn main() -> Result<()> {
         // )
         // Rapier
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_plugins(FluidMaterialPlugin)
+        .add_plugins(PackedMeshPlugin)
         // .add_plugins(RapierDebugRenderPlugin::default())
         // == Resources ==
-        .insert_resource(ChunksLoaded {
-            chunks: HashSet::new(),
-        })
+        .init_resource::<ChunksLoaded>()
+        .init_resource::<ChunkLoader>()
         .insert_resource(Generating(true))
         .insert_resource(ChunkBorderToggled(true))
+        .init_resource::<Fullbright>()
+        .init_resource::<AmbientOcclusion>()
         .init_resource::<InputState>()
+        .init_resource::<EntityBudget>()
+        .init_resource::<HintQueue>()
+        .init_resource::<SeenHints>()
+        .init_resource::<ChunkMap>()
+        .init_resource::<MeshCache>()
+        .init_resource::<MeshInsertBudget>()
+        .init_resource::<TargetedBlock>()
+        .init_resource::<EditHistory>()
+        .init_resource::<LeafDecay>()
+        .init_resource::<SelectedBlock>()
+        .init_resource::<Sneaking>()
+        .init_resource::<BreakProgress>()
+        .init_resource::<PlayerPhysicsConfig>()
+        .init_resource::<Flight>()
+        .init_resource::<CameraMode>()
+        .init_resource::<DebugChords>()
+        .init_resource::<EnvironmentSample>()
+        .init_resource::<WorldGenParams>()
+        .init_resource::<ChunkActivity>()
+        .init_resource::<MeshStats>()
+        .add_event::<DamageEvent>()
+        .add_event::<BlockInteracted>()
+        .add_event::<ChunkLoadRequested>()
+        .add_event::<ChunkReady>()
+        .add_event::<ChunkUnloaded>()
+        .add_event::<ChunkRescaled>()
+        .register_type::<Settings>()
+        .register_type::<WorldSeed>()
+        .register_type::<WorldGenParams>()
         // == Systems ==
-        .add_systems(Startup, (setup, setup_hud, spawn_player))
+        .add_systems(
+            PreStartup,
+            (
+                (
+                    load_settings,
+                    load_world_rules,
+                    load_world_seed,
+                    load_world_type,
+                ),
+                apply_deferred,
+                apply_loaded_report.run_if(resource_exists::<LoadedReport>()),
+            )
+                .chain(),
+        )
+        .add_systems(
+            Startup,
+            (
+                (
+                    setup,
+                    apply_deferred,
+                    (
+                        setup_eye_overlay,
+                        setup_fire_overlay,
+                        setup_environment_overlays,
+                    ),
+                )
+                    .chain(),
+                setup_hud,
+                setup_hints,
+                setup_fly_speed_text,
+                spawn_player,
+                setup_sky,
+                setup_world_generator,
+            ),
+        )
+        // The leaves decay at the same pace at any frame rate.
+        .add_systems(FixedUpdate, decay_leaves)
+        .add_systems(
+            PostStartup,
+            (
+                move_to_spawn_point,
+                move_to_report_position.run_if(resource_exists::<LoadedReport>()),
+            )
+                .chain(),
+        )
         .add_systems(
             Update,
             (
-                chunk_border,
-                debug_keyboard,
-                update_text,
-                chunk_system,
-                handle_mesh_tasks,
+                chunk_border.run_if(resource_exists::<DebugLines>()),
+                (detect_debug_chords, debug_keyboard).chain(),
+                (apply_fullbright, apply_mesh_options, apply_texture_filter).after(debug_keyboard),
+                (count_chunk_activity, count_chunk_meshes, update_text).chain(),
+                watch_settings,
+                apply_hud_palette.after(update_text),
+                toggle_controls_text,
+                (update_targeted_block, update_target_tooltip).chain(),
+                (trigger_hints, update_hint_text).chain(),
+                (
+                    regenerate_on_seed_change,
+                    update_spawn_chunks,
+                    chunk_system,
+                    spawn_chunk_tasks,
+                )
+                    .chain(),
+                (
+                    catch_up_after_pauses,
+                    handle_mesh_tasks,
+                    remesh_rescaled_chunks,
+                    update_chunk_colliders,
+                )
+                    .chain(),
                 cursor_grab_system,
-                move_player,
+                (adjust_fly_speed, update_fly_speed_text).chain(),
+                (update_sneaking, move_player).chain(),
                 player_look,
-                update_camera,
+                (toggle_freecam, move_freecam, update_camera)
+                    .chain()
+                    .after(detect_debug_chords),
+                (
+                    (push_out_of_blocks, suffocate_in_blocks).chain(),
+                    (
+                        burn_in_lava,
+                        tick_status_effect::<Burning>,
+                        tick_status_effect::<Suffocating>,
+                        apply_damage,
+                        update_fire_overlay,
+                    )
+                        .chain()
+                        .after(suffocate_in_blocks),
+                ),
+                (
+                    update_eye_overlay,
+                    (apply_sky_mode, update_sky, update_underwater_fog).chain(),
+                    (update_environment_sample, update_environment_effects).chain(),
+                )
+                    .after(update_camera),
                 read_result_system,
+                (
+                    despawn_peaceful_hostiles,
+                    despawn_distant_entities,
+                    apply_deferred,
+                    enforce_entity_budget,
+                )
+                    .chain(),
             ),
         )
         .run();
@@ -92,14 +350,26 @@ fn main() -> Result<()> {
 
 fn setup(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    (mut packed_materials, mut fluid_materials): (
+        ResMut<Assets<PackedChunkMaterial>>,
+        ResMut<Assets<FluidMaterial>>,
+    ),
+    mut images: ResMut<Assets<Image>>,
     mut grav_scale: Query<&mut GravityScale>,
+    settings: Res<Settings>,
 ) {
-    // Setup texture atlas
-    let texture_handle = asset_server.load("textures/blocks.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2::new(32.0, 32.0), 7, 7, None, None); //c2 r3
-    commands.insert_resource(GameTextureAtlas(texture_atlas));
+    // Setup texture atlas, it's needed before the first chunk is meshed so it's built right away.
+    let game_atlas = load_block_atlas(&mut images, &settings.graphics);
+    // Every chunk is drawn with the same few materials.
+    commands.insert_resource(ChunkMaterials::new(
+        &game_atlas,
+        &mut materials,
+        &mut packed_materials,
+        &mut fluid_materials,
+    ));
+    commands.insert_resource(game_atlas);
+    commands.insert_resource(PackedMeshes(settings.graphics.packed_meshes));
 
     // Sun
     let sun_light: f32 = 0.8;
@@ -1,6 +1,7 @@
 // This will make these modules available to all other modules in the crate
 
 pub(crate) use crate::game::common::*;
+pub(crate) use crate::game::settings::*;
 pub(crate) use bevy::prelude::*;
 pub(crate) use bevy_rapier3d::prelude::*;
 pub(crate) use noise::NoiseFn;
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::render::render_resource::{Extent3d, FilterMode, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+
+use crate::game::atlas::{
+    apply_texture_filter, build_block_atlas, missing_texture, read_textures, with_gutter,
+    AtlasError, ATLAS_GUTTER, BLOCK_TEXTURES_DIR, MISSING_TEXTURE,
+};
+use crate::prelude::*;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// An app with nothing but the images, for the atlas builder to put its textures in.
+fn image_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .add_asset::<Image>();
+    app
+}
+
+fn filled(size: u32, color: [u8; 4]) -> Image {
+    Image::new_fill(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &color,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn pixel(image: &Image, x: u32, y: u32) -> [u8; 4] {
+    let width = image.texture_descriptor.size.width;
+    let start = ((y * width + x) * 4) as usize;
+    image.data[start..start + 4].try_into().unwrap()
+}
+
+/// A 2x2 texture with a different colour in each corner.
+fn quarters() -> Image {
+    Image::new(
+        Extent3d {
+            width: 2,
+            height: 2,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        [RED, GREEN, BLUE, WHITE].concat(),
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn block_textures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join(BLOCK_TEXTURES_DIR)
+}
+
+#[test]
+fn textures_are_found_by_name_in_the_atlas() {
+    let mut app = image_app();
+    let mut images = app.world.resource_mut::<Assets<Image>>();
+    let textures = vec![
+        ("stone".to_string(), filled(32, RED)),
+        ("grass_top".to_string(), filled(16, BLUE)),
+    ];
+    let atlas = build_block_atlas(textures, &mut images).unwrap();
+
+    let stone = atlas.index("stone").unwrap();
+    let grass_top = atlas.index("grass_top").unwrap();
+    let missing = atlas.index(MISSING_TEXTURE).unwrap();
+    assert_eq!(atlas.atlas.len(), 3);
+    assert_ne!(stone, grass_top);
+    assert_eq!(atlas.index("dirt"), None);
+
+    // Each texture is copied into its own tile.
+    let image = images.get(&atlas.atlas.texture).unwrap();
+    let rect = atlas.atlas.textures[grass_top];
+    assert_eq!(rect.size(), Vec2::splat(16.0));
+    assert_eq!(pixel(image, rect.min.x as u32, rect.min.y as u32), BLUE);
+    let rect = atlas.atlas.textures[stone];
+    assert_eq!(
+        pixel(image, rect.max.x as u32 - 1, rect.max.y as u32 - 1),
+        RED
+    );
+    // Only the atlas is kept, the textures were copied out of.
+    assert_eq!(images.len(), 1);
+
+    // The blocks use the textures by name, and the checker for those that aren't there.
+    assert_eq!(atlas.tiles(BlockType::Stone, BlockFace::Left), &[stone]);
+    assert_eq!(atlas.tiles(BlockType::Grass, BlockFace::Top), &[grass_top]);
+    assert_eq!(atlas.tiles(BlockType::Grass, BlockFace::Left), &[missing]);
+    assert_eq!(atlas.tiles(BlockType::Dirt, BlockFace::Bottom), &[missing]);
+}
+
+#[test]
+fn missing_textures_are_drawn_with_the_checker() {
+    let grid = TextureAtlas::from_grid(Handle::default(), Vec2::new(32.0, 32.0), 2, 2, None, None);
+    let names = [("stone".to_string(), 1), (MISSING_TEXTURE.to_string(), 3)]
+        .into_iter()
+        .collect();
+    let atlas = GameTextureAtlas::new(grid, names, 3);
+    assert_eq!(atlas.tiles(BlockType::Stone, BlockFace::Top), &[1]);
+    for block in [BlockType::Air, BlockType::Log, BlockType::Cobblestone] {
+        for face in BlockFace::ALL {
+            assert_eq!(atlas.tiles(block, face), &[3], "{:?} {:?}", block, face);
+        }
+    }
+
+    let checker = missing_texture();
+    assert_eq!(checker.texture_descriptor.size.width, 32);
+    assert_eq!(pixel(&checker, 0, 0), [255, 0, 255, 255]);
+    assert_eq!(pixel(&checker, 8, 0), [0, 0, 0, 255]);
+    assert_eq!(pixel(&checker, 8, 8), [255, 0, 255, 255]);
+}
+
+#[test]
+fn unreadable_textures_are_left_out() {
+    let dir = std::env::temp_dir().join(format!("voxel-textures-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let stone = block_textures_dir().join("stone.png");
+    fs::copy(&stone, dir.join("stone.png")).unwrap();
+    fs::copy(&stone, dir.join("a_copy.png")).unwrap();
+    // The checker's name is taken, and a file that isn't an image can't be read.
+    fs::copy(&stone, dir.join(format!("{}.png", MISSING_TEXTURE))).unwrap();
+    fs::write(dir.join("broken.png"), b"not a png").unwrap();
+    fs::write(dir.join("notes.txt"), b"not a texture").unwrap();
+
+    let textures = read_textures(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+    let names: Vec<String> = textures
+        .unwrap()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(names, ["a_copy", "stone"]);
+
+    assert!(matches!(
+        read_textures(&dir.join("nowhere")),
+        Err(AtlasError::Io(..))
+    ));
+}
+
+#[test]
+fn every_block_texture_has_a_file() {
+    let textures = read_textures(&block_textures_dir()).unwrap();
+    for (name, texture) in &textures {
+        assert_eq!(
+            texture.texture_descriptor.size,
+            Extent3d {
+                width: 32,
+                height: 32,
+                depth_or_array_layers: 1,
+            },
+            "{}",
+            name
+        );
+    }
+
+    let mut app = image_app();
+    let mut images = app.world.resource_mut::<Assets<Image>>();
+    let atlas = build_block_atlas(textures, &mut images).unwrap();
+    let missing = atlas.index(MISSING_TEXTURE).unwrap();
+    for block in BlockType::ALL
+        .into_iter()
+        .filter(|&block| block != BlockType::Air)
+    {
+        for face in BlockFace::ALL {
+            assert!(
+                !atlas.tiles(block, face).contains(&missing),
+                "{:?} {:?} has no texture",
+                block,
+                face
+            );
+        }
+    }
+}
+
+#[test]
+fn tiles_are_surrounded_by_copies_of_their_edges() {
+    let padded = with_gutter(&quarters(), 2);
+    assert_eq!(padded.texture_descriptor.size.width, 6);
+    assert_eq!(padded.texture_descriptor.size.height, 6);
+    // Every pixel of the gutter is the closest pixel of the texture.
+    for y in 0..6 {
+        for x in 0..6 {
+            let expected = match (x < 3, y < 3) {
+                (true, true) => RED,
+                (false, true) => GREEN,
+                (true, false) => BLUE,
+                (false, false) => WHITE,
+            };
+            assert_eq!(pixel(&padded, x, y), expected, "({}, {})", x, y);
+        }
+    }
+
+    // In the atlas, the tile is only the texture, and right outside it are its edges.
+    let mut app = image_app();
+    let mut images = app.world.resource_mut::<Assets<Image>>();
+    let atlas = build_block_atlas(vec![("stone".to_string(), quarters())], &mut images).unwrap();
+    let image = images.get(&atlas.atlas.texture).unwrap();
+    let rect = atlas.atlas.textures[atlas.index("stone").unwrap()];
+    assert_eq!(rect.size(), Vec2::splat(2.0));
+    let (min, max) = (rect.min.as_uvec2(), rect.max.as_uvec2());
+    assert_eq!(pixel(image, min.x, min.y), RED);
+    for offset in 1..=ATLAS_GUTTER {
+        assert_eq!(pixel(image, min.x - offset, min.y - offset), RED);
+        assert_eq!(pixel(image, max.x - 1 + offset, min.y), GREEN);
+        assert_eq!(pixel(image, min.x, max.y - 1 + offset), BLUE);
+        assert_eq!(pixel(image, max.x - 1 + offset, max.y - 1 + offset), WHITE);
+    }
+}
+
+#[test]
+fn texture_coordinates_are_inset_into_the_tile() {
+    let grid = TextureAtlas::from_grid(Handle::default(), Vec2::new(16.0, 16.0), 2, 2, None, None);
+    let mut atlas = GameTextureAtlas::new(grid, HashMap::new(), 3);
+    assert_eq!(
+        atlas.tile_uvs(3),
+        [[0.5, 0.5], [1.0, 0.5], [1.0, 1.0], [0.5, 1.0]]
+    );
+
+    // Half a texel of a 16 texel tile in a 32 texel atlas.
+    atlas.uv_inset = 0.5;
+    let inset = 0.5 / 32.0;
+    assert_eq!(
+        atlas.tile_uvs(0),
+        [
+            [inset, inset],
+            [0.5 - inset, inset],
+            [0.5 - inset, 0.5 - inset],
+            [inset, 0.5 - inset],
+        ]
+    );
+}
+
+#[test]
+fn the_texture_filter_is_applied_to_the_atlas() {
+    let mut app = image_app();
+    let atlas =
+        build_block_atlas(Vec::new(), &mut app.world.resource_mut::<Assets<Image>>()).unwrap();
+    let handle = atlas.atlas.texture.clone();
+    app.insert_resource(atlas)
+        .init_resource::<Settings>()
+        .add_systems(Update, apply_texture_filter);
+
+    let mag_filter = |app: &App| {
+        let images = app.world.resource::<Assets<Image>>();
+        match &images.get(&handle).unwrap().sampler_descriptor {
+            ImageSampler::Descriptor(descriptor) => Some(descriptor.mag_filter),
+            ImageSampler::Default => None,
+        }
+    };
+    app.update();
+    assert_eq!(mag_filter(&app), Some(FilterMode::Nearest));
+
+    let mut settings = app.world.resource_mut::<Settings>();
+    settings.graphics.texture_filter = settings.graphics.texture_filter.toggled();
+    app.update();
+    assert_eq!(mag_filter(&app), Some(FilterMode::Linear));
+}
@@ -0,0 +1,99 @@
+use crate::game::biome::*;
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+/// The biome of a column, seen from a chunk. The local position can be outside of the chunk.
+fn biome_from_chunk(chunk: IVec2XZ, local_x: i32, local_z: i32) -> Biome {
+    biome_at(
+        IVec2XZ::new(
+            chunk.x * CHUNK_SIZE as i32 + local_x,
+            chunk.z * CHUNK_SIZE as i32 + local_z,
+        ),
+        SEED,
+    )
+}
+
+/// Pairs of neighbouring chunks whose corners aren't all in the same biome, along the x axis.
+fn border_chunks() -> Vec<(IVec2XZ, IVec2XZ)> {
+    (-64..64)
+        .map(|x| IVec2XZ::new(x, 0))
+        .filter(|&chunk| {
+            let corners =
+                [(0, 0), (0, 15), (31, 0), (31, 15)].map(|(x, z)| biome_from_chunk(chunk, x, z));
+            corners.iter().any(|&biome| biome != corners[0])
+        })
+        .map(|chunk| (chunk, chunk + IVec2XZ::new(1, 0)))
+        .collect()
+}
+
+#[test]
+fn every_biome_is_generated() {
+    let biomes: Vec<Biome> = (-32..32)
+        .flat_map(|x| (-32..32).map(move |z| biome_at(IVec2XZ::new(x * 64, z * 64), SEED)))
+        .collect();
+    for biome in Biome::ALL {
+        assert!(biomes.contains(&biome), "no {:?} on the grid", biome);
+    }
+}
+
+#[test]
+fn biome_noise_matches_biome_at() {
+    let noise = BiomeNoise::new(SEED);
+    for x in -20..20 {
+        for z in -20..20 {
+            let pos = IVec2XZ::new(x * 37, z * 53);
+            assert_eq!(noise.biome_at(pos), biome_at(pos, SEED));
+        }
+    }
+}
+
+#[test]
+fn biomes_agree_across_chunk_borders() {
+    let pairs = border_chunks();
+    assert!(!pairs.is_empty());
+    for (west, east) in pairs {
+        for z in 0..CHUNK_SIZE as i32 {
+            // The column right past the west chunk's border is the first column of the east chunk.
+            assert_eq!(
+                biome_from_chunk(west, CHUNK_SIZE as i32, z),
+                biome_from_chunk(east, 0, z)
+            );
+            assert_eq!(
+                biome_from_chunk(east, -1, z),
+                biome_from_chunk(west, CHUNK_SIZE as i32 - 1, z)
+            );
+        }
+    }
+}
+
+#[test]
+fn surface_follows_the_biome() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let pairs = border_chunks();
+    for &(west, east) in pairs.iter().take(3) {
+        for chunk in [west, east] {
+            let blocks = generator.generate_chunk(chunk);
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let biome = biome_from_chunk(chunk, x as i32, z as i32);
+                    let top = blocks
+                        .column(x, z)
+                        .iter()
+                        .rev()
+                        .copied()
+                        .find(|&block| block != BlockType::Air)
+                        .unwrap();
+                    if matches!(top, BlockType::Grass | BlockType::SnowyGrass) {
+                        assert_eq!(top, biome.surface_blocks().0, "{:?} ({}, {})", chunk, x, z);
+                    }
+                    if biome == Biome::Desert {
+                        assert_ne!(top, BlockType::Grass);
+                        assert_ne!(top, BlockType::Dirt);
+                    }
+                }
+            }
+        }
+    }
+}
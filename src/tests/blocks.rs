@@ -0,0 +1,407 @@
+use std::collections::HashSet;
+
+use crate::game::chunk::{face_visible, part_visible, pick_variant, texture_index};
+use crate::game::interaction::SelectedBlock;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+
+#[test]
+fn every_block_parses_from_its_names() {
+    for block in BlockType::ALL {
+        let name = block.display_name();
+        assert_eq!(BlockType::from_name(name), Some(block));
+        assert_eq!(BlockType::from_name(&name.to_uppercase()), Some(block));
+        assert_eq!(BlockType::from_name(&name.to_lowercase()), Some(block));
+        assert_eq!(
+            BlockType::from_name(&name.to_lowercase().replace(' ', "_")),
+            Some(block)
+        );
+        assert_eq!(BlockType::from_name(&format!("{:?}", block)), Some(block));
+    }
+}
+
+#[test]
+fn block_names_are_unique() {
+    for a in BlockType::ALL {
+        for b in BlockType::ALL {
+            if a != b {
+                assert_ne!(a.display_name(), b.display_name());
+            }
+        }
+    }
+}
+
+#[test]
+fn unknown_block_names_are_rejected() {
+    assert_eq!(BlockType::from_name(""), None);
+    assert_eq!(BlockType::from_name("diamond"), None);
+    assert_eq!(BlockType::from_name("stone ore"), None);
+}
+
+#[test]
+fn opposite_faces_pair_up() {
+    for face in BlockFace::ALL {
+        assert_ne!(face.opposite(), face);
+        assert_eq!(face.opposite().opposite(), face);
+    }
+}
+
+#[test]
+fn solid_cubes_cover_every_face() {
+    for block in BlockType::ALL {
+        for face in BlockFace::ALL {
+            // A slab covers the side it lies on, stairs that and their back too.
+            let floor = |top: bool| {
+                if top {
+                    BlockFace::Top
+                } else {
+                    BlockFace::Bottom
+                }
+            };
+            let covers = match block.shape() {
+                BlockShape::Cube => block.is_solid(),
+                BlockShape::Cross => false,
+                BlockShape::Slab { top } => face == floor(top),
+                BlockShape::Stairs { facing, top } => face == floor(top) || face == facing,
+            };
+            assert_eq!(block.covers_face(face), covers, "{:?} {:?}", block, face);
+        }
+    }
+}
+
+#[test]
+fn faces_are_culled_against_covering_neighbours() {
+    for face in BlockFace::ALL {
+        assert!(face_visible(BlockType::Stone, BlockType::Air, face));
+        assert!(face_visible(BlockType::Stone, BlockType::Water, face));
+        assert!(face_visible(BlockType::Water, BlockType::Lava, face));
+        assert!(!face_visible(BlockType::Stone, BlockType::Dirt, face));
+        assert!(!face_visible(BlockType::Water, BlockType::Stone, face));
+        assert!(!face_visible(BlockType::Water, BlockType::Water, face));
+        assert!(!face_visible(BlockType::Lava, BlockType::Lava, face));
+    }
+    // Ice is the top of the water under it, but its sides show against open water.
+    assert!(!face_visible(
+        BlockType::Ice,
+        BlockType::Water,
+        BlockFace::Bottom
+    ));
+    assert!(face_visible(
+        BlockType::Ice,
+        BlockType::Water,
+        BlockFace::Left
+    ));
+    assert!(!face_visible(
+        BlockType::Water,
+        BlockType::Ice,
+        BlockFace::Top
+    ));
+}
+
+#[test]
+fn slabs_only_cull_the_faces_under_them() {
+    let slab = BlockType::StoneSlab;
+    // The block under the slab is covered, the ones next to and over it show through the open half.
+    assert!(!face_visible(BlockType::Stone, slab, BlockFace::Top));
+    for face in [BlockFace::Left, BlockFace::Front, BlockFace::Bottom] {
+        assert!(face_visible(BlockType::Stone, slab, face), "{:?}", face);
+    }
+    // The top of a slab is in the middle of its cell, nothing covers it. Its sides and bottom are culled like a
+    // cube's.
+    assert!(face_visible(slab, BlockType::Stone, BlockFace::Top));
+    assert!(face_visible(slab, slab, BlockFace::Top));
+    assert!(!face_visible(slab, BlockType::Stone, BlockFace::Left));
+    assert!(!face_visible(slab, BlockType::Stone, BlockFace::Bottom));
+    // Two slabs side by side are one half-height floor, but a slab on a slab still shows its bottom over the gap.
+    assert!(!face_visible(slab, slab, BlockFace::Right));
+    assert!(face_visible(slab, slab, BlockFace::Bottom));
+}
+
+#[test]
+fn top_slabs_cull_the_faces_over_them() {
+    let slab = BlockType::StoneSlabTop;
+    assert!(!face_visible(BlockType::Stone, slab, BlockFace::Bottom));
+    assert!(face_visible(BlockType::Stone, slab, BlockFace::Top));
+    // Its bottom is in the middle of the cell, a bottom slab under it doesn't meet it.
+    assert!(face_visible(slab, BlockType::Stone, BlockFace::Bottom));
+    assert!(!face_visible(slab, BlockType::Stone, BlockFace::Top));
+    assert!(!face_visible(slab, slab, BlockFace::Left));
+    assert!(face_visible(slab, BlockType::StoneSlab, BlockFace::Left));
+    assert!(face_visible(BlockType::StoneSlab, slab, BlockFace::Top));
+}
+
+#[test]
+fn stairs_cull_with_their_back_and_their_slab() {
+    // The back of these stairs is on the back, they climb toward it.
+    let stairs = BlockType::StoneStairs;
+    assert_eq!(
+        stairs.shape(),
+        BlockShape::Stairs {
+            facing: BlockFace::Back,
+            top: false
+        }
+    );
+    // The block behind and the one under them are covered, the others see the step or the open half.
+    assert!(!face_visible(BlockType::Stone, stairs, BlockFace::Front));
+    assert!(!face_visible(BlockType::Stone, stairs, BlockFace::Top));
+    for face in [
+        BlockFace::Back,
+        BlockFace::Left,
+        BlockFace::Right,
+        BlockFace::Bottom,
+    ] {
+        assert!(face_visible(BlockType::Stone, stairs, face), "{:?}", face);
+    }
+    // Walled in by stone, only the top of the slab and the front of the step show.
+    for face in BlockFace::ALL {
+        assert_eq!(
+            face_visible(stairs, BlockType::Stone, face),
+            face == BlockFace::Top || face == BlockFace::Front,
+            "{:?}",
+            face
+        );
+    }
+    // Stairs side by side are one flight, their sides are hidden.
+    assert!(!face_visible(stairs, stairs, BlockFace::Left));
+    assert!(!face_visible(stairs, stairs, BlockFace::Right));
+    // In a row of them, the back of one covers the front of the next's slab, but its step stands over that slab.
+    let mut boxes = stairs.shape().boxes();
+    let (slab, step) = (boxes.next().unwrap(), boxes.next().unwrap());
+    assert!(!part_visible(stairs, stairs, BlockFace::Front, slab));
+    assert!(!part_visible(stairs, stairs, BlockFace::Back, slab));
+    assert!(part_visible(stairs, stairs, BlockFace::Back, step));
+    assert!(part_visible(stairs, stairs, BlockFace::Front, step));
+    // Turned another way they don't line up, and neither do upside down ones.
+    assert!(face_visible(
+        stairs,
+        BlockType::StoneStairsFront,
+        BlockFace::Left
+    ));
+    assert!(face_visible(
+        stairs,
+        BlockType::StoneStairsTop,
+        BlockFace::Left
+    ));
+    // Upside down, they cover the block over them and their back.
+    let hanging = BlockType::StoneStairsTop;
+    assert!(!face_visible(BlockType::Stone, hanging, BlockFace::Bottom));
+    assert!(face_visible(BlockType::Stone, hanging, BlockFace::Top));
+    assert!(!face_visible(BlockType::Stone, hanging, BlockFace::Front));
+}
+
+#[test]
+fn stairs_are_a_slab_and_a_step() {
+    for facing in [
+        BlockFace::Left,
+        BlockFace::Right,
+        BlockFace::Front,
+        BlockFace::Back,
+    ] {
+        for top in [false, true] {
+            let boxes: Vec<BlockBox> = BlockShape::stairs(facing, top).boxes().collect();
+            let (slab, step) = (boxes[0], boxes[1]);
+            assert_eq!(slab, BlockShape::Slab { top }.boxes().next().unwrap());
+            // The step is on the slab, on the half the back is on.
+            let onto = if top {
+                BlockFace::Bottom
+            } else {
+                BlockFace::Top
+            };
+            assert!(step.hidden_by(slab, onto.opposite()));
+            assert!(step.touches(facing) && step.touches(onto));
+            assert!(!step.touches(facing.opposite()));
+            let size = step.max - step.min;
+            assert_eq!(size.x * size.y * size.z, 0.25);
+        }
+    }
+}
+
+#[test]
+fn boxes_know_which_faces_of_the_cell_they_are_on() {
+    let bottom = BlockBox::half(BlockFace::Bottom);
+    assert_eq!(bottom.max, Vec3::new(1.0, 0.5, 1.0));
+    assert!(bottom.touches(BlockFace::Bottom) && bottom.touches(BlockFace::Left));
+    assert!(!bottom.touches(BlockFace::Top));
+    assert_eq!(bottom.plane(BlockFace::Top), 0.5);
+    // The bottom half of a side is inside the whole side, not the other way around.
+    assert!(bottom.within(BlockBox::CELL, BlockFace::Left));
+    assert!(!BlockBox::CELL.within(bottom, BlockFace::Left));
+    // Seen from above, the halves are the same square.
+    assert!(BlockBox::CELL.within(bottom, BlockFace::Top));
+    let quarter = BlockBox::half(BlockFace::Top).intersect(BlockBox::half(BlockFace::Front));
+    assert_eq!(quarter.min, Vec3::new(0.0, 0.5, 0.5));
+    assert!(quarter.hidden_by(bottom, BlockFace::Bottom));
+    assert!(!bottom.hidden_by(quarter, BlockFace::Top));
+}
+
+#[test]
+fn shaped_blocks_come_in_families() {
+    for block in BlockType::ALL {
+        let base = block.base();
+        assert_eq!(base.base(), base);
+        assert_eq!(base.with_shape(block.shape()), Some(block));
+    }
+    assert_eq!(
+        BlockType::StoneSlab.with_shape(BlockShape::Slab { top: true }),
+        Some(BlockType::StoneSlabTop)
+    );
+    assert_eq!(
+        BlockType::StoneStairsTop.with_shape(BlockShape::stairs(BlockFace::Left, false)),
+        Some(BlockType::StoneStairsLeft)
+    );
+    assert_eq!(BlockType::StoneSlab.with_shape(BlockShape::Cube), None);
+    // Only the base of a family is picked.
+    assert!(SelectedBlock::choices().any(|block| block == BlockType::StoneStairs));
+    assert!(!SelectedBlock::choices().any(|block| block == BlockType::StoneSlabTop));
+}
+
+#[test]
+fn texture_variants_are_stable_per_position() {
+    let variants = [1, 40, 41];
+    let mut used = [false; 3];
+    for x in -8..8 {
+        for z in -8..8 {
+            let pos = IVec3::new(x, 64, z);
+            let picked = pick_variant(&variants, pos);
+            assert_eq!(pick_variant(&variants, pos), picked);
+            let index = variants.iter().position(|&v| v == picked).unwrap();
+            used[index] = true;
+        }
+    }
+    assert!(used.iter().all(|&used| used));
+}
+
+#[test]
+fn neighbouring_blocks_pick_different_variants() {
+    let atlas = test_atlas();
+    for (block, face) in [
+        (BlockType::Stone, BlockFace::Front),
+        (BlockType::Dirt, BlockFace::Top),
+        (BlockType::Grass, BlockFace::Top),
+        (BlockType::Grass, BlockFace::Left),
+        (BlockType::Grass, BlockFace::Bottom),
+    ] {
+        let variants = atlas.tiles(block, face);
+        assert!(variants.len() >= 2, "{:?} {:?}", block, face);
+        // A single row of blocks along a wall already uses every one of them.
+        let picked: HashSet<usize> = (0..16)
+            .map(|x| texture_index(&atlas, block, face, IVec3::new(x, 70, 3)))
+            .collect();
+        assert_eq!(picked.len(), variants.len(), "{:?} {:?}", block, face);
+        assert!(
+            (0..16).any(|x| texture_index(&atlas, block, face, IVec3::new(x, 70, 3))
+                != texture_index(&atlas, block, face, IVec3::new(x + 1, 70, 3))),
+            "{:?} {:?}",
+            block,
+            face
+        );
+    }
+}
+
+#[test]
+fn single_texture_variants_are_always_used() {
+    let atlas = test_atlas();
+    for block in BlockType::ALL {
+        for face in BlockFace::ALL {
+            let variants = atlas.tiles(block, face);
+            assert!(!variants.is_empty());
+            if let [only] = variants {
+                assert_eq!(
+                    texture_index(&atlas, block, face, IVec3::new(-5, 70, 9)),
+                    *only
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn every_ore_has_its_own_texture() {
+    for ore in ORES.map(|veins| veins.ore) {
+        for other in BlockType::ALL.into_iter().filter(|&block| block != ore) {
+            assert_ne!(
+                ore.properties().textures.side,
+                other.properties().textures.side,
+                "{:?} looks like {:?}",
+                ore,
+                other
+            );
+        }
+    }
+}
+
+#[test]
+fn every_block_has_its_own_id() {
+    for (index, block) in BlockType::ALL.into_iter().enumerate() {
+        assert_eq!(BlockType::from_id(block.id()), Some(block));
+        for other in BlockType::ALL.into_iter().skip(index + 1) {
+            assert_ne!(block.id(), other.id(), "{:?} and {:?}", block, other);
+        }
+    }
+    // The ids are numbered from air up, without gaps.
+    let mut ids: Vec<u8> = BlockType::ALL.map(BlockType::id).to_vec();
+    ids.sort_unstable();
+    assert_eq!(ids, (0..BlockType::ALL.len() as u8).collect::<Vec<u8>>());
+}
+
+#[test]
+fn block_properties_agree_with_each_other() {
+    for block in BlockType::ALL {
+        let properties = block.properties();
+        assert_eq!(properties.id, block.id());
+        assert_eq!(properties.name, block.display_name());
+        let textures = properties.textures;
+        for names in [textures.top, textures.side, textures.bottom] {
+            assert!(!names.is_empty(), "{:?}", block);
+        }
+        assert!(!(properties.is_solid && properties.is_fluid), "{:?}", block);
+        assert!(
+            !(properties.is_solid && properties.is_transparent),
+            "{:?}",
+            block
+        );
+        if properties.shape == BlockShape::Cross {
+            assert!(!properties.is_solid && !properties.is_fluid, "{:?}", block);
+        }
+        if let Some(fluid) = properties.frozen_from {
+            assert!(fluid.is_fluid(), "{:?}", block);
+        }
+    }
+}
+
+#[test]
+fn cobblestone_is_a_placeable_stone() {
+    let cobblestone = BlockType::Cobblestone;
+    assert!(cobblestone.is_solid());
+    assert!(cobblestone.covers_face(BlockFace::Top));
+    assert_eq!(BlockType::from_name("cobblestone"), Some(cobblestone));
+    let atlas = test_atlas();
+    assert_eq!(
+        atlas.tiles(cobblestone, BlockFace::Top),
+        atlas.index("cobblestone").as_slice()
+    );
+    assert_ne!(
+        atlas.tiles(cobblestone, BlockFace::Front),
+        atlas.tiles(BlockType::Stone, BlockFace::Front)
+    );
+    assert!(SelectedBlock::choices().any(|block| block == cobblestone));
+}
+
+#[test]
+fn grass_has_a_top_side_and_bottom_and_only_the_top_is_tinted() {
+    let textures = BlockType::Grass.properties().textures;
+    assert_eq!(textures.top, ["grass_top", "grass_top_1", "grass_top_2"]);
+    assert_eq!(textures.side, ["grass_side", "grass_side_1"]);
+    assert_eq!(textures.bottom, BlockType::Dirt.properties().textures.top);
+    for face in BlockFace::ALL {
+        assert_eq!(
+            BlockType::Grass.properties().tint.applies(face),
+            face == BlockFace::Top
+        );
+    }
+    // Nothing else is tinted yet, the leaves keep the green of their texture.
+    assert!(BlockType::ALL
+        .into_iter()
+        .filter(|&block| block != BlockType::Grass)
+        .all(|block| block.properties().tint == Tint::None));
+}
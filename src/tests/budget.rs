@@ -0,0 +1,123 @@
+use crate::game::budget::*;
+use crate::prelude::*;
+
+fn test_world() -> World {
+    let mut world = World::new();
+    world.init_resource::<EntityBudget>();
+    world.insert_resource(PlayerPos {
+        pos: Vec3::ZERO,
+        rot: Quat::IDENTITY,
+    });
+    world
+}
+
+fn spawn(world: &mut World, category: EntityCategory, protected: bool, at: Vec3) -> Entity {
+    let budgeted = world.resource_mut::<EntityBudget>().track(category);
+    let mut entity = world.spawn((budgeted, Transform::from_translation(at)));
+    if protected {
+        entity.insert(Protected);
+    }
+    entity.id()
+}
+
+fn run_budget(world: &mut World) {
+    let mut schedule = Schedule::default();
+    schedule.add_systems(
+        (
+            despawn_distant_entities,
+            apply_deferred,
+            enforce_entity_budget,
+        )
+            .chain(),
+    );
+    schedule.run(world);
+}
+
+#[test]
+fn items_evict_oldest_first() {
+    let mut world = test_world();
+    let cap = world.resource::<EntityBudget>().items.cap;
+
+    let protected = spawn(&mut world, EntityCategory::Item, true, Vec3::ZERO);
+    let items: Vec<Entity> = (0..cap + 9)
+        .map(|_| spawn(&mut world, EntityCategory::Item, false, Vec3::ZERO))
+        .collect();
+    run_budget(&mut world);
+
+    // One protected plus cap + 9 items, so the ten oldest unprotected items go.
+    assert!(world.get_entity(protected).is_some());
+    assert!(items[..10].iter().all(|&e| world.get_entity(e).is_none()));
+    assert!(items[10..].iter().all(|&e| world.get_entity(e).is_some()));
+    assert_eq!(
+        world.resource::<EntityBudget>().count(EntityCategory::Item),
+        cap
+    );
+}
+
+#[test]
+fn mobs_over_the_cap_are_refused() {
+    let mut world = test_world();
+    let cap = world.resource::<EntityBudget>().mobs.cap;
+
+    let mobs: Vec<Entity> = (0..cap + 3)
+        .map(|_| spawn(&mut world, EntityCategory::Mob, false, Vec3::ZERO))
+        .collect();
+    run_budget(&mut world);
+
+    // The newest mobs are the ones that shouldn't have been spawned.
+    assert!(mobs[..cap].iter().all(|&e| world.get_entity(e).is_some()));
+    assert!(mobs[cap..].iter().all(|&e| world.get_entity(e).is_none()));
+    assert!(!world
+        .resource::<EntityBudget>()
+        .can_spawn(EntityCategory::Mob));
+    assert!(world
+        .resource::<EntityBudget>()
+        .can_spawn(EntityCategory::Particle));
+}
+
+#[test]
+fn protected_entities_are_never_evicted() {
+    let mut world = test_world();
+    let cap = world.resource::<EntityBudget>().particles.cap;
+
+    let protected: Vec<Entity> = (0..cap + 5)
+        .map(|_| spawn(&mut world, EntityCategory::Particle, true, Vec3::ZERO))
+        .collect();
+    let far_protected = spawn(
+        &mut world,
+        EntityCategory::Particle,
+        true,
+        Vec3::X * 10_000.0,
+    );
+    run_budget(&mut world);
+
+    assert!(protected.iter().all(|&e| world.get_entity(e).is_some()));
+    assert!(world.get_entity(far_protected).is_some());
+}
+
+#[test]
+fn distant_entities_are_despawned() {
+    let mut world = test_world();
+    let radius = world.resource::<EntityBudget>().items.despawn_distance;
+
+    let near = spawn(
+        &mut world,
+        EntityCategory::Item,
+        false,
+        Vec3::X * (radius - 1.0),
+    );
+    let far = spawn(
+        &mut world,
+        EntityCategory::Item,
+        false,
+        Vec3::X * (radius + 1.0),
+    );
+    run_budget(&mut world);
+
+    assert!(world.get_entity(near).is_some());
+    assert!(world.get_entity(far).is_none());
+    assert_eq!(
+        world.resource::<EntityBudget>().count(EntityCategory::Item),
+        1
+    );
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use bevy::render::mesh::Indices;
+
+use crate::game::chunk::{create_chunk_mesh, face_visible};
+use crate::game::generator::{cave_threshold, DefaultGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+use crate::tests::SEED;
+
+/// How deep a hole in the surface has to go to count as a cave entrance, not just a dent.
+const ENTRANCE_DEPTH: i32 = 4;
+
+#[test]
+fn caves_open_up_towards_the_surface() {
+    assert_eq!(
+        cave_threshold(CAVE_ENTRANCE_DEPTH, CAVE_THRESHOLD),
+        CAVE_THRESHOLD
+    );
+    assert_eq!(cave_threshold(100.0, CAVE_THRESHOLD), CAVE_THRESHOLD);
+    assert_eq!(cave_threshold(0.0, CAVE_THRESHOLD), CAVE_THRESHOLD_SURFACE);
+    let mut last = cave_threshold(0.0, CAVE_THRESHOLD);
+    for depth in 1..=CAVE_ENTRANCE_DEPTH as i32 {
+        let threshold = cave_threshold(depth as f32, CAVE_THRESHOLD);
+        assert!(
+            threshold >= last,
+            "the threshold goes down again at {}",
+            depth
+        );
+        last = threshold;
+    }
+}
+
+/// The first chunk along the x axis with a cave breaking through its surface, and the column of the entrance.
+fn chunk_with_entrance(generator: &DefaultGenerator) -> Option<(IVec2XZ, ChunkBlocks, IVec3)> {
+    (0..64).map(|x| IVec2XZ::new(x, 0)).find_map(|chunk| {
+        let blocks = generator.generate_chunk(chunk);
+        let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let top = generator.surface_y(IVec2XZ::new(origin.x + x, origin.z + z));
+                // Above the beaches, which caves never cut into.
+                if top < 72 {
+                    continue;
+                }
+                let open = (top - ENTRANCE_DEPTH..=top)
+                    .all(|y| blocks.get(x as usize, y as usize, z as usize) == BlockType::Air);
+                if open {
+                    return Some((chunk, blocks, IVec3::new(x, top, z)));
+                }
+            }
+        }
+        None
+    })
+}
+
+#[test]
+fn cave_entrances_are_meshed() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let (chunk, blocks, entrance) =
+        chunk_with_entrance(&generator).expect("no cave breaks through the surface");
+    let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+    let block_at = |pos: IVec3| {
+        if (0..CHUNK_SIZE as i32).contains(&pos.x) && (0..CHUNK_SIZE as i32).contains(&pos.z) {
+            if (0..CHUNK_HEIGHT as i32).contains(&pos.y) {
+                blocks.get(pos.x as usize, pos.y as usize, pos.z as usize)
+            } else {
+                BlockType::Air
+            }
+        } else {
+            generator.block_at(origin + pos)
+        }
+    };
+
+    // Every face of the chunk that borders something it doesn't hide behind, the walls of the entrance included.
+    let mut faces = 0;
+    let mut entrance_walls = 0;
+    for x in 0..CHUNK_SIZE as i32 {
+        for y in 0..CHUNK_HEIGHT as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let pos = IVec3::new(x, y, z);
+                let block = block_at(pos);
+                if block == BlockType::Air {
+                    continue;
+                }
+                // Plants are two quads, seen from both sides.
+                if block.shape() == BlockShape::Cross {
+                    faces += 4;
+                    continue;
+                }
+                for face in BlockFace::ALL {
+                    let neighbor = pos + face.offset();
+                    if face_visible(block, block_at(neighbor), face) {
+                        faces += 1;
+                        let shaft = neighbor.x == entrance.x && neighbor.z == entrance.z;
+                        if shaft && (entrance.y - ENTRANCE_DEPTH..=entrance.y).contains(&neighbor.y)
+                        {
+                            entrance_walls += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    assert!(
+        entrance_walls > 0,
+        "the entrance at {:?} has no walls",
+        origin + entrance
+    );
+
+    let (meshes, _) = create_chunk_mesh(chunk, test_atlas(), Arc::new(generator.clone()), true);
+    let quads: usize = meshes
+        .parts()
+        .map(|(mesh, _)| match mesh.indices() {
+            Some(Indices::U32(indices)) => indices.len() / 6,
+            _ => panic!("missing indices"),
+        })
+        .sum();
+    assert_eq!(quads, faces, "chunk {:?}", chunk);
+}
@@ -0,0 +1,323 @@
+use std::time::Duration;
+
+use crate::game::chunk::{insert_priority, needs_collider};
+use crate::prelude::*;
+
+const POSITION: IVec2XZ = IVec2XZ::new(3, -2);
+
+#[test]
+fn chunks_go_from_requested_to_ready() {
+    let mut chunks = ChunksLoaded::default();
+    let task = Entity::from_raw(1);
+
+    chunks.request(POSITION);
+    assert_eq!(chunks.state(POSITION), Some(ChunkState::Requested));
+    assert_eq!(chunks.requested().collect::<Vec<_>>(), vec![POSITION]);
+
+    chunks.start_generating(POSITION, task);
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Generating { task_entity: task })
+    );
+    assert_eq!(chunks.requested().count(), 0);
+
+    assert!(chunks.finish(POSITION, task));
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Ready { entity: task })
+    );
+
+    assert_eq!(chunks.unload(POSITION), Some(task));
+    assert_eq!(chunks.state(POSITION), None);
+}
+
+#[test]
+fn requested_chunks_unload_without_a_task() {
+    let mut chunks = ChunksLoaded::default();
+
+    chunks.request(POSITION);
+    assert_eq!(chunks.unload(POSITION), None);
+    assert_eq!(chunks.state(POSITION), None);
+    assert_eq!(chunks.requested().count(), 0);
+}
+
+#[test]
+fn chunks_unloaded_while_generating_are_dropped_when_the_task_finishes() {
+    let mut chunks = ChunksLoaded::default();
+    let task = Entity::from_raw(1);
+
+    chunks.request(POSITION);
+    chunks.start_generating(POSITION, task);
+
+    // The task keeps running, so there's nothing to despawn yet.
+    assert_eq!(chunks.unload(POSITION), None);
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Unloading { task_entity: task })
+    );
+    assert!(!chunks.is_wanted(POSITION));
+    assert_eq!(chunks.wanted().count(), 0);
+
+    assert!(!chunks.finish(POSITION, task));
+    assert_eq!(chunks.state(POSITION), None);
+}
+
+#[test]
+fn rerequested_chunks_keep_their_running_task() {
+    let mut chunks = ChunksLoaded::default();
+    let task = Entity::from_raw(1);
+
+    chunks.request(POSITION);
+    chunks.start_generating(POSITION, task);
+    chunks.unload(POSITION);
+
+    // Requested again before the first task completed.
+    chunks.request(POSITION);
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Generating { task_entity: task })
+    );
+    assert_eq!(chunks.requested().count(), 0);
+
+    assert!(chunks.finish(POSITION, task));
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Ready { entity: task })
+    );
+}
+
+#[test]
+fn stale_tasks_dont_finish_a_chunk() {
+    let mut chunks = ChunksLoaded::default();
+    let stale = Entity::from_raw(1);
+    let task = Entity::from_raw(2);
+
+    // Only the task the chunk is waiting for can finish it.
+    chunks.request(POSITION);
+    chunks.start_generating(POSITION, task);
+    assert!(!chunks.finish(POSITION, stale));
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Generating { task_entity: task })
+    );
+
+    // Finishing a chunk that was never requested is refused too.
+    assert!(!chunks.finish(IVec2XZ::new(0, 0), task));
+    assert_eq!(chunks.state(IVec2XZ::new(0, 0)), None);
+}
+
+#[test]
+fn spawn_chunk_radius_counts_whole_chunks() {
+    let center = IVec2XZ::new(-3, 4);
+    assert!(center.within(0).is_empty());
+    assert_eq!(
+        center.within(1).into_iter().collect::<Vec<_>>(),
+        vec![center]
+    );
+
+    let spawn_chunks = center.within(2);
+    assert_eq!(spawn_chunks.len(), 9);
+    assert!(spawn_chunks.contains(&IVec2XZ::new(-4, 5)));
+    assert!(!spawn_chunks.contains(&IVec2XZ::new(-5, 4)));
+}
+
+#[test]
+fn spawn_chunks_become_persistent_when_they_finish() {
+    let mut chunks = ChunksLoaded::default();
+    let task = Entity::from_raw(1);
+    chunks.set_persistent(POSITION.within(1));
+
+    chunks.request(POSITION);
+    chunks.start_generating(POSITION, task);
+    assert_eq!(chunks.counts(), (0, 0, 1));
+
+    assert!(chunks.finish(POSITION, task));
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Persistent { entity: task })
+    );
+    assert!(chunks.is_wanted(POSITION));
+    assert_eq!(chunks.counts(), (1, 1, 0));
+}
+
+#[test]
+fn changing_the_spawn_chunks_updates_loaded_chunks() {
+    let mut chunks = ChunksLoaded::default();
+    let entity = Entity::from_raw(1);
+    chunks.request(POSITION);
+    chunks.start_generating(POSITION, entity);
+    chunks.finish(POSITION, entity);
+
+    chunks.set_persistent(POSITION.within(1));
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Persistent { entity })
+    );
+
+    // Turning the spawn chunks off makes them normal chunks that can unload again.
+    chunks.set_persistent(Default::default());
+    assert_eq!(chunks.state(POSITION), Some(ChunkState::Ready { entity }));
+    assert_eq!(chunks.unload(POSITION), Some(entity));
+}
+
+#[test]
+fn persistent_chunks_refuse_to_unload() {
+    let mut chunks = ChunksLoaded::default();
+    let entity = Entity::from_raw(1);
+    chunks.set_persistent(POSITION.within(1));
+    chunks.request(POSITION);
+    chunks.start_generating(POSITION, entity);
+    chunks.finish(POSITION, entity);
+
+    assert_eq!(chunks.unload(POSITION), None);
+    assert_eq!(
+        chunks.state(POSITION),
+        Some(ChunkState::Persistent { entity })
+    );
+
+    // A reset forgets the chunks, but they are still spawn chunks.
+    chunks.clear();
+    assert_eq!(chunks.state(POSITION), None);
+    assert!(chunks.is_persistent(POSITION));
+}
+
+#[test]
+fn chunks_crossing_the_lod_distance_are_rescaled_with_their_coarse_neighbours() {
+    let mut chunks = ChunksLoaded::default();
+    for x in 0..4 {
+        chunks.request(IVec2XZ::new(x, 0));
+    }
+    let far_from = |start: i32| move |chunk: IVec2XZ| if chunk.x >= start { 2 } else { 1 };
+
+    // The new chunks get their scale, there's nothing to mesh again yet.
+    assert!(chunks.rescale(far_from(2)).is_empty());
+    assert_eq!(chunks.scale(IVec2XZ::new(1, 0)), Some(1));
+    assert_eq!(chunks.scale(IVec2XZ::new(2, 0)), Some(2));
+    assert_eq!(chunks.scale(IVec2XZ::new(4, 0)), None);
+
+    // The chunk that got coarser, and the coarse one next to it whose skirt towards it goes away. The full detail
+    // one on the other side has no skirts.
+    let mut rescaled = chunks.rescale(far_from(1));
+    rescaled.sort_by_key(|chunk| chunk.x);
+    assert_eq!(rescaled, vec![IVec2XZ::new(1, 0), IVec2XZ::new(2, 0)]);
+    assert_eq!(chunks.scale(IVec2XZ::new(1, 0)), Some(2));
+
+    // An unloaded chunk forgets its scale.
+    chunks.unload(IVec2XZ::new(3, 0));
+    assert!(chunks.rescale(far_from(1)).is_empty());
+    assert_eq!(chunks.scale(IVec2XZ::new(3, 0)), None);
+}
+
+/// Cached meshes that only hold the block at the origin, so the chunk they came from can be told apart.
+fn cached(block: BlockType) -> (ChunkMeshes, ChunkBlocks) {
+    let mut blocks = ChunkBlocks::default();
+    blocks.set(0, 0, 0, block);
+    let meshes = ChunkMeshes {
+        sections: Vec::new(),
+        scale: 1,
+    };
+    (meshes, blocks)
+}
+
+#[test]
+fn the_mesh_cache_drops_the_chunk_unloaded_first() {
+    let mut cache = MeshCache::default();
+    for x in 0..=MESH_CACHE_CAPACITY as i32 {
+        let (meshes, blocks) = cached(BlockType::Stone);
+        cache.insert(IVec2XZ::new(x, 0), meshes, blocks);
+    }
+    assert_eq!(cache.count(), MESH_CACHE_CAPACITY);
+    assert!(cache.take(IVec2XZ::new(0, 0)).is_none());
+
+    // Loading a chunk again takes it out, it's cached again when it unloads.
+    let last = IVec2XZ::new(MESH_CACHE_CAPACITY as i32, 0);
+    assert!(cache.take(last).is_some());
+    assert!(cache.take(last).is_none());
+    assert_eq!(cache.count(), MESH_CACHE_CAPACITY - 1);
+
+    cache.clear();
+    assert_eq!(cache.count(), 0);
+}
+
+#[test]
+fn caching_a_chunk_again_replaces_it() {
+    let mut cache = MeshCache::default();
+    let (meshes, blocks) = cached(BlockType::Stone);
+    cache.insert(POSITION, meshes, blocks);
+    let (meshes, blocks) = cached(BlockType::Dirt);
+    cache.insert(POSITION, meshes, blocks);
+
+    assert_eq!(cache.count(), 1);
+    let (_, blocks) = cache.take(POSITION).unwrap();
+    assert_eq!(blocks.get(0, 0, 0), BlockType::Dirt);
+}
+
+#[test]
+fn only_the_chunks_around_the_player_need_colliders() {
+    let player = Vec3::new(-0.5, 80.0, 20.0);
+    let center = IVec2XZ::containing(player);
+    assert_eq!(center, IVec2XZ::new(-1, 1));
+
+    assert!(needs_collider(center, player));
+    assert!(needs_collider(
+        center + IVec2XZ::new(COLLIDER_DISTANCE, 0),
+        player
+    ));
+    assert!(needs_collider(center + IVec2XZ::new(-2, 2), player));
+    assert!(!needs_collider(
+        center + IVec2XZ::new(COLLIDER_DISTANCE, 1),
+        player
+    ));
+    assert!(!needs_collider(
+        center + IVec2XZ::new(0, -RENDER_DISTANCE),
+        player
+    ));
+}
+
+#[test]
+fn the_insert_budget_always_lets_one_chunk_in() {
+    let budget = MeshInsertBudget::default();
+    assert!(budget.allows(0, MESH_INSERT_TIME * 10));
+    assert!(budget.allows(1, Duration::ZERO));
+    assert!(!budget.allows(1, MESH_INSERT_TIME));
+    assert!(!budget.allows(MESH_INSERTS_PER_FRAME, Duration::ZERO));
+}
+
+#[test]
+fn fewer_chunks_are_inserted_for_a_second_after_a_pause() {
+    let mut budget = MeshInsertBudget::default();
+    assert!(budget.allows(CATCH_UP_INSERTS_PER_FRAME, Duration::ZERO));
+
+    budget.start_catch_up();
+    assert!(budget.allows(0, MESH_INSERT_TIME));
+    assert!(!budget.allows(CATCH_UP_INSERTS_PER_FRAME, Duration::ZERO));
+    assert!(!budget.allows(1, CATCH_UP_INSERT_TIME));
+
+    budget.tick(CATCH_UP_TIME / 2);
+    assert!(budget.is_catching_up());
+    budget.tick(CATCH_UP_TIME);
+    assert!(!budget.is_catching_up());
+    assert!(budget.allows(CATCH_UP_INSERTS_PER_FRAME, Duration::ZERO));
+}
+
+#[test]
+fn remeshed_chunks_are_inserted_first_then_the_closest() {
+    let player = IVec2XZ::new(10, -4);
+    let mut chunks = vec![
+        (IVec2XZ::new(14, -4), false),
+        (IVec2XZ::new(10, -4), false),
+        (IVec2XZ::new(16, 0), true),
+        (IVec2XZ::new(11, -3), false),
+    ];
+    chunks.sort_by_key(|&(position, remeshed)| insert_priority(position, remeshed, player));
+    let order: Vec<IVec2XZ> = chunks.into_iter().map(|(position, _)| position).collect();
+    assert_eq!(
+        order,
+        vec![
+            IVec2XZ::new(16, 0),
+            IVec2XZ::new(10, -4),
+            IVec2XZ::new(11, -3),
+            IVec2XZ::new(14, -4),
+        ]
+    );
+}
@@ -0,0 +1,270 @@
+use crate::game::generator::{padded_index, PADDED_HEIGHT, PADDED_SIZE};
+use crate::prelude::*;
+
+#[test]
+fn world_positions_split_into_chunk_and_local() {
+    for (world, chunk, local) in [
+        (-17, -2, 15),
+        (-16, -1, 0),
+        (-15, -1, 1),
+        (-1, -1, 15),
+        (0, 0, 0),
+        (1, 0, 1),
+        (15, 0, 15),
+        (16, 1, 0),
+        (17, 1, 1),
+        (31, 1, 15),
+        (32, 2, 0),
+    ] {
+        let (chunk_x, local_x) = world_to_chunk_and_local(IVec3::new(world, 5, 3));
+        assert_eq!(chunk_x, IVec2XZ::new(chunk, 0), "x {}", world);
+        assert_eq!(local_x, UVec3::new(local, 5, 3), "x {}", world);
+
+        let (chunk_z, local_z) = world_to_chunk_and_local(IVec3::new(3, 5, world));
+        assert_eq!(chunk_z, IVec2XZ::new(0, chunk), "z {}", world);
+        assert_eq!(local_z, UVec3::new(3, 5, local), "z {}", world);
+    }
+}
+
+#[test]
+fn local_positions_round_trip() {
+    for chunk in [IVec2XZ::new(-2, 3), IVec2XZ::new(0, -1), IVec2XZ::new(1, 0)] {
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let world = IVec3::new(
+                    chunk.x * CHUNK_SIZE as i32 + x,
+                    CHUNK_HEIGHT as i32 - 1,
+                    chunk.z * CHUNK_SIZE as i32 + z,
+                );
+                assert_eq!(
+                    world_to_chunk_and_local(world),
+                    (
+                        chunk,
+                        UVec3::new(x as u32, CHUNK_HEIGHT as u32 - 1, z as u32)
+                    )
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn world_positions_find_their_chunk() {
+    assert_eq!(
+        IVec2XZ::containing(Vec3::new(0.0, 70.0, 0.0)),
+        IVec2XZ::new(0, 0)
+    );
+    assert_eq!(
+        IVec2XZ::containing(Vec3::new(15.9, 70.0, 16.0)),
+        IVec2XZ::new(0, 1)
+    );
+    assert_eq!(
+        IVec2XZ::containing(Vec3::new(-0.1, 70.0, -16.0)),
+        IVec2XZ::new(-1, -1)
+    );
+    assert_eq!(
+        IVec2XZ::containing(Vec3::new(-16.1, 300.0, -17.0)),
+        IVec2XZ::new(-2, -2)
+    );
+}
+
+#[test]
+fn local_indices_are_unique_and_in_range() {
+    let mut seen = vec![false; CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE];
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_HEIGHT {
+                let index = local_index(x, y, z);
+                assert!(!seen[index]);
+                seen[index] = true;
+            }
+        }
+    }
+    assert!(seen.iter().all(|&seen| seen));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn out_of_range_local_positions_are_caught() {
+    local_index(CHUNK_SIZE, 0, 0);
+}
+
+#[test]
+fn local_positions_undo_local_indices() {
+    for index in [
+        0,
+        1,
+        CHUNK_HEIGHT,
+        CHUNK_HEIGHT * CHUNK_SIZE + 7,
+        CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE - 1,
+    ] {
+        let pos = local_position(index);
+        assert_eq!(
+            local_index(pos.x as usize, pos.y as usize, pos.z as usize),
+            index
+        );
+    }
+    assert_eq!(local_position(CHUNK_HEIGHT + 3), UVec3::new(0, 3, 1));
+
+    let mut blocks = ChunkBlocks::default();
+    blocks.set(4, 70, 9, BlockType::Stone);
+    let stone: Vec<UVec3> = blocks
+        .iter()
+        .filter(|&(_, block)| block == BlockType::Stone)
+        .map(|(pos, _)| pos)
+        .collect();
+    assert_eq!(stone, vec![UVec3::new(4, 70, 9)]);
+    assert_eq!(
+        blocks.iter().count(),
+        CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE
+    );
+}
+
+#[test]
+fn padded_indices_are_unique_and_in_range() {
+    let total = PADDED_SIZE * PADDED_HEIGHT * PADDED_SIZE;
+    let mut seen = vec![false; total];
+    let size = CHUNK_SIZE as i32;
+    for x in -1..=size {
+        for z in -1..=size {
+            for y in -1..=CHUNK_HEIGHT as i32 {
+                let index = padded_index(IVec3::new(x, y, z));
+                assert!(!seen[index]);
+                seen[index] = true;
+            }
+        }
+    }
+    assert!(seen.iter().all(|&seen| seen));
+}
+
+/// A chunk map with the chunks around the origin loaded, every one of them empty.
+fn loaded_chunks() -> ChunkMap {
+    let mut chunk_map = ChunkMap::default();
+    for x in -2..=1 {
+        for z in -2..=1 {
+            chunk_map
+                .chunks
+                .insert(IVec2XZ::new(x, z), ChunkBlocks::default());
+        }
+    }
+    chunk_map
+}
+
+#[test]
+fn blocks_are_set_in_the_right_chunk() {
+    let mut chunk_map = loaded_chunks();
+    // Both sides of every chunk border around the origin, and the far corners.
+    let positions = [
+        IVec3::new(-1, 70, -1),
+        IVec3::new(0, 70, 0),
+        IVec3::new(-1, 70, 0),
+        IVec3::new(0, 70, -1),
+        IVec3::new(-16, 0, -16),
+        IVec3::new(-17, 1, 15),
+        IVec3::new(15, 255, 16),
+        IVec3::new(-32, 64, 31),
+    ];
+    for (i, &pos) in positions.iter().enumerate() {
+        let block = BlockType::ALL[i];
+        chunk_map.set_block(pos, block);
+        assert_eq!(chunk_map.get_block(pos), Some(block), "{:?}", pos);
+
+        let (chunk, local) = world_to_chunk_and_local(pos);
+        assert_eq!(
+            chunk_map.chunks[&chunk].get(local.x as usize, local.y as usize, local.z as usize),
+            block
+        );
+    }
+    // Nothing else changed.
+    let changed: usize = chunk_map
+        .chunks
+        .values()
+        .map(|blocks| {
+            (0..CHUNK_SIZE)
+                .flat_map(|x| (0..CHUNK_SIZE).map(move |z| (x, z)))
+                .map(|(x, z)| {
+                    blocks
+                        .column(x, z)
+                        .iter()
+                        .filter(|&&block| block != BlockType::Air)
+                        .count()
+                })
+                .sum::<usize>()
+        })
+        .sum();
+    assert_eq!(changed, positions.len());
+}
+
+#[test]
+fn blocks_outside_the_loaded_chunks_are_left_alone() {
+    let mut chunk_map = loaded_chunks();
+    for pos in [
+        IVec3::new(-33, 70, 0),
+        IVec3::new(32, 70, 0),
+        IVec3::new(0, 70, 32),
+        IVec3::new(0, -1, 0),
+        IVec3::new(0, CHUNK_HEIGHT as i32, 0),
+    ] {
+        chunk_map.set_block(pos, BlockType::Stone);
+        assert_eq!(chunk_map.get_block(pos), None, "{:?}", pos);
+    }
+    assert_eq!(chunk_map.chunks.len(), 16);
+    assert_eq!(
+        chunk_map.get_block(IVec3::new(-32, 70, 0)),
+        Some(BlockType::Air)
+    );
+}
+
+#[test]
+fn the_sky_comes_back_when_the_roof_is_broken() {
+    let mut chunk_map = loaded_chunks();
+    let floor = IVec3::new(-1, 64, 5);
+    chunk_map.set_block(floor, BlockType::Stone);
+    let standing = floor + IVec3::Y;
+    assert!(chunk_map.sky_exposed(standing));
+    assert_eq!(chunk_map.sky_top(IVec2XZ::new(-1, 5)), Some(64));
+
+    let roof = floor + IVec3::new(0, 4, 0);
+    chunk_map.set_block(roof, BlockType::Cobblestone);
+    assert!(!chunk_map.sky_exposed(standing));
+    assert!(chunk_map.sky_exposed(roof + IVec3::Y));
+    chunk_map.set_block(roof, BlockType::Air);
+    assert!(chunk_map.sky_exposed(standing));
+    assert_eq!(chunk_map.sky_top(IVec2XZ::new(-1, 5)), Some(64));
+
+    // A chunk that's loaded again is looked at again, and plants don't cover the sky.
+    let mut blocks = ChunkBlocks::default();
+    blocks.set(15, 200, 5, BlockType::TallGrass);
+    chunk_map.insert_chunk(IVec2XZ::new(-1, 0), blocks);
+    assert_eq!(chunk_map.sky_top(IVec2XZ::new(-1, 5)), None);
+    chunk_map.remove_chunk(IVec2XZ::new(-1, 0));
+    assert!(chunk_map.sky_exposed(standing));
+}
+
+#[test]
+fn leaves_and_fluids_leave_the_sky_open() {
+    let mut chunk_map = loaded_chunks();
+    let floor = IVec3::new(3, 64, -7);
+    chunk_map.set_block(floor, BlockType::Stone);
+    let standing = floor + IVec3::Y;
+
+    // A canopy with air under it, like a tree's, and a pool of water held up in the air above it.
+    for y in 70..73 {
+        chunk_map.set_block(IVec3::new(floor.x, y, floor.z), BlockType::Leaves);
+    }
+    chunk_map.set_block(IVec3::new(floor.x, 73, floor.z), BlockType::SpruceLeaves);
+    chunk_map.set_block(IVec3::new(floor.x, 80, floor.z), BlockType::Water);
+    assert!(chunk_map.sky_exposed(standing));
+    assert_eq!(chunk_map.sky_top(IVec2XZ::new(floor.x, floor.z)), Some(64));
+
+    // A chunk loaded with the canopy already in it is looked at the same way.
+    let (chunk, local) = world_to_chunk_and_local(floor);
+    let mut blocks = ChunkBlocks::default();
+    blocks.set(local.x as usize, 90, local.z as usize, BlockType::Leaves);
+    chunk_map.insert_chunk(chunk, blocks);
+    assert_eq!(chunk_map.sky_top(IVec2XZ::new(floor.x, floor.z)), None);
+
+    chunk_map.set_block(IVec3::new(floor.x, 91, floor.z), BlockType::Log);
+    assert_eq!(chunk_map.sky_top(IVec2XZ::new(floor.x, floor.z)), Some(91));
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::render::mesh::VertexAttributeValues;
+
+use crate::game::chunk::{mesh_blocks, part_visible, texture_index};
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+
+const SIZE: i32 = 8;
+const CASES: u64 = 300;
+
+/// A face of a block: the cell, the side and the atlas tile.
+type Face = (IVec3, BlockFace, usize);
+
+/// A random block for a case, including the ring of blocks just outside the box.
+///
+/// Every case gets its own amount of air, so both sparse and packed boxes are covered.
+fn random_block(case: u64, pos: IVec3) -> BlockType {
+    let air_percent = mix(case) % 100;
+    let hash = mix(mix(case ^ 0x5eed)
+        ^ ((pos.x as u32 as u64) << 40 | (pos.y as u32 as u64) << 20 | pos.z as u32 as u64));
+    if hash % 100 < air_percent {
+        BlockType::Air
+    } else {
+        BlockType::ALL[(hash / 100 % BlockType::ALL.len() as u64) as usize]
+    }
+}
+
+/// Every face that should be drawn, found by checking each face of each block on its own, with how many quads it
+/// has, and the cells of the crosses.
+fn expected_faces(case: u64, origin: IVec3) -> (HashMap<Face, usize>, HashSet<IVec3>) {
+    let mut faces = HashMap::new();
+    let mut crosses = HashSet::new();
+    let atlas = test_atlas();
+    for x in 0..SIZE {
+        for y in 0..SIZE {
+            for z in 0..SIZE {
+                let cell = IVec3::new(x, y, z);
+                let block = random_block(case, cell);
+                if block == BlockType::Air {
+                    continue;
+                }
+                // Crosses are always drawn whole, and never have faces.
+                if block.shape() == BlockShape::Cross {
+                    crosses.insert(origin + cell);
+                    continue;
+                }
+                for face in BlockFace::ALL {
+                    let neighbor = random_block(case, cell + face.offset());
+                    let world = origin + cell;
+                    let face_of = (world, face, texture_index(&atlas, block, face, world));
+                    // Slabs and stairs are checked box by box, see the tests of their culling in blocks.rs.
+                    if block.shape() != BlockShape::Cube {
+                        let quads = block
+                            .shape()
+                            .boxes()
+                            .filter(|&part| part_visible(block, neighbor, face, part))
+                            .count();
+                        if quads > 0 {
+                            faces.insert(face_of, quads);
+                        }
+                        continue;
+                    }
+                    // Fluids merge with themselves, everything else is hidden by a neighbour covering it.
+                    if neighbor == block || neighbor.covers_face(face.opposite()) {
+                        continue;
+                    }
+                    // Ice is the frozen top of the water under it.
+                    if block == BlockType::Ice
+                        && neighbor == BlockType::Water
+                        && face == BlockFace::Bottom
+                    {
+                        continue;
+                    }
+                    faces.insert(face_of, 1);
+                }
+            }
+        }
+    }
+    (faces, crosses)
+}
+
+fn face_from_normal(normal: [f32; 3]) -> BlockFace {
+    *BlockFace::ALL
+        .iter()
+        .find(|face| face.offset().as_vec3().to_array() == normal)
+        .unwrap_or_else(|| panic!("unexpected normal {:?}", normal))
+}
+
+/// Reads the faces back from the quads of a mesh, and the cells of the quads of the crosses.
+fn mesh_faces(mesh: &Mesh, atlas: &GameTextureAtlas) -> (Vec<Face>, Vec<IVec3>) {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("missing positions");
+    };
+    let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    else {
+        panic!("missing normals");
+    };
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        panic!("missing uvs");
+    };
+
+    let mut crosses = Vec::new();
+    let faces = (0..positions.len())
+        .step_by(4)
+        .filter_map(|first| {
+            let quad = &positions[first..first + 4];
+            assert!(normals[first..first + 4]
+                .iter()
+                .all(|&n| n == normals[first]));
+
+            // The quads of the crosses stand upright, but face up.
+            if quad.iter().any(|v| v[1] != quad[0][1]) && normals[first] == [0.0, 1.0, 0.0] {
+                let center = quad.iter().map(|&v| Vec3::from(v)).sum::<Vec3>() / 4.0;
+                crosses.push(center.floor().as_ivec3());
+                return None;
+            }
+            let face = face_from_normal(normals[first]);
+
+            // Step back from the middle of the quad into the block it belongs to.
+            // Fluid tops are lowered a bit, which still lands in the same cell, and so do the faces of the slabs
+            // and the stairs in the middle of theirs.
+            let center = quad.iter().map(|&v| Vec3::from(v)).sum::<Vec3>() / 4.0;
+            let cell = (center - face.offset().as_vec3() * 0.25).floor().as_ivec3();
+
+            // The middle of the texture, the boxes of the slabs and the stairs only show part of their tile.
+            let uv_center = uvs[first..first + 4]
+                .iter()
+                .map(|&uv| Vec2::from(uv))
+                .sum::<Vec2>()
+                / 4.0;
+            let tile = atlas
+                .atlas
+                .textures
+                .iter()
+                .position(|rect| {
+                    let (min, max) = (rect.min / atlas.atlas.size, rect.max / atlas.atlas.size);
+                    uv_center.cmpgt(min).all() && uv_center.cmplt(max).all()
+                })
+                .unwrap_or_else(|| panic!("uv {:?} isn't in a tile", uv_center));
+
+            Some((cell, face, tile))
+        })
+        .collect();
+    (faces, crosses)
+}
+
+#[test]
+fn mesher_emits_exactly_the_visible_faces() {
+    let atlas = test_atlas();
+    for case in 0..CASES {
+        // Move the box around too, the textures are picked per world position.
+        let origin = IVec3::new(
+            (mix(case) % 64) as i32 - 32,
+            (mix(case + 1) % 64) as i32,
+            (mix(case + 2) % 64) as i32 - 32,
+        );
+        let mesh = mesh_blocks(UVec3::splat(SIZE as u32), origin, &atlas, |pos| {
+            random_block(case, pos)
+        });
+
+        let (faces, crosses) = mesh_faces(&mesh, &atlas);
+        let mut found: HashMap<Face, usize> = HashMap::new();
+        for face in faces {
+            *found.entry(face).or_default() += 1;
+        }
+
+        let (expected, expected_crosses) = expected_faces(case, origin);
+        let missing: Vec<_> = expected
+            .iter()
+            .filter(|(face, quads)| found.get(face) != Some(quads))
+            .collect();
+        let extra: Vec<_> = found
+            .iter()
+            .filter(|(face, _)| !expected.contains_key(face))
+            .collect();
+        assert!(
+            missing.is_empty() && extra.is_empty(),
+            "case {}: missing {:?}, extra {:?}",
+            case,
+            missing,
+            extra
+        );
+
+        // Two quads a cross, both sides of a quad share its vertices.
+        let cells: HashSet<IVec3> = crosses.iter().copied().collect();
+        assert_eq!(cells, expected_crosses, "case {}", case);
+        assert_eq!(crosses.len(), cells.len() * 2, "case {}", case);
+    }
+}
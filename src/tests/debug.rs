@@ -0,0 +1,122 @@
+use crate::game::capture::CLEAN_CAPTURE_KEY;
+use crate::game::debug::*;
+use crate::game::hud::controls_text;
+use crate::game::interaction::reach_block;
+use crate::game::projectiles::THROW_KEY;
+use crate::prelude::*;
+
+#[test]
+fn chords_need_the_modifier_first() {
+    let mut chords = DebugChords::default();
+
+    // Without the modifier the keys are left to the game.
+    chords.update(false, [KeyCode::G], 0.0);
+    assert!(!chords.triggered(DebugAction::ChunkBorders));
+
+    // Holding the modifier, then pressing the key.
+    chords.update(true, [], 0.1);
+    chords.update(true, [KeyCode::G], 0.2);
+    assert!(chords.triggered(DebugAction::ChunkBorders));
+    // It only fires on the frame the key went down.
+    chords.update(true, [], 0.3);
+    assert!(!chords.triggered(DebugAction::ChunkBorders));
+
+    // Both going down in the same frame counts too.
+    chords.update(true, [DEBUG_MODIFIER, KeyCode::F], 0.4);
+    assert!(chords.triggered(DebugAction::Fullbright));
+
+    // The key first and the modifier after doesn't.
+    chords.update(false, [KeyCode::V], 0.5);
+    chords.update(true, [DEBUG_MODIFIER], 0.6);
+    assert!(!chords.triggered(DebugAction::VSync));
+}
+
+#[test]
+fn resetting_the_chunks_needs_a_second_press() {
+    let mut chords = DebugChords::default();
+    chords.update(true, [KeyCode::R], 0.0);
+    assert!(!chords.triggered(DebugAction::ResetChunks));
+    chords.update(true, [KeyCode::R], 0.5);
+    assert!(chords.triggered(DebugAction::ResetChunks));
+
+    // A third press starts over.
+    chords.update(true, [KeyCode::R], 0.7);
+    assert!(!chords.triggered(DebugAction::ResetChunks));
+
+    // Too late, the second press is the first one of a new try.
+    chords.update(true, [KeyCode::R], 10.0);
+    chords.update(true, [KeyCode::R], 10.0 + CONFIRM_WINDOW + 0.1);
+    assert!(!chords.triggered(DebugAction::ResetChunks));
+    chords.update(true, [KeyCode::R], 10.0 + CONFIRM_WINDOW + 0.2);
+    assert!(chords.triggered(DebugAction::ResetChunks));
+}
+
+#[test]
+fn every_chord_has_its_own_key() {
+    let gameplay = [
+        KeyCode::W,
+        KeyCode::A,
+        KeyCode::S,
+        KeyCode::D,
+        KeyCode::Space,
+        KeyCode::ShiftLeft,
+        KeyCode::Escape,
+        THROW_KEY,
+        CLEAN_CAPTURE_KEY,
+    ];
+    let controls = controls_text();
+    for action in DebugAction::ALL {
+        assert!(!gameplay.contains(&action.key()), "{:?}", action);
+        assert_eq!(
+            DebugAction::ALL
+                .iter()
+                .filter(|other| other.key() == action.key())
+                .count(),
+            1
+        );
+        assert!(controls.contains(&action.control_line()), "{:?}", action);
+    }
+    assert!(controls.contains("F3 + R - Reset Chunks (press twice)"));
+}
+
+#[test]
+fn a_box_has_twelve_distinct_edges_of_its_size() {
+    let (min, max) = (Vec3::new(-1.0, 2.0, 3.0), Vec3::new(1.0, 3.0, 6.0));
+    let edges = box_edges(min, max);
+    for (i, (start, end)) in edges.iter().enumerate() {
+        // Each edge runs along one axis, from the min to the max.
+        let along = *end - *start;
+        assert_eq!((along.cmpne(Vec3::ZERO).bitmask()).count_ones(), 1);
+        assert!(along.cmpge(Vec3::ZERO).all());
+        assert!(!edges[..i].contains(&(*start, *end)));
+    }
+    let length: f32 = edges.iter().map(|(start, end)| start.distance(*end)).sum();
+    assert_eq!(length, 4.0 * (2.0 + 1.0 + 3.0));
+}
+
+#[test]
+fn the_highlight_tracks_blocks_at_negative_coordinates() {
+    // A single log at (-1, 64, -17), in the chunk (-1, -2).
+    let log = IVec3::new(-1, 64, -17);
+    let mut blocks = ChunkBlocks::default();
+    blocks.set(15, 64, 15, BlockType::Log);
+    blocks.set(14, 64, 15, BlockType::Water);
+    let mut chunk_map = ChunkMap::default();
+    chunk_map.chunks.insert(IVec2XZ::new(-1, -2), blocks);
+
+    let hit = reach_block(&chunk_map, Vec3::new(-0.5, 66.5, -16.5), -Vec3::Y).unwrap();
+    assert_eq!(hit.pos, log);
+    let (min, max) = highlight_box(hit.pos);
+    assert!(min.abs_diff_eq(Vec3::new(-1.0, 64.0, -17.0) - HIGHLIGHT_INFLATION, 1e-6));
+    assert!(max.abs_diff_eq(Vec3::new(0.0, 65.0, -16.0) + HIGHLIGHT_INFLATION, 1e-6));
+
+    // Nothing is highlighted through the water, or out of reach.
+    assert_eq!(
+        reach_block(&chunk_map, Vec3::new(-1.5, 66.5, -16.5), -Vec3::Y),
+        None
+    );
+    assert_eq!(
+        reach_block(&chunk_map, Vec3::new(-0.5, 71.0, -16.5), -Vec3::Y),
+        None
+    );
+}
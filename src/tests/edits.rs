@@ -0,0 +1,173 @@
+use crate::game::edits::*;
+use crate::game::leaves::LeafDecay;
+use crate::prelude::*;
+
+fn edit(x: i32, old: BlockType, new: BlockType) -> EditRecord {
+    EditRecord {
+        pos: IVec3::new(x, 70, 3),
+        old,
+        new,
+    }
+}
+
+#[test]
+fn undo_and_redo_interleave() {
+    let mut history = EditHistory::default();
+    let place = edit(0, BlockType::Air, BlockType::Stone);
+    let dig = edit(1, BlockType::Dirt, BlockType::Air);
+    history.record(place);
+    history.record(dig);
+
+    // Undone from the last one back, and redone in the same order they were made.
+    assert_eq!(history.undo(), Some(vec![dig.inverse()]));
+    assert_eq!(history.redo(), Some(vec![dig]));
+    assert_eq!(history.undo(), Some(vec![dig.inverse()]));
+    assert_eq!(history.undo(), Some(vec![place.inverse()]));
+    assert_eq!(history.undo(), None);
+    assert_eq!(history.redo(), Some(vec![place]));
+
+    // A new edit drops whatever was still undone.
+    let build = edit(2, BlockType::Air, BlockType::Log);
+    history.record(build);
+    assert!(!history.can_redo());
+    assert_eq!(history.undo(), Some(vec![build.inverse()]));
+    assert_eq!(history.undo(), Some(vec![place.inverse()]));
+    assert!(!history.can_undo());
+
+    // Edits that don't change anything aren't worth an undo.
+    history.record(edit(3, BlockType::Stone, BlockType::Stone));
+    assert!(!history.can_undo());
+}
+
+#[test]
+fn a_stamp_is_undone_at_once() {
+    let mut history = EditHistory::default();
+    let dig = edit(-1, BlockType::Grass, BlockType::Air);
+    history.record(dig);
+
+    history.begin_batch();
+    let stamp: Vec<EditRecord> = (0..4)
+        .map(|x| edit(x, BlockType::Air, BlockType::Leaves))
+        .collect();
+    for &edit in &stamp {
+        history.record(edit);
+    }
+    history.end_batch();
+    // An empty batch isn't an action.
+    history.begin_batch();
+    history.end_batch();
+
+    let undone = history.undo().unwrap();
+    assert_eq!(
+        undone,
+        stamp
+            .iter()
+            .rev()
+            .map(|edit| edit.inverse())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(history.redo(), Some(stamp));
+    history.undo();
+    assert_eq!(history.undo(), Some(vec![dig.inverse()]));
+}
+
+#[test]
+fn the_oldest_actions_are_forgotten() {
+    let mut history = EditHistory::default();
+    for x in 0..EDIT_HISTORY_LIMIT as i32 + 10 {
+        history.record(edit(x, BlockType::Air, BlockType::Stone));
+    }
+    let mut undone = 0;
+    while let Some(edits) = history.undo() {
+        undone += 1;
+        assert!(edits[0].pos.x >= 10);
+    }
+    assert_eq!(undone, EDIT_HISTORY_LIMIT);
+}
+
+#[test]
+fn edits_in_unloaded_chunks_are_skipped() {
+    let mut chunk_map = ChunkMap::default();
+    chunk_map
+        .chunks
+        .insert(IVec2XZ::new(0, 0), ChunkBlocks::default());
+
+    let edits = [
+        edit(5, BlockType::Air, BlockType::Stone),
+        edit(6, BlockType::Air, BlockType::Stone),
+        edit(-5, BlockType::Air, BlockType::Stone),
+    ];
+    assert_eq!(
+        apply_edits(&mut chunk_map, &mut LeafDecay::default(), &edits),
+        vec![ChunkSection {
+            position: IVec2XZ::new(0, 0),
+            index: 4
+        }]
+    );
+    assert_eq!(
+        chunk_map.get_block(IVec3::new(5, 70, 3)),
+        Some(BlockType::Stone)
+    );
+    assert_eq!(chunk_map.get_block(IVec3::new(-5, 70, 3)), None);
+}
+
+#[test]
+fn edits_on_a_border_remesh_the_neighbour() {
+    assert_eq!(
+        touched_chunks(IVec3::new(5, 70, 3)),
+        vec![IVec2XZ::new(0, 0)]
+    );
+    assert_eq!(
+        touched_chunks(IVec3::new(-16, 70, 15)),
+        vec![
+            IVec2XZ::new(-1, 0),
+            IVec2XZ::new(-2, 0),
+            IVec2XZ::new(-1, 1),
+            IVec2XZ::new(-2, 1)
+        ]
+    );
+
+    let mut chunk_map = ChunkMap::default();
+    for x in -1..=0 {
+        chunk_map
+            .chunks
+            .insert(IVec2XZ::new(x, 0), ChunkBlocks::default());
+    }
+    let edits = [
+        edit(0, BlockType::Air, BlockType::Stone),
+        edit(1, BlockType::Air, BlockType::Stone),
+    ];
+    assert_eq!(
+        apply_edits(&mut chunk_map, &mut LeafDecay::default(), &edits),
+        [IVec2XZ::new(0, 0), IVec2XZ::new(-1, 0)]
+            .map(|position| ChunkSection { position, index: 4 })
+    );
+}
+
+#[test]
+fn edits_on_a_section_border_remesh_the_section_next_to_it() {
+    let indices = |pos: IVec3| -> Vec<usize> {
+        touched_sections(pos)
+            .into_iter()
+            .map(|section| section.index)
+            .collect()
+    };
+    assert_eq!(indices(IVec3::new(5, 70, 3)), [4]);
+    assert_eq!(indices(IVec3::new(5, 64, 3)), [4, 3]);
+    assert_eq!(indices(IVec3::new(5, 79, 3)), [4, 5]);
+    // There's nothing below the bottom section or above the top one.
+    assert_eq!(indices(IVec3::new(5, 0, 3)), [0]);
+    assert_eq!(indices(IVec3::new(5, 255, 3)), [15]);
+
+    // A block in the corner of a chunk and a section touches both sections of all four chunks.
+    let sections = touched_sections(IVec3::new(0, 16, 0));
+    assert_eq!(sections.len(), 8);
+    for position in [(0, 0), (-1, 0), (0, -1), (-1, -1)] {
+        for index in [0, 1] {
+            assert!(sections.contains(&ChunkSection {
+                position: IVec2XZ::new(position.0, position.1),
+                index
+            }));
+        }
+    }
+}
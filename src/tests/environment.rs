@@ -0,0 +1,125 @@
+use crate::game::biome::Biome;
+use crate::game::environment::*;
+use crate::prelude::*;
+
+/// The highest block of [`underground`].
+const GROUND: Option<i32> = Some(63);
+
+/// Stone up to y 63, with a lava pocket at y 30 below the origin.
+fn underground(pos: IVec3) -> Option<BlockType> {
+    Some(match pos {
+        IVec3 { x: 0, y: 30, z: 0 } => BlockType::Lava,
+        pos if pos.y < 64 => BlockType::Stone,
+        _ => BlockType::Air,
+    })
+}
+
+#[test]
+fn samples_find_the_ground_overhead_and_the_lava() {
+    let cave = EnvironmentSample::at(
+        Vec3::new(2.5, 31.5, 0.5),
+        Biome::Plains,
+        1.0,
+        GROUND,
+        underground,
+    );
+    assert_eq!(cave.depth, 32);
+    assert_eq!(cave.lava_distance, Some(Vec3::new(2.0, 1.0, 0.0).length()));
+    assert!(cave.heat() > 0.5);
+    assert_eq!(cave.frost(), 0.0);
+
+    // Out of reach of the lava, or close to it but not deep enough.
+    let far = EnvironmentSample::at(
+        Vec3::new(20.5, 31.5, 0.5),
+        Biome::Plains,
+        1.0,
+        GROUND,
+        underground,
+    );
+    assert_eq!((far.lava_distance, far.heat()), (None, 0.0));
+    let shallow = EnvironmentSample {
+        depth: HEAT_DEPTH - 1,
+        ..cave.clone()
+    };
+    assert_eq!(shallow.heat(), 0.0);
+
+    let outside = EnvironmentSample::at(
+        Vec3::new(0.5, 66.0, 0.5),
+        Biome::Plains,
+        1.0,
+        GROUND,
+        underground,
+    );
+    assert_eq!((outside.depth, outside.lava_distance), (0, None));
+}
+
+#[test]
+fn frost_comes_with_cold_nights_and_altitude() {
+    let day = EnvironmentSample {
+        biome: Biome::Tundra,
+        altitude: 80.0,
+        sun_height: 0.8,
+        ..default()
+    };
+    assert_eq!(day.frost(), 0.0);
+    let night = EnvironmentSample {
+        sun_height: -0.5,
+        ..day.clone()
+    };
+    assert_eq!(night.frost(), 1.0);
+    let desert_night = EnvironmentSample {
+        biome: Biome::Desert,
+        ..night.clone()
+    };
+    assert_eq!(desert_night.frost(), 0.0);
+    let sheltered = EnvironmentSample {
+        depth: 3,
+        ..night.clone()
+    };
+    assert_eq!(sheltered.frost(), 0.0);
+
+    // Anywhere, getting colder the higher up.
+    let frost_at = |altitude| {
+        EnvironmentSample {
+            altitude,
+            ..desert_night.clone()
+        }
+        .frost()
+    };
+    assert_eq!(frost_at(FROST_ALTITUDE), 0.0);
+    assert!(frost_at(FROST_ALTITUDE + 10.0) < frost_at(FROST_ALTITUDE + 30.0));
+    assert_eq!(frost_at(FROST_ALTITUDE + 50.0), 1.0);
+}
+
+#[test]
+fn effects_can_be_turned_off() {
+    let settings = Settings::default();
+    assert!(settings.graphics.frost_effect && settings.graphics.heat_effect);
+    let settings: Settings = ron::from_str("(graphics: (heat_effect: false))").unwrap();
+    assert!(settings.graphics.frost_effect && !settings.graphics.heat_effect);
+}
+
+#[test]
+fn the_eye_is_in_a_fluid_below_its_surface() {
+    // Two blocks of water on the stone, with the surface in the top one.
+    let pond = |pos: IVec3| {
+        Some(match pos.y {
+            64 | 65 => BlockType::Water,
+            y if y < 64 => BlockType::Stone,
+            _ => BlockType::Air,
+        })
+    };
+    assert_eq!(
+        eye_fluid(Vec3::new(0.5, 64.95, 0.5), pond),
+        Some(BlockType::Water)
+    );
+    assert_eq!(
+        eye_fluid(Vec3::new(0.5, 65.85, 0.5), pond),
+        Some(BlockType::Water)
+    );
+    // Above the surface, or in the ground.
+    assert_eq!(eye_fluid(Vec3::new(0.5, 65.95, 0.5), pond), None);
+    assert_eq!(eye_fluid(Vec3::new(0.5, 63.5, 0.5), pond), None);
+    // Nothing is loaded yet.
+    assert_eq!(eye_fluid(Vec3::new(0.5, 64.5, 0.5), |_| None), None);
+}
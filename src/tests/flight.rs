@@ -0,0 +1,72 @@
+use crate::game::flight::*;
+use crate::prelude::*;
+
+/// Flies towards `target` from rest for one second, returning the distance covered.
+fn fly_for_a_second(fps: u32, target: Vec3) -> Vec3 {
+    let config = PlayerPhysicsConfig::default();
+    let delta = 1.0 / fps as f32;
+    let mut velocity = Vec3::ZERO;
+    let mut position = Vec3::ZERO;
+    for _ in 0..fps {
+        velocity = approach_velocity(velocity, target, config.fly_smoothing, delta);
+        position += velocity * delta;
+    }
+    position
+}
+
+#[test]
+fn flight_smoothing_is_frame_rate_independent() {
+    let target = Vec3::new(10.0, 0.0, 0.0);
+    let slow = fly_for_a_second(30, target);
+    let fast = fly_for_a_second(240, target);
+    assert!(
+        slow.distance(fast) < 0.3,
+        "30 FPS flew {:?}, 240 FPS flew {:?}",
+        slow,
+        fast
+    );
+    // The smoothing costs about one time constant of distance.
+    assert!((fast.x - 9.0).abs() < 0.1, "{:?}", fast);
+}
+
+#[test]
+fn releasing_the_keys_stops_within_a_third_of_a_second() {
+    let config = PlayerPhysicsConfig::default();
+    let velocity = approach_velocity(Vec3::X * 10.0, Vec3::ZERO, config.fly_smoothing, 0.3);
+    assert!(velocity.length() < 0.6, "{:?}", velocity);
+}
+
+#[test]
+fn double_tapping_jump_toggles_flying() {
+    let config = PlayerPhysicsConfig::default();
+    let mut flight = Flight::default();
+
+    assert!(!flight.jump_pressed(1.0, &config));
+    assert!(flight.jump_pressed(1.2, &config));
+    assert!(flight.flying);
+
+    // The third tap of a triple tap starts over.
+    assert!(!flight.jump_pressed(1.4, &config));
+    assert!(flight.flying);
+
+    // Too slow.
+    assert!(!flight.jump_pressed(2.0, &config));
+    assert!(flight.flying);
+    assert!(flight.jump_pressed(2.1, &config));
+    assert!(!flight.flying);
+}
+
+#[test]
+fn fly_speed_is_clamped() {
+    let config = PlayerPhysicsConfig::default();
+    let mut flight = Flight::default();
+
+    flight.scroll(1.0, &config);
+    assert!((flight.multiplier - config.fly_multiplier_step).abs() < 1e-6);
+
+    flight.scroll(100.0, &config);
+    assert_eq!(flight.multiplier, config.max_fly_multiplier);
+
+    flight.scroll(-100.0, &config);
+    assert_eq!(flight.multiplier, config.min_fly_multiplier);
+}
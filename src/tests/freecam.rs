@@ -0,0 +1,39 @@
+use crate::game::freecam::*;
+use crate::prelude::*;
+
+#[test]
+fn leaving_the_freecam_restores_the_camera() {
+    let mut mode = CameraMode::default();
+    let before =
+        Transform::from_xyz(1.5, 80.5, -3.0).looking_at(Vec3::new(10.0, 70.0, 4.0), Vec3::Y);
+    let mut camera = before;
+
+    mode.toggle(&mut camera);
+    assert!(mode.is_freecam());
+    camera.translation += Vec3::new(100.0, 20.0, -50.0);
+    camera.rotation = Quat::from_rotation_x(1.0);
+
+    mode.toggle(&mut camera);
+    assert!(!mode.is_freecam());
+    assert_eq!(camera, before);
+}
+
+#[test]
+fn chunks_follow_the_player_until_told_otherwise() {
+    let mut mode = CameraMode::default();
+    let (camera, player) = (Vec3::new(500.0, 90.0, 0.0), Vec3::new(0.0, 72.0, 0.0));
+    assert_eq!(mode.streaming_center(camera, player), camera);
+
+    let mut transform = Transform::from_translation(player);
+    mode.toggle(&mut transform);
+    assert_eq!(mode.streaming_center(camera, player), player);
+
+    mode.stream_from_camera = true;
+    assert_eq!(mode.streaming_center(camera, player), camera);
+
+    // Streaming goes back to the player along with the camera.
+    mode.toggle(&mut transform);
+    assert!(!mode.stream_from_camera);
+    mode.toggle(&mut transform);
+    assert_eq!(mode.streaming_center(camera, player), player);
+}
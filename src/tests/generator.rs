@@ -0,0 +1,263 @@
+use std::sync::Arc;
+
+use crate::game::chunk::{create_chunk_mesh, get_verts_indices};
+use crate::game::generator::*;
+use noise::Perlin;
+
+use crate::game::rules::{WorldGenParams, WorldRules};
+use crate::prelude::*;
+use crate::tests::mesh::{chunk_meshes_hash, test_atlas};
+use crate::tests::SEED;
+
+#[test]
+fn generators_mesh_differently() {
+    let chunk = IVec2XZ::new(0, 0);
+    let (default, _) = create_chunk_mesh(
+        chunk,
+        test_atlas(),
+        Arc::new(DefaultGenerator::new(SEED, WorldRules::default())),
+        true,
+    );
+    let (flat, flat_blocks) = create_chunk_mesh(
+        chunk,
+        test_atlas(),
+        Arc::new(SuperflatGenerator::default()),
+        true,
+    );
+    assert_ne!(chunk_meshes_hash(&default), chunk_meshes_hash(&flat));
+
+    // Only the top and the bottom of a flat world are drawn, the chunk is surrounded by more of it. They're both in
+    // the bottom section, the ones above are air.
+    let faces = flat.sections[0].solid_mesh.count_vertices() / 4;
+    assert!(flat.sections[1..].iter().all(SectionMesh::is_empty));
+    assert_eq!(faces, CHUNK_SIZE * CHUNK_SIZE * 2);
+    assert_eq!(flat_blocks.get(3, 3, 3), BlockType::Grass);
+}
+
+#[test]
+fn superflat_stacks_its_layers() {
+    let generator = SuperflatGenerator {
+        layers: vec![
+            (BlockType::Bedrock, 1),
+            (BlockType::Stone, 3),
+            (BlockType::Sand, 2),
+        ],
+    };
+    let column: Vec<BlockType> = (-1..8)
+        .map(|y| generator.block_at(IVec3::new(-20, y, 7)))
+        .collect();
+    assert_eq!(
+        column,
+        [
+            BlockType::Air,
+            BlockType::Bedrock,
+            BlockType::Stone,
+            BlockType::Stone,
+            BlockType::Stone,
+            BlockType::Sand,
+            BlockType::Sand,
+            BlockType::Air,
+            BlockType::Air,
+        ]
+    );
+}
+
+#[test]
+fn default_block_at_matches_the_generated_chunk() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    // (-1, -1) has trees, their blocks have to come out of `block_at` too.
+    for chunk in [IVec2XZ::new(0, 0), IVec2XZ::new(-1, -1)] {
+        let blocks = generator.generate_chunk(chunk);
+        let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+        // Only the outermost columns, the neighbouring chunks never look further in.
+        for x in 0..CHUNK_SIZE {
+            for z in [0, CHUNK_SIZE - 1] {
+                for (local_x, local_z) in [(x, z), (z, x)] {
+                    for y in 0..CHUNK_HEIGHT {
+                        let pos = origin + IVec3::new(local_x as i32, y as i32, local_z as i32);
+                        assert_eq!(
+                            generator.block_at(pos),
+                            blocks.get(local_x, y, local_z),
+                            "{:?}",
+                            pos
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn superflat_chunks_stack_the_layers_everywhere() {
+    let generator = SuperflatGenerator::default();
+    let chunk = IVec2XZ::new(-3, 8);
+    let blocks = generator.generate_chunk(chunk);
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_HEIGHT {
+                let pos = IVec3::new(x as i32 - 48, y as i32, z as i32 + 128);
+                assert_eq!(blocks.get(x, y, z), generator.block_at(pos), "{:?}", pos);
+            }
+        }
+    }
+}
+
+#[test]
+fn superflat_collider_is_on_top_of_the_layers() {
+    let (mesh, _) = create_chunk_mesh(
+        IVec2XZ::new(0, 0),
+        test_atlas(),
+        Arc::new(SuperflatGenerator::default()),
+        true,
+    );
+    let (vertices, indices) = get_verts_indices(&mesh.sections[0].solid_mesh);
+    let collider = Collider::trimesh(vertices, indices);
+    let hit = collider.cast_ray(
+        Vec3::ZERO,
+        Quat::IDENTITY,
+        Vec3::new(7.5, 20.0, 7.5),
+        Vec3::NEG_Y,
+        100.0,
+        true,
+    );
+    // Falling from y 20 onto the grass on top of the four layers.
+    assert_eq!(hit, Some(16.0));
+}
+
+/// A hash of every block of a chunk, in storage order. Built on `mix`, so it's the same in every Rust version.
+fn blocks_hash(blocks: &ChunkBlocks) -> u64 {
+    let mut hash = 0;
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for &block in blocks.column(x, z) {
+                hash = mix(hash ^ block as u64);
+            }
+        }
+    }
+    hash
+}
+
+/// Block hashes from before the heightmap was cached per chunk. Only generation changes that are meant to change
+/// the terrain may change these. All of them changed when the surface octaves became a normalized fBm, and
+/// again when a spline started shaping their heights.
+const GOLDEN_BLOCK_HASHES: [((i32, i32), u64); 4] = [
+    ((0, 0), 0x11de45fe6417df8a),
+    ((-1, -1), 0x293c0ff4ebd44fcc),
+    ((7, 9), 0x94af5d3dfe3bf58d),
+    ((-13, 4), 0xd18766ce4933610c),
+];
+
+#[test]
+fn chunk_blocks_match_golden_hashes() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    for ((x, z), hash) in GOLDEN_BLOCK_HASHES {
+        let blocks = generator.generate_chunk(IVec2XZ::new(x, z));
+        assert_eq!(blocks_hash(&blocks), hash, "chunk ({}, {})", x, z);
+    }
+}
+
+#[test]
+fn padded_blocks_match_block_at() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    for chunk in [IVec2XZ::new(0, 0), IVec2XZ::new(-1, -1)] {
+        let blocks = generator.generate_chunk(chunk);
+        let padded = generator.pad_chunk(chunk, &blocks);
+        let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+        let size = CHUNK_SIZE as i32;
+        for x in -1..=size {
+            for z in -1..=size {
+                let inside = (0..size).contains(&x) && (0..size).contains(&z);
+                for y in 0..CHUNK_HEIGHT as i32 {
+                    let local = IVec3::new(x, y, z);
+                    // The chunk itself is copied, the margin (corners included) is sampled.
+                    let expected = if inside {
+                        blocks.get(x as usize, y as usize, z as usize)
+                    } else {
+                        generator.block_at(origin + local)
+                    };
+                    assert_eq!(padded.get(local), expected, "{:?}", origin + local);
+                }
+            }
+        }
+        // Nothing above or below the world, nor past the margin.
+        assert_eq!(padded.get(IVec3::new(0, -1, 0)), BlockType::Air);
+        assert_eq!(
+            padded.get(IVec3::new(-1, CHUNK_HEIGHT as i32, 0)),
+            BlockType::Air
+        );
+        assert_eq!(padded.get(IVec3::new(-2, 10, 0)), BlockType::Air);
+    }
+}
+
+#[test]
+fn fbm_stays_in_the_range_of_the_noise() {
+    let perlin = Perlin::new(SEED);
+    for (octaves, lacunarity, persistence) in [
+        (1, 2.0, 0.5),
+        (3, 2.0, 1.0),
+        (6, 2.0, 0.5),
+        (8, 3.0, 0.8),
+        (4, 1.5, 1.5),
+    ] {
+        let (mut min, mut max) = (f64::MAX, f64::MIN);
+        for x in 0..200 {
+            for z in 0..200 {
+                let value = fbm(
+                    &perlin,
+                    x as f64 * 0.037,
+                    z as f64 * 0.041,
+                    octaves,
+                    lacunarity,
+                    persistence,
+                );
+                min = min.min(value);
+                max = max.max(value);
+            }
+        }
+        let params = (octaves, lacunarity, persistence);
+        assert!(min >= -1.0 && max <= 1.0, "{:?}: {}..{}", params, min, max);
+        // More octaves average out, but not into nothing.
+        assert!(min < -0.3 && max > 0.3, "{:?}: {}..{}", params, min, max);
+    }
+}
+
+#[test]
+fn fbm_with_one_octave_is_the_noise() {
+    let perlin = Perlin::new(SEED);
+    for (x, z) in [(0.3, 0.7), (-12.25, 4.5), (100.1, -3.9)] {
+        let noise = perlin.get([x, z]);
+        assert_eq!(fbm(&perlin, x, z, 1, 2.0, 0.5), noise);
+        // Octaves without any amplitude don't count either.
+        assert_eq!(fbm(&perlin, x, z, 5, 2.0, 0.0), noise);
+    }
+}
+
+#[test]
+fn the_surface_follows_the_height_spline() {
+    let params = WorldGenParams::default();
+    let outputs = params.height_spline.points.iter().map(|point| point.output);
+    let lowest = outputs.clone().fold(f32::MAX, f32::min);
+    let highest = outputs.fold(f32::MIN, f32::max);
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let (mut plains, mut mountains, mut columns) = (0, 0, 0);
+    for x in -40..40 {
+        for z in -40..40 {
+            let y = generator.surface_y(IVec2XZ::new(x * 37, z * 41)) as f32;
+            // Rivers only ever cut into the surface.
+            assert!(y <= highest, "{} at ({}, {})", y, x, z);
+            assert!(y >= lowest.min(RIVER_BED_HEIGHT as f32) - 1.0);
+            columns += 1;
+            plains += (70.0..=75.0).contains(&y) as usize;
+            mountains += (y >= 150.0) as usize;
+        }
+    }
+    // Mostly plains, with a few mountains.
+    assert!(plains * 3 > columns, "{} of {} plains", plains, columns);
+    assert!(
+        mountains > 0 && mountains * 20 < columns,
+        "{} of {} mountains",
+        mountains,
+        columns
+    );
+}
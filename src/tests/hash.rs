@@ -0,0 +1,133 @@
+use crate::game::hash::*;
+use crate::prelude::IVec2XZ;
+use crate::tests::SEED;
+use bevy::prelude::IVec3;
+
+const PREVIEW_SIZE: i32 = 256;
+
+/// A greyscale preview of `rand01` over a flat patch of the world, row by row.
+fn preview(seed: u64, y: i32) -> Vec<f32> {
+    (0..PREVIEW_SIZE)
+        .flat_map(|z| (0..PREVIEW_SIZE).map(move |x| rand01(seed, IVec3::new(x, y, z))))
+        .collect()
+}
+
+fn mean(values: impl Iterator<Item = f32> + Clone) -> f32 {
+    let count = values.clone().count() as f32;
+    values.sum::<f32>() / count
+}
+
+#[test]
+fn hashes_never_change() {
+    // Worlds are generated from these, changing them changes every world.
+    assert_eq!(
+        hash_pos(feature_seed(SEED, 1), IVec3::new(1, 2, 3)),
+        0x6116_7f3e_a73b_3bd1
+    );
+    assert_eq!(
+        hash_pos(feature_seed(SEED, 1), IVec3::new(-1, 70, -3)),
+        0x8671_6fdc_11e7_99ed
+    );
+    assert_eq!(
+        rng_for(feature_seed(SEED, 2), IVec3::ZERO).next_u64(),
+        0x6d33_62e2_c486_09f2
+    );
+}
+
+#[test]
+fn values_are_uniform() {
+    let values = preview(feature_seed(SEED, 0), 64);
+    let average = mean(values.iter().copied());
+    let variance = mean(values.iter().map(|v| (v - average) * (v - average)));
+    assert!((average - 0.5).abs() < 0.01, "mean {}", average);
+    assert!(
+        (variance - 1.0 / 12.0).abs() < 0.005,
+        "variance {}",
+        variance
+    );
+    assert!(values.iter().all(|v| (0.0..1.0).contains(v)));
+
+    let hits = (0..PREVIEW_SIZE)
+        .filter(|&x| chance(feature_seed(SEED, 0), IVec3::new(x, 0, 0), 0.25))
+        .count();
+    assert!((40..=88).contains(&hits), "{} hits", hits);
+}
+
+#[test]
+fn the_preview_has_no_lattice() {
+    let values = preview(feature_seed(SEED, 0), 64);
+    let at = |x: i32, z: i32| values[(z * PREVIEW_SIZE + x) as usize] - 0.5;
+
+    // A lattice shows up as rows or columns that are brighter than the rest.
+    for line in 0..PREVIEW_SIZE {
+        let row = mean((0..PREVIEW_SIZE).map(|x| at(x, line)));
+        let column = mean((0..PREVIEW_SIZE).map(|z| at(line, z)));
+        assert!(row.abs() < 0.1 && column.abs() < 0.1, "line {}", line);
+    }
+
+    // Or as blocks that look like the ones a step away, next door or a chunk over.
+    for step in [1, 2, 16, 32] {
+        let pairs =
+            || (0..PREVIEW_SIZE - step).flat_map(|z| (0..PREVIEW_SIZE - step).map(move |x| (x, z)));
+        let along_x = mean(pairs().map(|(x, z)| at(x, z) * at(x + step, z))) * 12.0;
+        let along_z = mean(pairs().map(|(x, z)| at(x, z) * at(x, z + step))) * 12.0;
+        assert!(
+            along_x.abs() < 0.03,
+            "x correlation {} at {}",
+            along_x,
+            step
+        );
+        assert!(
+            along_z.abs() < 0.03,
+            "z correlation {} at {}",
+            along_z,
+            step
+        );
+    }
+}
+
+#[test]
+fn seeds_and_salts_give_different_streams() {
+    let pos = IVec3::new(5, 60, -9);
+    let first = |seed| rng_for(seed, pos).next_u64();
+    assert_ne!(first(feature_seed(SEED, 1)), first(feature_seed(SEED, 2)));
+    assert_ne!(
+        first(feature_seed(SEED, 1)),
+        first(feature_seed(SEED + 1, 1))
+    );
+    assert_ne!(
+        first(feature_seed(SEED, 1)),
+        rng_for(feature_seed(SEED, 1), pos + IVec3::Y).next_u64()
+    );
+
+    let mut rng = rng_for(feature_seed(SEED, 1), pos);
+    let stream: Vec<u64> = (0..8).map(|_| rng.below(100)).collect();
+    let mut again = rng_for(feature_seed(SEED, 1), pos);
+    assert_eq!(stream, (0..8).map(|_| again.below(100)).collect::<Vec<_>>());
+    assert!(stream.iter().all(|&v| v < 100));
+    assert!((0..1000)
+        .map(|_| rng.rand01())
+        .all(|v| (0.0..1.0).contains(&v)));
+}
+
+#[test]
+fn chunks_get_their_own_streams() {
+    let seed = feature_seed(SEED, 3);
+    let chunk = IVec2XZ::new(-4, 11);
+    let stream = |chunk| {
+        let mut rng = chunk_rng(seed, chunk);
+        (0..16).map(|_| rng.next_u64()).collect::<Vec<_>>()
+    };
+    assert_eq!(stream(chunk), stream(chunk));
+    for neighbour in [
+        IVec2XZ::new(-3, 11),
+        IVec2XZ::new(-4, 12),
+        IVec2XZ::new(11, -4),
+    ] {
+        assert_ne!(stream(chunk), stream(neighbour), "{:?}", neighbour);
+    }
+    assert_ne!(
+        stream(chunk)[0],
+        chunk_rng(feature_seed(SEED, 4), chunk).next_u64()
+    );
+}
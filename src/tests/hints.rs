@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use crate::game::hints::*;
+
+#[test]
+fn hints_are_shown_once_in_order() {
+    let mut hints = HintQueue::default();
+    let mut seen = SeenHints::default();
+
+    hints.push(Hint::ReleaseCursor, &mut seen);
+    hints.push(Hint::Sprint, &mut seen);
+    hints.push(Hint::ReleaseCursor, &mut seen);
+
+    hints.tick(Duration::ZERO);
+    assert_eq!(hints.current(), Some(Hint::ReleaseCursor));
+
+    // Long enough for the first hint to run out.
+    hints.tick(Duration::from_secs(10));
+    assert_eq!(hints.current(), Some(Hint::Sprint));
+
+    hints.tick(Duration::from_secs(10));
+    assert_eq!(hints.current(), None);
+
+    // Seen hints don't come back.
+    hints.push(Hint::Sprint, &mut seen);
+    hints.tick(Duration::ZERO);
+    assert_eq!(hints.current(), None);
+}
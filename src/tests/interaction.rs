@@ -0,0 +1,478 @@
+use std::sync::Arc;
+
+use bevy::render::mesh::VertexAttributeValues;
+
+use crate::game::camera::{guard_edges, player_collider, PLAYER_HALF_HEIGHT};
+use crate::game::chunk::{create_chunk_mesh, remesh_chunk};
+use crate::game::cracks::{crack_stage, CRACK_STAGES};
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::interaction::*;
+use crate::game::leaves::LeafDecay;
+use crate::game::placement::WorldAabb;
+use crate::game::rules::WorldRules;
+use crate::game::target::BlockHit;
+use crate::prelude::*;
+use crate::tests::mesh::{chunk_meshes_hash, test_atlas};
+use crate::tests::SEED;
+
+/// A chunk at the origin with bedrock at y 0, stone up to y 63, water at y 64 and a flower on the stone next to it.
+fn chunk_map() -> ChunkMap {
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let column = blocks.column_mut(x, z);
+            column[0] = BlockType::Bedrock;
+            column[1..64].fill(BlockType::Stone);
+        }
+    }
+    blocks.set(3, 64, 3, BlockType::Water);
+    blocks.set(5, 64, 3, BlockType::Poppy);
+    let mut chunk_map = ChunkMap::default();
+    chunk_map.chunks.insert(IVec2XZ::new(0, 0), blocks);
+    chunk_map
+}
+
+#[test]
+fn the_reach_looks_through_fluids_but_not_plants() {
+    let chunk_map = chunk_map();
+    let through_water = reach_block(&chunk_map, Vec3::new(3.5, 66.5, 3.5), -Vec3::Y);
+    assert_eq!(through_water.map(|hit| hit.pos), Some(IVec3::new(3, 63, 3)));
+    let flower = reach_block(&chunk_map, Vec3::new(5.5, 66.5, 3.5), -Vec3::Y);
+    assert_eq!(flower.map(|hit| hit.pos), Some(IVec3::new(5, 64, 3)));
+
+    // Just out of reach.
+    let far = Vec3::new(8.5, 64.0 + REACH_DISTANCE + 0.1, 8.5);
+    assert_eq!(reach_block(&chunk_map, far, -Vec3::Y), None);
+    // Nothing is hit in the chunks that aren't loaded.
+    assert_eq!(
+        reach_block(&chunk_map, Vec3::new(-0.5, 64.5, 3.5), -Vec3::Y),
+        None
+    );
+}
+
+#[test]
+fn broken_blocks_turn_into_air_except_bedrock() {
+    let mut chunk_map = chunk_map();
+    let mut decay = LeafDecay::default();
+    let stone = IVec3::new(2, 63, 2);
+    let edit = break_block(&mut chunk_map, &mut decay, stone).unwrap();
+    assert_eq!(
+        (edit.pos, edit.old, edit.new),
+        (stone, BlockType::Stone, BlockType::Air)
+    );
+    assert_eq!(chunk_map.get_block(stone), Some(BlockType::Air));
+
+    // Air can't be broken again, and bedrock not at all.
+    assert_eq!(break_block(&mut chunk_map, &mut decay, stone), None);
+    assert_eq!(
+        break_block(&mut chunk_map, &mut decay, IVec3::new(2, 0, 2)),
+        None
+    );
+    assert_eq!(
+        chunk_map.get_block(IVec3::new(2, 0, 2)),
+        Some(BlockType::Bedrock)
+    );
+    assert_eq!(
+        break_block(&mut chunk_map, &mut decay, IVec3::new(-2, 10, 2)),
+        None
+    );
+}
+
+/// The player's box with their feet at `feet`.
+fn player_at(feet: Vec3) -> WorldAabb {
+    WorldAabb::of_collider(
+        &player_collider(),
+        &Transform::from_translation(feet + Vec3::Y),
+    )
+}
+
+/// A hit in the middle of a face of the block.
+fn hit(pos: IVec3, normal: IVec3) -> BlockHit {
+    BlockHit {
+        pos,
+        normal,
+        distance: 1.0,
+        point: pos.as_vec3() + Vec3::splat(0.5) + normal.as_vec3() * 0.5,
+    }
+}
+
+#[test]
+fn slabs_go_in_the_half_that_was_hit() {
+    let mut chunk_map = chunk_map();
+    let mut decay = LeafDecay::default();
+    let far_away = player_at(Vec3::new(100.5, 64.0, 100.5));
+    let mut place = |hit: BlockHit| {
+        place_block(
+            &mut chunk_map,
+            &mut decay,
+            hit,
+            BlockType::StoneSlab,
+            far_away,
+        )
+        .unwrap()
+        .new
+    };
+    // On the floor it's a bottom slab.
+    assert_eq!(
+        place(hit(IVec3::new(2, 63, 2), IVec3::Y)),
+        BlockType::StoneSlab
+    );
+    // Under a ceiling it's a top one.
+    assert_eq!(
+        place(hit(IVec3::new(2, 66, 2), IVec3::NEG_Y)),
+        BlockType::StoneSlabTop
+    );
+    // Against a wall it's the half of the wall that was hit.
+    let mut high = hit(IVec3::new(8, 64, 8), IVec3::X);
+    high.point.y = 64.75;
+    assert_eq!(place(high), BlockType::StoneSlabTop);
+    let mut low = hit(IVec3::new(8, 65, 8), IVec3::X);
+    low.point.y = 65.25;
+    assert_eq!(place(low), BlockType::StoneSlab);
+}
+
+#[test]
+fn stairs_are_turned_by_the_hit() {
+    let stairs = |normal: IVec3, point: Vec3| {
+        let mut hit = hit(IVec3::new(2, 63, 2), normal);
+        hit.point = point;
+        placed_shape(BlockType::StoneStairs, hit).shape()
+    };
+    // Against a side their back is on the block that was hit, in the half that was hit.
+    assert_eq!(
+        stairs(IVec3::X, Vec3::new(3.0, 63.25, 2.5)),
+        BlockShape::stairs(BlockFace::Left, false)
+    );
+    assert_eq!(
+        stairs(IVec3::NEG_Z, Vec3::new(2.5, 63.75, 2.0)),
+        BlockShape::stairs(BlockFace::Front, true)
+    );
+    // On a floor they climb away from the edge closest to the hit, upside down under a ceiling.
+    assert_eq!(
+        stairs(IVec3::Y, Vec3::new(2.5, 64.0, 2.9)),
+        BlockShape::stairs(BlockFace::Back, false)
+    );
+    assert_eq!(
+        stairs(IVec3::Y, Vec3::new(2.1, 64.0, 2.6)),
+        BlockShape::stairs(BlockFace::Right, false)
+    );
+    assert_eq!(
+        stairs(IVec3::NEG_Y, Vec3::new(2.5, 63.0, 2.2)),
+        BlockShape::stairs(BlockFace::Front, true)
+    );
+    // The other blocks have one shape.
+    assert_eq!(
+        placed_shape(BlockType::Stone, hit(IVec3::new(2, 63, 2), IVec3::X)),
+        BlockType::Stone
+    );
+}
+
+#[test]
+fn blocks_are_placed_against_the_hit_face() {
+    let mut chunk_map = chunk_map();
+    let mut decay = LeafDecay::default();
+    let far_away = player_at(Vec3::new(100.5, 64.0, 100.5));
+    let edit = place_block(
+        &mut chunk_map,
+        &mut decay,
+        hit(IVec3::new(2, 63, 2), IVec3::Y),
+        BlockType::Log,
+        far_away,
+    )
+    .unwrap();
+    assert_eq!(
+        (edit.pos, edit.old, edit.new),
+        (IVec3::new(2, 64, 2), BlockType::Air, BlockType::Log)
+    );
+    assert_eq!(
+        chunk_map.get_block(IVec3::new(2, 64, 2)),
+        Some(BlockType::Log)
+    );
+
+    // Not into a block, not from inside one, and not into the chunks that aren't loaded.
+    let stone_side = hit(IVec3::new(2, 62, 2), IVec3::X);
+    assert_eq!(
+        place_block(
+            &mut chunk_map,
+            &mut decay,
+            stone_side,
+            BlockType::Dirt,
+            far_away
+        ),
+        None
+    );
+    let inside = hit(IVec3::new(2, 63, 2), IVec3::ZERO);
+    assert_eq!(
+        place_block(
+            &mut chunk_map,
+            &mut decay,
+            inside,
+            BlockType::Dirt,
+            far_away
+        ),
+        None
+    );
+    let outside = hit(IVec3::new(0, 63, 2), IVec3::NEG_X);
+    assert_eq!(
+        place_block(
+            &mut chunk_map,
+            &mut decay,
+            outside,
+            BlockType::Dirt,
+            far_away
+        ),
+        None
+    );
+
+    // Fluids are replaced, plants aren't.
+    let water = hit(IVec3::new(3, 63, 3), IVec3::Y);
+    assert!(place_block(&mut chunk_map, &mut decay, water, BlockType::Sand, far_away).is_some());
+    let flower = hit(IVec3::new(5, 63, 3), IVec3::Y);
+    assert_eq!(
+        place_block(
+            &mut chunk_map,
+            &mut decay,
+            flower,
+            BlockType::Sand,
+            far_away
+        ),
+        None
+    );
+}
+
+#[test]
+fn solid_blocks_cant_be_placed_inside_the_player() {
+    let mut chunk_map = chunk_map();
+    let mut decay = LeafDecay::default();
+    let player = player_at(Vec3::new(8.5, 64.0, 8.5));
+    let feet = hit(IVec3::new(8, 63, 8), IVec3::Y);
+    assert_eq!(
+        place_block(&mut chunk_map, &mut decay, feet, BlockType::Stone, player),
+        None
+    );
+    // Right next to the player is fine, and so is water, it doesn't push them out.
+    let beside = hit(IVec3::new(9, 63, 8), IVec3::Y);
+    assert!(place_block(&mut chunk_map, &mut decay, beside, BlockType::Stone, player).is_some());
+    assert!(place_block(&mut chunk_map, &mut decay, feet, BlockType::Water, player).is_some());
+}
+
+#[test]
+fn sneaking_places_on_a_chest_instead_of_opening_it() {
+    let mut chunk_map = chunk_map();
+    let mut decay = LeafDecay::default();
+    let chest = IVec3::new(4, 63, 4);
+    chunk_map.set_block(chest, BlockType::Chest);
+    let far_away = player_at(Vec3::new(100.5, 64.0, 100.5));
+    let on_chest = hit(chest, IVec3::Y);
+
+    let opened = use_block(
+        &mut chunk_map,
+        &mut decay,
+        on_chest,
+        BlockType::Dirt,
+        far_away,
+        false,
+    );
+    assert_eq!(
+        opened,
+        Some(BlockUse::Interacted(BlockInteracted {
+            pos: chest,
+            block: BlockType::Chest,
+        }))
+    );
+    assert_eq!(chunk_map.get_block(chest + IVec3::Y), Some(BlockType::Air));
+
+    let placed = use_block(
+        &mut chunk_map,
+        &mut decay,
+        on_chest,
+        BlockType::Dirt,
+        far_away,
+        true,
+    );
+    assert!(matches!(placed, Some(BlockUse::Placed(edit)) if edit.pos == chest + IVec3::Y));
+    assert_eq!(chunk_map.get_block(chest + IVec3::Y), Some(BlockType::Dirt));
+    assert_eq!(chunk_map.get_block(chest), Some(BlockType::Chest));
+}
+
+#[test]
+fn a_click_on_stone_places_with_or_without_sneaking() {
+    let mut chunk_map = chunk_map();
+    let mut decay = LeafDecay::default();
+    let far_away = player_at(Vec3::new(100.5, 64.0, 100.5));
+    for (x, sneaking) in [(6, false), (7, true)] {
+        let stone = IVec3::new(x, 63, 6);
+        let used = use_block(
+            &mut chunk_map,
+            &mut decay,
+            hit(stone, IVec3::Y),
+            BlockType::Dirt,
+            far_away,
+            sneaking,
+        );
+        assert!(matches!(used, Some(BlockUse::Placed(edit)) if edit.pos == stone + IVec3::Y));
+    }
+    assert!(!BlockType::Stone.has_interaction());
+    assert!(BlockType::Chest.has_interaction());
+}
+
+#[test]
+fn blocks_break_after_being_held_on_for_their_hardness() {
+    let stone = (IVec3::new(1, 63, 1), BlockType::Stone);
+    let dirt = (IVec3::new(2, 63, 1), BlockType::Dirt);
+    let mut progress = BreakProgress::default();
+
+    // The first frame only starts it.
+    assert_eq!(progress.hold(Some(stone), 0.1), None);
+    assert_eq!(progress.hold(Some(stone), 1.0), None);
+    assert_eq!(progress.fraction(), Some((stone.0, 1.0 / 1.5)));
+    assert_eq!(crack_stage(1.0 / 1.5), 3);
+
+    // Looking away starts over, so does letting go.
+    assert_eq!(progress.hold(Some(dirt), 1.0), None);
+    assert_eq!(progress.fraction(), Some((dirt.0, 0.0)));
+    progress.release();
+    assert_eq!(progress.fraction(), None);
+    assert_eq!(progress.hold(Some(dirt), 0.1), None);
+    assert_eq!(progress.hold(Some(dirt), 0.3), None);
+    assert_eq!(progress.hold(Some(dirt), 0.3), Some(dirt.0));
+    assert_eq!(progress.fraction(), None);
+
+    // Plants go right away, bedrock never does.
+    let poppy = (IVec3::new(5, 64, 3), BlockType::Poppy);
+    assert_eq!(progress.hold(Some(poppy), 0.0), Some(poppy.0));
+    let bedrock = (IVec3::new(1, 0, 1), BlockType::Bedrock);
+    for _ in 0..100 {
+        assert_eq!(progress.hold(Some(bedrock), 1.0), None);
+    }
+    assert_eq!(progress.fraction(), None);
+    assert_eq!(crack_stage(0.0), 0);
+    assert_eq!(crack_stage(1.0), CRACK_STAGES - 1);
+}
+
+#[test]
+fn sneaking_players_stop_at_the_edge_and_slide_along_it() {
+    let mut chunk_map = chunk_map();
+    // A pillar to stand on, with the stone around it gone.
+    for x in 4..=8 {
+        for z in 4..=8 {
+            if (x, z) != (6, 6) {
+                chunk_map.set_block(IVec3::new(x, 63, z), BlockType::Air);
+            }
+        }
+    }
+    let body = Vec3::new(6.5, 64.0 + PLAYER_HALF_HEIGHT + 0.1, 6.5);
+
+    // Slowly, the middle of the feet stays on the pillar.
+    let slow = Vec3::new(0.5, 0.0, -0.5);
+    assert_eq!(guard_edges(&chunk_map, body, slow), slow);
+
+    // Off the side is stopped, along it isn't.
+    let edge = body + Vec3::new(0.4, 0.0, 0.0);
+    let step = Vec3::new(1.0, 0.0, 0.0);
+    assert_eq!(guard_edges(&chunk_map, edge, step), Vec3::ZERO);
+    let along = Vec3::new(1.0, 0.0, -0.5);
+    assert_eq!(
+        guard_edges(&chunk_map, edge, along),
+        Vec3::new(0.0, 0.0, -0.5)
+    );
+
+    // In the corner, neither way is on the pillar.
+    let corner = body + Vec3::new(0.4, 0.0, 0.4);
+    assert_eq!(
+        guard_edges(&chunk_map, corner, Vec3::new(1.0, 0.0, 1.0)),
+        Vec3::ZERO
+    );
+}
+
+#[test]
+fn placed_fluids_get_a_lowered_top() {
+    let generator: Arc<dyn WorldGenerator> =
+        Arc::new(DefaultGenerator::new(SEED, WorldRules::default()));
+    let mut chunk_map = chunk_map();
+    let mut decay = LeafDecay::default();
+    let far_away = player_at(Vec3::new(100.5, 64.0, 100.5));
+    let top = hit(IVec3::new(8, 63, 8), IVec3::Y);
+    place_block(&mut chunk_map, &mut decay, top, BlockType::Lava, far_away).unwrap();
+
+    let blocks = chunk_map.chunks[&IVec2XZ::new(0, 0)].clone();
+    let (meshes, _) = remesh_chunk(
+        IVec2XZ::new(0, 0),
+        vec![ChunkSection::containing(top.pos + top.normal).index],
+        blocks,
+        ChunkMap::default(),
+        test_atlas(),
+        generator,
+        true,
+    );
+    // The lava has a mesh of its own, apart from the water next to it.
+    let fluid_meshes = &meshes.sections[0].fluid_meshes;
+    let blocks: Vec<BlockType> = fluid_meshes.iter().map(|fluid| fluid.block).collect();
+    assert_eq!(blocks, [BlockType::Water, BlockType::Lava]);
+    let fluid = &fluid_meshes[1];
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        fluid.mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("missing positions");
+    };
+    assert_eq!(positions.len(), 5 * 4);
+    let lava_top = positions
+        .iter()
+        .map(|&position| Vec3::from(position) + fluid.center)
+        .filter(|position| {
+            (8.0..=9.0).contains(&position.x)
+                && (8.0..=9.0).contains(&position.z)
+                && (position.y - 64.9).abs() < 1e-4
+        })
+        .count();
+    // The top, and the upper corners of the four sides that stop at it.
+    assert_eq!(lava_top, 4 + 4 * 2);
+    assert!(positions
+        .iter()
+        .all(|&position| (Vec3::from(position) + fluid.center).y < 65.0 - 1e-4));
+}
+
+#[test]
+fn the_selection_cycles_through_the_placeable_blocks() {
+    let choices: Vec<BlockType> = SelectedBlock::choices().collect();
+    assert!(choices.contains(&BlockType::Water) && choices.contains(&BlockType::Lava));
+    assert!(!choices.contains(&BlockType::Air) && !choices.contains(&BlockType::Bedrock));
+    assert!(!choices.iter().any(|block| block.is_plant()));
+
+    let mut selected = SelectedBlock::default();
+    let start = selected;
+    selected.cycle(1);
+    assert_ne!(selected, start);
+    selected.cycle(-1);
+    assert_eq!(selected, start);
+    // All the way round.
+    selected.cycle(-(choices.len() as i32));
+    assert_eq!(selected, start);
+    selected.cycle(-1);
+    assert_eq!(selected.0, *choices.last().unwrap());
+}
+
+#[test]
+fn remeshing_an_unedited_chunk_gives_the_same_mesh() {
+    let generator: Arc<dyn WorldGenerator> =
+        Arc::new(DefaultGenerator::new(SEED, WorldRules::default()));
+    let position = IVec2XZ::new(3, -2);
+    let (mesh, blocks) = create_chunk_mesh(position, test_atlas(), generator.clone(), true);
+
+    // One neighbour is loaded, the others come from the generator.
+    let mut neighbors = ChunkMap::default();
+    let east = position + IVec2XZ::new(1, 0);
+    neighbors
+        .chunks
+        .insert(east, generator.generate_chunk(east));
+    let (remeshed, _) = remesh_chunk(
+        position,
+        (0..SECTIONS).collect(),
+        blocks,
+        neighbors,
+        test_atlas(),
+        generator,
+        true,
+    );
+    assert_eq!(chunk_meshes_hash(&remeshed), chunk_meshes_hash(&mesh));
+}
@@ -0,0 +1,73 @@
+use bevy::math::Vec3Swizzles;
+
+use crate::game::generator::{FloatingIslandsGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+#[test]
+fn islands_float_with_grass_on_top() {
+    let generator = FloatingIslandsGenerator::new(SEED, WorldRules::default());
+    let (mut columns, mut islands, mut ores) = (0, 0, 0);
+    for chunk in IVec2XZ::new(0, 0).within(3) {
+        let blocks = generator.generate_chunk(chunk);
+        let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let column = blocks.column(x, z);
+                columns += 1;
+                islands += column.iter().any(|&block| block != BlockType::Air) as usize;
+                for (y, &block) in column.iter().enumerate() {
+                    if block == BlockType::Air {
+                        continue;
+                    }
+                    // Nothing under the islands, not even bedrock.
+                    assert!(
+                        (ISLAND_BOTTOM..=ISLAND_TOP).contains(&(y as i32)),
+                        "{:?} at y {}",
+                        block,
+                        y
+                    );
+                    // Every block facing up is grass, and the grass is never covered.
+                    assert_eq!(
+                        column[y + 1] == BlockType::Air,
+                        block == BlockType::Grass,
+                        "({}, {}, {})",
+                        x,
+                        y,
+                        z
+                    );
+                    ores += ORES.iter().any(|veins| veins.ore == block) as usize;
+                }
+                // The neighbouring chunks see the same blocks at the border.
+                if x == 0 || z == CHUNK_SIZE - 1 {
+                    for y in ISLAND_BOTTOM..=ISLAND_TOP {
+                        let pos = origin + IVec3::new(x as i32, y, z as i32);
+                        assert_eq!(generator.block_at(pos), column[y as usize], "{:?}", pos);
+                    }
+                }
+            }
+        }
+    }
+    assert!(
+        islands * 10 > columns && islands * 10 < columns * 8,
+        "{} of {} columns have islands",
+        islands,
+        columns
+    );
+    assert!(ores > 0);
+}
+
+#[test]
+fn the_player_spawns_on_an_island() {
+    let generator = FloatingIslandsGenerator::new(SEED, WorldRules::default());
+    let spawn = generator.spawn_point();
+    assert_ne!(spawn, WORLD_SPAWN);
+    assert!(spawn.xz().distance(WORLD_SPAWN.xz()) <= ISLAND_SPAWN_SEARCH as f32 * 1.5);
+
+    // Standing on the grass, with room for the head.
+    let ground = spawn.floor().as_ivec3() - IVec3::Y * 2;
+    assert_eq!(generator.block_at(ground), BlockType::Grass);
+    assert_eq!(generator.block_at(ground + IVec3::Y), BlockType::Air);
+    assert_eq!(generator.block_at(ground + IVec3::Y * 2), BlockType::Air);
+}
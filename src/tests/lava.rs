@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use crate::game::chunk::{collider_geometry, create_chunk_mesh, face_visible, get_verts_indices};
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+use crate::tests::SEED;
+
+/// The first chunk along the x axis with a lava lake in its caves.
+fn lava_chunk(generator: &DefaultGenerator) -> (IVec2XZ, ChunkBlocks) {
+    (0..64)
+        .map(|x| IVec2XZ::new(x, 0))
+        .map(|chunk| (chunk, generator.generate_chunk(chunk)))
+        .find(|(_, blocks)| {
+            (0..CHUNK_SIZE).any(|x| {
+                (0..CHUNK_SIZE)
+                    .any(|z| blocks.column(x, z)[..=LAVA_HEIGHT].contains(&BlockType::Lava))
+            })
+        })
+        .expect("no lava lake in the caves")
+}
+
+#[test]
+fn caves_below_the_lava_height_are_flooded() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    for x in -2..2 {
+        for z in -2..2 {
+            let blocks = generator.generate_chunk(IVec2XZ::new(x, z));
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let column = blocks.column(x, z);
+                    assert!(!column[..=LAVA_HEIGHT].contains(&BlockType::Air));
+                    // Right above the lakes the caves are dry, the lava ponds are up on the surface.
+                    assert!(!column[LAVA_HEIGHT + 1..40].contains(&BlockType::Lava));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn lava_lakes_continue_across_chunk_borders() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let (chunk, _) = lava_chunk(&generator);
+    let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+    // The neighbours see the same lava past their borders as the chunk generates.
+    for (neighbor, offset) in [
+        (IVec2XZ::new(-1, 0), -1),
+        (IVec2XZ::new(1, 0), CHUNK_SIZE as i32),
+    ] {
+        let blocks = generator.generate_chunk(chunk + neighbor);
+        let local_x = offset.rem_euclid(CHUNK_SIZE as i32) as usize;
+        for z in 0..CHUNK_SIZE {
+            for y in 0..=LAVA_HEIGHT {
+                let pos = origin + IVec3::new(offset, y as i32, z as i32);
+                assert_eq!(
+                    generator.block_at(pos),
+                    blocks.get(local_x, y, z),
+                    "{:?}",
+                    pos
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn lava_has_no_collider() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let (chunk, blocks) = lava_chunk(&generator);
+    let (meshes, _) = create_chunk_mesh(chunk, test_atlas(), Arc::new(generator.clone()), true);
+
+    // The colliders are built from the solid meshes only, and there are no plants around the lava to leave out.
+    for section in &meshes.sections {
+        let (_, all) = get_verts_indices(&section.solid_mesh);
+        let (_, solid) = collider_geometry(&section.solid_mesh, chunk, &blocks);
+        assert_eq!(solid.len(), all.len());
+    }
+
+    // Every visible fluid face is in the fluid meshes instead.
+    let fluid: Vec<[u32; 3]> = meshes
+        .sections
+        .iter()
+        .flat_map(|section| &section.fluid_meshes)
+        .flat_map(|fluid| get_verts_indices(&fluid.mesh).1)
+        .collect();
+    let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+    let block_at = |local: IVec3| {
+        if local.y < 0 || local.y >= CHUNK_HEIGHT as i32 {
+            BlockType::Air
+        } else if (0..CHUNK_SIZE as i32).contains(&local.x)
+            && (0..CHUNK_SIZE as i32).contains(&local.z)
+        {
+            blocks.get(local.x as usize, local.y as usize, local.z as usize)
+        } else {
+            generator.block_at(origin + local)
+        }
+    };
+    let mut fluid_faces = 0;
+    for x in 0..CHUNK_SIZE as i32 {
+        for z in 0..CHUNK_SIZE as i32 {
+            for y in 0..CHUNK_HEIGHT as i32 {
+                let cell = IVec3::new(x, y, z);
+                let block = block_at(cell);
+                if !matches!(block, BlockType::Water | BlockType::Lava) {
+                    continue;
+                }
+                fluid_faces += BlockFace::ALL
+                    .into_iter()
+                    .filter(|&face| face_visible(block, block_at(cell + face.offset()), face))
+                    .count();
+            }
+        }
+    }
+    assert!(fluid_faces > 0);
+    assert_eq!(fluid.len(), fluid_faces * 2);
+}
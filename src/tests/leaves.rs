@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::game::generator::{SharedGenerator, SuperflatGenerator};
+use crate::game::interaction::break_block;
+use crate::game::leaves::*;
+use crate::game::trees::{Tree, TreeSpecies};
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+
+/// Runs the decay like the game would, breaking the leaves it returns, until nothing is left to check.
+fn decay(world: &mut HashMap<IVec3, BlockType>, changed: IVec3) -> usize {
+    let block_at = |world: &HashMap<IVec3, BlockType>, pos| {
+        Some(world.get(&pos).copied().unwrap_or(BlockType::Air))
+    };
+    let mut decay = LeafDecay::default();
+    decay.block_changed(changed, 0, |pos| block_at(world, pos));
+
+    let mut broken = 0;
+    let mut tick = 0;
+    while decay.pending() > 0 {
+        tick += 1;
+        assert!(tick < 10_000, "the decay never settles");
+        let decayed = decay.tick(tick, |pos| block_at(world, pos));
+        assert!(decayed.len() <= DECAY_CHECKS_PER_TICK);
+        for pos in decayed {
+            world.remove(&pos);
+            broken += 1;
+            decay.block_changed(pos, tick, |pos| block_at(world, pos));
+        }
+    }
+    broken
+}
+
+#[test]
+fn detached_leaves_decay() {
+    let mut world = HashMap::new();
+    for x in 0..3 {
+        for y in 0..3 {
+            for z in 0..3 {
+                world.insert(IVec3::new(x, y, z), BlockType::Leaves);
+            }
+        }
+    }
+    // The log that held the cube was just broken from under it.
+    assert_eq!(decay(&mut world, IVec3::new(1, -1, 1)), 27);
+    assert!(world.is_empty());
+}
+
+#[test]
+fn leaves_within_reach_of_a_log_stay() {
+    let tree = Tree {
+        base: IVec3::new(0, 71, 0),
+        trunk_height: 5,
+        species: TreeSpecies::Oak,
+    };
+    let mut world: HashMap<IVec3, BlockType> = tree.blocks().collect();
+    let leaves = world.values().filter(|&&b| b == BlockType::Leaves).count();
+    assert_eq!(decay(&mut world, tree.base + IVec3::Y * 4), 0);
+    assert_eq!(
+        world.values().filter(|&&b| b == BlockType::Leaves).count(),
+        leaves
+    );
+}
+
+#[test]
+fn only_leaves_past_the_reach_decay() {
+    // A log with a row of leaves sticking out, longer than the reach.
+    let mut world = HashMap::from([(IVec3::ZERO, BlockType::Log)]);
+    for x in 1..=LEAF_REACH + 2 {
+        world.insert(IVec3::new(x, 0, 0), BlockType::Leaves);
+    }
+    // The block past the end of the row was broken.
+    assert_eq!(decay(&mut world, IVec3::new(LEAF_REACH + 3, 0, 0)), 2);
+    assert!((1..=LEAF_REACH).all(|x| world.contains_key(&IVec3::new(x, 0, 0))));
+}
+
+#[test]
+fn leaves_next_to_unloaded_blocks_stay() {
+    let block_at = |pos: IVec3| match pos.x {
+        0 => Some(BlockType::Leaves),
+        x if x < 0 => None,
+        _ => Some(BlockType::Air),
+    };
+    assert!(is_supported(IVec3::ZERO, block_at));
+    assert!(!is_supported(IVec3::new(5, 0, 0), |_| Some(BlockType::Air)));
+}
+
+#[test]
+fn felling_a_trunk_takes_the_canopy_with_it() {
+    let tree = Tree {
+        base: IVec3::new(8, 64, 8),
+        trunk_height: 5,
+        species: TreeSpecies::Oak,
+    };
+    let mut chunk_map = ChunkMap::default();
+    chunk_map.insert_chunk(IVec2XZ::new(0, 0), ChunkBlocks::default());
+    for (pos, block) in tree.blocks() {
+        chunk_map.set_block(pos, block);
+    }
+    let leaves = |world: &World| {
+        let chunk_map = world.resource::<ChunkMap>();
+        chunk_map.chunks[&IVec2XZ::new(0, 0)]
+            .iter()
+            .filter(|&(_, block)| block.is_leaves())
+            .count()
+    };
+
+    let mut world = World::new();
+    world.insert_resource(chunk_map);
+    world.init_resource::<LeafDecay>();
+    world.init_resource::<ChunksLoaded>();
+    world.init_resource::<AmbientOcclusion>();
+    world.init_resource::<PackedMeshes>();
+    world.insert_resource(test_atlas());
+    world.insert_resource(SharedGenerator(Arc::new(SuperflatGenerator::default())));
+    let mut schedule = Schedule::default();
+    schedule.add_systems(decay_leaves);
+    let canopy = leaves(&world);
+    assert!(canopy > 0);
+
+    // The canopy hangs on the rest of the trunk while only the bottom log is gone.
+    let fell = |world: &mut World, logs: std::ops::Range<i32>| {
+        world.resource_scope(|world, mut chunk_map: Mut<ChunkMap>| {
+            let mut decay = world.resource_mut::<LeafDecay>();
+            for y in logs {
+                break_block(&mut chunk_map, &mut decay, tree.base + IVec3::Y * y).unwrap();
+            }
+        });
+    };
+    fell(&mut world, 0..1);
+    for _ in 0..DECAY_DELAY + DECAY_JITTER + 1 {
+        schedule.run(&mut world);
+    }
+    assert_eq!(leaves(&world), canopy);
+
+    fell(&mut world, 1..tree.trunk_height);
+    for _ in 0..1000 {
+        schedule.run(&mut world);
+    }
+    assert_eq!(leaves(&world), 0);
+    assert_eq!(world.resource::<LeafDecay>().pending(), 0);
+}
@@ -0,0 +1,310 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use bevy::render::mesh::VertexAttributeValues;
+
+use crate::game::chunk::{
+    chunk_system, handle_mesh_tasks, remesh_rescaled_chunks, spawn_chunk_tasks, ChunkMaterials,
+};
+use crate::game::freecam::CameraMode;
+use crate::game::generator::{SharedGenerator, SuperflatGenerator};
+use crate::game::lod::lod_scale;
+use crate::game::packed::is_packed;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+
+/// How many frames the streaming gets to load a circle of chunks.
+const MAX_FRAMES: usize = 5000;
+
+/// A chunk event, in the order the systems sent them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lifecycle {
+    Requested(IVec2XZ),
+    Ready(IVec2XZ, Entity),
+    Unloaded(IVec2XZ),
+}
+
+#[derive(Resource, Default)]
+struct EventLog(Vec<Lifecycle>);
+
+fn record_events(
+    mut requested: EventReader<ChunkLoadRequested>,
+    mut ready: EventReader<ChunkReady>,
+    mut unloaded: EventReader<ChunkUnloaded>,
+    mut log: ResMut<EventLog>,
+) {
+    log.0
+        .extend(requested.iter().map(|event| Lifecycle::Requested(event.0)));
+    log.0.extend(
+        ready
+            .iter()
+            .map(|event| Lifecycle::Ready(event.position, event.entity)),
+    );
+    log.0
+        .extend(unloaded.iter().map(|event| Lifecycle::Unloaded(event.0)));
+}
+
+/// The chunk streaming with the real systems and task pool, but nothing drawn. The chunks are superflat, they're
+/// quick to generate.
+fn streaming_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .add_asset::<Mesh>()
+        .add_event::<ChunkLoadRequested>()
+        .add_event::<ChunkReady>()
+        .add_event::<ChunkUnloaded>()
+        .add_event::<ChunkRescaled>()
+        .init_resource::<ChunksLoaded>()
+        .init_resource::<ChunkLoader>()
+        .init_resource::<ChunkMap>()
+        .init_resource::<MeshCache>()
+        .init_resource::<MeshInsertBudget>()
+        .init_resource::<CameraMode>()
+        .init_resource::<AmbientOcclusion>()
+        .init_resource::<PackedMeshes>()
+        .init_resource::<EventLog>()
+        .insert_resource(Generating(true))
+        .insert_resource(test_atlas())
+        .insert_resource(SharedGenerator(Arc::new(SuperflatGenerator::default())))
+        .insert_resource(ChunkMaterials {
+            solid: Handle::default(),
+            packed: Handle::default(),
+            water: Handle::default(),
+            lava: Handle::default(),
+        })
+        .insert_resource(PlayerPos {
+            pos: Vec3::ZERO,
+            rot: Quat::IDENTITY,
+        })
+        .add_systems(
+            Update,
+            (
+                chunk_system,
+                spawn_chunk_tasks,
+                handle_mesh_tasks,
+                remesh_rescaled_chunks,
+                record_events,
+            )
+                .chain(),
+        );
+    app.world.spawn((Camera3d::default(), Transform::default()));
+    app
+}
+
+/// Moves the camera, and the player with it.
+fn move_camera(app: &mut App, position: Vec3) {
+    let mut camera = app.world.query_filtered::<&mut Transform, With<Camera3d>>();
+    camera.single_mut(&mut app.world).translation = position;
+    app.world.resource_mut::<PlayerPos>().pos = position;
+}
+
+/// The chunks in the load distance of a centre chunk, the same circle the streaming loads.
+fn load_circle(center: IVec2XZ) -> Vec<IVec2XZ> {
+    let radius = LOAD_DISTANCE;
+    (-radius..=radius)
+        .flat_map(|x| (-radius..=radius).map(move |z| IVec2XZ::new(x, z)))
+        .filter(|offset| offset.x * offset.x + offset.z * offset.z <= radius * radius)
+        .map(|offset| center + offset)
+        .collect()
+}
+
+/// Runs frames until every chunk in the load distance of the centre is ready.
+fn settle(app: &mut App, center: IVec2XZ) {
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        let chunks = app.world.resource::<ChunksLoaded>();
+        if load_circle(center)
+            .into_iter()
+            .all(|chunk| chunks.is_ready(chunk))
+        {
+            return;
+        }
+        // The tasks run on other threads, they're given a moment.
+        thread::sleep(Duration::from_micros(200));
+    }
+    panic!("the chunks around {:?} didn't load", center);
+}
+
+fn take_log(app: &mut App) -> Vec<Lifecycle> {
+    std::mem::take(&mut app.world.resource_mut::<EventLog>().0)
+}
+
+#[test]
+fn chunks_are_requested_then_ready_then_unloaded() {
+    let mut app = streaming_app();
+    let start = IVec2XZ::new(0, 0);
+    settle(&mut app, start);
+
+    // Every chunk in the load distance was asked for once, and was ready after that with the entity it's loaded as.
+    let log = take_log(&mut app);
+    let loaded = load_circle(start);
+    for &chunk in &loaded {
+        let requested = log
+            .iter()
+            .position(|&event| event == Lifecycle::Requested(chunk))
+            .unwrap_or_else(|| panic!("{:?} wasn't requested", chunk));
+        let ready: Vec<(usize, Entity)> = log
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &event)| match event {
+                Lifecycle::Ready(position, entity) if position == chunk => Some((index, entity)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            ready.len(),
+            1,
+            "{:?} was ready {} times",
+            chunk,
+            ready.len()
+        );
+        assert!(requested < ready[0].0);
+        assert_eq!(
+            app.world.resource::<ChunksLoaded>().state(chunk),
+            Some(ChunkState::Ready { entity: ready[0].1 })
+        );
+    }
+    assert!(!log
+        .iter()
+        .any(|event| matches!(event, Lifecycle::Unloaded(_))));
+
+    // Far enough away that nothing stays, every chunk that was ready is unloaded once before the new ones are.
+    let far = IVec2XZ::new(4 * UNLOAD_DISTANCE, 0);
+    move_camera(
+        &mut app,
+        Vec3::new(far.x as f32 * CHUNK_SIZE as f32, 80.0, 8.0),
+    );
+    settle(&mut app, far);
+    let log = take_log(&mut app);
+    let unloaded: Vec<IVec2XZ> = log
+        .iter()
+        .filter_map(|&event| match event {
+            Lifecycle::Unloaded(chunk) => Some(chunk),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(unloaded.len(), loaded.len());
+    assert!(unloaded.iter().all(|chunk| loaded.contains(chunk)));
+    let first_ready = log
+        .iter()
+        .position(|event| matches!(event, Lifecycle::Ready(..)))
+        .unwrap();
+    let last_unloaded = log
+        .iter()
+        .rposition(|event| matches!(event, Lifecycle::Unloaded(_)))
+        .unwrap();
+    assert!(last_unloaded < first_ready);
+    assert!(load_circle(far)
+        .iter()
+        .all(|&chunk| log.contains(&Lifecycle::Requested(chunk))));
+}
+
+#[test]
+fn packed_sections_are_drawn_by_a_child_at_their_corner() {
+    let mut app = streaming_app();
+    app.insert_resource(PackedMeshes(true));
+    settle(&mut app, IVec2XZ::new(0, 0));
+
+    let mut sections = app
+        .world
+        .query::<(&ChunkSection, Option<&Handle<Mesh>>, Option<&ChunkCollider>)>();
+    let mut packed = app
+        .world
+        .query_filtered::<(&Parent, &Transform, &Handle<Mesh>), With<PackedSection>>();
+    let children: Vec<(Entity, Vec3, Handle<Mesh>)> = packed
+        .iter(&app.world)
+        .map(|(parent, transform, mesh)| (parent.get(), transform.translation, mesh.clone()))
+        .collect();
+    assert!(!children.is_empty());
+    for (parent, translation, mesh) in children {
+        // The section keeps the collider, the child only draws.
+        let (section, solid, collider) = sections.get(&app.world, parent).unwrap();
+        assert_eq!(translation, section.origin().as_vec3());
+        assert!(solid.is_none());
+        assert!(collider.is_some());
+        let meshes = app.world.resource::<Assets<Mesh>>();
+        assert!(meshes.get(&mesh).is_some_and(is_packed));
+    }
+}
+
+/// Runs frames until every chunk around the centre is meshed at the scale of its distance, and nothing is being
+/// meshed again.
+fn settle_scales(app: &mut App, center: IVec2XZ) {
+    settle(app, center);
+    let mut chunks = app
+        .world
+        .query_filtered::<&ChunkMesh, (Without<ComputeMeshTask>, Without<Remeshing>)>();
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        let settled: Vec<(IVec2XZ, usize)> = chunks
+            .iter(&app.world)
+            .map(|chunk| (chunk.position, chunk.scale))
+            .collect();
+        if load_circle(center)
+            .iter()
+            .all(|&chunk| settled.contains(&(chunk, lod_scale(chunk - center))))
+        {
+            return;
+        }
+        thread::sleep(Duration::from_micros(200));
+    }
+    panic!("the chunks around {:?} weren't rescaled", center);
+}
+
+/// The solid mesh of the bottom section of a chunk, and whether it has a collider.
+fn bottom_section(app: &mut App, chunk: IVec2XZ) -> (Mesh, bool) {
+    let mut sections = app
+        .world
+        .query::<(&ChunkSection, &Handle<Mesh>, Option<&ChunkCollider>)>();
+    let (handle, collider) = sections
+        .iter(&app.world)
+        .find(|(section, ..)| section.position == chunk && section.index == 0)
+        .map(|(_, handle, collider)| (handle.clone(), collider.is_some()))
+        .unwrap();
+    let meshes = app.world.resource::<Assets<Mesh>>();
+    (meshes.get(&handle).unwrap().clone(), collider)
+}
+
+/// Whether a mesh has a face looking towards -x on the plane at `x`.
+fn faces_left_at(mesh: &Mesh, x: f32) -> bool {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("the mesh has no positions");
+    };
+    let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    else {
+        panic!("the mesh has no normals");
+    };
+    positions
+        .iter()
+        .zip(normals)
+        .any(|(position, normal)| position[0] == x && *normal == [-1.0, 0.0, 0.0])
+}
+
+#[test]
+fn far_chunks_are_coarser_with_skirts_towards_the_detailed_ones() {
+    let mut app = streaming_app();
+    settle_scales(&mut app, IVec2XZ::new(0, 0));
+
+    // The first coarse chunk hangs a skirt towards the detailed one next to it, the one behind it doesn't.
+    let edge = IVec2XZ::new(LOD_DISTANCE + 1, 0);
+    let (mesh, collider) = bottom_section(&mut app, edge);
+    assert!(!collider);
+    assert!(faces_left_at(&mesh, (edge.x * CHUNK_SIZE as i32) as f32));
+    let behind = edge + IVec2XZ::new(1, 0);
+    let (mesh, _) = bottom_section(&mut app, behind);
+    assert!(!faces_left_at(&mesh, (behind.x * CHUNK_SIZE as i32) as f32));
+    assert!(bottom_section(&mut app, IVec2XZ::new(0, 0)).1);
+
+    // Walking towards them brings the detail back, and the chunks left behind lose it.
+    let center = IVec2XZ::new(3, 0);
+    move_camera(
+        &mut app,
+        Vec3::new(center.x as f32 * CHUNK_SIZE as f32 + 8.0, 80.0, 8.0),
+    );
+    settle_scales(&mut app, center);
+    assert!(bottom_section(&mut app, edge).1);
+    assert!(!bottom_section(&mut app, IVec2XZ::new(-3, 0)).1);
+}
@@ -0,0 +1,195 @@
+use crate::game::chunk::ChunkMeshData;
+use crate::game::lod::*;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+
+/// Two triangles folded along a shared edge, one facing up and one facing back. One end of the edge is a little
+/// off in the second one.
+fn folded_triangles() -> ChunkMeshData {
+    let up = [0.0, 1.0, 0.0];
+    let back = [0.0, 0.0, -1.0];
+    ChunkMeshData {
+        positions: vec![
+            [0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0],
+            [1.002, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ],
+        normals: vec![up, up, up, back, back, back],
+        uvs: (0..6).map(|i| [i as f32, 0.0]).collect(),
+        colors: Vec::new(),
+        indices: vec![0, 1, 2, 3, 4, 5],
+    }
+}
+
+#[test]
+fn welding_merges_the_shared_edge() {
+    let welded = weld_vertices(folded_triangles(), 0.01);
+    assert_eq!(welded.positions.len(), 4);
+    assert_eq!(welded.indices, vec![0, 1, 2, 0, 2, 3]);
+    // The merged vertices keep the first of their positions and UVs.
+    assert_eq!(welded.positions[2], [1.0, 0.0, 0.0]);
+    assert_eq!(
+        welded.uvs,
+        vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [5.0, 0.0]]
+    );
+
+    let folded = Vec3::new(0.0, 1.0, -1.0).normalize().to_array();
+    assert_eq!(
+        welded.normals,
+        vec![folded, [0.0, 1.0, 0.0], folded, [0.0, 0.0, -1.0]]
+    );
+
+    // Too far apart to be welded.
+    assert_eq!(weld_vertices(folded_triangles(), 0.001).positions.len(), 5);
+}
+
+/// A chunk with a stone floor 4 blocks thick, and a pillar 2 blocks wide on it.
+fn floor_with_pillar() -> ChunkBlocks {
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let height = if (6..8).contains(&x) && (6..8).contains(&z) {
+                10
+            } else {
+                4
+            };
+            blocks.column_mut(x, z)[..height].fill(BlockType::Stone);
+        }
+    }
+    blocks
+}
+
+/// Nothing in the floor is tinted.
+fn white(_: IVec2XZ) -> [f32; 3] {
+    [1.0; 3]
+}
+
+#[test]
+fn smooth_lod_chunks_share_their_vertices() {
+    let atlas = test_atlas();
+    let chunk = IVec2XZ::new(1, -2);
+    let (flat, _) = mesh_lod(&floor_with_pillar(), chunk, 2, &atlas, false, white, &[]);
+    let (smooth, _) = mesh_lod(&floor_with_pillar(), chunk, 2, &atlas, true, white, &[]);
+
+    // Everything is on the grid of the cells, the pillar is the highest, and the border of the chunk isn't walled in.
+    assert!(flat
+        .positions
+        .iter()
+        .all(|p| p.iter().all(|v| v % 2.0 == 0.0) && p[1] <= 10.0));
+    assert!(flat
+        .positions
+        .iter()
+        .zip(&flat.normals)
+        .all(|(p, n)| n[1] != 0.0 || p[1] >= 4.0));
+    assert!(flat
+        .positions
+        .iter()
+        .all(|p| (16.0..=32.0).contains(&p[0]) && (-32.0..=-16.0).contains(&p[2])));
+
+    // The same triangles, with fewer vertices.
+    assert!(smooth.positions.len() < flat.positions.len());
+    assert_eq!(smooth.indices.len(), flat.indices.len());
+    for (&welded, &original) in smooth.indices.iter().zip(&flat.indices) {
+        assert_eq!(
+            smooth.positions[welded as usize],
+            flat.positions[original as usize]
+        );
+    }
+    assert!(smooth
+        .normals
+        .iter()
+        .all(|&n| (Vec3::from(n).length() - 1.0).abs() < 1e-5));
+
+    let normal_at = |position: [f32; 3]| {
+        let index = smooth
+            .positions
+            .iter()
+            .position(|&p| p == position)
+            .unwrap();
+        Vec3::from(smooth.normals[index])
+    };
+    // Out on the floor nothing changes, but the corners of the pillar get rounded off.
+    assert_eq!(normal_at([20.0, 4.0, -28.0]), Vec3::Y);
+    let corner = normal_at([22.0, 10.0, -26.0]);
+    assert!(
+        corner.y > 0.0 && corner.x < 0.0 && corner.z < 0.0,
+        "{}",
+        corner
+    );
+}
+
+#[test]
+fn coarse_border_edges_have_skirts_beneath() {
+    let atlas = test_atlas();
+    let chunk = IVec2XZ::new(1, -2);
+    let (plain, _) = mesh_lod(&floor_with_pillar(), chunk, 2, &atlas, true, white, &[]);
+    let (skirted, _) = mesh_lod(
+        &floor_with_pillar(),
+        chunk,
+        2,
+        &atlas,
+        true,
+        white,
+        &CHUNK_SIDES,
+    );
+
+    // The skirts come after the surface, which doesn't change.
+    assert_eq!(
+        skirted.positions[..plain.positions.len()],
+        plain.positions[..]
+    );
+    let skirts = &skirted.positions[plain.positions.len()..];
+    let skirt_normals = &skirted.normals[plain.positions.len()..];
+    // One quad under every cell of the floor along the four sides.
+    assert_eq!(skirts.len(), 4 * 4 * CHUNK_SIZE / 2);
+
+    // The normals pointing out of the chunk on the sides a position is on, two of them in the corners.
+    let outward = |[x, _, z]: [f32; 3]| {
+        [
+            (x == 16.0, [-1.0, 0.0, 0.0]),
+            (x == 32.0, [1.0, 0.0, 0.0]),
+            (z == -32.0, [0.0, 0.0, -1.0]),
+            (z == -16.0, [0.0, 0.0, 1.0]),
+        ]
+        .into_iter()
+        .filter_map(|(on_side, normal)| on_side.then_some(normal))
+        .collect::<Vec<_>>()
+    };
+    let border_tops: Vec<[f32; 3]> = plain
+        .positions
+        .iter()
+        .zip(&plain.normals)
+        .filter(|(&p, n)| n[1] > 0.0 && !outward(p).is_empty())
+        .map(|(&p, _)| p)
+        .collect();
+    assert!(!border_tops.is_empty());
+    for top in border_tops {
+        let below = [top[0], top[1] - LOD_SKIRT_DEPTH, top[2]];
+        for corner in [top, below] {
+            assert!(
+                skirts
+                    .iter()
+                    .zip(skirt_normals)
+                    .any(|(&p, n)| p == corner && outward(p).contains(n)),
+                "no skirt at {:?}",
+                corner
+            );
+        }
+    }
+    // The skirts hang along the sides and nowhere else.
+    assert!(skirts.iter().all(|&p| !outward(p).is_empty()));
+}
+
+#[test]
+fn skirts_face_the_neighbours_at_another_detail() {
+    let chunk = IVec2XZ::new(3, 3);
+    let sides = skirt_sides(chunk, 2, |neighbour| match (neighbour.x, neighbour.z) {
+        (4, 3) | (3, 4) => Some(2),
+        (2, 3) => Some(1),
+        _ => None,
+    });
+    assert_eq!(sides, vec![BlockFace::Left, BlockFace::Back]);
+}
@@ -0,0 +1,778 @@
+use std::collections::{hash_map::DefaultHasher, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use bevy::render::mesh::{Indices, MeshVertexAttribute, VertexAttributeValues};
+
+use crate::game::atlas::MISSING_TEXTURE;
+use crate::game::biome::Biome;
+use crate::game::chunk::{
+    collider_geometry, corner_light, create_chunk_mesh, face_visible, fluid_material,
+    get_verts_indices, mesh_block_parts, mesh_blocks, texture_index, ChunkMeshData, AO_BRIGHTNESS,
+    WATER_TINT,
+};
+use crate::game::fluid_material::{scroll_fluids, FluidMaterial};
+use crate::game::generator::{chunk_origin, DefaultGenerator, SuperflatGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+/// The textures of the test atlas, in the order of its tiles. It's the layout the block textures had before they
+/// were split into files, so the golden hashes didn't change with it, and the variants of the textures after it.
+pub const TEST_TILES: [&str; 39] = [
+    "bedrock",
+    "stone",
+    "dirt",
+    "grass_top",
+    "grass_side",
+    "log_side",
+    "leaves",
+    "coal_ore",
+    "iron_ore",
+    "gold_ore",
+    "sand",
+    "unused_11",
+    "log_top",
+    "unused_13",
+    "redstone_ore",
+    "diamond_ore",
+    "snow",
+    "snowy_grass_side",
+    "spruce_log_side",
+    "spruce_log_top",
+    "spruce_leaves",
+    "lava",
+    "water",
+    "ice",
+    "tall_grass",
+    "poppy",
+    "dandelion",
+    "gravel",
+    "cobblestone",
+    "chest_top",
+    "chest_side",
+    "stone_1",
+    "stone_2",
+    "dirt_1",
+    "dirt_2",
+    "grass_top_1",
+    "grass_top_2",
+    "grass_side_1",
+    MISSING_TEXTURE,
+];
+
+/// A 7 by 7 grid atlas with the [`TEST_TILES`], it doesn't need the texture files.
+pub fn test_atlas() -> GameTextureAtlas {
+    let grid = TextureAtlas::from_grid(Handle::default(), Vec2::new(32.0, 32.0), 7, 7, None, None);
+    let names = TEST_TILES
+        .iter()
+        .enumerate()
+        .map(|(index, &name)| (name.to_string(), index))
+        .collect();
+    GameTextureAtlas::new(grid, names, TEST_TILES.len() - 1)
+}
+
+fn float3(mesh: &Mesh, attribute: MeshVertexAttribute) -> &Vec<[f32; 3]> {
+    match mesh.attribute(attribute) {
+        Some(VertexAttributeValues::Float32x3(values)) => values,
+        _ => panic!("missing attribute"),
+    }
+}
+
+/// Hashes the set of quads in the solid and the fluid meshes of every section of a chunk, so the order the faces
+/// were emitted in doesn't matter. The fluids are moved back into place first, it's the same hash as if they were
+/// one mesh.
+///
+/// Moving the fluids back isn't exact, so the positions are hashed to a thousandth of a block. The shade of the
+/// ambient occlusion is hashed too, when the mesh has one.
+pub fn chunk_meshes_hash(meshes: &ChunkMeshes) -> u64 {
+    let quads = meshes
+        .parts()
+        .flat_map(|(mesh, offset)| quads(mesh, offset))
+        .collect();
+    hash_quads(quads)
+}
+
+fn quads(mesh: &Mesh, offset: Vec3) -> Vec<Vec<u32>> {
+    let positions = float3(mesh, Mesh::ATTRIBUTE_POSITION);
+    let normals = float3(mesh, Mesh::ATTRIBUTE_NORMAL);
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        panic!("missing uvs");
+    };
+    let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) => colors.as_slice(),
+        _ => &[],
+    };
+    let Some(Indices::U32(indices)) = mesh.indices() else {
+        panic!("missing indices");
+    };
+
+    indices
+        .chunks(6)
+        .map(|quad| {
+            let mut key: Vec<u32> = quad.iter().map(|&i| i - quad[0]).collect();
+            for i in quad[0]..quad[0] + 4 {
+                let i = i as usize;
+                let position = Vec3::from(positions[i]) + offset;
+                key.extend(
+                    position
+                        .to_array()
+                        .map(|v| (v * 1000.0).round() as i32 as u32),
+                );
+                key.extend(normals[i].iter().map(|v| v.to_bits()));
+                key.extend(uvs[i].iter().map(|v| v.to_bits()));
+                if let Some(color) = colors.get(i) {
+                    key.extend(color.iter().map(|v| v.to_bits()));
+                }
+            }
+            key
+        })
+        .collect()
+}
+
+fn hash_quads(mut quads: Vec<Vec<u32>>) -> u64 {
+    quads.sort_unstable();
+    let mut hasher = DefaultHasher::new();
+    quads.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Mesh hashes from before the block storage was flattened. Generation changes that are supposed to
+/// keep the terrain identical must keep these. (5, -3), (-17, 12) and (9, -16) were updated when biomes
+/// changed their surface blocks, and (-1, -1), (5, -3) and (-17, 12) when they got trees. (5, -3) changed
+/// again when cold biomes got spruces, and all of them when redstone moved below y 16 and when the ores
+/// became veins. (-1, -1) changed when its peak got snowed over and (5, -3) when its water froze, and all
+/// of them again when caves started breaking through the surface, when the veins moved onto the shared
+/// position hash and when the bedrock got uneven. (-1, -1) and (-17, 12) changed when plants started
+/// growing on the grass, and all of them when gravel and dirt patches went into the stone. (5, -3), (-17, 12)
+/// and (9, -16) changed when the trees and ponds moved onto the shared position hash, and all of them when the
+/// surface octaves became a normalized fBm, again when a spline started shaping their heights and when the
+/// positions started being hashed to a thousandth of a block, for the fluids that got a mesh of their own, and when
+/// the ambient occlusion started shading the corners and turning the quads, and when the stone, the dirt and the grass
+/// got more than one texture.
+const GOLDEN_MESH_HASHES: [((i32, i32), u64); 5] = [
+    ((0, 0), 0xccd15865f4cf7c90),
+    ((-1, -1), 0xac0e2ef671cd8e7d),
+    ((5, -3), 0x83dfafcceb6314e3),
+    ((-17, 12), 0x6f44f6ed4e4dbeb2),
+    ((9, -16), 0xfa9675c83847515e),
+];
+
+#[test]
+fn chunk_meshes_match_golden_hashes() {
+    for ((x, z), hash) in GOLDEN_MESH_HASHES {
+        let (meshes, _) = create_chunk_mesh(
+            IVec2XZ::new(x, z),
+            test_atlas(),
+            Arc::new(DefaultGenerator::new(SEED, WorldRules::default())),
+            true,
+        );
+        assert_eq!(chunk_meshes_hash(&meshes), hash, "chunk ({}, {})", x, z);
+    }
+}
+
+#[test]
+fn sections_of_air_and_buried_sections_are_empty() {
+    let generator = SuperflatGenerator {
+        layers: vec![(BlockType::Stone, 48)],
+    };
+    let (meshes, _) =
+        create_chunk_mesh(IVec2XZ::new(0, 0), test_atlas(), Arc::new(generator), true);
+    let empty: Vec<bool> = meshes.sections.iter().map(SectionMesh::is_empty).collect();
+    // The bottom of the world and the top of the stone are drawn, the stone in between is buried.
+    let mut expected = [true; SECTIONS];
+    expected[0] = false;
+    expected[2] = false;
+    assert_eq!(empty, expected);
+    // Only the sections with faces get a collider.
+    assert!(meshes.sections[0].collider.is_some());
+    assert!(meshes.sections[1].collider.is_none());
+    // Each one is meshed in its own 16 blocks.
+    let positions = float3(&meshes.sections[2].solid_mesh, Mesh::ATTRIBUTE_POSITION);
+    assert!(positions.iter().all(|position| position[1] == 48.0));
+}
+
+#[test]
+fn sections_are_filled_with_air_opaque_blocks_or_a_mix() {
+    let mut blocks = ChunkBlocks::default();
+    assert_eq!(blocks.section_fill(0), SectionFill::Air);
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            blocks.column_mut(x, z)[..2 * SECTION_HEIGHT].fill(BlockType::Stone);
+        }
+    }
+    blocks.set(3, 5, 7, BlockType::IronOre);
+    assert_eq!(blocks.section_fill(0), SectionFill::Opaque);
+    // Plants let the faces behind them show, so do the fluids and a cave.
+    for block in [BlockType::TallGrass, BlockType::Water, BlockType::Air] {
+        blocks.set(15, 31, 0, block);
+        assert_eq!(blocks.section_fill(1), SectionFill::Mixed);
+    }
+    assert_eq!(blocks.section_fill(2), SectionFill::Air);
+}
+
+#[test]
+fn opaque_sections_mesh_the_same_as_looking_at_every_block() {
+    let chunk = IVec2XZ::new(5, -3);
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let atlas = test_atlas();
+    let (meshes, blocks) =
+        create_chunk_mesh(chunk, atlas.clone(), Arc::new(generator.clone()), true);
+    let padded = generator.pad_chunk(chunk, &blocks);
+
+    let opaque: Vec<usize> = (0..SECTIONS)
+        .filter(|&index| blocks.section_fill(index) == SectionFill::Opaque)
+        .collect();
+    assert!(!opaque.is_empty());
+    for index in opaque {
+        let offset = IVec3::new(0, (index * SECTION_HEIGHT) as i32, 0);
+        let (solid, fluids) = mesh_block_parts(
+            UVec3::new(CHUNK_SIZE as u32, SECTION_HEIGHT as u32, CHUNK_SIZE as u32),
+            chunk_origin(chunk) + offset,
+            &atlas,
+            true,
+            |column| generator.biome_at(column).grass_tint(),
+            |pos| padded.get(pos + offset),
+        );
+        assert!(fluids.is_empty());
+        // The same faces in the same order, not only the same set of them.
+        let section = &meshes.sections[index].solid_mesh;
+        let expected = solid.into_mesh();
+        for attribute in [
+            Mesh::ATTRIBUTE_POSITION,
+            Mesh::ATTRIBUTE_NORMAL,
+            Mesh::ATTRIBUTE_UV_0,
+            Mesh::ATTRIBUTE_COLOR,
+        ] {
+            assert_eq!(
+                section
+                    .attribute(attribute.id)
+                    .map(|values| values.get_bytes()),
+                expected
+                    .attribute(attribute.id)
+                    .map(|values| values.get_bytes()),
+                "section {}",
+                index
+            );
+        }
+        assert_eq!(
+            section.get_index_buffer_bytes(),
+            expected.get_index_buffer_bytes()
+        );
+    }
+}
+
+#[test]
+fn a_pool_only_meshes_its_outside() {
+    // A 3x3x3 pool of water with stone under it and air everywhere else.
+    let (solid, fluids) = mesh_block_parts(
+        UVec3::splat(5),
+        IVec3::ZERO,
+        &test_atlas(),
+        false,
+        |_| [1.0; 3],
+        |pos| {
+            if pos.cmpge(IVec3::new(1, 1, 1)).all() && pos.cmple(IVec3::new(3, 3, 3)).all() {
+                BlockType::Water
+            } else if pos.y == 0 && pos.cmpge(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(5)).all()
+            {
+                BlockType::Stone
+            } else {
+                BlockType::Air
+            }
+        },
+    );
+    assert_eq!(fluids.len(), 1);
+    let (block, water) = &fluids[0];
+    assert_eq!(*block, BlockType::Water);
+    // The top and the four sides, not the bottom on the stone nor anything inside.
+    assert_eq!(water.indices.len() / 6, 5 * 3 * 3);
+    // Nothing of the water is in the solid mesh, and the whole slab is drawn, it's seen through the water.
+    assert_eq!(solid.indices.len() / 6, 5 * 5 + 4 * 5 + 5 * 5);
+}
+
+#[test]
+fn water_is_see_through_and_lava_isnt() {
+    let atlas = test_atlas();
+    let water = fluid_material(BlockType::Water, &atlas);
+    assert_eq!(water.alpha_mode, AlphaMode::Blend);
+    assert_eq!(water.base_color, WATER_TINT);
+    assert!(water.double_sided);
+
+    let lava = fluid_material(BlockType::Lava, &atlas);
+    assert_eq!(lava.alpha_mode, AlphaMode::Opaque);
+    assert_eq!(lava.texture, atlas.atlas.texture);
+}
+
+#[test]
+fn the_sides_of_a_water_column_stop_at_its_surface() {
+    // Two blocks of water standing in the air, on a stone floor.
+    let (_, fluids) = mesh_block_parts(
+        UVec3::new(3, 4, 3),
+        IVec3::ZERO,
+        &test_atlas(),
+        false,
+        |_| [1.0; 3],
+        |pos| match pos {
+            IVec3 {
+                x: 1,
+                y: 1 | 2,
+                z: 1,
+            } => BlockType::Water,
+            pos if pos.y == 0 => BlockType::Stone,
+            _ => BlockType::Air,
+        },
+    );
+    let (_, water) = &fluids[0];
+    // The surface and the sides of both blocks, not the bottom on the stone.
+    assert_eq!(water.indices.len() / 6, 1 + 2 * 4);
+    let heights: HashSet<u32> = water
+        .positions
+        .iter()
+        .map(|position| (position[1] * 10.0).round() as u32)
+        .collect();
+    assert_eq!(heights, HashSet::from([10, 20, 29]));
+}
+
+#[test]
+fn a_slab_is_the_bottom_half_of_its_cell() {
+    // A slab on a stone floor, with a stone block next to it.
+    let (solid, _) = mesh_block_parts(
+        UVec3::new(4, 3, 3),
+        IVec3::ZERO,
+        &test_atlas(),
+        false,
+        |_| [1.0; 3],
+        |pos| match pos {
+            IVec3 { x: 1, y: 1, z: 1 } => BlockType::StoneSlab,
+            IVec3 { x: 2, y: 1, z: 1 } => BlockType::Stone,
+            pos if pos.y == 0 => BlockType::Stone,
+            _ => BlockType::Air,
+        },
+    );
+    let in_slab = |position: &[f32; 3]| {
+        (1.0..=2.0).contains(&position[0])
+            && (1.0..=2.0).contains(&position[2])
+            && (1.0..=1.5).contains(&position[1])
+    };
+    let slab: Vec<usize> = (0..solid.positions.len())
+        .filter(|&vertex| in_slab(&solid.positions[vertex]) && solid.positions[vertex][1] > 1.0)
+        .collect();
+    // The top and the upper corners of the three open sides, the side against the stone is hidden.
+    assert_eq!(slab.len(), 4 + 3 * 2);
+    assert!(slab.iter().all(|&vertex| solid.positions[vertex][1] == 1.5));
+    // The stone shows its whole side over the slab.
+    let stone_side = solid
+        .positions
+        .iter()
+        .zip(&solid.normals)
+        .filter(|(position, normal)| position[0] == 2.0 && **normal == [-1.0, 0.0, 0.0])
+        .count();
+    assert_eq!(stone_side, 4);
+    // The sides show the bottom half of the texture.
+    let tile = test_atlas().tile_uvs(1);
+    let half = (tile[0][1] + tile[3][1]) / 2.0;
+    let left = (0..solid.positions.len())
+        .find(|&vertex| {
+            solid.positions[vertex][1] == 1.5 && solid.normals[vertex] == [-1.0, 0.0, 0.0]
+        })
+        .unwrap();
+    assert!((solid.uvs[left][1] - half).abs() < 1e-6);
+}
+
+#[test]
+fn stairs_are_meshed_from_their_boxes() {
+    // Lone stairs with their back on the back.
+    let (solid, _) = mesh_block_parts(
+        UVec3::ONE,
+        IVec3::ZERO,
+        &test_atlas(),
+        false,
+        |_| [1.0; 3],
+        |pos| {
+            if pos == IVec3::ZERO {
+                BlockType::StoneStairs
+            } else {
+                BlockType::Air
+            }
+        },
+    );
+    // Every face of the slab, and every face of the step but the one it stands on.
+    assert_eq!(solid.positions.len(), (6 + 5) * 4);
+    let quads: Vec<(&[[f32; 3]], [f32; 3])> = solid
+        .positions
+        .chunks(4)
+        .zip(solid.normals.chunks(4))
+        .map(|(quad, normals)| (quad, normals[0]))
+        .collect();
+    // Two tops: the slab's in the middle of the cell, under the step at the back, and the step's over the back half.
+    let tops: Vec<_> = quads
+        .iter()
+        .filter(|(_, normal)| *normal == [0.0, 1.0, 0.0])
+        .map(|(quad, _)| quad)
+        .collect();
+    assert_eq!(tops.len(), 2);
+    assert!(tops
+        .iter()
+        .any(|quad| quad.iter().all(|v| v[1] == 1.0 && v[2] <= 0.5)));
+    assert!(tops.iter().any(|quad| quad.iter().all(|v| v[1] == 0.5)));
+    // The front of the step is in the middle of the cell, over the front of the slab.
+    let fronts: Vec<_> = quads
+        .iter()
+        .filter(|(_, normal)| *normal == [0.0, 0.0, 1.0])
+        .map(|(quad, _)| quad)
+        .collect();
+    assert_eq!(fronts.len(), 2);
+    assert!(fronts
+        .iter()
+        .any(|quad| quad.iter().all(|v| v[2] == 0.5 && v[1] >= 0.5)));
+    assert!(fronts
+        .iter()
+        .any(|quad| quad.iter().all(|v| v[2] == 1.0 && v[1] <= 0.5)));
+    // Nothing sticks out of the cell.
+    assert!(solid
+        .positions
+        .iter()
+        .flatten()
+        .all(|&coordinate| (0.0..=1.0).contains(&coordinate)));
+}
+
+#[test]
+fn shaped_blocks_collide_like_their_boxes() {
+    // A top slab and upside down stairs hanging in the air.
+    let mut blocks = ChunkBlocks::default();
+    blocks.set(4, 65, 4, BlockType::StoneSlabTop);
+    blocks.set(6, 65, 4, BlockType::StoneStairsTop);
+    let size = UVec3::new(CHUNK_SIZE as u32, CHUNK_HEIGHT as u32, CHUNK_SIZE as u32);
+    let mesh = mesh_blocks(size, IVec3::ZERO, &test_atlas(), |pos| {
+        if pos.cmpge(IVec3::ZERO).all() && pos.cmplt(size.as_ivec3()).all() {
+            blocks.get(pos.x as usize, pos.y as usize, pos.z as usize)
+        } else {
+            BlockType::Air
+        }
+    });
+    let (vertices, triangles) = get_verts_indices(&mesh);
+    let (_, collider) = collider_geometry(&mesh, IVec2XZ::new(0, 0), &blocks);
+
+    // Every face is the side of a box, including the ones in the middle of the cells, like the slab's bottom.
+    assert_eq!(collider.len(), triangles.len());
+    assert!(collider
+        .iter()
+        .any(|triangle| triangle.iter().all(|&i| vertices[i as usize].y == 65.5)));
+}
+
+#[test]
+fn stair_boxes_show_their_part_of_the_tile() {
+    let atlas = test_atlas();
+    let (solid, _) = mesh_block_parts(
+        UVec3::ONE,
+        IVec3::ZERO,
+        &atlas,
+        false,
+        |_| [1.0; 3],
+        |pos| {
+            if pos == IVec3::ZERO {
+                BlockType::StoneStairsTop
+            } else {
+                BlockType::Air
+            }
+        },
+    );
+    let [first, across, _, down] = atlas
+        .tile_uvs(texture_index(
+            &atlas,
+            BlockType::StoneStairsTop,
+            BlockFace::Front,
+            IVec3::ZERO,
+        ))
+        .map(Vec2::from);
+    // The hanging step's front shows the lower half of the tile, on the half of the side it covers.
+    let step_front = solid
+        .positions
+        .chunks(4)
+        .zip(solid.normals.chunks(4))
+        .position(|(quad, normals)| normals[0] == [0.0, 0.0, 1.0] && quad[0][2] == 0.5)
+        .unwrap();
+    for (corner, uv) in solid.positions[step_front * 4..step_front * 4 + 4]
+        .iter()
+        .zip(&solid.uvs[step_front * 4..step_front * 4 + 4])
+    {
+        // The front is seen from the front, its left edge is at the far x.
+        let expected =
+            first + (1.0 - corner[0]) * (across - first) + (1.0 - corner[1]) * (down - first);
+        assert!(Vec2::from(*uv).distance(expected) < 1e-6, "{:?}", corner);
+    }
+}
+
+#[test]
+fn fluids_have_no_collider() {
+    let generator = SuperflatGenerator {
+        layers: vec![(BlockType::Stone, 64), (BlockType::Water, 4)],
+    };
+    let (meshes, _) =
+        create_chunk_mesh(IVec2XZ::new(0, 0), test_atlas(), Arc::new(generator), true);
+    // The section of the sea floor collides, the water on it is only drawn.
+    assert!(meshes.sections[3].collider.is_some());
+    let sea = &meshes.sections[4];
+    assert_eq!(sea.fluid_meshes.len(), 1);
+    assert!(sea.collider.is_none());
+}
+
+#[test]
+fn lava_glows_and_the_fluids_scroll_through_their_tiles() {
+    let atlas = test_atlas();
+    let lava = fluid_material(BlockType::Lava, &atlas);
+    assert_eq!(lava.emissive, Color::WHITE);
+    assert_eq!(
+        fluid_material(BlockType::Water, &atlas).emissive,
+        Color::BLACK
+    );
+
+    // The texture wraps around the fluid's own tile.
+    let tile = atlas.atlas.textures[atlas.index("lava").unwrap()];
+    let size = atlas.atlas.size;
+    assert_eq!(lava.tile.min, tile.min / size);
+    assert_eq!(lava.tile.max, tile.max / size);
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(AssetPlugin::default())
+        .add_asset::<FluidMaterial>()
+        .add_systems(Update, scroll_fluids);
+    let handle = app.world.resource_mut::<Assets<FluidMaterial>>().add(lava);
+    app.update();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    app.update();
+    let offset = app
+        .world
+        .resource::<Assets<FluidMaterial>>()
+        .get(&handle)
+        .unwrap()
+        .offset;
+    assert!(
+        offset.x == 0.0 && offset.y > 0.0 && offset.y < 1.0,
+        "{}",
+        offset
+    );
+}
+
+#[test]
+fn corners_between_blocks_get_darker() {
+    let top = [
+        [0.0, 1.0, 0.0],
+        [0.0, 1.0, 1.0],
+        [1.0, 1.0, 1.0],
+        [1.0, 1.0, 0.0],
+    ];
+    let light = |occluders: &[IVec3]| {
+        corner_light(IVec3::ZERO, BlockFace::Top, &top, &|pos| {
+            occluders.contains(&pos)
+        })
+    };
+    assert_eq!(light(&[]), [3; 4]);
+    // A block along the +x edge shades both corners on that side.
+    assert_eq!(light(&[IVec3::new(1, 1, 0)]), [3, 3, 2, 2]);
+    // Only the corner it touches is shaded by a block across the diagonal.
+    assert_eq!(light(&[IVec3::new(1, 1, 1)]), [3, 3, 2, 3]);
+    // Two edge blocks shade their corner fully.
+    assert_eq!(light(&[IVec3::new(1, 1, 0), IVec3::new(0, 1, 1)])[2], 0);
+    // Blocks below the face don't shade it.
+    assert_eq!(light(&[IVec3::new(1, 0, 1)]), [3; 4]);
+}
+
+#[test]
+fn occluded_quads_are_split_through_their_lighter_corners() {
+    // A block with another one diagonally above it.
+    let mesh = |ambient_occlusion| {
+        let (solid, _) = mesh_block_parts(
+            UVec3::splat(2),
+            IVec3::ZERO,
+            &test_atlas(),
+            ambient_occlusion,
+            |_| [1.0; 3],
+            |pos| {
+                if pos == IVec3::ZERO || pos == IVec3::ONE {
+                    BlockType::Stone
+                } else {
+                    BlockType::Air
+                }
+            },
+        );
+        solid
+    };
+    let top_of = |data: &ChunkMeshData| {
+        (0..data.positions.len() / 4)
+            .find(|&quad| {
+                data.positions[quad * 4] == [0.0, 1.0, 0.0]
+                    && data.normals[quad * 4] == [0.0, 1.0, 0.0]
+            })
+            .unwrap()
+    };
+
+    let shaded = mesh(true);
+    assert_eq!(shaded.colors.len(), shaded.positions.len());
+    let quad = top_of(&shaded);
+    let corner = |i: usize| shaded.colors[quad * 4 + i][0];
+    assert_eq!(corner(2), AO_BRIGHTNESS[2]);
+    assert_eq!([corner(0), corner(1), corner(3)], [1.0; 3]);
+    let first = quad as u32 * 4;
+    assert_eq!(
+        shaded.indices[quad * 6..quad * 6 + 6],
+        [0, 1, 3, 1, 2, 3].map(|i| first + i)
+    );
+
+    let flat = mesh(false);
+    assert!(flat.colors.is_empty());
+    let quad = top_of(&flat);
+    let first = quad as u32 * 4;
+    assert_eq!(
+        flat.indices[quad * 6..quad * 6 + 6],
+        [0, 1, 2, 0, 2, 3].map(|i| first + i)
+    );
+    assert!(flat.into_mesh().attribute(Mesh::ATTRIBUTE_COLOR).is_none());
+}
+
+/// The faces of a chunk's meshes on the plane x = `plane` facing along x, as the block positions they're on.
+fn faces_on_plane(meshes: &ChunkMeshes, plane: f32, normal_x: f32) -> Vec<(i32, i32)> {
+    let mut faces = Vec::new();
+    for (mesh, offset) in meshes.parts() {
+        let positions = float3(mesh, Mesh::ATTRIBUTE_POSITION);
+        let normals = float3(mesh, Mesh::ATTRIBUTE_NORMAL);
+        for quad in 0..positions.len() / 4 {
+            let corners = (0..4).map(|i| Vec3::from(positions[quad * 4 + i]) + offset);
+            if normals[quad * 4][0] != normal_x
+                || !corners.clone().all(|c| (c.x - plane).abs() < 0.01)
+            {
+                continue;
+            }
+            let min = corners.fold(Vec3::splat(f32::MAX), Vec3::min);
+            faces.push((min.y.round() as i32, min.z.round() as i32));
+        }
+    }
+    faces.sort_unstable();
+    faces
+}
+
+#[test]
+fn no_faces_between_solid_blocks_across_a_chunk_border() {
+    let generator = Arc::new(DefaultGenerator::new(SEED, WorldRules::default()));
+    let (west, west_blocks) =
+        create_chunk_mesh(IVec2XZ::new(0, 0), test_atlas(), generator.clone(), true);
+    let (east, east_blocks) = create_chunk_mesh(IVec2XZ::new(1, 0), test_atlas(), generator, true);
+
+    // Both chunks mesh their side of the border from the same blocks, with the same rule.
+    let border = CHUNK_SIZE as f32;
+    let (mut expected_west, mut expected_east) = (Vec::new(), Vec::new());
+    let mut solid_pairs = 0;
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_HEIGHT {
+            let inner = west_blocks.get(CHUNK_SIZE - 1, y, z);
+            let outer = east_blocks.get(0, y, z);
+            if inner.is_solid() && outer.is_solid() {
+                solid_pairs += 1;
+            }
+            let cube = inner != BlockType::Air && inner.shape() == BlockShape::Cube;
+            if cube && face_visible(inner, outer, BlockFace::Right) {
+                expected_west.push((y as i32, z as i32));
+            }
+            let cube = outer != BlockType::Air && outer.shape() == BlockShape::Cube;
+            if cube && face_visible(outer, inner, BlockFace::Left) {
+                expected_east.push((y as i32, z as i32));
+            }
+        }
+    }
+    assert!(solid_pairs > 0, "the border should cut through the ground");
+    expected_west.sort_unstable();
+    expected_east.sort_unstable();
+    assert_eq!(faces_on_plane(&west, border, 1.0), expected_west);
+    assert_eq!(faces_on_plane(&east, border, -1.0), expected_east);
+    for (y, z) in expected_west.iter().chain(&expected_east) {
+        let (y, z) = (*y as usize, *z as usize);
+        assert!(
+            !(west_blocks.get(CHUNK_SIZE - 1, y, z).is_solid()
+                && east_blocks.get(0, y, z).is_solid()),
+            "a face between two solid blocks at y {} z {}",
+            y,
+            z
+        );
+    }
+}
+
+/// The color of every face of a mesh by its normal and world column, the same for all four corners.
+fn face_colors(mesh: &Mesh) -> Vec<([f32; 3], IVec2XZ, [f32; 4])> {
+    let positions = float3(mesh, Mesh::ATTRIBUTE_POSITION);
+    let normals = float3(mesh, Mesh::ATTRIBUTE_NORMAL);
+    let Some(VertexAttributeValues::Float32x4(colors)) = mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+    else {
+        panic!("missing colors");
+    };
+    assert_eq!(colors.len(), positions.len());
+    (0..positions.len())
+        .step_by(4)
+        .map(|first| {
+            assert!(colors[first..first + 4].iter().all(|&c| c == colors[first]));
+            let corner = positions[first..first + 4]
+                .iter()
+                .fold(Vec3::MAX, |min, &v| min.min(Vec3::from(v)));
+            let column = IVec2XZ::new(corner.x as i32, corner.z as i32);
+            (normals[first], column, colors[first])
+        })
+        .collect()
+}
+
+#[test]
+fn grass_tops_are_tinted_by_their_biome() {
+    // A plains column right next to a desert one, along a row of the world.
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let plains = (-4096..4096)
+        .map(|x| IVec2XZ::new(x, 0))
+        .find(|&column| {
+            generator.biome_at(column) == Biome::Plains
+                && generator.biome_at(column + IVec2XZ::new(1, 0)) == Biome::Desert
+        })
+        .expect("no plains next to a desert");
+
+    // A strip of grass over the border, in the open so nothing shades the tops.
+    for ambient_occlusion in [false, true] {
+        let origin = IVec3::new(plains.x - 1, 64, plains.z);
+        let (solid, _) = mesh_block_parts(
+            UVec3::new(4, 1, 1),
+            origin,
+            &test_atlas(),
+            ambient_occlusion,
+            |column| generator.biome_at(column).grass_tint(),
+            |pos| {
+                if pos.y == 0 && (0..4).contains(&pos.x) && pos.z == 0 {
+                    BlockType::Grass
+                } else {
+                    BlockType::Air
+                }
+            },
+        );
+        let faces = face_colors(&solid.into_mesh());
+        let mut tops: Vec<(i32, [f32; 4])> = faces
+            .iter()
+            .filter(|(normal, _, _)| *normal == [0.0, 1.0, 0.0])
+            .map(|&(_, column, color)| (column.x - plains.x, color))
+            .collect();
+        tops.sort_by_key(|&(x, _)| x);
+        let tint = |x: i32| {
+            let [r, g, b] = generator.biome_at(plains + IVec2XZ::new(x, 0)).grass_tint();
+            [r, g, b, 1.0]
+        };
+        let expected: Vec<(i32, [f32; 4])> = (-1..3).map(|x| (x, tint(x))).collect();
+        assert_eq!(tops, expected, "ambient occlusion {}", ambient_occlusion);
+        // The tint changes over the border.
+        assert_eq!(tops[1].1[..3], Biome::Plains.grass_tint());
+        assert_eq!(tops[2].1[..3], Biome::Desert.grass_tint());
+        assert_ne!(tops[1].1, tops[2].1);
+
+        // The sides and the bottoms keep the colors of their textures.
+        if !ambient_occlusion {
+            assert!(faces
+                .iter()
+                .filter(|(normal, _, _)| *normal != [0.0, 1.0, 0.0])
+                .all(|&(_, _, color)| color == [1.0; 4]));
+        }
+    }
+}
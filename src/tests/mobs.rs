@@ -0,0 +1,63 @@
+use crate::game::mobs::*;
+use crate::prelude::*;
+
+#[test]
+fn peaceful_clears_hostiles_in_one_frame() {
+    let mut world = World::new();
+    world.insert_resource(Settings::default());
+    let hostile = world.spawn(Hostile).id();
+    let passive = world.spawn_empty().id();
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems(despawn_peaceful_hostiles);
+
+    // Normal difficulty keeps them.
+    schedule.run(&mut world);
+    assert!(world.get_entity(hostile).is_some());
+
+    world.resource_mut::<Settings>().difficulty = Difficulty::Peaceful;
+    schedule.run(&mut world);
+    assert!(world.get_entity(hostile).is_none());
+    assert!(world.get_entity(passive).is_some());
+}
+
+/// Stone up to y 63 with a cave under all of it at y 20, and a hut on the surface at x 8.
+fn lit_and_dark_chunk() -> ChunkMap {
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let column = blocks.column_mut(x, z);
+            column[..64].fill(BlockType::Stone);
+            column[20..23].fill(BlockType::Air);
+        }
+    }
+    blocks.set(8, 67, 4, BlockType::Stone);
+    let mut chunk_map = ChunkMap::default();
+    chunk_map.insert_chunk(IVec2XZ::new(0, 0), blocks);
+    chunk_map
+}
+
+#[test]
+fn hostiles_only_spawn_in_the_dark() {
+    let chunk_map = lit_and_dark_chunk();
+    let cave = IVec3::new(1, 20, 4);
+    let field = IVec3::new(1, 64, 4);
+    let hut = IVec3::new(8, 64, 4);
+    for night in [false, true] {
+        assert!(is_spawn_candidate(&chunk_map, cave, night));
+        assert_eq!(is_spawn_candidate(&chunk_map, field, night), night);
+        // A roof keeps the night out, but not the light inside.
+        assert!(!is_spawn_candidate(&chunk_map, hut, night));
+    }
+    // Only where there's ground under the feet and room for the head.
+    assert!(!is_spawn_candidate(&chunk_map, cave + IVec3::Y, true));
+    assert!(!is_spawn_candidate(&chunk_map, cave + IVec3::Y * 2, true));
+    assert!(!is_spawn_candidate(&chunk_map, IVec3::new(1, 30, 4), true));
+
+    let player = Vec3::new(8.0, 30.0, 8.0);
+    let cells: Vec<IVec3> = (0..256)
+        .filter_map(|seed| find_spawn_cell(&chunk_map, player, seed, false))
+        .collect();
+    assert!(!cells.is_empty());
+    assert!(cells.iter().all(|&cell| cell.y == 20));
+}
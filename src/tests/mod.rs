@@ -1,3 +1,48 @@
-// use bevy::prelude::*;
+/// The seed every test generates its terrain with. The golden mesh hashes are for this seed.
+pub const SEED: u32 = 2137;
 
-// todo: create tests
+mod atlas;
+mod biome;
+mod blocks;
+mod budget;
+mod caves;
+mod chunks;
+mod coords;
+mod culling;
+mod debug;
+mod edits;
+mod environment;
+mod flight;
+mod freecam;
+mod generator;
+mod hash;
+mod hints;
+mod interaction;
+mod islands;
+mod lava;
+mod leaves;
+mod lifecycle;
+mod lod;
+mod mesh;
+mod mobs;
+mod ores;
+mod packed;
+mod placement;
+mod plants;
+mod ponds;
+mod projectiles;
+mod ravines;
+mod report;
+mod rivers;
+mod rules;
+mod schematic;
+mod sky;
+mod snow;
+mod soak;
+mod spline;
+mod stamp;
+mod status;
+mod suffocation;
+mod teleport;
+mod trees;
+mod tune;
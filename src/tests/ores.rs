@@ -0,0 +1,164 @@
+use noise::Perlin;
+
+use crate::game::generator::{patch_block, DefaultGenerator, WorldGenerator};
+use crate::game::ores::*;
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+fn without_ores() -> DefaultGenerator {
+    DefaultGenerator::new(
+        SEED,
+        WorldRules {
+            generate_ores: false,
+            ..default()
+        },
+    )
+}
+
+#[test]
+fn veins_are_connected_blobs() {
+    let veins = veins_in_chunk(IVec2XZ::new(3, -2), SEED);
+    assert_eq!(
+        veins.len() as u32,
+        ORES.iter().map(|ore| ore.veins_per_chunk).sum::<u32>()
+    );
+    for vein in veins {
+        let ore = ORES.iter().find(|ore| ore.ore == vein.ore).unwrap();
+        assert!(ore.heights.contains(&vein.origin.y));
+
+        let blocks = vein.blocks();
+        assert_eq!(blocks.len() as u32, vein.size);
+        assert!(ore.sizes.contains(&vein.size));
+        for (i, &pos) in blocks.iter().enumerate().skip(1) {
+            assert!((pos - vein.origin).abs().max_element() <= VEIN_REACH);
+            // Every block was walked to from one placed before it.
+            assert!(
+                blocks[..i]
+                    .iter()
+                    .any(|&other| (pos - other).abs().to_array().iter().sum::<i32>() == 1),
+                "{:?} is cut off from {:?}",
+                pos,
+                vein
+            );
+        }
+    }
+}
+
+#[test]
+fn veins_and_patches_only_replace_stone() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let plain = without_ores();
+    let perlin = Perlin::new(SEED);
+    let chunk = IVec2XZ::new(0, 0);
+    let (with, without) = (generator.generate_chunk(chunk), plain.generate_chunk(chunk));
+
+    let (mut ores, mut patches) = (0, 0);
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let column = with.column(x, z).iter().zip(without.column(x, z));
+            for (y, (&ore, &block)) in column.enumerate() {
+                if ore == block {
+                    continue;
+                }
+                assert_eq!(block, BlockType::Stone);
+                if ORES.iter().any(|veins| veins.ore == ore) {
+                    ores += 1;
+                } else {
+                    let pos = IVec3::new(x as i32, y as i32, z as i32);
+                    assert_eq!(patch_block(pos, &perlin), Some(ore), "{:?}", pos);
+                    patches += 1;
+                }
+            }
+        }
+    }
+    assert!(ores > 0 && patches > 0);
+}
+
+#[test]
+fn gravel_and_dirt_patches_are_blobs_at_any_depth() {
+    let perlin = Perlin::new(SEED);
+    let (mut gravel, mut dirt) = (Vec::new(), Vec::new());
+    for x in 0..64 {
+        for y in 1..128 {
+            for z in 0..64 {
+                let pos = IVec3::new(x, y, z);
+                match patch_block(pos, &perlin) {
+                    Some(BlockType::Gravel) => gravel.push(pos),
+                    Some(BlockType::Dirt) => dirt.push(pos),
+                    _ => {}
+                }
+            }
+        }
+    }
+    let volume = 64 * 127 * 64;
+    for patches in [&gravel, &dirt] {
+        assert!(
+            patches.len() > volume / 200 && patches.len() < volume / 10,
+            "{} of {}",
+            patches.len(),
+            volume
+        );
+        // Big enough blobs that most of their blocks are inside, not on the edge.
+        let inside = patches
+            .iter()
+            .filter(|&&pos| {
+                BlockFace::ALL.iter().all(|face| {
+                    patch_block(pos + face.offset(), &perlin) == patch_block(pos, &perlin)
+                })
+            })
+            .count();
+        assert!(
+            inside * 3 > patches.len(),
+            "{} of {}",
+            inside,
+            patches.len()
+        );
+        // Not tied to a height range like the ores are.
+        assert!(patches.iter().any(|pos| pos.y < 64) && patches.iter().any(|pos| pos.y >= 64));
+    }
+    // The patches are their own noise, they don't follow the ores.
+    let ore_blocks: Vec<IVec3> = (0..4)
+        .flat_map(|x| (0..4).map(move |z| IVec2XZ::new(x, z)))
+        .flat_map(|chunk| veins_in_chunk(chunk, SEED))
+        .flat_map(|vein| vein.blocks())
+        .filter(|pos| pos.y >= 1 && pos.y < 128)
+        .collect();
+    let in_patches = ore_blocks
+        .iter()
+        .filter(|&&pos| patch_block(pos, &perlin).is_some())
+        .count();
+    let share = (gravel.len() + dirt.len()) as f32 / volume as f32;
+    let ore_share = in_patches as f32 / ore_blocks.len() as f32;
+    assert!(
+        (ore_share - share).abs() < 0.1,
+        "{} of the ores are in patches, {} of everything",
+        ore_share,
+        share
+    );
+}
+
+#[test]
+fn veins_continue_across_chunk_borders() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let plain = without_ores();
+    let chunk = IVec2XZ::new(0, 0);
+    let neighbor = IVec2XZ::new(1, 0);
+    let border = CHUNK_SIZE as i32;
+
+    // The stone blocks of the neighbour that a vein from this chunk grows into.
+    let crossing: Vec<IVec3> = veins_in_chunk(chunk, SEED)
+        .iter()
+        .flat_map(|vein| vein.blocks())
+        .filter(|pos| (border..2 * border).contains(&pos.x) && (0..border).contains(&pos.z))
+        .filter(|&pos| plain.block_at(pos) == BlockType::Stone)
+        .collect();
+    assert!(!crossing.is_empty(), "no vein crosses the border");
+
+    let blocks = generator.generate_chunk(neighbor);
+    for pos in crossing {
+        let block = blocks.get((pos.x - border) as usize, pos.y as usize, pos.z as usize);
+        assert_ne!(block, BlockType::Stone, "{:?}", pos);
+        assert_eq!(generator.block_at(pos), block, "{:?}", pos);
+    }
+}
@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+
+use crate::game::chunk::{create_chunk_mesh, get_verts_indices, mesh_blocks};
+use crate::game::generator::DefaultGenerator;
+use crate::game::packed::{
+    is_packed, pack_chunk_meshes, pack_mesh, MeshStats, PackedVertex, ATTRIBUTE_PACKED,
+    MAX_PACKED_GLOW, MAX_PACKED_POSITION,
+};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+use crate::tests::SEED;
+
+fn packed_vertices(mesh: &Mesh) -> &Vec<[u32; 2]> {
+    match mesh.attribute(ATTRIBUTE_PACKED) {
+        Some(VertexAttributeValues::Uint32x2(vertices)) => vertices,
+        _ => panic!("not packed"),
+    }
+}
+
+#[test]
+fn packed_vertices_unpack_to_the_same_vertex() {
+    let vertices = [
+        PackedVertex {
+            position: UVec3::ZERO,
+            face: BlockFace::Top,
+            corner: BVec2::new(false, false),
+            tile: 0,
+            glow: 0,
+            color: [0; 4],
+        },
+        PackedVertex {
+            position: UVec3::splat(MAX_PACKED_POSITION),
+            face: BlockFace::Back,
+            corner: BVec2::new(true, true),
+            tile: 255,
+            glow: MAX_PACKED_GLOW,
+            color: [255; 4],
+        },
+        PackedVertex {
+            position: UVec3::new(16, 3, 9),
+            face: BlockFace::Left,
+            corner: BVec2::new(true, false),
+            tile: 29,
+            glow: 6,
+            color: [115, 166, 212, 255],
+        },
+    ];
+    for vertex in vertices {
+        assert_eq!(PackedVertex::unpack(vertex.pack()), vertex);
+    }
+    // The color is the whole second word, the first one has the rest.
+    assert_eq!(vertices[1].pack()[1], u32::MAX);
+    assert_eq!(vertices[0].pack(), [0, 0]);
+}
+
+#[test]
+fn packed_chunk_meshes_keep_their_vertices() {
+    let chunk = IVec2XZ::new(2, -1);
+    let atlas = test_atlas();
+    let generator = Arc::new(DefaultGenerator::new(SEED, WorldRules::default()));
+    let (meshes, _) = create_chunk_mesh(chunk, atlas.clone(), generator.clone(), true);
+    let (mut packed, _) = create_chunk_mesh(chunk, atlas.clone(), generator, true);
+    pack_chunk_meshes(chunk, &mut packed, &atlas);
+
+    let mut packed_sections = 0;
+    for (section, packed_section) in meshes.sections.iter().zip(&packed.sections) {
+        let mesh = &section.solid_mesh;
+        let packed_mesh = &packed_section.solid_mesh;
+        if mesh.count_vertices() == 0 {
+            assert!(!is_packed(packed_mesh));
+            continue;
+        }
+        packed_sections += 1;
+        let origin = ChunkSection {
+            position: chunk,
+            index: section.index,
+        }
+        .origin()
+        .as_vec3();
+
+        // Only the packed attribute is left, with the same triangles in 16 bits.
+        assert!(is_packed(packed_mesh));
+        assert_eq!(packed_mesh.attributes().count(), 1);
+        let (Some(Indices::U32(indices)), Some(Indices::U16(packed_indices))) =
+            (mesh.indices(), packed_mesh.indices())
+        else {
+            panic!("the packed indices aren't 16-bit");
+        };
+        assert!(indices
+            .iter()
+            .zip(packed_indices)
+            .all(|(&index, &packed)| index == packed as u32));
+
+        let (
+            Some(VertexAttributeValues::Float32x3(positions)),
+            Some(VertexAttributeValues::Float32x3(normals)),
+            Some(VertexAttributeValues::Float32x2(uvs)),
+            Some(VertexAttributeValues::Float32x4(colors)),
+        ) = (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+            mesh.attribute(Mesh::ATTRIBUTE_UV_0),
+            mesh.attribute(Mesh::ATTRIBUTE_COLOR),
+        )
+        else {
+            panic!("unexpected attributes");
+        };
+        for (index, &vertex) in packed_vertices(packed_mesh).iter().enumerate() {
+            let vertex = PackedVertex::unpack(vertex);
+            assert_eq!(
+                vertex.position.as_vec3() + origin,
+                Vec3::from(positions[index])
+            );
+            assert_eq!(vertex.face.offset().as_vec3(), Vec3::from(normals[index]));
+            let corners = atlas.tile_uvs(vertex.tile);
+            let uv = [
+                if vertex.corner.x {
+                    corners[2][0]
+                } else {
+                    corners[0][0]
+                },
+                if vertex.corner.y {
+                    corners[2][1]
+                } else {
+                    corners[0][1]
+                },
+            ];
+            assert_eq!(uv, uvs[index]);
+            for (channel, color) in vertex.color.iter().zip(colors[index]) {
+                assert!((*channel as f32 / 255.0 - color).abs() <= 0.5 / 255.0);
+            }
+        }
+
+        // The collider geometry can be read back from the packed mesh, relative to the section.
+        let (vertices, triangles) = get_verts_indices(mesh);
+        let (packed_vertices, packed_triangles) = get_verts_indices(packed_mesh);
+        assert_eq!(triangles, packed_triangles);
+        assert!(vertices
+            .iter()
+            .zip(&packed_vertices)
+            .all(|(&vertex, &packed)| vertex == packed + origin));
+        // The fluids aren't packed, and the collider was built before.
+        assert_eq!(
+            section.fluid_meshes.len(),
+            packed_section.fluid_meshes.len()
+        );
+        assert_eq!(
+            section.collider.is_some(),
+            packed_section.collider.is_some()
+        );
+    }
+    assert!(packed_sections > 0);
+}
+
+#[test]
+fn the_precious_ores_glint_in_the_packed_meshes() {
+    let atlas = test_atlas();
+    let glint = (ORE_GLINT * MAX_PACKED_GLOW as f32).round() as u8;
+    for (block, glow) in [
+        (BlockType::DiamondOre, glint),
+        (BlockType::GoldOre, glint),
+        (BlockType::RedstoneOre, glint),
+        (BlockType::CoalOre, 0),
+        (BlockType::Stone, 0),
+    ] {
+        let mesh = mesh_blocks(UVec3::ONE, IVec3::ZERO, &atlas, |pos| {
+            if pos == IVec3::ZERO {
+                block
+            } else {
+                BlockType::Air
+            }
+        });
+        let packed = pack_mesh(&mesh, Vec3::ZERO, &atlas).unwrap();
+        assert!(
+            packed_vertices(&packed)
+                .iter()
+                .all(|&vertex| PackedVertex::unpack(vertex).glow == glow),
+            "{:?}",
+            block
+        );
+    }
+}
+
+#[test]
+fn meshes_that_dont_fit_arent_packed() {
+    let atlas = test_atlas();
+    let stone = |pos: IVec3| {
+        if pos == IVec3::ZERO {
+            BlockType::Stone
+        } else {
+            BlockType::Air
+        }
+    };
+    let one = UVec3::ONE;
+    let mesh = mesh_blocks(one, IVec3::ZERO, &atlas, stone);
+    assert!(pack_mesh(&mesh, Vec3::ZERO, &atlas).is_some());
+    // Too far from the origin, or behind it.
+    let far = mesh_blocks(one, IVec3::new(31, 0, 0), &atlas, stone);
+    assert!(pack_mesh(&far, Vec3::ZERO, &atlas).is_none());
+    assert!(pack_mesh(&mesh, Vec3::X, &atlas).is_none());
+    // Not on whole blocks, like the tops of the fluids.
+    let water = mesh_blocks(one, IVec3::ZERO, &atlas, |pos| {
+        if pos == IVec3::ZERO {
+            BlockType::Water
+        } else {
+            BlockType::Air
+        }
+    });
+    assert!(pack_mesh(&water, Vec3::ZERO, &atlas).is_none());
+    // Texture coordinates that aren't a tile of the atlas.
+    let mut stretched = mesh.clone();
+    if let Some(VertexAttributeValues::Float32x2(uvs)) =
+        stretched.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+    {
+        uvs[0] = [0.0, 0.0];
+    }
+    assert!(pack_mesh(&stretched, Vec3::ZERO, &atlas).is_none());
+}
+
+#[test]
+fn packed_meshes_take_a_sixth_of_the_memory() {
+    let atlas = test_atlas();
+    let mesh = mesh_blocks(UVec3::new(4, 2, 4), IVec3::ZERO, &atlas, |pos| {
+        if pos.cmplt(IVec3::new(4, 2, 4)).all() && pos.cmpge(IVec3::ZERO).all() {
+            BlockType::Stone
+        } else {
+            BlockType::Air
+        }
+    });
+    let packed = pack_mesh(&mesh, Vec3::ZERO, &atlas).unwrap();
+
+    let mut unpacked_stats = MeshStats::default();
+    unpacked_stats.add(&mesh);
+    let mut packed_stats = MeshStats::default();
+    packed_stats.add(&packed);
+    let (vertices, triangles) = (mesh.count_vertices(), get_verts_indices(&mesh).1.len());
+    // The mesh has no colors, its vertices are 32 bytes.
+    assert_eq!(
+        unpacked_stats,
+        MeshStats {
+            vertices,
+            triangles,
+            bytes: vertices * 32 + triangles * 3 * 4,
+            unpacked_bytes: vertices * 32 + triangles * 3 * 4,
+        }
+    );
+    assert_eq!(
+        packed_stats,
+        MeshStats {
+            vertices,
+            triangles,
+            bytes: vertices * 8 + triangles * 3 * 2,
+            unpacked_bytes: vertices * 48 + triangles * 3 * 4,
+        }
+    );
+}
@@ -0,0 +1,49 @@
+use crate::game::camera::player_collider;
+use crate::game::placement::*;
+use crate::prelude::*;
+
+/// The player's box with their feet at `feet`.
+fn player_at(feet: Vec3) -> WorldAabb {
+    WorldAabb::of_collider(
+        &player_collider(),
+        &Transform::from_translation(feet + Vec3::Y),
+    )
+}
+
+#[test]
+fn player_box_matches_the_collider() {
+    let aabb = player_at(Vec3::new(8.5, 71.0, 8.5));
+    assert!(aabb.min.abs_diff_eq(Vec3::new(8.0, 71.0, 8.0), 1e-5));
+    assert!(aabb.max.abs_diff_eq(Vec3::new(9.0, 73.0, 9.0), 1e-5));
+}
+
+#[test]
+fn standing_on_a_block_blocks_placing_at_your_feet() {
+    let player = player_at(Vec3::new(8.5, 71.0, 8.5));
+    assert!(player.overlaps_block(IVec3::new(8, 71, 8)));
+    assert!(player.overlaps_block(IVec3::new(8, 72, 8)));
+    // The block you're standing on and the ones right next to you are only touching.
+    assert!(!player.overlaps_block(IVec3::new(8, 70, 8)));
+    assert!(!player.overlaps_block(IVec3::new(9, 71, 8)));
+    assert!(!player.overlaps_block(IVec3::new(8, 73, 8)));
+}
+
+#[test]
+fn jumping_clears_the_cell_below_at_the_right_moment() {
+    // On the way up the feet are still in the cell.
+    assert!(player_at(Vec3::new(8.5, 71.6, 8.5)).overlaps_block(IVec3::new(8, 71, 8)));
+    // At the apex they're above it, so you can pillar up.
+    assert!(!player_at(Vec3::new(8.5, 72.1, 8.5)).overlaps_block(IVec3::new(8, 71, 8)));
+    // Landing exactly on top of it still counts as clear.
+    assert!(!player_at(Vec3::new(8.5, 72.0, 8.5)).overlaps_block(IVec3::new(8, 71, 8)));
+}
+
+#[test]
+fn standing_on_a_corner_blocks_four_cells() {
+    let player = player_at(Vec3::new(8.0, 71.0, 8.0));
+    for (x, z) in [(7, 7), (7, 8), (8, 7), (8, 8)] {
+        assert!(player.overlaps_block(IVec3::new(x, 71, z)), "{} {}", x, z);
+    }
+    assert!(!player.overlaps_block(IVec3::new(9, 71, 8)));
+    assert!(!player.overlaps_block(IVec3::new(6, 71, 7)));
+}
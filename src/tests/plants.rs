@@ -0,0 +1,94 @@
+use crate::game::chunk::{collider_geometry, get_verts_indices, mesh_blocks};
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::plants::plant_for_column;
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+use crate::tests::SEED;
+
+#[test]
+fn plants_grow_on_the_grass() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let (mut plants, mut flowers) = (0, 0);
+    for chunk in [
+        IVec2XZ::new(-3, 0),
+        IVec2XZ::new(-1, -3),
+        IVec2XZ::new(-2, 0),
+    ] {
+        let blocks = generator.generate_chunk(chunk);
+        let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let column = blocks.column(x, z);
+                for y in 1..CHUNK_HEIGHT {
+                    if !column[y].is_plant() {
+                        continue;
+                    }
+                    assert_eq!(column[y - 1], BlockType::Grass);
+                    let pos = origin + IVec3::new(x as i32, y as i32, z as i32);
+                    assert_eq!(
+                        plant_for_column(IVec2XZ::new(pos.x, pos.z), SEED),
+                        Some(column[y])
+                    );
+                    // The neighbouring chunks see the same plants at the border.
+                    if x == 0 || z == 0 || x == CHUNK_SIZE - 1 || z == CHUNK_SIZE - 1 {
+                        assert_eq!(generator.block_at(pos), column[y], "{:?}", pos);
+                    }
+                    plants += 1;
+                    flowers += (column[y] != BlockType::TallGrass) as usize;
+                }
+            }
+        }
+    }
+    assert!(plants > 20, "{} plants", plants);
+    assert!(flowers > 0 && flowers < plants / 2, "{} flowers", flowers);
+
+    let bare = DefaultGenerator::new(
+        SEED,
+        WorldRules {
+            generate_structures: false,
+            ..default()
+        },
+    );
+    let blocks = bare.generate_chunk(IVec2XZ::new(-3, 0));
+    assert!((0..CHUNK_SIZE)
+        .flat_map(|x| (0..CHUNK_SIZE).map(move |z| (x, z)))
+        .all(|(x, z)| !blocks.column(x, z).iter().any(|block| block.is_plant())));
+}
+
+#[test]
+fn plants_are_crosses_without_colliders() {
+    // A grass floor with tall grass on one block and a poppy next to it.
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            blocks.column_mut(x, z)[..=64].fill(BlockType::Grass);
+        }
+    }
+    blocks.set(4, 65, 4, BlockType::TallGrass);
+    blocks.set(5, 65, 4, BlockType::Poppy);
+
+    let size = UVec3::new(CHUNK_SIZE as u32, CHUNK_HEIGHT as u32, CHUNK_SIZE as u32);
+    let mesh = mesh_blocks(size, IVec3::ZERO, &test_atlas(), |pos| {
+        if pos.cmpge(IVec3::ZERO).all() && pos.cmplt(size.as_ivec3()).all() {
+            blocks.get(pos.x as usize, pos.y as usize, pos.z as usize)
+        } else {
+            BlockType::Air
+        }
+    });
+    let (vertices, triangles) = get_verts_indices(&mesh);
+    let (_, collider) = collider_geometry(&mesh, IVec2XZ::new(0, 0), &blocks);
+
+    // The grass under the plants is still drawn, and the plants are two quads each, from both sides.
+    let tops = CHUNK_SIZE * CHUNK_SIZE;
+    let sides = 4 * CHUNK_SIZE * 65;
+    let bottoms = CHUNK_SIZE * CHUNK_SIZE;
+    let plants = 2 * 2 * 2 * 2;
+    assert_eq!(triangles.len(), 2 * (tops + sides + bottoms) + plants);
+
+    // The player walks right through the plants.
+    assert_eq!(collider.len(), triangles.len() - plants);
+    assert!(collider
+        .iter()
+        .all(|triangle| triangle.iter().all(|&i| vertices[i as usize].y <= 65.0)));
+}
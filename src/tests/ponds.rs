@@ -0,0 +1,120 @@
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::ponds::*;
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+/// Bedrock, stone and dirt with a grass top at y 70.
+fn flat_chunk() -> ChunkBlocks {
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for (y, block) in blocks.column_mut(x, z).iter_mut().enumerate().take(71) {
+                *block = match y {
+                    0 => BlockType::Bedrock,
+                    1..=66 => BlockType::Stone,
+                    67..=69 => BlockType::Dirt,
+                    _ => BlockType::Grass,
+                };
+            }
+        }
+    }
+    blocks
+}
+
+fn test_pond(lava: bool) -> Pond {
+    Pond {
+        center: (8, 8),
+        radius_x: 3,
+        radius_z: 3,
+        depth: 2,
+        lava,
+    }
+}
+
+#[test]
+fn pond_placement_is_deterministic() {
+    for x in -8..8 {
+        for z in -8..8 {
+            let chunk = IVec2XZ::new(x, z);
+            assert_eq!(pond_for_chunk(chunk, SEED), pond_for_chunk(chunk, SEED));
+        }
+    }
+}
+
+#[test]
+fn ponds_stay_away_from_chunk_borders() {
+    for x in -32..32 {
+        for z in -32..32 {
+            if let Some(pond) = pond_for_chunk(IVec2XZ::new(x, z), SEED) {
+                // The scorch ring is two blocks wide, and the border column must stay untouched.
+                assert!(pond.center.0 >= pond.radius_x + 3);
+                assert!(pond.center.1 >= pond.radius_z + 3);
+                assert!(pond.center.0 + pond.radius_x + 3 < CHUNK_SIZE);
+                assert!(pond.center.1 + pond.radius_z + 3 < CHUNK_SIZE);
+            }
+        }
+    }
+}
+
+#[test]
+fn water_pond_fill_and_lining() {
+    let mut blocks = flat_chunk();
+    assert!(carve_pond(&test_pond(false), &mut blocks));
+
+    // Filled up to the old surface, two deep in the middle, lined with sand.
+    assert_eq!(blocks.get(8, 70, 8), BlockType::Water);
+    assert_eq!(blocks.get(8, 69, 8), BlockType::Water);
+    assert_eq!(blocks.get(8, 68, 8), BlockType::Sand);
+    // The edge of the bowl is one deep.
+    assert_eq!(blocks.get(11, 70, 8), BlockType::Water);
+    assert_eq!(blocks.get(11, 69, 8), BlockType::Sand);
+    // The shore next to the water is lined too.
+    assert_eq!(blocks.get(12, 70, 8), BlockType::Sand);
+    assert_eq!(blocks.get(13, 70, 8), BlockType::Grass);
+    assert_eq!(blocks.get(8, 71, 8), BlockType::Air);
+}
+
+#[test]
+fn lava_pond_scorches_grass() {
+    let mut blocks = flat_chunk();
+    assert!(carve_pond(&test_pond(true), &mut blocks));
+
+    assert_eq!(blocks.get(8, 70, 8), BlockType::Lava);
+    assert_eq!(blocks.get(8, 68, 8), BlockType::Stone);
+    assert_eq!(blocks.get(12, 70, 8), BlockType::Stone);
+    // Two block ring of dirt around the pond.
+    assert_eq!(blocks.get(13, 70, 8), BlockType::Dirt);
+    assert_eq!(blocks.get(14, 70, 8), BlockType::Grass);
+}
+
+#[test]
+fn pond_does_not_hang_over_caves() {
+    let mut blocks = flat_chunk();
+    blocks.set(8, 68, 8, BlockType::Air);
+    let before = blocks.clone();
+
+    assert!(!carve_pond(&test_pond(false), &mut blocks));
+    assert!(blocks == before);
+}
+
+#[test]
+fn known_ponds_for_seed() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+
+    // Water pond at chunk (15, 11), centred at local (8, 8) with its surface at y 72.
+    let water = IVec2XZ::new(15, 11);
+    assert!(pond_for_chunk(water, SEED).is_some_and(|p| p.center == (8, 8) && !p.lava));
+    let blocks = generator.generate_chunk(water);
+    assert_eq!(blocks.get(8, 72, 8), BlockType::Water);
+    assert_eq!(blocks.get(8, 70, 8), BlockType::Water);
+    assert_eq!(blocks.get(8, 69, 8), BlockType::Sand);
+    assert_eq!(blocks.get(8, 73, 8), BlockType::Air);
+
+    // Lava pond at chunk (4, -12), centred at local (8, 6) with its surface at y 71.
+    let lava = IVec2XZ::new(4, -12);
+    assert!(pond_for_chunk(lava, SEED).is_some_and(|p| p.center == (8, 6) && p.lava));
+    let blocks = generator.generate_chunk(lava);
+    assert_eq!(blocks.get(8, 71, 6), BlockType::Lava);
+    assert_eq!(blocks.get(8, 68, 6), BlockType::Stone);
+}
@@ -0,0 +1,110 @@
+use crate::game::projectiles::*;
+use crate::game::target::{raycast_blocks, BlockHit};
+use crate::prelude::*;
+
+/// Solid ground up to y 63, the top of it at y 64.
+fn floor(pos: IVec3) -> bool {
+    pos.y < 64
+}
+
+fn nothing(_position: Vec3) -> Option<Entity> {
+    None
+}
+
+/// The block a throw lands on, frame after frame at the given frame time.
+fn landing_block(origin: Vec3, velocity: Vec3, frame: f32) -> Option<IVec3> {
+    let mut projectile = Projectile::new(origin, velocity);
+    while !projectile.is_expired() {
+        if let Some(Impact::Block(hit)) = projectile.advance(frame, floor, nothing) {
+            return Some(hit.pos);
+        }
+    }
+    None
+}
+
+#[test]
+fn rays_stop_at_the_first_block() {
+    let down = raycast_blocks(Vec3::new(0.5, 70.5, -3.5), -Vec3::Y, 10.0, floor);
+    assert_eq!(
+        down,
+        Some(BlockHit {
+            pos: IVec3::new(0, 63, -4),
+            normal: IVec3::Y,
+            distance: 6.5,
+            point: Vec3::new(0.5, 64.0, -3.5),
+        })
+    );
+
+    // Diagonally into the side of a wall, on the negative side of the grid.
+    let wall = |pos: IVec3| pos.x <= -5;
+    let hit = raycast_blocks(
+        Vec3::new(-0.5, 70.2, 0.3),
+        Vec3::new(-1.0, 0.0, -1.0),
+        20.0,
+        wall,
+    )
+    .unwrap();
+    assert_eq!(hit.pos, IVec3::new(-5, 70, -4));
+    assert_eq!(hit.normal, IVec3::X);
+    assert!((hit.distance - 3.5 * 2f32.sqrt()).abs() < 1e-4);
+
+    // Out of reach, or starting inside.
+    assert_eq!(
+        raycast_blocks(Vec3::new(0.5, 70.5, 0.5), -Vec3::Y, 6.0, floor),
+        None
+    );
+    assert_eq!(
+        raycast_blocks(Vec3::new(0.5, 70.5, 0.5), Vec3::Y, 100.0, floor),
+        None
+    );
+    let inside = raycast_blocks(Vec3::new(0.5, 60.5, 0.5), Vec3::X, 1.0, floor).unwrap();
+    assert_eq!(
+        (inside.pos, inside.normal),
+        (IVec3::new(0, 60, 0), IVec3::ZERO)
+    );
+}
+
+#[test]
+fn the_same_throw_lands_on_the_same_block() {
+    let origin = Vec3::new(0.5, 70.0, 0.5);
+    let velocity = Vec3::new(12.0, 8.0, -5.0);
+    let landing = landing_block(origin, velocity, 1.0 / 60.0).unwrap();
+    assert_eq!(landing_block(origin, velocity, 1.0 / 60.0), Some(landing));
+    assert_eq!(landing.y, 63);
+
+    // The frame rate doesn't matter, only whole steps are simulated.
+    for frame in [1.0 / 144.0, 1.0 / 30.0, 0.25] {
+        assert_eq!(
+            landing_block(origin, velocity, frame),
+            Some(landing),
+            "{}",
+            frame
+        );
+    }
+
+    // Thrown straight ahead from 6 blocks up, it lands about sqrt(2 * 6 / g) seconds out.
+    let flat = landing_block(Vec3::new(0.5, 70.0, 0.5), Vec3::X * THROW_SPEED, 1.0 / 60.0).unwrap();
+    let expected = THROW_SPEED * (2.0 * 6.0 / GRAVITY).sqrt();
+    assert!((flat.x as f32 - expected).abs() <= 1.0, "{:?}", flat);
+}
+
+#[test]
+fn projectiles_hit_mobs_and_expire() {
+    let mob = Entity::from_raw(7);
+    let mob_at = Vec3::new(5.0, 70.0, 0.5);
+    let target_at = |position: Vec3| (position.distance(mob_at) < MOB_HIT_RADIUS).then_some(mob);
+    let mut projectile = Projectile::new(Vec3::new(0.5, 70.0, 0.5), Vec3::X * THROW_SPEED);
+    assert_eq!(
+        projectile.advance(1.0, floor, target_at),
+        Some(Impact::Entity(mob))
+    );
+    assert!(projectile.position.distance(mob_at) < MOB_HIT_RADIUS);
+
+    // Thrown up with nothing to land on, it runs out of time.
+    let mut projectile = Projectile::new(Vec3::new(0.5, 70.0, 0.5), Vec3::Y * THROW_SPEED);
+    assert_eq!(
+        projectile.advance(PROJECTILE_LIFETIME + 1.0, |_| false, nothing),
+        None
+    );
+    assert!(projectile.is_expired());
+}
@@ -0,0 +1,143 @@
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::ravines::*;
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+fn ravines() -> impl Iterator<Item = Ravine> {
+    (-8..8)
+        .flat_map(|x| (-8..8).map(move |z| IVec2XZ::new(x, z)))
+        .filter_map(|region| ravine_in_region(region, SEED))
+}
+
+fn chunk_of(point: Vec2) -> IVec2XZ {
+    IVec2XZ::new(
+        (point.x.floor() as i32).div_euclid(CHUNK_SIZE as i32),
+        (point.y.floor() as i32).div_euclid(CHUNK_SIZE as i32),
+    )
+}
+
+/// A block in the middle of a ravine, this high above its floor.
+fn middle(ravine: &Ravine, height: f32) -> IVec3 {
+    let point = ravine.path[ravine.path.len() / 2];
+    IVec3::new(
+        point.x.floor() as i32,
+        (ravine.floor + height) as i32,
+        point.y.floor() as i32,
+    )
+}
+
+#[test]
+fn ravines_narrow_down_towards_their_ends() {
+    let mut found = 0;
+    for ravine in ravines().take(8) {
+        found += 1;
+        let (min, max) = ravine.bounds();
+        assert!(ravine
+            .path
+            .iter()
+            .all(|point| point.cmpge(min).all() && point.cmple(max).all()));
+        assert!(ravine.carves(middle(&ravine, 20.0)), "{:?}", ravine);
+        // Not below the floor, and not past the ends.
+        assert!(!ravine.carves(middle(&ravine, -1.0)));
+        let last = ravine.path.len() - 1;
+        for (end, next) in [(0, 1), (last, last - 1)] {
+            let (end, next) = (ravine.path[end], ravine.path[next]);
+            let past = end + (end - next).normalize() * 2.0;
+            let past = IVec3::new(past.x.floor() as i32, 100, past.y.floor() as i32);
+            assert!(!ravine.carves(past), "{:?}", past);
+        }
+    }
+    assert!(found > 0, "no ravines around the origin");
+}
+
+#[test]
+fn every_chunk_along_a_ravine_sees_it() {
+    let ravine = ravines().next().unwrap();
+    for &point in &ravine.path {
+        let near = ravines_near(chunk_of(point), SEED);
+        assert!(
+            near.contains(&ravine),
+            "{:?} misses the ravine",
+            chunk_of(point)
+        );
+    }
+}
+
+#[test]
+fn ravines_cut_across_chunk_borders() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let ravine = ravines()
+        .find(|ravine| !ravine.floods(WATER_HEIGHT, |column| generator.surface_y(column) as f32))
+        .expect("every ravine is flooded");
+
+    // Two neighbouring chunks the middle of the ravine runs through.
+    let (first, second) = ravine
+        .path
+        .windows(2)
+        .map(|pair| (chunk_of(pair[0]), chunk_of(pair[1])))
+        .find(|(a, b)| a != b)
+        .unwrap();
+    let mut carved = 0;
+    for chunk in [first, second] {
+        let blocks = generator.generate_chunk(chunk);
+        let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let top =
+                    generator.surface_y(IVec2XZ::new(origin.x + x as i32, origin.z + z as i32));
+                for y in 0..top.min(CHUNK_HEIGHT as i32) {
+                    let pos = origin + IVec3::new(x as i32, y, z as i32);
+                    if !ravine.carves(pos) || y <= 1 {
+                        continue;
+                    }
+                    let block = blocks.get(x, y as usize, z);
+                    assert!(!block.is_solid(), "{:?} at {:?}", block, pos);
+                    assert_eq!(generator.block_at(pos), block, "{:?}", pos);
+                    carved += 1;
+                }
+            }
+        }
+    }
+    assert!(carved > 0);
+}
+
+#[test]
+fn ravines_under_the_ocean_flood() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let surface = |column: IVec2XZ| generator.surface_y(column) as f32;
+    let ravine = ravines()
+        .find(|ravine| ravine.floods(WATER_HEIGHT, surface))
+        .expect("no ravine reaches the ocean");
+
+    // The water reaches all along the ravine, also where it runs under land.
+    let mut flooded = 0;
+    for point in &ravine.path {
+        let column = IVec2XZ::new(point.x.floor() as i32, point.y.floor() as i32);
+        let pos = IVec3::new(column.x, WATER_HEIGHT as i32 - 1, column.z);
+        if !ravine.carves(pos) || surface(column) <= WATER_HEIGHT as f32 {
+            continue;
+        }
+        assert_eq!(generator.block_at(pos), BlockType::Water, "{:?}", pos);
+        flooded += 1;
+    }
+    assert!(flooded > 0, "the ravine never runs under land");
+    assert_eq!(
+        ravine_fill(
+            IVec3::new(0, WATER_HEIGHT as i32, 0),
+            WATER_HEIGHT,
+            true,
+            true
+        ),
+        BlockType::Ice
+    );
+    assert_eq!(
+        ravine_fill(
+            IVec3::new(0, LAVA_HEIGHT as i32, 0),
+            WATER_HEIGHT,
+            false,
+            false
+        ),
+        BlockType::Lava
+    );
+}
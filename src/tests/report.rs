@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::report::*;
+use crate::game::rules::{WorldRules, WorldType};
+use crate::prelude::*;
+use crate::tests::SEED;
+
+#[test]
+fn block_ids_are_stable_and_unique() {
+    let ids: HashSet<u8> = BlockType::ALL.iter().map(|block| block.id()).collect();
+    assert_eq!(ids.len(), BlockType::ALL.len());
+    for block in BlockType::ALL {
+        assert_eq!(BlockType::from_id(block.id()), Some(block));
+    }
+    // Written to files, these must never change.
+    assert_eq!(BlockType::Air.id(), 0);
+    assert_eq!(BlockType::Stone.id(), 2);
+    assert_eq!(BlockType::Gravel.id(), 23);
+    assert_eq!(BlockType::from_id(200), None);
+}
+
+#[test]
+fn exported_chunks_read_back_the_same() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let position = IVec2XZ::new(-1, 3);
+    let blocks = generator.generate_chunk(position);
+    let export = ChunkExport::new(position, &blocks);
+    assert!(export.blocks() == Ok(blocks.clone()));
+    // Most columns are a few long runs.
+    assert!(export.runs.len() < CHUNK_SIZE * CHUNK_SIZE * 16);
+
+    let mut short = export.clone();
+    short.runs.pop();
+    assert!(short.blocks().is_err());
+    let mut unknown = export;
+    unknown.runs[0].0 = 200;
+    assert_eq!(unknown.blocks().err().unwrap(), "unknown block id 200");
+}
+
+#[test]
+fn reports_load_what_was_written() {
+    let world_type = WorldType::parse("flat:bedrock,3*dirt,grass").unwrap();
+    assert_eq!(
+        WorldType::parse(&world_type.to_arg()),
+        Ok(world_type.clone())
+    );
+    let report = BugReport {
+        seed: SEED,
+        world_type: world_type.to_arg(),
+        rules: WorldRules::default(),
+        position: [12.5, 80.0, -3.25],
+        yaw: 1.0,
+        pitch: -0.5,
+        look_direction: [0.0, 0.0, -1.0],
+        flying: true,
+        chunks: vec![ChunkRecord {
+            position: [0, -1],
+            state: "Requested".to_string(),
+            blocks: None,
+        }],
+    };
+
+    let dir = std::env::temp_dir().join(format!("voxel-report-{}", std::process::id()));
+    let chunk = (IVec2XZ::new(0, -1), ChunkBlocks::default());
+    report.write(&dir, &Settings::default(), &[chunk]).unwrap();
+    let loaded = BugReport::load(&dir);
+    let chunk_file = dir.join(ChunkExport::file_name(IVec2XZ::new(0, -1)));
+    assert!(chunk_file.exists() && dir.join(REPORT_SETTINGS_FILE).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(loaded, Ok(report));
+
+    assert!(BugReport::load(&dir).is_err());
+}
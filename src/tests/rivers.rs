@@ -0,0 +1,60 @@
+use crate::game::generator::{carve_river, DefaultGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+#[test]
+fn rivers_never_raise_the_surface() {
+    for height in [40.0, 66.0, WATER_HEIGHT as f32, 90.0, 150.0] {
+        // The middle of a river is always the riverbed, unless the ground is lower already.
+        assert_eq!(
+            carve_river(height, 0.0),
+            height.min(RIVER_BED_HEIGHT as f32)
+        );
+        // Past the valley the surface is left alone.
+        assert_eq!(carve_river(height, RIVER_CHANNEL + RIVER_VALLEY), height);
+
+        let mut last = carve_river(height, 0.0);
+        for step in 0..=100 {
+            let carved = carve_river(height, step as f64 * 0.001);
+            assert!(carved <= height);
+            assert!(carved >= last, "the valley sides go down again");
+            last = carved;
+        }
+    }
+}
+
+#[test]
+fn rivers_are_filled_with_water() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let rivers: Vec<IVec2XZ> = (0..2048)
+        .map(|x| IVec2XZ::new(x, 0))
+        .filter(|&column| generator.is_river(column))
+        .collect();
+    assert!(!rivers.is_empty(), "no river crosses the x axis");
+
+    for column in rivers {
+        let at = |y: usize| generator.block_at(IVec3::new(column.x, y as i32, column.z));
+        for y in RIVER_BED_HEIGHT + 1..WATER_HEIGHT {
+            assert_eq!(at(y), BlockType::Water, "{:?} at y {}", column, y);
+        }
+        // Rivers in cold biomes freeze over.
+        assert!(
+            matches!(at(WATER_HEIGHT), BlockType::Water | BlockType::Ice),
+            "{:?}",
+            column
+        );
+        assert_eq!(at(WATER_HEIGHT + 1), BlockType::Air);
+        // The riverbed is sand, or the floor of the ocean the river runs into.
+        let bed = (0..=RIVER_BED_HEIGHT)
+            .rev()
+            .map(at)
+            .find(|&block| block != BlockType::Water)
+            .unwrap();
+        assert!(
+            matches!(bed, BlockType::Sand | BlockType::Stone),
+            "{:?}",
+            bed
+        );
+    }
+}
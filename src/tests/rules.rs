@@ -0,0 +1,238 @@
+use crate::game::generator::{bedrock_thickness, DefaultGenerator, WorldGenerator};
+use crate::game::rules::*;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+const CHUNKS: [IVec2XZ; 3] = [
+    IVec2XZ::new(0, 0),
+    IVec2XZ::new(-1, -1),
+    IVec2XZ::new(5, -3),
+];
+
+fn generate(rules: WorldRules) -> Vec<ChunkBlocks> {
+    let generator = DefaultGenerator::new(SEED, rules);
+    CHUNKS
+        .iter()
+        .map(|&chunk| generator.generate_chunk(chunk))
+        .collect()
+}
+
+fn columns(blocks: &ChunkBlocks) -> impl Iterator<Item = &[BlockType]> {
+    (0..CHUNK_SIZE).flat_map(move |x| (0..CHUNK_SIZE).map(move |z| blocks.column(x, z)))
+}
+
+/// Whether any column has air under its highest terrain block. Trees stand on the terrain, with air under their leaves.
+fn has_air_below_surface(chunks: &[ChunkBlocks]) -> bool {
+    chunks.iter().flat_map(columns).any(|column| {
+        let top = column
+            .iter()
+            .rposition(|&block| !(block == BlockType::Air || block.is_log() || block.is_leaves()))
+            .unwrap();
+        column[..top].contains(&BlockType::Air)
+    })
+}
+
+fn count(chunks: &[ChunkBlocks], predicate: impl Fn(usize, BlockType) -> bool) -> usize {
+    chunks
+        .iter()
+        .flat_map(columns)
+        .map(|column| {
+            column
+                .iter()
+                .enumerate()
+                .filter(|&(y, &block)| predicate(y, block))
+                .count()
+        })
+        .sum()
+}
+
+fn is_ore(block: BlockType) -> bool {
+    matches!(
+        block,
+        BlockType::DiamondOre
+            | BlockType::RedstoneOre
+            | BlockType::GoldOre
+            | BlockType::IronOre
+            | BlockType::CoalOre
+    )
+}
+
+#[test]
+fn turning_caves_off_leaves_no_air_below_the_surface() {
+    assert!(has_air_below_surface(&generate(WorldRules::default())));
+
+    let no_caves = generate(WorldRules {
+        generate_caves: false,
+        ..default()
+    });
+    assert!(!has_air_below_surface(&no_caves));
+    assert_eq!(count(&no_caves, |_, block| block == BlockType::Lava), 0);
+}
+
+#[test]
+fn turning_ores_off_leaves_plain_stone() {
+    assert!(count(&generate(WorldRules::default()), |_, block| is_ore(block)) > 0);
+
+    let no_ores = generate(WorldRules {
+        generate_ores: false,
+        ..default()
+    });
+    assert_eq!(count(&no_ores, |_, block| is_ore(block)), 0);
+}
+
+#[test]
+fn amplified_terrain_is_taller() {
+    let highest = |chunks: &[ChunkBlocks]| {
+        chunks
+            .iter()
+            .flat_map(columns)
+            .filter_map(|column| column.iter().rposition(|&block| block != BlockType::Air))
+            .max()
+            .unwrap()
+    };
+    let normal = highest(&generate(WorldRules::default()));
+    let amplified = highest(&generate(WorldRules {
+        amplified: true,
+        ..default()
+    }));
+    assert!(amplified > normal, "{} <= {}", amplified, normal);
+    assert!(amplified < CHUNK_HEIGHT - 1);
+}
+
+#[test]
+fn bedrock_is_uneven_unless_flat() {
+    let above_floor = |y: usize, block: BlockType| y > 0 && block == BlockType::Bedrock;
+    let flat = generate(WorldRules {
+        bedrock_flat: true,
+        ..default()
+    });
+    assert_eq!(count(&flat, above_floor), 0);
+
+    let uneven = generate(WorldRules::default());
+    assert!(count(&uneven, above_floor) > 0);
+    assert_eq!(
+        count(&uneven, |y, block| y >= BEDROCK_MAX_THICKNESS as usize
+            && block == BlockType::Bedrock),
+        0
+    );
+    // The bottom layer is still solid.
+    assert_eq!(
+        count(&uneven, |y, block| y == 0 && block == BlockType::Bedrock),
+        CHUNKS.len() * CHUNK_SIZE * CHUNK_SIZE
+    );
+}
+
+#[test]
+fn caves_never_cut_into_the_bedrock() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let mut thicknesses = [0; BEDROCK_MAX_THICKNESS as usize];
+    for x in -64..64 {
+        for z in -64..64 {
+            let column = IVec2XZ::new(x * 7, z * 5);
+            let thickness = bedrock_thickness(column, SEED);
+            thicknesses[thickness as usize - 1] += 1;
+            for y in 0..thickness {
+                let pos = IVec3::new(column.x, y, column.z);
+                assert_eq!(generator.block_at(pos), BlockType::Bedrock, "{:?}", pos);
+            }
+        }
+    }
+    // Every thickness shows up about as often.
+    assert!(thicknesses.iter().all(|&n| n > 3000), "{:?}", thicknesses);
+}
+
+#[test]
+fn rules_are_overridden_from_the_command_line() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        WorldRules::overrides_from_args(&args(&["game", "--rules", "caves=false"])),
+        Some("caves=false")
+    );
+    assert_eq!(
+        WorldRules::overrides_from_args(&args(&["game", "--rules=ores=false"])),
+        Some("ores=false")
+    );
+    assert_eq!(WorldRules::overrides_from_args(&args(&["game"])), None);
+
+    let mut rules = WorldRules::default();
+    rules
+        .apply_overrides("caves=false, amplified=true")
+        .unwrap();
+    assert!(!rules.generate_caves);
+    assert!(rules.amplified);
+    assert!(rules.generate_ores);
+}
+
+#[test]
+fn invalid_overrides_change_nothing() {
+    let mut rules = WorldRules::default();
+    assert!(rules.apply_overrides("caves=false,lakes=false").is_err());
+    assert!(rules.apply_overrides("caves=nope").is_err());
+    assert!(rules.apply_overrides("caves").is_err());
+    assert_eq!(rules, WorldRules::default());
+}
+
+#[test]
+fn seed_is_read_from_the_command_line() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        WorldSeed::from_args(&args(&["game", "--seed", "42"])),
+        Some(WorldSeed(42))
+    );
+    assert_eq!(
+        WorldSeed::from_args(&args(&["game", "--rules=caves=false", "--seed=7"])),
+        Some(WorldSeed(7))
+    );
+    assert_eq!(WorldSeed::from_args(&args(&["game"])), None);
+    assert_eq!(WorldSeed::from_args(&args(&["game", "--seed", "-1"])), None);
+    assert_eq!(WorldSeed::from_args(&args(&["game", "--seedling=1"])), None);
+}
+
+#[test]
+fn the_seed_changes_the_terrain() {
+    let rules = WorldRules::default();
+    let chunk = IVec2XZ::new(0, 0);
+    let generate = |seed| DefaultGenerator::new(seed, rules).generate_chunk(chunk);
+    assert!(generate(SEED) == generate(SEED));
+    assert!(generate(SEED) != generate(SEED + 1));
+}
+
+#[test]
+fn world_type_is_parsed() {
+    assert_eq!(WorldType::parse("normal"), Ok(WorldType::Normal));
+    assert_eq!(
+        WorldType::parse("floating_islands"),
+        Ok(WorldType::FloatingIslands)
+    );
+    assert_eq!(
+        WorldType::parse("flat"),
+        Ok(WorldType::Flat {
+            layers: vec![
+                (BlockType::Bedrock, 1),
+                (BlockType::Dirt, 2),
+                (BlockType::Grass, 1),
+            ]
+        })
+    );
+    assert_eq!(
+        WorldType::parse("flat:bedrock, 3*stone,grass_block"),
+        Ok(WorldType::Flat {
+            layers: vec![
+                (BlockType::Bedrock, 1),
+                (BlockType::Stone, 3),
+                (BlockType::Grass, 1),
+            ]
+        })
+    );
+    assert!(WorldType::parse("amplified").is_err());
+    assert!(WorldType::parse("flat:bedrock,x*dirt").is_err());
+    assert!(WorldType::parse("flat:bedrock,cheese").is_err());
+    assert!(WorldType::parse("flat:0*dirt").is_err());
+    assert!(WorldType::parse("flat:300*dirt").is_err());
+
+    let args: Vec<String> = ["game", "--world-type=flat"].map(String::from).to_vec();
+    assert!(matches!(
+        WorldType::from_args(&args),
+        Some(WorldType::Flat { .. })
+    ));
+}
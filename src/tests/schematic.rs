@@ -0,0 +1,59 @@
+use crate::game::schematic::*;
+use crate::prelude::*;
+
+#[test]
+fn schematics_parse_into_blocks() {
+    let schematic = Schematic::parse(include_str!("fixtures/hut.ron")).unwrap();
+    let cells: Vec<(UVec3, BlockType)> = schematic.cells().collect();
+
+    assert_eq!(cells.len(), 27);
+    assert!(cells.contains(&(UVec3::new(2, 0, 1), BlockType::Stone)));
+    assert!(cells.contains(&(UVec3::new(0, 1, 2), BlockType::Log)));
+    assert!(cells.contains(&(UVec3::new(1, 1, 1), BlockType::Air)));
+}
+
+#[test]
+fn unknown_block_names_become_stone() {
+    let schematic = Schematic::parse(include_str!("fixtures/hut.ron")).unwrap();
+
+    assert_eq!(
+        schematic.unknown_names().collect::<Vec<_>>(),
+        vec!["Marble"]
+    );
+    assert!(schematic
+        .cells()
+        .filter(|&(offset, _)| offset.y == 2)
+        .all(|(_, block)| block == BlockType::Stone));
+}
+
+#[test]
+fn schematics_with_the_wrong_block_count_are_rejected() {
+    let error = Schematic::parse(include_str!("fixtures/short.ron")).unwrap_err();
+    assert!(matches!(
+        error,
+        SchematicError::WrongBlockCount {
+            expected: 8,
+            found: 7
+        }
+    ));
+}
+
+#[test]
+fn palette_indices_are_checked() {
+    let error =
+        Schematic::parse("(size: (1, 1, 1), palette: [\"Stone\"], blocks: [1])").unwrap_err();
+    assert!(matches!(error, SchematicError::BadPaletteIndex(1)));
+}
+
+#[test]
+fn sizes_with_too_many_blocks_are_rejected() {
+    let max = u32::MAX;
+    let contents = format!("(size: ({max}, {max}, {max}), palette: [\"Stone\"], blocks: [])");
+    let error = Schematic::parse(&contents).unwrap_err();
+    assert!(matches!(
+        error,
+        SchematicError::TooLarge {
+            size: (u32::MAX, u32::MAX, u32::MAX)
+        }
+    ));
+}
@@ -0,0 +1,56 @@
+use crate::game::sky::*;
+use crate::prelude::*;
+
+fn brightness(color: Color) -> f32 {
+    let [r, g, b, _] = color.as_rgba_f32();
+    r + g + b
+}
+
+#[test]
+fn the_sky_darkens_as_the_sun_sets() {
+    let mut last = sky_colors(1.0);
+    for step in 1..=20 {
+        let colors = sky_colors(1.0 - step as f32 * 0.1);
+        assert!(brightness(colors.zenith) <= brightness(last.zenith) + 1e-6);
+        last = colors;
+    }
+    // Past the night and the day nothing changes anymore.
+    assert_eq!(sky_colors(-0.5), sky_colors(-1.0));
+    assert_eq!(sky_colors(0.5), sky_colors(1.0));
+    // The sky doesn't jump at the horizon.
+    let (below, above) = (sky_colors(-0.001), sky_colors(0.001));
+    assert!((brightness(below.horizon) - brightness(above.horizon)).abs() < 0.05);
+}
+
+#[test]
+fn stars_only_come_out_at_night() {
+    assert_eq!(star_brightness(0.3), 0.0);
+    assert_eq!(star_brightness(0.0), 0.0);
+    assert!(star_brightness(-0.1) > 0.0);
+    assert_eq!(star_brightness(-0.5), 1.0);
+}
+
+#[test]
+fn stars_are_spread_over_the_whole_sky() {
+    let stars = star_directions(1000);
+    assert_eq!(stars, star_directions(1000));
+    assert!(stars.iter().all(|star| (star.length() - 1.0).abs() < 1e-4));
+    let above = stars.iter().filter(|star| star.y > 0.0).count();
+    assert!((400..600).contains(&above), "{} of the stars are up", above);
+}
+
+#[test]
+fn old_settings_files_keep_the_atmosphere() {
+    let settings: Settings = ron::from_str("(spawn_chunk_radius: 3)").unwrap();
+    assert_eq!(settings.graphics.sky, SkyMode::Atmosphere);
+    let settings: Settings = ron::from_str("(graphics: (sky: Gradient))").unwrap();
+    assert_eq!(settings.graphics.sky, SkyMode::Gradient);
+}
+#[test]
+fn sky_systems_have_disjoint_queries() {
+    // Initializing the systems panics if two of their queries could get the same transform mutably.
+    let mut world = World::new();
+    let mut schedule = Schedule::default();
+    schedule.add_systems((update_sky, apply_sky_mode));
+    schedule.initialize(&mut world).unwrap();
+}
@@ -0,0 +1,61 @@
+use crate::game::biome::BiomeNoise;
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+/// The highest block of a column that isn't air.
+fn top_block(generator: &DefaultGenerator, column: IVec2XZ) -> (i32, BlockType) {
+    (0..CHUNK_HEIGHT as i32)
+        .rev()
+        .map(|y| (y, generator.block_at(IVec3::new(column.x, y, column.z))))
+        .find(|&(_, block)| block != BlockType::Air)
+        .unwrap()
+}
+
+#[test]
+fn mountain_tops_are_snowed_over() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let mut snowy = 0;
+    for x in -64..64 {
+        for z in -64..64 {
+            let (y, block) = top_block(&generator, IVec2XZ::new(x * 16, z * 16));
+            // Trees don't grow up there, so the top is always the ground.
+            if y > SNOW_HEIGHT as i32 {
+                assert_ne!(block, BlockType::Grass, "{:?}", (x * 16, y, z * 16));
+                assert_ne!(block, BlockType::SnowyGrass, "{:?}", (x * 16, y, z * 16));
+                snowy += (block == BlockType::Snow) as usize;
+            } else {
+                assert_ne!(block, BlockType::Snow, "{:?}", (x * 16, y, z * 16));
+            }
+        }
+    }
+    assert!(snowy > 0, "no mountain reaches the snow");
+}
+
+#[test]
+fn water_freezes_in_cold_biomes() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let biomes = BiomeNoise::new(SEED);
+    let (mut frozen, mut open) = (0, 0);
+    for x in -64..64 {
+        for z in -64..64 {
+            let column = IVec2XZ::new(x * 16, z * 16);
+            let (y, block) = top_block(&generator, column);
+            if y != WATER_HEIGHT as i32 || !matches!(block, BlockType::Water | BlockType::Ice) {
+                continue;
+            }
+            let cold = biomes.biome_at(column).is_cold();
+            assert_eq!(block == BlockType::Ice, cold, "{:?}", column);
+            // Only the top of the water freezes.
+            let below = generator.block_at(IVec3::new(column.x, y - 1, column.z));
+            assert_ne!(below, BlockType::Ice);
+            if cold {
+                frozen += 1;
+            } else {
+                open += 1;
+            }
+        }
+    }
+    assert!(frozen > 0 && open > 0, "{} frozen, {} open", frozen, open);
+}
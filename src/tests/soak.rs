@@ -0,0 +1,77 @@
+use bevy::math::Vec3Swizzles;
+
+use crate::game::soak::*;
+use crate::prelude::*;
+
+#[test]
+fn soak_options_are_parsed() {
+    let args: Vec<String> = ["game", "--soak", "minutes=0.5,speed=20,memory_mb=16"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let soak = SoakTest::from_args(&args).unwrap().unwrap();
+    assert_eq!(soak.duration, 30.0);
+    assert_eq!(soak.speed, 20.0);
+    assert_eq!(soak.size, SoakTest::default().size);
+    assert_eq!(soak.memory_mb, Some(16.0));
+
+    assert_eq!(SoakTest::parse(""), Ok(SoakTest::default()));
+    assert!(SoakTest::from_args(&args[..1]).is_none());
+    for wrong in ["minutes", "minutes=-1", "minutes=soon", "hours=2"] {
+        assert!(SoakTest::parse(wrong).is_err(), "{}", wrong);
+    }
+}
+
+#[test]
+fn the_soak_flight_is_a_figure_eight_over_the_spawn() {
+    let soak = SoakTest::parse("speed=100,size=1000").unwrap();
+    let lap = 6.0 * soak.size / soak.speed;
+    let start = soak.position(0.0);
+    assert_eq!(start.xz(), WORLD_SPAWN.xz());
+
+    // It reaches out on both sides and crosses itself over the spawn half way.
+    let east = soak.position(lap / 4.0);
+    let west = soak.position(lap * 3.0 / 4.0);
+    assert!((east.x - WORLD_SPAWN.x - soak.size).abs() < 1e-2);
+    assert!((west.x - WORLD_SPAWN.x + soak.size).abs() < 1e-2);
+    assert!(soak.position(lap / 2.0).xz().distance(WORLD_SPAWN.xz()) < 1.0);
+
+    // No jumps between frames, and it stays put once the time is up.
+    for step in 0..1000 {
+        let seconds = step as f32 * lap / 1000.0;
+        let moved = soak
+            .position(seconds + 0.1)
+            .distance(soak.position(seconds));
+        assert!(moved <= soak.speed * 0.1 * 1.5, "{} at {}", moved, seconds);
+    }
+    assert_eq!(
+        soak.position(soak.duration),
+        soak.position(soak.duration + 60.0)
+    );
+}
+
+#[test]
+fn soak_reports_name_what_went_wrong() {
+    let passed = SoakReport {
+        settled: true,
+        ready_chunks: 197,
+        peak_chunks: 206,
+        max_chunks: 206,
+        memory_bytes: 1000,
+        memory_budget_bytes: 1000,
+        ..default()
+    };
+    assert!(passed.problems().is_empty());
+
+    let failed = SoakReport {
+        peak_chunks: 300,
+        missing_chunks: 2,
+        leaked_meshes: 1,
+        stale_blocks: vec![IVec2XZ::new(40, -3)],
+        memory_bytes: 2000,
+        panics: 1,
+        ..passed.clone()
+    };
+    assert_eq!(failed.problems().len(), 6);
+    assert_eq!(failed.problems()[2], "1 meshes and 0 materials leaked");
+}
@@ -0,0 +1,63 @@
+use crate::game::rules::WorldGenParams;
+use crate::prelude::*;
+
+fn terrain_like(interpolation: SplineInterpolation) -> Spline {
+    Spline::new(
+        &[(-1.0, 40.0), (-0.1, 70.0), (0.2, 74.0), (0.55, 200.0)],
+        interpolation,
+    )
+}
+
+#[test]
+fn splines_go_through_their_points() {
+    for interpolation in [SplineInterpolation::Linear, SplineInterpolation::Smoothstep] {
+        let spline = terrain_like(interpolation);
+        for point in &spline.points {
+            assert_eq!(spline.evaluate(point.input), point.output);
+        }
+        // Flat past both ends.
+        assert_eq!(spline.evaluate(-5.0), 40.0);
+        assert_eq!(spline.evaluate(0.9), 200.0);
+    }
+
+    let linear = terrain_like(SplineInterpolation::Linear);
+    assert!((linear.evaluate(0.05) - 72.0).abs() < 1e-4);
+    // Smoothstep eases out of the point, so it's behind the straight line an eighth of the way in.
+    let smooth = terrain_like(SplineInterpolation::Smoothstep);
+    assert!(smooth.evaluate(-0.0625) < linear.evaluate(-0.0625));
+}
+
+#[test]
+fn rising_splines_interpolate_monotonically() {
+    let splines = [
+        terrain_like(SplineInterpolation::Linear),
+        terrain_like(SplineInterpolation::Smoothstep),
+        WorldGenParams::default().height_spline,
+    ];
+    for spline in splines {
+        let mut previous = f32::MIN;
+        for step in -1200..=1200 {
+            let height = spline.evaluate(step as f32 / 1000.0);
+            assert!(height >= previous, "{:?} at {}", spline.interpolation, step);
+            previous = height;
+        }
+    }
+}
+
+#[test]
+fn degenerate_splines_dont_break() {
+    assert_eq!(
+        Spline::new(&[], SplineInterpolation::Linear).evaluate(0.3),
+        0.3
+    );
+    let single = Spline::new(&[(0.0, 70.0)], SplineInterpolation::Smoothstep);
+    assert_eq!(single.evaluate(-1.0), 70.0);
+    assert_eq!(single.evaluate(1.0), 70.0);
+    // Two points at the same input are a cliff.
+    let step = Spline::new(
+        &[(0.0, 70.0), (0.5, 70.0), (0.5, 120.0)],
+        SplineInterpolation::Linear,
+    );
+    assert_eq!(step.evaluate(0.49), 70.0);
+    assert_eq!(step.evaluate(0.51), 120.0);
+}
@@ -0,0 +1,122 @@
+use crate::game::edits::{apply_edits, EditHistory};
+use crate::game::leaves::LeafDecay;
+use crate::game::schematic::Schematic;
+use crate::game::stamp::*;
+use crate::game::trees::TreeSpecies;
+use crate::prelude::*;
+
+/// Chunks filled with stone up to y 63.
+fn stone_chunks(positions: &[IVec2XZ]) -> ChunkMap {
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            blocks.column_mut(x, z)[..64].fill(BlockType::Stone);
+        }
+    }
+    let mut chunk_map = ChunkMap::default();
+    for &position in positions {
+        chunk_map.chunks.insert(position, blocks.clone());
+    }
+    chunk_map
+}
+
+#[test]
+fn trees_are_stamped_across_a_chunk_border() {
+    let mut chunk_map = stone_chunks(&[IVec2XZ::new(0, 0), IVec2XZ::new(1, 0)]);
+    let mut history = EditHistory::default();
+    // On the last column of the first chunk, the canopy hangs over into the second.
+    let ground = IVec3::new(CHUNK_SIZE as i32 - 1, 63, 8);
+    chunk_map.set_block(ground + IVec3::Y, BlockType::TallGrass);
+    let changed = grow_tree(&mut chunk_map, &mut history, ground, TreeSpecies::Oak).unwrap();
+
+    assert_eq!(chunk_map.get_block(ground + IVec3::Y), Some(BlockType::Log));
+    let (_, local) = world_to_chunk_and_local(ground + IVec3::Y);
+    let first = &chunk_map.chunks[&IVec2XZ::new(0, 0)];
+    assert_eq!(
+        first.get(local.x as usize, local.y as usize, local.z as usize),
+        BlockType::Log
+    );
+    let second = &chunk_map.chunks[&IVec2XZ::new(1, 0)];
+    let leaves = (0..CHUNK_HEIGHT)
+        .filter(|&y| second.get(0, y, 8) == BlockType::Leaves)
+        .count();
+    assert!(leaves > 0);
+    // The grass in the way was pushed aside by the trunk, the stone under the tree stays.
+    assert_eq!(chunk_map.get_block(ground), Some(BlockType::Stone));
+    for position in [IVec2XZ::new(0, 0), IVec2XZ::new(1, 0)] {
+        assert!(changed.iter().any(|section| section.position == position));
+    }
+
+    // The whole tree is taken back at once.
+    let undone = apply_edits(
+        &mut chunk_map,
+        &mut LeafDecay::default(),
+        &history.undo().unwrap(),
+    );
+    assert!(!undone.is_empty());
+    assert_eq!(
+        chunk_map.get_block(ground + IVec3::Y),
+        Some(BlockType::TallGrass)
+    );
+    assert!((0..CHUNK_HEIGHT)
+        .all(|y| chunk_map.chunks[&IVec2XZ::new(1, 0)].get(0, y, 8) != BlockType::Leaves));
+}
+
+#[test]
+fn nothing_is_stamped_into_chunks_that_arent_loaded() {
+    let mut chunk_map = stone_chunks(&[IVec2XZ::new(0, 0)]);
+    let mut history = EditHistory::default();
+    let corner = IVec3::new(0, 63, CHUNK_SIZE as i32 - 1);
+    let missing = grow_tree(&mut chunk_map, &mut history, corner, TreeSpecies::Spruce);
+
+    let expected = vec![IVec2XZ::new(-1, 0), IVec2XZ::new(-1, 1), IVec2XZ::new(0, 1)];
+    assert_eq!(missing, Err(MissingChunks(expected)));
+    assert_eq!(
+        missing.unwrap_err().to_string(),
+        "the chunks (-1, 0), (-1, 1), (0, 1) aren't loaded"
+    );
+    assert_eq!(chunk_map.get_block(corner + IVec3::Y), Some(BlockType::Air));
+    assert!(history.undo().is_none());
+}
+
+#[test]
+fn schematics_are_pasted_into_the_hill_as_one_edit() {
+    let mut chunk_map = stone_chunks(&[IVec2XZ::new(0, 0), IVec2XZ::new(1, 0)]);
+    let mut history = EditHistory::default();
+    let schematic = Schematic::parse(include_str!("fixtures/hut.ron")).unwrap();
+    // Sunk one block into the stone and across the chunk border.
+    let origin = IVec3::new(CHUNK_SIZE as i32 - 2, 62, 4);
+    let changed = paste_schematic(&mut chunk_map, &mut history, &schematic, origin).unwrap();
+
+    assert_eq!(
+        chunk_map.get_block(origin + IVec3::new(2, 1, 0)),
+        Some(BlockType::Log)
+    );
+    // The hollow middle is dug out of the hill, the unknown roof block is stone.
+    assert_eq!(
+        chunk_map.get_block(origin + IVec3::new(1, 1, 1)),
+        Some(BlockType::Air)
+    );
+    assert_eq!(
+        chunk_map.get_block(origin + IVec3::new(1, 2, 1)),
+        Some(BlockType::Stone)
+    );
+    for position in [IVec2XZ::new(0, 0), IVec2XZ::new(1, 0)] {
+        assert!(changed.iter().any(|section| section.position == position));
+    }
+
+    apply_edits(
+        &mut chunk_map,
+        &mut LeafDecay::default(),
+        &history.undo().unwrap(),
+    );
+    assert_eq!(
+        chunk_map.get_block(origin + IVec3::new(1, 1, 1)),
+        Some(BlockType::Stone)
+    );
+    assert_eq!(
+        chunk_map.get_block(origin + IVec3::new(1, 2, 1)),
+        Some(BlockType::Air)
+    );
+    assert!(history.undo().is_none());
+}
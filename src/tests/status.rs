@@ -0,0 +1,96 @@
+use crate::game::status::*;
+use crate::prelude::*;
+
+#[test]
+fn burning_hurts_until_it_wears_off() {
+    let mut burning = Burning::default();
+    // The first hit lands right away.
+    assert_eq!(burning.tick(0.0), BURN_DAMAGE);
+
+    let mut damage = 0.0;
+    let mut ticks = 0;
+    while !burning.is_over() {
+        damage += burning.tick(0.25);
+        ticks += 1;
+    }
+    assert_eq!(ticks as f32 * 0.25, BURN_DURATION);
+    assert_eq!(damage, BURN_DURATION / BURN_DAMAGE_INTERVAL * BURN_DAMAGE);
+}
+
+#[test]
+fn lava_keeps_the_fire_going() {
+    let mut burning = Burning::default();
+    burning.tick(BURN_DURATION - 0.5);
+    burning.rekindle();
+    assert_eq!(burning.remaining, BURN_DURATION);
+    // A long frame doesn't hurt for longer than the fire lasts.
+    assert_eq!(
+        burning.tick(100.0),
+        (BURN_DURATION / BURN_DAMAGE_INTERVAL).floor() * BURN_DAMAGE
+    );
+    assert!(burning.is_over());
+}
+
+#[test]
+fn bodies_overlap_the_blocks_they_reach_into() {
+    let count = |center: Vec3| body_blocks(center, |_| Some(BlockType::Air)).len();
+    // Right in the middle of a column, two blocks tall.
+    assert_eq!(count(Vec3::new(0.5, 1.0, 0.5)), 2);
+    // Straddling two columns and three blocks.
+    assert_eq!(count(Vec3::new(1.0, 1.5, 0.5)), 6);
+    assert!(body_blocks(Vec3::new(0.5, 1.0, 0.5), |_| None).is_empty());
+}
+
+/// A chunk at the origin with a lava pool next to a water pool, both up to and including y 70.
+fn pools() -> ChunkMap {
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let fluid = if x < 8 {
+                BlockType::Lava
+            } else {
+                BlockType::Water
+            };
+            blocks.column_mut(x, z)[..=70].fill(fluid);
+        }
+    }
+    let mut chunk_map = ChunkMap::default();
+    chunk_map.chunks.insert(IVec2XZ::new(0, 0), blocks);
+    chunk_map
+}
+
+#[test]
+fn lava_burns_and_water_puts_it_out() {
+    let mut world = World::new();
+    world.insert_resource(pools());
+    world.init_resource::<Time>();
+    world.init_resource::<Events<DamageEvent>>();
+    let player = world
+        .spawn((
+            TransformBundle::from(Transform::from_xyz(3.5, 70.0, 3.5)),
+            KinematicCharacterController::default(),
+            Health::default(),
+        ))
+        .id();
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems(
+        (
+            burn_in_lava,
+            apply_deferred,
+            tick_status_effect::<Burning>,
+            apply_damage,
+        )
+            .chain(),
+    );
+    schedule.run(&mut world);
+    assert!(world.get::<Burning>(player).is_some());
+    assert_eq!(
+        world.get::<Health>(player).unwrap().current,
+        MAX_HEALTH - BURN_DAMAGE
+    );
+
+    world.get_mut::<Transform>(player).unwrap().translation.x = 12.5;
+    schedule.run(&mut world);
+    assert!(world.get::<Burning>(player).is_none());
+}
@@ -0,0 +1,133 @@
+use crate::game::freecam::CameraMode;
+use crate::game::status::{apply_damage, tick_status_effect, DamageEvent, Health, MAX_HEALTH};
+use crate::game::suffocation::*;
+use crate::prelude::*;
+
+/// A single loaded chunk at the origin, solid stone up to and including y 70.
+fn stone_map() -> ChunkMap {
+    let mut blocks = ChunkBlocks::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            blocks.column_mut(x, z)[..=70].fill(BlockType::Stone);
+        }
+    }
+    let mut chunk_map = ChunkMap::default();
+    chunk_map.chunks.insert(IVec2XZ::new(0, 0), blocks);
+    chunk_map
+}
+
+fn set(chunk_map: &mut ChunkMap, pos: IVec3, block: BlockType) {
+    let blocks = chunk_map.chunks.get_mut(&IVec2XZ::new(0, 0)).unwrap();
+    blocks.set(pos.x as usize, pos.y as usize, pos.z as usize, block);
+}
+
+#[test]
+fn eye_cells_round_down() {
+    assert_eq!(eye_cell(Vec3::new(8.5, 70.2, 8.5)), IVec3::new(8, 70, 8));
+    assert_eq!(
+        eye_cell(Vec3::new(-0.5, 70.0, -16.5)),
+        IVec3::new(-1, 70, -17)
+    );
+}
+
+#[test]
+fn players_are_pushed_up_out_of_the_ground() {
+    let chunk_map = stone_map();
+    assert_eq!(
+        free_cell_near(&chunk_map, Vec3::new(8.5, 70.2, 8.5)),
+        Some(IVec3::new(8, 71, 8))
+    );
+}
+
+#[test]
+fn the_closest_free_neighbour_wins() {
+    let mut chunk_map = stone_map();
+    set(&mut chunk_map, IVec3::new(7, 60, 8), BlockType::Air);
+    set(&mut chunk_map, IVec3::new(8, 61, 8), BlockType::Water);
+
+    assert_eq!(
+        free_cell_near(&chunk_map, Vec3::new(7.5, 61.1, 8.5)),
+        Some(IVec3::new(7, 60, 8))
+    );
+    assert_eq!(
+        free_cell_near(&chunk_map, Vec3::new(7.9, 61.6, 8.5)),
+        Some(IVec3::new(8, 61, 8))
+    );
+}
+
+#[test]
+fn enclosed_players_stay_put() {
+    let chunk_map = stone_map();
+    assert_eq!(free_cell_near(&chunk_map, Vec3::new(8.5, 40.5, 8.5)), None);
+}
+
+#[test]
+fn unloaded_cells_are_not_free() {
+    let chunk_map = stone_map();
+    // The neighbour at x -1 is in a chunk that isn't loaded.
+    assert_eq!(free_cell_near(&chunk_map, Vec3::new(0.5, 40.5, 8.5)), None);
+}
+
+#[test]
+fn players_suffocate_until_their_head_is_free() {
+    let mut world = World::new();
+    world.insert_resource(stone_map());
+    world.init_resource::<CameraMode>();
+    world.init_resource::<Events<DamageEvent>>();
+    let mut time = Time::default();
+    time.update();
+    world.insert_resource(time);
+    // Deep in the stone, there's nowhere to push the head to.
+    let camera = world
+        .spawn((
+            Camera3d::default(),
+            TransformBundle::from(Transform::from_xyz(8.5, 40.5, 8.5)),
+        ))
+        .id();
+    let player = world
+        .spawn((
+            TransformBundle::from(Transform::from_xyz(8.5, 39.5, 8.5)),
+            KinematicCharacterController::default(),
+            Health::default(),
+        ))
+        .id();
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems(
+        (
+            push_out_of_blocks,
+            suffocate_in_blocks,
+            apply_deferred,
+            tick_status_effect::<Suffocating>,
+            apply_damage,
+        )
+            .chain(),
+    );
+    let mut run = |world: &mut World, seconds: f32| {
+        let mut time = world.resource_mut::<Time>();
+        let last = time.last_update().unwrap();
+        time.update_with_instant(last + std::time::Duration::from_secs_f32(seconds));
+        schedule.run(world);
+    };
+
+    // The first hit lands after a whole interval, then one every interval.
+    run(&mut world, SUFFOCATION_DAMAGE_INTERVAL * 0.5);
+    assert!(world.get::<Suffocating>(player).is_some());
+    assert_eq!(world.get::<Health>(player).unwrap().current, MAX_HEALTH);
+    run(&mut world, SUFFOCATION_DAMAGE_INTERVAL);
+    run(&mut world, SUFFOCATION_DAMAGE_INTERVAL);
+    assert_eq!(
+        world.get::<Health>(player).unwrap().current,
+        MAX_HEALTH - 2.0 * SUFFOCATION_DAMAGE
+    );
+
+    // Out in the air, it stops.
+    world.get_mut::<Transform>(camera).unwrap().translation.y = 80.5;
+    run(&mut world, SUFFOCATION_DAMAGE_INTERVAL);
+    assert!(world.get::<Suffocating>(player).is_none());
+    run(&mut world, SUFFOCATION_DAMAGE_INTERVAL * 4.0);
+    assert_eq!(
+        world.get::<Health>(player).unwrap().current,
+        MAX_HEALTH - 2.0 * SUFFOCATION_DAMAGE
+    );
+}
@@ -0,0 +1,324 @@
+use crate::game::chunk::{in_view_cone, load_priority, stream_chunks};
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+/// How many mesh tasks the pretend task pool finishes in a frame, oldest first, and how long a task takes.
+const FINISHED_PER_FRAME: usize = 2;
+const TASK_FRAMES: u32 = 4;
+const FRAME: f32 = 1.0 / 60.0;
+
+/// The chunk streaming without Bevy: tasks are entities in a queue that finish in the order they were started.
+#[derive(Default)]
+struct Streaming {
+    chunks: ChunksLoaded,
+    loader: ChunkLoader,
+    /// The running tasks, with the frame they were started in.
+    tasks: Vec<(IVec2XZ, Entity, u32)>,
+    frame: u32,
+    entities: u32,
+    /// Every chunk a task was started for, in order.
+    started: Vec<IVec2XZ>,
+    /// Where the camera looks. Zero looks nowhere, all the chunks are in view.
+    forward: Vec3,
+    paused: bool,
+}
+
+impl Streaming {
+    fn frame(&mut self, center: IVec2XZ) -> bool {
+        self.frame += 1;
+        let plan = stream_chunks(
+            &mut self.chunks,
+            &mut self.loader,
+            center,
+            self.forward,
+            !self.paused,
+        );
+        self.tasks
+            .retain(|(_, entity, _)| !plan.despawn.contains(entity));
+        for chunk in plan.dispatch {
+            self.entities += 1;
+            let entity = Entity::from_raw(self.entities);
+            self.chunks.start_generating(chunk, entity);
+            self.tasks.push((chunk, entity, self.frame));
+            self.started.push(chunk);
+        }
+        let finished = self
+            .tasks
+            .iter()
+            .take(FINISHED_PER_FRAME)
+            .take_while(|&&(_, _, started)| started + TASK_FRAMES <= self.frame)
+            .count();
+        for (chunk, entity, _) in self.tasks.drain(..finished) {
+            self.chunks.finish(chunk, entity);
+        }
+        plan.teleported
+    }
+}
+
+#[test]
+fn teleports_drop_the_chunks_left_behind() {
+    let mut streaming = Streaming::default();
+    for _ in 0..5 {
+        assert!(!streaming.frame(IVec2XZ::new(0, 0)));
+    }
+    assert!(!streaming.tasks.is_empty());
+
+    let target = IVec2XZ::new(625, 0);
+    assert!(streaming.frame(target));
+    assert!(streaming.loader.is_teleporting());
+    assert_eq!(streaming.loader.tasks_per_frame(), TELEPORT_TASKS_PER_FRAME);
+    // Nothing is being generated back at the origin anymore.
+    assert!(streaming.tasks.iter().all(|(chunk, ..)| {
+        let distance = *chunk - target;
+        distance.x * distance.x + distance.z * distance.z <= RENDER_DISTANCE * RENDER_DISTANCE
+    }));
+
+    // The 3x3 chunks at the destination are ready first, which ends the teleport.
+    let mut frames = 1;
+    while streaming.loader.is_teleporting() {
+        streaming.frame(target);
+        frames += 1;
+        assert!(frames < 20, "the destination never became ready");
+    }
+    assert!(destination(target).all(|chunk| streaming.chunks.is_ready(chunk)));
+    assert_eq!(streaming.loader.tasks_per_frame(), CHUNK_TASKS_PER_FRAME);
+}
+
+#[test]
+fn walking_into_the_next_chunk_is_not_a_teleport() {
+    let mut streaming = Streaming::default();
+    for x in 0..=TELEPORT_DISTANCE * 3 {
+        assert!(!streaming.frame(IVec2XZ::new(x, 0)));
+    }
+    assert!(!streaming.frame(IVec2XZ::new(TELEPORT_DISTANCE * 4, 0)));
+    assert!(streaming.frame(IVec2XZ::new(TELEPORT_DISTANCE * 5 + 1, 0)));
+}
+
+#[test]
+fn the_player_never_falls_through_the_ground_after_a_teleport() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let mut streaming = Streaming::default();
+    for _ in 0..5 {
+        streaming.frame(IVec2XZ::new(0, 0));
+    }
+
+    // Teleported a block above the ground, 10000 blocks away, while the origin is still generating.
+    let (x, z) = (10_000, 0);
+    let surface = (0..CHUNK_HEIGHT as i32)
+        .rev()
+        .find(|&y| generator.block_at(IVec3::new(x, y, z)).is_solid())
+        .unwrap() as f32
+        + 1.0;
+    let mut player = Vec3::new(x as f32 + 0.5, surface + 1.0, z as f32 + 0.5);
+    let mut velocity = 0.0;
+    let mut held = 0;
+
+    for _ in 0..120 {
+        streaming.frame(IVec2XZ::containing(player));
+        streaming.loader.check_ground(player, &streaming.chunks);
+        if streaming.loader.hold_player {
+            velocity = 0.0;
+            held += 1;
+            continue;
+        }
+        velocity -= 9.81 * FRAME;
+        player.y += velocity * FRAME;
+        // The ground only has a collider once its chunk is ready.
+        if streaming.chunks.is_ready(IVec2XZ::containing(player)) && player.y < surface {
+            player.y = surface;
+            velocity = 0.0;
+        }
+        assert!(player.y >= surface, "fell to {}", player.y);
+    }
+    assert!(held > 0);
+    assert_eq!(player.y, surface);
+}
+
+#[test]
+fn the_world_grows_outward_from_the_player() {
+    let mut streaming = Streaming::default();
+    let center = IVec2XZ::new(3, -7);
+    for _ in 0..200 {
+        streaming.frame(center);
+        assert!(streaming.chunks.tasks_in_flight() <= MAX_CHUNK_TASKS_IN_FLIGHT);
+        assert_eq!(streaming.chunks.tasks_in_flight(), streaming.tasks.len());
+    }
+    // The cap was reached, the pretend pool is slower than the tasks are started.
+    assert!(streaming.started.len() > MAX_CHUNK_TASKS_IN_FLIGHT);
+
+    let distances: Vec<i32> = streaming
+        .started
+        .iter()
+        .map(|&chunk| {
+            let distance = chunk - center;
+            distance.x * distance.x + distance.z * distance.z
+        })
+        .collect();
+    assert_eq!(distances[0], 0, "the chunk under the player goes first");
+    assert!(
+        distances.windows(2).all(|pair| pair[0] <= pair[1]),
+        "closer chunks are started first"
+    );
+}
+
+#[test]
+fn standing_still_doesnt_scan_for_chunks_again() {
+    let mut streaming = Streaming::default();
+    let center = IVec2XZ::new(0, 0);
+    while streaming.chunks.counts().2 > 0 || streaming.started.is_empty() {
+        streaming.frame(center);
+    }
+
+    // Forgetting the chunks behind the streaming's back goes unnoticed while the centre stays the same.
+    streaming.chunks.clear();
+    let plan = stream_chunks(
+        &mut streaming.chunks,
+        &mut streaming.loader,
+        center,
+        streaming.forward,
+        true,
+    );
+    assert!(plan.dispatch.is_empty() && plan.unloaded.is_empty());
+    assert_eq!(streaming.chunks.counts(), (0, 0, 0));
+
+    // Until a rescan is asked for, like resetting the chunks does.
+    streaming.loader.rescan();
+    let plan = stream_chunks(
+        &mut streaming.chunks,
+        &mut streaming.loader,
+        center,
+        streaming.forward,
+        true,
+    );
+    assert_eq!(plan.dispatch.first(), Some(&center));
+    assert!(streaming.chunks.counts().2 > 0);
+
+    // Moving into the next chunk scans again on its own, the column left behind is unloaded.
+    let plan = stream_chunks(
+        &mut streaming.chunks,
+        &mut streaming.loader,
+        center + IVec2XZ::new(1, 0),
+        streaming.forward,
+        true,
+    );
+    assert!(!plan.unloaded.is_empty());
+    assert!(plan.unloaded.iter().all(|chunk| chunk.x <= 0));
+}
+
+#[test]
+fn chunks_in_front_of_the_camera_load_first() {
+    // Looking along +x and a little down, like flying over the ground.
+    let camera = Transform::default().looking_to(Vec3::new(1.0, -0.3, 0.0), Vec3::Y);
+    let forward = camera.forward();
+    let mut offsets = vec![
+        IVec2XZ::new(-1, 0),
+        IVec2XZ::new(5, 4),
+        IVec2XZ::new(0, 3),
+        IVec2XZ::new(6, 0),
+        IVec2XZ::new(-2, -2),
+        IVec2XZ::new(2, -1),
+        IVec2XZ::new(0, 0),
+    ];
+    offsets.sort_by_key(|&offset| load_priority(offset, forward));
+    assert_eq!(
+        offsets,
+        vec![
+            IVec2XZ::new(0, 0),
+            IVec2XZ::new(2, -1),
+            IVec2XZ::new(6, 0),
+            IVec2XZ::new(5, 4),
+            // Behind and to the side, the closest first.
+            IVec2XZ::new(-1, 0),
+            IVec2XZ::new(-2, -2),
+            IVec2XZ::new(0, 3),
+        ]
+    );
+
+    // Looking straight down, nothing is behind the camera.
+    let down = Transform::default()
+        .looking_to(Vec3::NEG_Y, Vec3::Z)
+        .forward();
+    assert!(in_view_cone(IVec2XZ::new(-4, 0), down));
+    assert!(!in_view_cone(IVec2XZ::new(-4, 0), forward));
+}
+
+#[test]
+fn flying_forward_streams_the_chunks_ahead_first() {
+    let mut streaming = Streaming {
+        forward: Vec3::Z,
+        ..default()
+    };
+    while streaming.started.len() < 40 {
+        streaming.frame(IVec2XZ::new(0, 0));
+    }
+    // The 3x3 chunks around the player go first, like after a teleport, the ones ahead right after them.
+    let near: Vec<IVec2XZ> = destination(IVec2XZ::new(0, 0)).collect();
+    let rest: Vec<IVec2XZ> = streaming.started[..40]
+        .iter()
+        .copied()
+        .filter(|chunk| !near.contains(chunk))
+        .collect();
+    assert_eq!(rest.len(), 40 - near.len());
+    assert!(rest.iter().all(|&chunk| in_view_cone(chunk, Vec3::Z)));
+}
+
+#[test]
+fn walking_back_and_forth_over_a_border_loads_every_chunk_once() {
+    let mut streaming = Streaming::default();
+    let (here, there) = (IVec2XZ::new(0, 0), IVec2XZ::new(1, 0));
+    for step in 0..10 {
+        let center = if step % 2 == 0 { here } else { there };
+        for _ in 0..200 {
+            streaming.frame(center);
+        }
+        assert_eq!(streaming.chunks.counts().2, 0, "the chunks never settled");
+    }
+
+    let mut started = streaming.started.clone();
+    started.sort_by_key(|chunk| (chunk.x, chunk.z));
+    let loaded = started.len();
+    started.dedup();
+    assert_eq!(started.len(), loaded, "a chunk was loaded more than once");
+
+    // Moving on far enough does unload the chunks left behind.
+    for _ in 0..200 {
+        streaming.frame(IVec2XZ::new(UNLOAD_DISTANCE - LOAD_DISTANCE + 2, 0));
+    }
+    assert!(!streaming.chunks.is_ready(IVec2XZ::new(-LOAD_DISTANCE, 0)));
+}
+
+#[test]
+fn chunks_still_unload_while_the_generation_is_paused() {
+    let mut streaming = Streaming::default();
+    let start = IVec2XZ::new(0, 0);
+    while streaming.chunks.counts().2 > 0 || streaming.started.is_empty() {
+        streaming.frame(start);
+    }
+    let (loaded, ..) = streaming.chunks.counts();
+
+    // Flying on while paused starts nothing new, but the chunks left behind go away.
+    streaming.paused = true;
+    let started = streaming.started.len();
+    for x in 1..=2 * UNLOAD_DISTANCE {
+        streaming.frame(IVec2XZ::new(x, 0));
+    }
+    assert_eq!(streaming.started.len(), started);
+    assert_eq!(streaming.chunks.counts().2, 0);
+    let (paused, ..) = streaming.chunks.counts();
+    assert!(
+        paused < loaded / 2,
+        "{} of {} chunks are still loaded",
+        paused,
+        loaded
+    );
+    assert!(!streaming.chunks.is_ready(start));
+
+    // Going on again loads the chunks around the player. The chunk system asks for the rescan when it does.
+    streaming.paused = false;
+    streaming.loader.rescan();
+    let center = IVec2XZ::new(2 * UNLOAD_DISTANCE, 0);
+    streaming.frame(center);
+    assert_eq!(streaming.started[started], center);
+}
@@ -0,0 +1,286 @@
+use crate::game::biome::{Biome, BiomeNoise};
+use crate::game::chunk::mesh_blocks;
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::rules::WorldRules;
+use crate::game::trees::*;
+use crate::prelude::*;
+use crate::tests::mesh::test_atlas;
+use crate::tests::SEED;
+
+fn grass_everywhere(_column: IVec2XZ) -> Option<i32> {
+    Some(70)
+}
+
+fn block(blocks: &ChunkBlocks, local: IVec3) -> BlockType {
+    blocks.get(local.x as usize, local.y as usize, local.z as usize)
+}
+
+#[test]
+fn tree_placement_is_deterministic() {
+    let noise = BiomeNoise::new(SEED);
+    for x in -4..4 {
+        for z in -4..4 {
+            let chunk = IVec2XZ::new(x, z);
+            assert_eq!(
+                trees_near(chunk, SEED, &noise, grass_everywhere),
+                trees_near(chunk, SEED, &noise, grass_everywhere)
+            );
+        }
+    }
+}
+
+#[test]
+fn neighbours_agree_on_shared_trees() {
+    let noise = BiomeNoise::new(SEED);
+    let west = trees_near(IVec2XZ::new(0, 0), SEED, &noise, grass_everywhere);
+    let east = trees_near(IVec2XZ::new(1, 0), SEED, &noise, grass_everywhere);
+    // Trees close to the shared border are seen by both chunks.
+    for tree in west
+        .iter()
+        .filter(|tree| tree.base.x >= 16 - CANOPY_RADIUS - 1)
+    {
+        assert!(
+            east.contains(tree),
+            "{:?} is missing from the east chunk",
+            tree
+        );
+    }
+    for tree in east
+        .iter()
+        .filter(|tree| tree.base.x < 16 + CANOPY_RADIUS + 1)
+    {
+        assert!(
+            west.contains(tree),
+            "{:?} is missing from the west chunk",
+            tree
+        );
+    }
+}
+
+/// The tree whose trunk starts at a local position, if there is one.
+fn tree_from_blocks(blocks: &ChunkBlocks, origin: IVec3, local: IVec3) -> Option<Tree> {
+    let log = block(blocks, local);
+    let species = [TreeSpecies::Oak, TreeSpecies::Spruce]
+        .into_iter()
+        .find(|species| species.log() == log)?;
+    let below = block(blocks, local - IVec3::Y);
+    if !matches!(below, BlockType::Grass | BlockType::SnowyGrass) {
+        return None;
+    }
+    let trunk_height = (0..)
+        .take_while(|&y| block(blocks, local + IVec3::Y * y) == log)
+        .count() as i32;
+    Some(Tree {
+        base: origin + local,
+        trunk_height,
+        species,
+    })
+}
+
+#[test]
+fn trees_span_chunk_borders() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+
+    // Find a generated tree in the last column of a chunk, its canopy reaches into the chunk to the east.
+    let (chunk, tree) = (-40..40)
+        .map(|x| IVec2XZ::new(x, 0))
+        .find_map(|chunk| {
+            let blocks = generator.generate_chunk(chunk);
+            let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+            (0..CHUNK_SIZE as i32)
+                .flat_map(|z| (1..CHUNK_HEIGHT as i32 - 8).map(move |y| (y, z)))
+                .find_map(|(y, z)| {
+                    tree_from_blocks(&blocks, origin, IVec3::new(CHUNK_SIZE as i32 - 1, y, z))
+                })
+                .map(|tree| (chunk, tree))
+        })
+        .expect("no tree at a chunk border");
+
+    let east = chunk + IVec2XZ::new(1, 0);
+    let east_blocks = generator.generate_chunk(east);
+    let east_origin = IVec3::new(east.x * CHUNK_SIZE as i32, 0, east.z * CHUNK_SIZE as i32);
+    let overhanging: Vec<IVec3> = tree
+        .blocks()
+        .map(|(pos, _)| pos - east_origin)
+        .filter(|local| local.x >= 0 && (0..CHUNK_SIZE as i32).contains(&local.z))
+        .collect();
+    assert!(!overhanging.is_empty());
+    // The leaves don't replace terrain, but they're never missing where there's room for them.
+    for &local in &overhanging {
+        assert_ne!(
+            block(&east_blocks, local),
+            BlockType::Air,
+            "{:?} of {:?} is missing from the east chunk",
+            local,
+            tree
+        );
+    }
+    assert!(overhanging
+        .iter()
+        .any(|&local| block(&east_blocks, local) == tree.species.leaves()));
+}
+
+#[test]
+fn logs_win_over_leaves() {
+    let trunk = Tree {
+        base: IVec3::new(0, 71, 0),
+        trunk_height: 6,
+        species: TreeSpecies::Oak,
+    };
+    let neighbour = Tree {
+        base: IVec3::new(1, 71, 0),
+        trunk_height: 4,
+        species: TreeSpecies::Oak,
+    };
+    // The top of the neighbour's canopy overlaps the taller trunk.
+    let pos = IVec3::new(0, 74, 0);
+    assert_eq!(neighbour.block_at(pos), Some(BlockType::Leaves));
+    assert_eq!(
+        tree_block_at(&[neighbour, trunk], pos),
+        Some(BlockType::Log)
+    );
+    assert_eq!(
+        tree_block_at(&[trunk, neighbour], pos),
+        Some(BlockType::Log)
+    );
+
+    let mut blocks = ChunkBlocks::default();
+    place_trees(IVec2XZ::new(0, 0), &[neighbour, trunk], &mut blocks);
+    assert_eq!(blocks.get(0, 74, 0), BlockType::Log);
+}
+
+#[test]
+fn trunk_faces_are_hidden_inside_the_tree() {
+    let tree = Tree {
+        base: IVec3::new(2, 0, 2),
+        trunk_height: 5,
+        species: TreeSpecies::Oak,
+    };
+    let atlas = test_atlas();
+    let mesh = mesh_blocks(UVec3::new(5, 8, 5), IVec3::ZERO, &atlas, |pos| {
+        tree.block_at(pos).unwrap_or(BlockType::Air)
+    });
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(bevy::render::mesh::VertexAttributeValues::Float32x3(positions)) => positions,
+        _ => panic!("missing positions"),
+    };
+
+    // Inside the trunk, the only horizontal face is the bottom of the lowest log.
+    let in_trunk = |quad: &[[f32; 3]]| {
+        quad.iter()
+            .all(|v| (2.0..=3.0).contains(&v[0]) && (2.0..=3.0).contains(&v[2]))
+            && quad.iter().all(|v| v[1] == quad[0][1])
+            && quad[0][1] <= tree.trunk_height as f32
+    };
+    let horizontal = positions.chunks(4).filter(|quad| in_trunk(quad)).count();
+    assert_eq!(horizontal, 1);
+}
+
+/// The tree blocks around a trunk, one row of x per z from the bottom up: `#` for logs, `*` for leaves.
+fn footprint(generator: &DefaultGenerator, base: IVec3, layers: i32) -> Vec<String> {
+    let (chunk, _) = world_to_chunk_and_local(base);
+    let blocks = generator.generate_chunk(chunk);
+    let origin = IVec3::new(chunk.x * CHUNK_SIZE as i32, 0, chunk.z * CHUNK_SIZE as i32);
+    (0..layers)
+        .map(|y| {
+            let rows: Vec<String> = (-CANOPY_RADIUS..=CANOPY_RADIUS)
+                .map(|z| {
+                    (-CANOPY_RADIUS..=CANOPY_RADIUS)
+                        .map(
+                            |x| match block(&blocks, base + IVec3::new(x, y, z) - origin) {
+                                b if b.is_log() => '#',
+                                b if b.is_leaves() => '*',
+                                _ => '.',
+                            },
+                        )
+                        .collect()
+                })
+                .collect();
+            rows.join(" ")
+        })
+        .collect()
+}
+
+#[test]
+fn known_oak_for_seed() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let base = IVec3::new(-141, 73, -52);
+    // An oak, its random corners included.
+    assert_eq!(
+        footprint(&generator, base, 9),
+        [
+            "..... ..... ..#.. ..... .....",
+            "..... ..... ..#.. ..... .....",
+            "..... ..... ..#.. ..... .....",
+            "..... ..... ..#.. ..... .....",
+            "****. ***** **#** ***** *****",
+            "****. ***** **#** ***** ****.",
+            "..... .***. .***. ..*.. .....",
+            "..... ..*.. .***. ..*.. .....",
+            "..... ..... ..... ..... .....",
+        ]
+    );
+    let (chunk, local) = world_to_chunk_and_local(base);
+    let blocks = generator.generate_chunk(chunk);
+    assert_eq!(block(&blocks, local.as_ivec3()), BlockType::Log);
+    assert_eq!(
+        block(&blocks, local.as_ivec3() - IVec3::Y),
+        BlockType::Grass
+    );
+}
+
+#[test]
+fn known_spruce_for_seed() {
+    let generator = DefaultGenerator::new(SEED, WorldRules::default());
+    let base = IVec3::new(23, 73, -89);
+    assert_eq!(
+        footprint(&generator, base, 10),
+        [
+            "..... ..... ..#.. ..... .....",
+            "..... ..... ..#.. ..... .....",
+            ".***. ***** **#** ***** .***.",
+            "..... .***. .*#*. .***. .....",
+            ".***. ***** **#** ***** .***.",
+            "..... .***. .*#*. .***. .....",
+            "..... ..*.. .*#*. ..*.. .....",
+            "..... ..... ..*.. ..... .....",
+            "..... ..... ..... ..... .....",
+            "..... ..... ..... ..... .....",
+        ]
+    );
+    let (chunk, local) = world_to_chunk_and_local(base);
+    let blocks = generator.generate_chunk(chunk);
+    assert_eq!(block(&blocks, local.as_ivec3()), BlockType::SpruceLog);
+    assert_eq!(
+        block(&blocks, local.as_ivec3() + IVec3::Y * 7),
+        BlockType::SpruceLeaves
+    );
+}
+
+#[test]
+fn biomes_grow_their_own_species() {
+    assert_eq!(Biome::Plains.tree_species(), Some(TreeSpecies::Oak));
+    assert_eq!(Biome::Taiga.tree_species(), Some(TreeSpecies::Spruce));
+    assert_eq!(Biome::Desert.tree_species(), None);
+    for species in [TreeSpecies::Oak, TreeSpecies::Spruce] {
+        let (shortest, tallest) = species.trunk_heights();
+        for trunk_height in shortest..=tallest {
+            let tree = Tree {
+                base: IVec3::ZERO,
+                trunk_height,
+                species,
+            };
+            // The canopy fits in the box the chunks look for trees in.
+            for (pos, block) in tree.blocks() {
+                assert!(pos.x.abs() <= CANOPY_RADIUS && pos.z.abs() <= CANOPY_RADIUS);
+                assert!(block == species.log() || block == species.leaves());
+            }
+            assert_eq!(
+                tree.blocks()
+                    .filter(|&(_, block)| block == species.log())
+                    .count(),
+                trunk_height as usize
+            );
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::game::biome::BiomeNoise;
+use crate::game::generator::{DefaultGenerator, WorldGenerator};
+use crate::game::rules::{WorldGenParams, WorldRules};
+use crate::game::tune::*;
+use crate::prelude::*;
+use crate::tests::SEED;
+
+#[test]
+fn presets_keep_every_parameter_through_ron() {
+    let mut presets = Presets::default();
+    presets.0.insert(
+        "islands".to_string(),
+        WorldGenParams {
+            octaves: 5,
+            persistence: 0.5,
+            cave_scale: 0.1,
+            cave_threshold: 0.2,
+            water_height: 90,
+            biome_scale: 0.004,
+            ..default()
+        },
+    );
+    presets.0.insert("default".to_string(), default());
+
+    let contents = ron::ser::to_string_pretty(&presets, ron::ser::PrettyConfig::default()).unwrap();
+    assert_eq!(ron::from_str::<Presets>(&contents).unwrap(), presets);
+}
+
+#[test]
+fn presets_without_a_parameter_get_its_default() {
+    let presets: Presets = ron::from_str(r#"{"hills": (octaves: 6)}"#).unwrap();
+    assert_eq!(
+        presets.0["hills"],
+        WorldGenParams {
+            octaves: 6,
+            ..default()
+        }
+    );
+}
+
+#[test]
+fn raising_the_water_level_floods_the_oceans_deeper() {
+    let raised = WorldGenParams {
+        water_height: WATER_HEIGHT + 10,
+        ..default()
+    };
+    let normal = DefaultGenerator::new(SEED, WorldRules::default());
+    let flooded = DefaultGenerator::with_params(SEED, WorldRules::default(), raised);
+    let ocean = (0..4096)
+        .map(|x| IVec2XZ::new(x, 0))
+        .find(|&column| normal.surface_y(column) < WATER_HEIGHT as i32 - 8)
+        .expect("no ocean along the x axis");
+    assert_eq!(flooded.surface_y(ocean), normal.surface_y(ocean));
+
+    // Under the top of the raised water, which freezes over in the cold biomes, and over its sand floor.
+    let above = IVec3::new(ocean.x, WATER_HEIGHT as i32 + 8, ocean.z);
+    assert_eq!(normal.block_at(above), BlockType::Air);
+    assert_eq!(flooded.block_at(above), BlockType::Water);
+}
+
+#[test]
+fn the_biome_scale_stretches_the_biomes() {
+    let normal = BiomeNoise::new(SEED);
+    let bigger = BiomeNoise::with_scale(SEED, BIOME_SCALE / 2.0);
+    for x in (-2000..2000).step_by(97) {
+        for z in (-2000..2000).step_by(89) {
+            assert_eq!(
+                bigger.biome_at(IVec2XZ::new(x * 2, z * 2)),
+                normal.biome_at(IVec2XZ::new(x, z)),
+                "{} {}",
+                x,
+                z
+            );
+        }
+    }
+}